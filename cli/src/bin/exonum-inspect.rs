@@ -0,0 +1,188 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read-only inspection of the node database.
+//!
+//! The database is opened in the read-only mode, so the tool can be used while the node
+//! is running. Commands:
+//!
+//! - `list` lists all indexes with their types, number of values and sizes
+//! - `aggregator` prints the state hash and the state aggregator of the database
+//! - `entries` dumps entries of an index as JSON lines. Values of the core indexes with blocks,
+//!   transactions and transaction locations are decoded; other values are hex-encoded
+//! - `proof` prints a proof of authenticity for a Merkelized index, tied to the latest block
+
+use exonum::{
+    blockchain::{Block, TxLocation},
+    merkledb::{inspect, BinaryValue, Database, DbOptions, RocksDB, SystemSchema},
+    messages::Verified,
+    runtime::{AnyTx, SnapshotExt},
+};
+use failure::{format_err, Error};
+use serde_json::{json, Value};
+use structopt::StructOpt;
+
+use std::{borrow::Cow, path::PathBuf};
+
+/// Inspect the contents of an Exonum node database.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "exonum-inspect")]
+struct Inspect {
+    /// Path to a database directory.
+    #[structopt(long, short = "d")]
+    db_path: PathBuf,
+    /// Inspection to perform.
+    #[structopt(subcommand)]
+    query: Query,
+}
+
+/// Available inspections.
+#[derive(StructOpt, Debug)]
+enum Query {
+    /// List all indexes in the database.
+    #[structopt(name = "list")]
+    List,
+    /// Print the state hash and the state aggregator of the database.
+    #[structopt(name = "aggregator")]
+    Aggregator,
+    /// Dump entries of an index as JSON lines.
+    #[structopt(name = "entries")]
+    Entries {
+        /// Index address as printed by the `list` command, e.g., `core.blocks`
+        /// or `token.history[0a0b]`.
+        index: String,
+        /// Maximum number of entries to dump.
+        #[structopt(long, short = "l")]
+        limit: Option<usize>,
+        /// Output values of the core indexes as hex instead of decoding them.
+        #[structopt(long)]
+        raw: bool,
+    },
+    /// Print a proof of authenticity for a Merkelized index.
+    #[structopt(name = "proof")]
+    Proof {
+        /// Full name of the index, e.g., `token.wallets`.
+        index: String,
+    },
+}
+
+/// Decoder of values of a known core index.
+type Decoder = fn(&[u8]) -> Result<Value, Error>;
+
+fn decode<T: BinaryValue + serde::Serialize>(bytes: &[u8]) -> Result<Value, Error> {
+    let value = T::from_bytes(Cow::Borrowed(bytes))?;
+    Ok(serde_json::to_value(value)?)
+}
+
+fn decode_transaction(bytes: &[u8]) -> Result<Value, Error> {
+    let transaction = Verified::<AnyTx>::from_bytes(Cow::Borrowed(bytes))?;
+    let raw = transaction.as_raw();
+    let payload = transaction.payload();
+    Ok(json!({
+        "author": raw.author,
+        "signature": raw.signature,
+        "call_info": payload.call_info,
+        "arguments": hex::encode(&payload.arguments),
+    }))
+}
+
+fn core_decoder(index: &str) -> Option<Decoder> {
+    match index {
+        "core.blocks" => Some(decode::<Block>),
+        "core.transactions" => Some(decode_transaction),
+        "core.transactions_locations" => Some(decode::<TxLocation>),
+        _ => None,
+    }
+}
+
+impl Inspect {
+    fn run(self) -> Result<(), Error> {
+        let db = RocksDB::open_read_only(&self.db_path, &DbOptions::default())?;
+        let snapshot = db.snapshot();
+
+        match self.query {
+            Query::List => {
+                println!(
+                    "{:<48} {:<12} {:>12} {:>12} {:>14}",
+                    "INDEX", "TYPE", "LEN", "ENTRIES", "SIZE"
+                );
+                for info in inspect::indexes(&*snapshot) {
+                    let stats = info.contents(&*snapshot).stats();
+                    println!(
+                        "{:<48} {:<12} {:>12} {:>12} {:>14}",
+                        info.to_string(),
+                        format!("{:?}", info.index_type()),
+                        stats.len,
+                        stats.raw_entries,
+                        stats.size
+                    );
+                }
+            }
+
+            Query::Aggregator => {
+                let schema = SystemSchema::new(&*snapshot);
+                let mut indexes = serde_json::Map::new();
+                for (name, hash) in schema.state_aggregator().iter() {
+                    let value = if schema.is_prefix_aggregated(&name) {
+                        let prefix_indexes: serde_json::Map<_, _> = schema
+                            .prefix_state_aggregator(&name)
+                            .iter()
+                            .map(|(name, hash)| (name, json!(hash)))
+                            .collect();
+                        json!({ "hash": hash, "indexes": prefix_indexes })
+                    } else {
+                        json!(hash)
+                    };
+                    indexes.insert(name, value);
+                }
+                let output = json!({
+                    "state_hash": schema.state_hash(),
+                    "indexes": indexes,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+
+            Query::Entries { index, limit, raw } => {
+                let info = inspect::indexes(&*snapshot)
+                    .into_iter()
+                    .find(|info| info.to_string() == index)
+                    .ok_or_else(|| format_err!("Index `{}` does not exist", index))?;
+                let decoder = if raw { None } else { core_decoder(&index) };
+
+                let contents = info.contents(&*snapshot);
+                let entries = contents.entries().take(limit.unwrap_or(usize::max_value()));
+                for (key, value) in entries {
+                    let value = match decoder {
+                        Some(decoder) => decoder(&value)?,
+                        None => Value::String(hex::encode(&value)),
+                    };
+                    let entry = json!({ "key": hex::encode(&key), "value": value });
+                    println!("{}", serde_json::to_string(&entry)?);
+                }
+            }
+
+            Query::Proof { index } => {
+                let proof = snapshot.proof_for_index(&index).ok_or_else(|| {
+                    format_err!("Index `{}` does not exist or is not Merkelized", index)
+                })?;
+                println!("{}", serde_json::to_string_pretty(&proof)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn main() -> Result<(), Error> {
+    Inspect::from_args().run()
+}
@@ -186,6 +186,7 @@ impl ExonumCommand for Finalize {
             network: private_config.network,
             mempool: private_config.mempool,
             database: private_config.database,
+            history: private_config.history,
             thread_pool_size: private_config.thread_pool_size,
             connect_list,
             keys: private_config.keys,
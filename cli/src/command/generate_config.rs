@@ -160,6 +160,7 @@ impl ExonumCommand for GenerateConfig {
             network: Default::default(),
             mempool: Default::default(),
             database: Default::default(),
+            history: None,
             thread_pool_size: Default::default(),
             connect_list: Default::default(),
             keys,
@@ -38,6 +38,10 @@ pub struct GenerateTemplate {
     /// Supervisor service mode. Possible options are "simple" and "decentralized".
     #[structopt(long)]
     pub supervisor_mode: SupervisorMode,
+    /// Soft limit on the size of changes (in bytes) made by a single transaction.
+    /// Transactions exceeding the limit fail. The size is not limited by default.
+    #[structopt(long)]
+    pub tx_fork_size_limit: Option<usize>,
 }
 
 impl ExonumCommand for GenerateTemplate {
@@ -47,6 +51,7 @@ impl ExonumCommand for GenerateTemplate {
             general: GeneralConfig {
                 validators_count: self.validators_count,
                 supervisor_mode: self.supervisor_mode,
+                tx_fork_size_limit: self.tx_fork_size_limit,
             },
             validator_keys: None,
         };
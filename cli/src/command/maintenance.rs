@@ -15,14 +15,19 @@
 //! Standard Exonum CLI command used to perform different maintenance actions.
 
 use exonum::{
-    exonum_merkledb::{Database, RocksDB},
+    blockchain::Schema,
+    exonum_merkledb::{dump, integrity, Database, RocksDB},
     helpers::clear_consensus_messages_cache,
 };
-use failure::Error;
+use failure::{bail, ensure, Error};
 use serde_derive::{Deserialize, Serialize};
 use structopt::StructOpt;
 
-use std::path::PathBuf;
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
 
 use crate::{
     command::{ExonumCommand, StandardResult},
@@ -50,6 +55,23 @@ pub enum Action {
     /// Clear consensus messages cache.
     #[structopt(name = "clear-cache")]
     ClearCache,
+    /// Export the database contents into a portable dump file.
+    #[structopt(name = "export")]
+    Export {
+        /// Path to the dump file to be created.
+        #[structopt(long, short = "o")]
+        output: PathBuf,
+    },
+    /// Check integrity of the database, recomputing hashes of all Merkelized indexes.
+    #[structopt(name = "check-integrity")]
+    CheckIntegrity,
+    /// Restore the database from a dump file. The database directory must not exist.
+    #[structopt(name = "import")]
+    Import {
+        /// Path to the dump file.
+        #[structopt(long, short = "i")]
+        input: PathBuf,
+    },
 }
 
 impl Action {
@@ -64,14 +86,83 @@ impl Action {
         db.merge_sync(fork.into_patch())?;
         Ok(())
     }
+
+    fn export(node_config: &Path, db_path: &Path, output: &Path) -> Result<(), Error> {
+        let node_config: NodeConfig = load_config_file(node_config)?;
+        let db = RocksDB::open(db_path, &node_config.private_config.database)?;
+        let writer = BufWriter::new(File::create(output)?);
+        dump::export(&*db.snapshot(), writer)?;
+        Ok(())
+    }
+
+    fn check_integrity(node_config: &Path, db_path: &Path) -> Result<(), Error> {
+        let node_config: NodeConfig = load_config_file(node_config)?;
+        let db = RocksDB::open(db_path, &node_config.private_config.database)?;
+        let inconsistencies = integrity::check(&*db.snapshot());
+        if !inconsistencies.is_empty() {
+            let report: Vec<_> = inconsistencies
+                .iter()
+                .map(|inconsistency| format!("  {}", inconsistency))
+                .collect();
+            bail!(
+                "Database integrity check found {} inconsistencies:\n{}",
+                inconsistencies.len(),
+                report.join("\n")
+            );
+        }
+        Ok(())
+    }
+
+    fn import(node_config: &Path, db_path: &Path, input: &Path) -> Result<(), Error> {
+        ensure!(
+            !db_path.exists(),
+            "Database directory {} already exists",
+            db_path.display()
+        );
+        let node_config: NodeConfig = load_config_file(node_config)?;
+        let reader = BufReader::new(File::open(input)?);
+        let result = RocksDB::open(db_path, &node_config.private_config.database)
+            .map_err(Error::from)
+            .and_then(|db| Self::import_into(&db, reader));
+        if result.is_err() {
+            // The partially imported database is useless; remove it so that
+            // the import can be retried.
+            fs::remove_dir_all(db_path).ok();
+        }
+        result
+    }
+
+    fn import_into(db: &RocksDB, reader: BufReader<File>) -> Result<(), Error> {
+        let state_hash = dump::import(db, reader)?;
+        // Check that the imported state corresponds to the latest committed block.
+        let snapshot = db.snapshot();
+        let schema = Schema::new(&snapshot);
+        if let Some(block_hash) = schema.block_hashes_by_height().last() {
+            let block = match schema.blocks().get(&block_hash) {
+                Some(block) => block,
+                None => bail!("Block {:?} is missing in the imported database", block_hash),
+            };
+            ensure!(
+                block.state_hash == state_hash,
+                "State hash of the imported database ({:?}) does not match \
+                 the state hash of the latest block ({:?})",
+                state_hash,
+                block.state_hash
+            );
+        }
+        Ok(())
+    }
 }
 
 impl ExonumCommand for Maintenance {
     fn execute(self) -> Result<StandardResult, Error> {
-        match self.action {
+        match &self.action {
             Action::ClearCache => {
                 Action::clear_cache(self.node_config.clone(), self.db_path.clone())?
             }
+            Action::Export { output } => Action::export(&self.node_config, &self.db_path, output)?,
+            Action::CheckIntegrity => Action::check_integrity(&self.node_config, &self.db_path)?,
+            Action::Import { input } => Action::import(&self.node_config, &self.db_path, input)?,
         }
         Ok(StandardResult::Maintenance {
             node_config_path: self.node_config,
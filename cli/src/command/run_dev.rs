@@ -65,6 +65,7 @@ impl ExonumCommand for RunDev {
             common_config: common_config.clone(),
             validators_count: 1,
             supervisor_mode: SupervisorMode::Simple,
+            tx_fork_size_limit: None,
         };
         generate_template.execute()?;
 
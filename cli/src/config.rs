@@ -17,7 +17,7 @@
 use exonum::{
     blockchain::{ConsensusConfig, ValidatorKeys},
     events::NetworkConfiguration,
-    exonum_merkledb::DbOptions,
+    exonum_merkledb::{DbOptions, HistoryOptions},
     keys::{read_keys_from_file, Keys},
     node::{ConnectListConfig, MemoryPoolConfig, NodeApiConfig, NodeConfig as CoreNodeConfig},
 };
@@ -36,6 +36,10 @@ pub struct GeneralConfig {
     pub validators_count: u32,
     /// Supervisor service mode.
     pub supervisor_mode: SupervisorMode,
+    /// Soft limit on the size of changes (in bytes) made by a single transaction.
+    /// `None` means that the size is not limited.
+    #[serde(default)]
+    pub tx_fork_size_limit: Option<usize>,
 }
 
 /// Public configuration of the node. Is shared among validators.
@@ -71,6 +75,10 @@ pub struct NodePrivateConfig {
     /// Optional database configuration.
     #[serde(default)]
     pub database: DbOptions,
+    /// Options for recording historical blockchain states.
+    /// `None` means that the history is not recorded.
+    #[serde(default)]
+    pub history: Option<HistoryOptions>,
     /// Amount of threads used for transactions verification.
     pub thread_pool_size: Option<u8>,
     /// Information about peers within network.
@@ -100,8 +108,10 @@ impl Into<CoreNodeConfig> for NodeConfig {
             mempool: self.private_config.mempool,
             services_configs: Default::default(),
             database: self.private_config.database,
+            history: self.private_config.history,
             connect_list: self.private_config.connect_list,
             thread_pool_size: self.private_config.thread_pool_size,
+            tx_fork_size_limit: self.public_config.general.tx_fork_size_limit,
             master_key_path: self.private_config.master_key_path,
             keys: self.private_config.keys,
         }
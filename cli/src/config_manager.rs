@@ -106,6 +106,7 @@ mod tests {
                 network: Default::default(),
                 mempool: Default::default(),
                 database: Default::default(),
+                history: None,
                 thread_pool_size: None,
                 connect_list: Default::default(),
                 keys: Default::default(),
@@ -115,6 +116,7 @@ mod tests {
                 general: GeneralConfig {
                     validators_count: 1,
                     supervisor_mode: Mode::Simple,
+                    tx_fork_size_limit: None,
                 },
                 validator_keys: None,
             },
@@ -47,8 +47,15 @@
 //!
 //! * `run-dev` command automatically generates network configuration with a single node and runs
 //! it. This command can be useful for fast testing of the services during development process.
-//! * `maintenance` command contains only `clear-cache` functionality at the moment. It allows to
-//! clear node's consensus messages cache to fix rare node out-of-sync issues.
+//! * `maintenance` command contains `clear-cache`, `export`, `import` and `check-integrity`
+//! actions. `clear-cache` allows to clear node's consensus messages cache to fix rare node
+//! out-of-sync issues. `export` and `import` allow to move the node database between machines
+//! or storage backends using a portable dump file. `check-integrity` verifies that Merkelized
+//! data in the database is not corrupted.
+//! * `exonum-inspect` binary opens the node database in the read-only mode and allows to
+//! list indexes with their sizes, print the state aggregator, dump entries of an index
+//! (decoding blocks and transactions from the core schema) and obtain index proofs.
+//! The database can be inspected while the node is running.
 //!
 //! ## How to Extend Parameters
 //!
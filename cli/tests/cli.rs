@@ -498,6 +498,55 @@ fn test_clear_cache() {
         .unwrap();
 }
 
+#[test]
+fn test_check_integrity() {
+    let env = ConfigSpec::new_without_pass();
+    let db_path = env.output_dir().join("db0");
+
+    env.command("maintenance")
+        .with_named_arg("--node-config", &env.expected_node_config_file(0))
+        .with_named_arg("--db-path", &db_path)
+        .with_arg("check-integrity")
+        .run()
+        .unwrap();
+}
+
+#[test]
+fn test_export_and_import() {
+    let env = ConfigSpec::new_without_pass();
+    let db_path = env.output_dir().join("db0");
+    let dump_path = env.output_dir().join("db0.dump");
+    let imported_db_path = env.output_dir().join("db1");
+
+    env.command("maintenance")
+        .with_named_arg("--node-config", &env.expected_node_config_file(0))
+        .with_named_arg("--db-path", &db_path)
+        .with_arg("export")
+        .with_named_arg("--output", &dump_path)
+        .run()
+        .unwrap();
+    assert!(dump_path.exists());
+
+    env.command("maintenance")
+        .with_named_arg("--node-config", &env.expected_node_config_file(0))
+        .with_named_arg("--db-path", &imported_db_path)
+        .with_arg("import")
+        .with_named_arg("--input", &dump_path)
+        .run()
+        .unwrap();
+    assert!(imported_db_path.exists());
+
+    // Importing into an existing database is prohibited.
+    let result = env
+        .command("maintenance")
+        .with_named_arg("--node-config", &env.expected_node_config_file(0))
+        .with_named_arg("--db-path", &imported_db_path)
+        .with_arg("import")
+        .with_named_arg("--input", &dump_path)
+        .run();
+    assert!(result.is_err());
+}
+
 #[test]
 fn run_node_with_simple_supervisor() {
     run_node_with_supervisor(&SupervisorMode::Simple).unwrap();
@@ -520,6 +569,7 @@ fn different_supervisor_modes_in_public_configs() -> Result<(), failure::Error>
         network: Default::default(),
         mempool: Default::default(),
         database: Default::default(),
+        history: None,
         thread_pool_size: None,
         connect_list: Default::default(),
         keys: Default::default(),
@@ -560,6 +610,7 @@ fn public_config(supervisor_mode: SupervisorMode) -> NodePublicConfig {
         general: GeneralConfig {
             validators_count: 2,
             supervisor_mode,
+            tx_fork_size_limit: None,
         },
         validator_keys: Some(keys),
     }
@@ -574,6 +625,7 @@ fn run_node_with_supervisor(supervisor_mode: &SupervisorMode) -> Result<(), fail
         common_config: common_config_path.clone(),
         validators_count: 1,
         supervisor_mode: supervisor_mode.clone(),
+        tx_fork_size_limit: None,
     };
     generate_template.execute()?;
 
@@ -12,11 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use darling::{ast::Fields, FromDeriveInput, FromField, FromMeta};
+use darling::{
+    ast::{Fields, Style},
+    FromDeriveInput, FromField, FromMeta,
+};
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
 use quote::{quote, ToTokens};
-use syn::{spanned::Spanned, Data, DataStruct, DeriveInput, Generics};
+use syn::{spanned::Spanned, Data, DataEnum, DataStruct, DeriveInput, Generics, Type};
 
 use std::collections::HashSet;
 
@@ -427,3 +430,248 @@ pub fn impl_from_access(input: TokenStream) -> TokenStream {
     let tokens = quote!(#from_access);
     tokens.into()
 }
+
+#[derive(Debug)]
+struct BinaryKey {
+    ident: Ident,
+    generics: Generics,
+    kind: BinaryKeyKind,
+}
+
+#[derive(Debug)]
+enum BinaryKeyKind {
+    Struct(Fields<KeyField>),
+    Enum(Vec<Ident>),
+}
+
+#[derive(Debug, Default, FromMeta)]
+struct KeyFieldAttrs {
+    #[darling(default)]
+    variable_size: bool,
+}
+
+#[derive(Debug)]
+struct KeyField {
+    span: Span,
+    ident: Option<Ident>,
+    ty: Type,
+    variable_size: bool,
+}
+
+impl FromField for KeyField {
+    fn from_field(field: &syn::Field) -> darling::Result<Self> {
+        let attrs = find_meta_attrs("binary_key", &field.attrs)
+            .map(|meta| KeyFieldAttrs::from_nested_meta(&meta))
+            .unwrap_or_else(|| Ok(KeyFieldAttrs::default()))?;
+
+        Ok(Self {
+            span: field.span(),
+            ident: field.ident.clone(),
+            ty: field.ty.clone(),
+            variable_size: attrs.variable_size,
+        })
+    }
+}
+
+impl FromDeriveInput for BinaryKey {
+    fn from_derive_input(input: &DeriveInput) -> darling::Result<Self> {
+        let kind = match &input.data {
+            Data::Struct(DataStruct { fields, .. }) => {
+                let fields = Fields::try_from(fields)?;
+                if fields.fields.is_empty() {
+                    let msg = "`BinaryKey` cannot be derived for structs without fields";
+                    return Err(darling::Error::custom(msg));
+                }
+                let last_index = fields.fields.len() - 1;
+                for (i, field) in fields.fields.iter().enumerate() {
+                    if field.variable_size && i != last_index {
+                        let msg = "Only the last field of the key may have variable size";
+                        return Err(darling::Error::custom(msg).with_span(&field.span));
+                    }
+                }
+                BinaryKeyKind::Struct(fields)
+            }
+
+            Data::Enum(DataEnum { variants, .. }) => {
+                if variants.is_empty() {
+                    let msg = "`BinaryKey` cannot be derived for enums without variants";
+                    return Err(darling::Error::custom(msg));
+                }
+                if variants.len() > 256 {
+                    let msg = "`BinaryKey` cannot be derived for enums with more than 256 variants";
+                    return Err(darling::Error::custom(msg));
+                }
+                let mut idents = Vec::with_capacity(variants.len());
+                for variant in variants {
+                    if !variant.fields.is_empty() {
+                        let msg = "`BinaryKey` can be derived only for enums without fields";
+                        return Err(darling::Error::custom(msg).with_span(&variant.fields));
+                    }
+                    idents.push(variant.ident.clone());
+                }
+                BinaryKeyKind::Enum(idents)
+            }
+
+            Data::Union(_) => {
+                return Err(darling::Error::unsupported_shape(
+                    "`BinaryKey` can be only implemented for structs and enums",
+                ));
+            }
+        };
+
+        Ok(Self {
+            ident: input.ident.clone(),
+            generics: input.generics.clone(),
+            kind,
+        })
+    }
+}
+
+impl BinaryKey {
+    fn struct_impl(
+        fields: &Fields<KeyField>,
+    ) -> (proc_macro2::TokenStream, Option<proc_macro2::TokenStream>) {
+        let key = quote!(exonum_merkledb::BinaryKey);
+        let fixed_key = quote!(exonum_merkledb::FixedSizeKey);
+
+        let mut size_terms = vec![];
+        let mut write_stmts = vec![];
+        let mut read_stmts = vec![];
+        // Sizes of the fields preceding the current one.
+        let mut prev_sizes = vec![];
+        let mut field_vars = vec![];
+
+        for (i, field) in fields.fields.iter().enumerate() {
+            let ty = &field.ty;
+            let accessor = match &field.ident {
+                Some(ident) => quote!(#ident),
+                None => {
+                    let index = syn::Index::from(i);
+                    quote!(#index)
+                }
+            };
+            let var = Ident::new(&format!("field_{}", i), Span::call_site());
+            let start = if prev_sizes.is_empty() {
+                quote!(0)
+            } else {
+                quote!(#(#prev_sizes)+*)
+            };
+
+            if field.variable_size {
+                size_terms.push(quote!(#key::size(&self.#accessor)));
+                write_stmts.push(quote!(#key::write(&self.#accessor, &mut buffer[#start..]);));
+                read_stmts.push(quote!(let #var = <#ty as #key>::read(&buffer[#start..]);));
+            } else {
+                let size = quote!(<#ty as #fixed_key>::SIZE);
+                let end = quote!(#(#prev_sizes +)* #size);
+                size_terms.push(size.clone());
+                write_stmts.push(quote!(#key::write(&self.#accessor, &mut buffer[#start..#end]);));
+                read_stmts.push(quote!(let #var = <#ty as #key>::read(&buffer[#start..#end]);));
+                prev_sizes.push(size);
+            }
+
+            field_vars.push(match &field.ident {
+                Some(ident) => quote!(#ident: #var),
+                None => quote!(#var),
+            });
+        }
+
+        let constructor = match fields.style {
+            Style::Struct => quote!(Self { #(#field_vars,)* }),
+            _ => quote!(Self(#(#field_vars,)*)),
+        };
+
+        let key_impl = quote! {
+            fn size(&self) -> usize {
+                #(#size_terms)+*
+            }
+
+            fn write(&self, buffer: &mut [u8]) -> usize {
+                #(#write_stmts)*
+                #key::size(self)
+            }
+
+            fn read(buffer: &[u8]) -> Self {
+                #(#read_stmts)*
+                #constructor
+            }
+        };
+
+        let is_fixed = fields.fields.iter().all(|field| !field.variable_size);
+        let fixed_size = if is_fixed {
+            Some(quote!(#(#prev_sizes)+*))
+        } else {
+            None
+        };
+        (key_impl, fixed_size)
+    }
+
+    fn enum_impl(&self, variants: &[Ident]) -> proc_macro2::TokenStream {
+        let name = &self.ident;
+        let paths: Vec<_> = variants
+            .iter()
+            .map(|variant| quote!(#name::#variant))
+            .collect();
+        let tags: Vec<_> = (0..variants.len()).map(|i| i as u8).collect();
+
+        quote! {
+            fn size(&self) -> usize {
+                1
+            }
+
+            fn write(&self, buffer: &mut [u8]) -> usize {
+                buffer[0] = match self {
+                    #(#paths => #tags,)*
+                };
+                1
+            }
+
+            fn read(buffer: &[u8]) -> Self {
+                match buffer[0] {
+                    #(#tags => #paths,)*
+                    tag => panic!("Invalid tag for `{}` key: {}", stringify!(#name), tag),
+                }
+            }
+        }
+    }
+}
+
+impl ToTokens for BinaryKey {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let name = &self.ident;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+
+        let (key_impl, fixed_size) = match &self.kind {
+            BinaryKeyKind::Struct(fields) => Self::struct_impl(fields),
+            BinaryKeyKind::Enum(variants) => (self.enum_impl(variants), Some(quote!(1))),
+        };
+
+        let expanded = quote! {
+            impl #impl_generics exonum_merkledb::BinaryKey for #name #ty_generics #where_clause {
+                #key_impl
+            }
+        };
+        tokens.extend(expanded);
+
+        if let Some(fixed_size) = fixed_size {
+            let expanded = quote! {
+                impl #impl_generics exonum_merkledb::FixedSizeKey
+                    for #name #ty_generics #where_clause
+                {
+                    const SIZE: usize = #fixed_size;
+                }
+            };
+            tokens.extend(expanded);
+        }
+    }
+}
+
+pub fn impl_binary_key(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    let binary_key = match BinaryKey::from_derive_input(&input) {
+        Ok(key) => key,
+        Err(e) => return e.write_errors().into(),
+    };
+    let tokens = quote!(#binary_key);
+    tokens.into()
+}
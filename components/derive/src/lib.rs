@@ -130,6 +130,58 @@ pub fn from_access(input: TokenStream) -> TokenStream {
     db_traits::impl_from_access(input)
 }
 
+/// Derive `BinaryKey` trait.
+///
+/// The macro can be applied to `struct`s with at least one field and to `enum`s without fields.
+/// The target type must implement `Clone`.
+///
+/// Struct fields are serialized one after another in the declaration order. Since keys
+/// are compared bytewise, the resulting key is sorted by the first field, then by the second
+/// field, and so on, provided that the serialization of each field preserves the ordering.
+/// To make the concatenation unambiguous, all fields except for the last one must implement
+/// `FixedSizeKey`; this is checked at compile time. The last field may have variable size
+/// (e.g., be a `String`) if it is marked with the `variable_size` attribute. The macro
+/// implements `FixedSizeKey` for the struct if all its fields have fixed size, so the struct
+/// may itself be used as a field of another composite key.
+///
+/// An enum is serialized as a single byte with the zero-based index of the variant
+/// in the declaration order; thus, the order of keys is the same as the one derived via
+/// `#[derive(PartialOrd, Ord)]`. The enum may have at most 256 variants.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Clone, BinaryKey)]
+/// enum Kind {
+///     Deposit,
+///     Withdrawal,
+/// }
+///
+/// #[derive(Clone, BinaryKey)]
+/// struct HistoryKey {
+///     owner: PublicKey,
+///     height: Height,
+///     kind: Kind,
+///     #[binary_key(variable_size)]
+///     memo: String,
+/// }
+/// ```
+///
+/// # Field Attributes
+///
+/// ## `variable_size`
+///
+/// ```text
+/// #[binary_key(variable_size)]
+/// ```
+///
+/// Allows the last field of the struct to have a type not implementing `FixedSizeKey`.
+/// The field occupies the remaining part of the key.
+#[proc_macro_derive(BinaryKey, attributes(binary_key))]
+pub fn binary_key(input: TokenStream) -> TokenStream {
+    db_traits::impl_binary_key(input)
+}
+
 /// Derive `ServiceDispatcher` trait.
 ///
 /// # Attributes:
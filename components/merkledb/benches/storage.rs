@@ -282,6 +282,18 @@ fn proof_map_insert_with_merge(b: &mut Bencher<'_>, len: usize) {
     );
 }
 
+fn proof_map_bulk_load(b: &mut Bencher<'_>, len: usize) {
+    let db = TemporaryDB::default();
+    let data = generate_random_kv(len);
+    b.iter_with_setup(
+        || (db.fork(), data.clone()),
+        |(fork, data)| {
+            let mut table = fork.get_proof_map::<_, Hash, Vec<u8>>(NAME);
+            table.bulk_load(data);
+        },
+    );
+}
+
 fn proof_list_index_build_proofs(b: &mut Bencher<'_>, len: usize) {
     let data = generate_random_values(len);
     let db = TemporaryDB::default();
@@ -434,6 +446,7 @@ pub fn bench_storage(c: &mut Criterion) {
         "storage/proof_map/insert/merge",
         proof_map_insert_with_merge,
     );
+    bench_fn(c, "storage/proof_map/bulk_load", proof_map_bulk_load);
     bench_fn(
         c,
         "storage/proof_map/proofs/build",
@@ -2,10 +2,14 @@
 
 use super::{Access, FromAccess};
 use crate::{
-    indexes::proof_map::{Raw, ToProofPath},
+    indexes::{
+        indexed_map::SecondaryIndexes,
+        proof_map::{Raw, ToProofPath},
+    },
     views::IndexType,
-    BinaryKey, BinaryValue, Entry, Group, IndexAddress, KeySetIndex, ListIndex, MapIndex,
-    ObjectHash, ProofEntry, ProofListIndex, ProofMapIndex, SparseListIndex, ValueSetIndex,
+    BinaryKey, BinaryValue, Entry, Group, IndexAddress, IndexedMap, KeySetIndex, ListIndex,
+    MapIndex, ObjectHash, ProofEntry, ProofKeySetIndex, ProofListIndex, ProofMapIndex,
+    SparseListIndex, ValueSetIndex,
 };
 
 /// Extension trait allowing for easy access to indexes from any type implementing
@@ -119,6 +123,22 @@ pub trait AccessExt: Access {
         MapIndex::from_access(self, addr.into()).unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
     }
 
+    /// Gets a map index with secondary indexes `S` with the specified address.
+    ///
+    /// # Panics
+    ///
+    /// If the index exists, but is not an indexed map, or has secondary indexes other than `S`.
+    fn get_indexed_map<I, K, V, S>(self, addr: I) -> IndexedMap<Self::Base, K, V, S>
+    where
+        I: Into<IndexAddress>,
+        K: BinaryKey + ?Sized,
+        V: BinaryValue,
+        S: SecondaryIndexes<V>,
+    {
+        IndexedMap::from_access(self, addr.into())
+            .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
+    }
+
     /// Gets a Merkelized list index with the specified address.
     ///
     /// # Panics
@@ -199,6 +219,38 @@ pub trait AccessExt: Access {
             .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
     }
 
+    /// Gets a Merkelized key set index with the specified address.
+    ///
+    /// # Panics
+    ///
+    /// If the index exists, but is not a Merkelized key set.
+    fn get_proof_key_set<I, K>(self, addr: I) -> ProofKeySetIndex<Self::Base, K>
+    where
+        I: Into<IndexAddress>,
+        K: BinaryKey + ObjectHash + ?Sized,
+    {
+        ProofKeySetIndex::from_access(self, addr.into())
+            .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
+    }
+
+    /// Gets a generic Merkelized key set. Requires explicit `KeyMode` to be specified.
+    ///
+    /// # Panics
+    ///
+    /// If the index exists, but is not a Merkelized key set.
+    fn get_generic_proof_key_set<I, K, KeyMode>(
+        self,
+        addr: I,
+    ) -> ProofKeySetIndex<Self::Base, K, KeyMode>
+    where
+        I: Into<IndexAddress>,
+        K: BinaryKey + ?Sized,
+        KeyMode: ToProofPath<K>,
+    {
+        ProofKeySetIndex::<_, _, KeyMode>::from_access(self, addr.into())
+            .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
+    }
+
     /// Gets a sparse list index with the specified address.
     ///
     /// # Panics
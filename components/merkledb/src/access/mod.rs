@@ -5,10 +5,10 @@ use failure::{Error, Fail};
 use std::{borrow::Cow, fmt};
 
 pub use self::extensions::AccessExt;
-pub use crate::views::{AsReadonly, RawAccess, RawAccessMut};
+pub use crate::views::{AsReadonly, GroupKeys, RawAccess, RawAccessMut};
 
 use crate::validation::assert_valid_name_component;
-use crate::views::{IndexAddress, IndexMetadata, IndexType, ViewWithMetadata};
+use crate::views::{IndexAddress, IndexMetadata, IndexType, ValueCompression, ViewWithMetadata};
 
 mod extensions;
 
@@ -52,6 +52,41 @@ pub trait Access: Clone {
         addr: IndexAddress,
         index_type: IndexType,
     ) -> Result<ViewWithMetadata<Self::Base>, AccessError>;
+
+    /// Gets or creates a generic view with the specified address. If the index is created,
+    /// its values are compressed with the specified algorithm; otherwise, the compression
+    /// recorded in the index metadata is used.
+    ///
+    /// The default implementation ignores `compression` and falls back
+    /// to [`get_or_create_view`](#tymethod.get_or_create_view).
+    fn get_or_create_compressed_view(
+        self,
+        addr: IndexAddress,
+        index_type: IndexType,
+        compression: ValueCompression,
+    ) -> Result<ViewWithMetadata<Self::Base>, AccessError> {
+        let _ = compression;
+        self.get_or_create_view(addr, index_type)
+    }
+}
+
+/// Access allowing to enumerate the indexes in a group.
+///
+/// This trait is implemented for all accesses provided by the crate and is sealed; i.e.,
+/// it cannot be implemented outside the crate. Thus, custom `Access` implementations
+/// are not required to support enumeration of groups. The trait is used as a bound by the
+/// iteration methods of [`Group`].
+///
+/// [`Group`]: ../indexes/group/struct.Group.html
+pub trait AccessGroups: Access + sealed::Sealed {
+    /// Iterates over keys of indexes in a group with the specified address, starting from
+    /// the serialized key `from` (inclusive). Keys are yielded in the serialized form.
+    fn group_keys(self, group_addr: IndexAddress, from: &[u8]) -> GroupKeys<Self::Base>;
+}
+
+pub(crate) mod sealed {
+    /// Marker trait preventing implementations of `AccessGroups` outside the crate.
+    pub trait Sealed {}
 }
 
 impl<T: RawAccess> Access for T {
@@ -68,6 +103,23 @@ impl<T: RawAccess> Access for T {
     ) -> Result<ViewWithMetadata<Self::Base>, AccessError> {
         ViewWithMetadata::get_or_create(self, &addr, index_type)
     }
+
+    fn get_or_create_compressed_view(
+        self,
+        addr: IndexAddress,
+        index_type: IndexType,
+        compression: ValueCompression,
+    ) -> Result<ViewWithMetadata<Self::Base>, AccessError> {
+        ViewWithMetadata::get_or_create_compressed(self, &addr, index_type, compression)
+    }
+}
+
+impl<T: RawAccess> sealed::Sealed for T {}
+
+impl<T: RawAccess> AccessGroups for T {
+    fn group_keys(self, group_addr: IndexAddress, from: &[u8]) -> GroupKeys<Self::Base> {
+        GroupKeys::new(self, &group_addr, from)
+    }
 }
 
 /// Access that prepends the specified prefix to each created view. The prefix is separated
@@ -126,6 +178,104 @@ impl<T: Access> Access for Prefixed<'_, T> {
         let prefixed_addr = addr.prepend_name(self.prefix.as_ref());
         self.access.get_or_create_view(prefixed_addr, index_type)
     }
+
+    fn get_or_create_compressed_view(
+        self,
+        addr: IndexAddress,
+        index_type: IndexType,
+        compression: ValueCompression,
+    ) -> Result<ViewWithMetadata<Self::Base>, AccessError> {
+        let prefixed_addr = addr.prepend_name(self.prefix.as_ref());
+        self.access
+            .get_or_create_compressed_view(prefixed_addr, index_type, compression)
+    }
+}
+
+impl<T: AccessGroups> sealed::Sealed for Prefixed<'_, T> {}
+
+impl<T: AccessGroups> AccessGroups for Prefixed<'_, T> {
+    fn group_keys(self, group_addr: IndexAddress, from: &[u8]) -> GroupKeys<Self::Base> {
+        let prefixed_addr = group_addr.prepend_name(self.prefix.as_ref());
+        self.access.group_keys(prefixed_addr, from)
+    }
+}
+
+/// Access that compresses values of the created indexes with the specified algorithm.
+///
+/// Compression is recorded in the metadata of an index when the index is created, so
+/// the index can be accessed afterwards in any other way (e.g., from a `Snapshot`);
+/// its values will be decompressed transparently. Conversely, if an index already exists,
+/// `Compressed` access uses the compression recorded in its metadata.
+///
+/// Compression does not influence hashes of Merkelized indexes. It is most useful for indexes
+/// with large values, such as documents or serialized proofs.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{
+///     access::{AccessExt, Compressed}, Database, TemporaryDB, ValueCompression,
+/// };
+///
+/// let db = TemporaryDB::new();
+/// let fork = db.fork();
+/// let compressed = Compressed::new(&fork, ValueCompression::Zstd);
+/// compressed.get_map("documents").put(&1_u64, "document".repeat(100));
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let documents = snapshot.get_map::<_, u64, String>("documents");
+/// assert_eq!(documents.get(&1).unwrap().len(), 800);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compressed<T> {
+    access: T,
+    compression: ValueCompression,
+}
+
+impl<T: Access> Compressed<T> {
+    /// Creates a new compressed access.
+    pub fn new(access: T, compression: ValueCompression) -> Self {
+        Self {
+            access,
+            compression,
+        }
+    }
+}
+
+impl<T: Access> Access for Compressed<T> {
+    type Base = T::Base;
+
+    fn get_index_metadata(self, addr: IndexAddress) -> Result<Option<IndexMetadata>, AccessError> {
+        self.access.get_index_metadata(addr)
+    }
+
+    fn get_or_create_view(
+        self,
+        addr: IndexAddress,
+        index_type: IndexType,
+    ) -> Result<ViewWithMetadata<Self::Base>, AccessError> {
+        self.access
+            .get_or_create_compressed_view(addr, index_type, self.compression)
+    }
+
+    fn get_or_create_compressed_view(
+        self,
+        addr: IndexAddress,
+        index_type: IndexType,
+        compression: ValueCompression,
+    ) -> Result<ViewWithMetadata<Self::Base>, AccessError> {
+        self.access
+            .get_or_create_compressed_view(addr, index_type, compression)
+    }
+}
+
+impl<T: AccessGroups> sealed::Sealed for Compressed<T> {}
+
+impl<T: AccessGroups> AccessGroups for Compressed<T> {
+    fn group_keys(self, group_addr: IndexAddress, from: &[u8]) -> GroupKeys<Self::Base> {
+        self.access.group_keys(group_addr, from)
+    }
 }
 
 /// Access error together with the location information.
@@ -188,6 +338,36 @@ pub enum AccessErrorKind {
     #[fail(display = "Invalid tombstone location. Tombstones can only be created in migrations")]
     InvalidTombstone,
 
+    /// Index does not exist.
+    #[fail(display = "Index does not exist")]
+    DoesNotExist,
+
+    /// Index already exists.
+    #[fail(display = "Index already exists")]
+    AlreadyExists,
+
+    /// Secondary indexes declared for an `IndexedMap` differ from the ones recorded
+    /// in the index metadata. Changing secondary indexes of an existing map requires
+    /// a data migration.
+    #[fail(
+        display = "Secondary indexes mismatch: expected {:?}, but got {:?}",
+        expected, actual
+    )]
+    SecondaryIndexesMismatch {
+        /// Names of secondary indexes declared for the map.
+        expected: Vec<String>,
+        /// Names of secondary indexes recorded in the index metadata.
+        actual: Vec<String>,
+    },
+
+    /// Values of the index are compressed with an algorithm not supported with the enabled
+    /// crate features.
+    #[fail(
+        display = "Unsupported value compression {:?}; enable the corresponding crate feature",
+        _0
+    )]
+    UnsupportedCompression(ValueCompression),
+
     /// Custom error.
     #[fail(display = "{}", _0)]
     Custom(#[fail(cause)] Error),
@@ -374,4 +554,51 @@ mod tests {
         }
         assert_eq!(fork.get_list::<_, u64>("foo").len(), 3);
     }
+
+    #[test]
+    fn compressed_access_works() {
+        use crate::{views::View, ObjectHash, ProofMapIndex};
+
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let value = "value".repeat(100);
+        {
+            let compressed = Compressed::new(&fork, ValueCompression::Zstd);
+            let mut map = compressed.get_proof_map::<_, u64, String>("compressed");
+            map.put(&1, value.clone());
+            map.put(&2, "short".to_owned());
+            compressed.get_map("docs").put(&1_u64, value.clone());
+            fork.get_proof_map("plain").put(&1_u64, value.clone());
+            fork.get_proof_map("plain").put(&2_u64, "short".to_owned());
+        }
+        db.merge_sync(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        // Compression is recorded in the metadata and applied for all accesses.
+        let map: ProofMapIndex<_, u64, String> = snapshot.get_proof_map("compressed");
+        assert_eq!(map.get(&1), Some(value.clone()));
+        assert_eq!(map.get(&2), Some("short".to_owned()));
+        let mut values: Vec<_> = map.values().collect();
+        values.sort();
+        assert_eq!(values, vec!["short".to_owned(), value.clone()]);
+        // Compression does not influence index hashes.
+        let plain: ProofMapIndex<_, u64, String> = snapshot.get_proof_map("plain");
+        assert_eq!(map.object_hash(), plain.object_hash());
+
+        // Values are stored compressed.
+        let metadata = (&snapshot).get_index_metadata("docs".into()).unwrap();
+        let metadata = metadata.unwrap();
+        assert_eq!(metadata.compression(), ValueCompression::Zstd);
+        let raw_view = View::new(&snapshot, metadata.resolve(&"docs".into()));
+        let raw_value: Vec<u8> = raw_view.get(&1_u64).unwrap();
+        assert!(raw_value.len() < value.len());
+
+        // Existing indexes retain their compression.
+        let fork = db.fork();
+        Compressed::new(&fork, ValueCompression::Zstd)
+            .get_proof_map::<_, u64, String>("plain")
+            .put(&3, value.clone());
+        let metadata = (&fork).get_index_metadata("plain".into()).unwrap();
+        assert_eq!(metadata.unwrap().compression(), ValueCompression::None);
+    }
 }
@@ -0,0 +1,493 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of the read cache over an arbitrary database.
+
+use std::{
+    collections::HashMap,
+    fmt, mem,
+    ops::Bound,
+    result::Result as StdResult,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use crate::{
+    db::{Iter, ViewChanges},
+    Database, Patch, ResolvedAddress, Result, Snapshot,
+};
+
+/// Approximate memory overhead of a single cache entry in bytes. Used for cache size accounting.
+const ENTRY_OVERHEAD: usize = 64;
+
+/// Statistics of a [`CachedDB`] cache.
+///
+/// [`CachedDB`]: struct.CachedDB.html
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of lookups served from the cache.
+    pub hits: u64,
+    /// Number of lookups forwarded to the underlying database.
+    pub misses: u64,
+    /// Number of merges that invalidated cache entries.
+    pub invalidations: u64,
+    /// Approximate size of the cached data in bytes.
+    pub size: usize,
+}
+
+/// Database wrapper caching the results of point reads (`Snapshot::get` and `Snapshot::contains`)
+/// in memory.
+///
+/// The cache is useful for nodes serving heavy read traffic, in which the same entries
+/// (e.g., the consensus configuration or the latest block) are read repeatedly.
+/// Iteration is not cached and is forwarded to the underlying database.
+///
+/// Cache entries changed by a merged patch are invalidated on each [`merge`]. Snapshots created
+/// before the merge continue to observe the database state as of their creation. The wrapped
+/// database must not be modified by the means other than `CachedDB`; otherwise, the cache
+/// may return stale data.
+///
+/// The cache size is bounded by the `capacity` in bytes specified on creation. When the cache
+/// is full, the least recently used entries are evicted. Eviction is approximate: entries
+/// are split into two generations, and the older generation is dropped as a whole.
+///
+/// [`merge`]: trait.Database.html#tymethod.merge
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{access::AccessExt, CachedDB, Database, TemporaryDB};
+///
+/// let db = CachedDB::new(TemporaryDB::new(), 1 << 20);
+/// let fork = db.fork();
+/// fork.get_entry("entry").set(42_u64);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// for _ in 0..10 {
+///     let snapshot = db.snapshot();
+///     assert_eq!(snapshot.get_entry::<_, u64>("entry").get(), Some(42));
+/// }
+/// let stats = db.stats();
+/// assert!(stats.hits > stats.misses);
+/// ```
+pub struct CachedDB<T> {
+    inner: T,
+    cache: Arc<Mutex<Cache>>,
+    // Taken for writing during merges and for reading during snapshot creation, so that
+    // the snapshot of the inner database and the cache epoch are consistent.
+    merge_lock: RwLock<()>,
+}
+
+impl<T: Database> CachedDB<T> {
+    /// Wraps the database with a read cache. `capacity` specifies the maximum cache size
+    /// in bytes.
+    pub fn new(inner: T, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(Cache::new(capacity))),
+            merge_lock: RwLock::new(()),
+        }
+    }
+
+    /// Returns a reference to the wrapped database.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns cache statistics accumulated since the database creation.
+    pub fn stats(&self) -> CacheStats {
+        let cache = self.cache.lock().unwrap();
+        CacheStats {
+            size: cache.size(),
+            ..cache.stats
+        }
+    }
+
+    /// Removes all entries from the cache. Statistics are retained.
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn merge_with<F>(&self, patch: Patch, merge: F) -> Result<()>
+    where
+        F: FnOnce(&T, Patch) -> Result<()>,
+    {
+        let invalidated: Vec<_> = patch
+            .changes()
+            .map(|(address, changes)| (address.to_owned(), Invalidation::new(changes)))
+            .collect();
+
+        let _guard = self.merge_lock.write().unwrap();
+        merge(&self.inner, patch)?;
+        self.cache.lock().unwrap().invalidate(&invalidated);
+        Ok(())
+    }
+}
+
+impl<T: Database> Database for CachedDB<T> {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        let _guard = self.merge_lock.read().unwrap();
+        let inner = self.inner.snapshot();
+        let epoch = self.cache.lock().unwrap().epoch;
+        Box::new(CachedSnapshot {
+            inner,
+            cache: Arc::clone(&self.cache),
+            epoch,
+        })
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.merge_with(patch, T::merge)
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.merge_with(patch, T::merge_sync)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CachedDB<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cache = self.cache.lock().unwrap();
+        formatter
+            .debug_struct("CachedDB")
+            .field("inner", &self.inner)
+            .field("capacity", &cache.capacity)
+            .field("stats", &cache.stats)
+            .finish()
+    }
+}
+
+impl<T: Database> From<CachedDB<T>> for Arc<dyn Database> {
+    fn from(db: CachedDB<T>) -> Self {
+        Arc::new(db)
+    }
+}
+
+/// Snapshot of a `CachedDB`.
+struct CachedSnapshot {
+    inner: Box<dyn Snapshot>,
+    cache: Arc<Mutex<Cache>>,
+    /// Cache epoch as of the snapshot creation.
+    epoch: u64,
+}
+
+impl fmt::Debug for CachedSnapshot {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("CachedSnapshot")
+            .field("epoch", &self.epoch)
+            .finish()
+    }
+}
+
+impl Snapshot for CachedSnapshot {
+    fn get(&self, name: &ResolvedAddress, key: &[u8]) -> Option<Vec<u8>> {
+        if let Ok(value) = self.cache.lock().unwrap().get(name, key, self.epoch) {
+            return value;
+        }
+        let value = self.inner.get(name, key);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(name, key, value.clone(), self.epoch);
+        value
+    }
+
+    fn contains(&self, name: &ResolvedAddress, key: &[u8]) -> bool {
+        if let Ok(value) = self.cache.lock().unwrap().get(name, key, self.epoch) {
+            return value.is_some();
+        }
+        self.inner.contains(name, key)
+    }
+
+    fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_> {
+        self.inner.iter(name, from)
+    }
+
+    fn iter_rev(&self, name: &ResolvedAddress, upper_bound: Bound<&[u8]>) -> Iter<'_> {
+        self.inner.iter_rev(name, upper_bound)
+    }
+}
+
+/// Cache entries invalidated in a single view by a merged patch.
+enum Invalidation {
+    /// All entries of the view are invalidated.
+    All,
+    /// Only the specified keys are invalidated.
+    Keys(Vec<Vec<u8>>),
+}
+
+impl Invalidation {
+    fn new(changes: &ViewChanges) -> Self {
+        if changes.is_cleared() || changes.has_removed_ranges() {
+            Invalidation::All
+        } else {
+            Invalidation::Keys(changes.data.keys().cloned().collect())
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    value: Option<Vec<u8>>,
+    /// Cache epoch at which the entry was inserted. The entry is valid for all snapshots
+    /// created at this epoch or later.
+    epoch: u64,
+}
+
+impl CacheEntry {
+    fn size(&self, key: &[u8]) -> usize {
+        ENTRY_OVERHEAD + key.len() + self.value.as_ref().map_or(0, Vec::len)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Generation {
+    entries: HashMap<ResolvedAddress, HashMap<Vec<u8>, CacheEntry>>,
+    size: usize,
+}
+
+impl Generation {
+    fn get(&self, name: &ResolvedAddress, key: &[u8]) -> Option<&CacheEntry> {
+        self.entries.get(name)?.get(key)
+    }
+
+    fn take(&mut self, name: &ResolvedAddress, key: &[u8]) -> Option<CacheEntry> {
+        let view_entries = self.entries.get_mut(name)?;
+        let entry = view_entries.remove(key)?;
+        if view_entries.is_empty() {
+            self.entries.remove(name);
+        }
+        self.size -= entry.size(key);
+        Some(entry)
+    }
+
+    fn insert(&mut self, name: &ResolvedAddress, key: Vec<u8>, entry: CacheEntry) {
+        self.size += entry.size(&key);
+        if !self.entries.contains_key(name) {
+            self.entries.insert(name.to_owned(), HashMap::new());
+        }
+        let view_entries = self.entries.get_mut(name).unwrap();
+        if let Some(old_entry) = view_entries.insert(key.clone(), entry) {
+            self.size -= old_entry.size(&key);
+        }
+    }
+
+    fn invalidate(&mut self, name: &ResolvedAddress, invalidation: &Invalidation) {
+        match invalidation {
+            Invalidation::All => {
+                if let Some(view_entries) = self.entries.remove(name) {
+                    for (key, entry) in &view_entries {
+                        self.size -= entry.size(key);
+                    }
+                }
+            }
+            Invalidation::Keys(keys) => {
+                for key in keys {
+                    self.take(name, key);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Cache {
+    /// Number of merges performed since the cache creation.
+    epoch: u64,
+    capacity: usize,
+    /// Recently used entries.
+    current: Generation,
+    /// Entries that were not used since the last generation switch. Used entries
+    /// are moved to the current generation.
+    previous: Generation,
+    stats: CacheStats,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            epoch: 0,
+            capacity,
+            current: Generation::default(),
+            previous: Generation::default(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.current.size + self.previous.size
+    }
+
+    /// Looks up a value valid for the snapshot with the specified epoch. Returns `Err(_)`
+    /// if the value is not cached and should be retrieved from the underlying database.
+    fn get(
+        &mut self,
+        name: &ResolvedAddress,
+        key: &[u8],
+        epoch: u64,
+    ) -> StdResult<Option<Vec<u8>>, ()> {
+        if self.current.get(name, key).is_none() {
+            if let Some(entry) = self.previous.take(name, key) {
+                self.current.insert(name, key.to_vec(), entry);
+                self.switch_generation_if_full();
+            }
+        }
+
+        let value = self
+            .current
+            .get(name, key)
+            .filter(|entry| entry.epoch <= epoch)
+            .map(|entry| entry.value.clone())
+            .ok_or(());
+        if value.is_ok() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        value
+    }
+
+    /// Caches a value read by the snapshot with the specified epoch. The value is ignored
+    /// if the snapshot is outdated, i.e., if patches were merged since its creation.
+    fn insert(&mut self, name: &ResolvedAddress, key: &[u8], value: Option<Vec<u8>>, epoch: u64) {
+        if epoch != self.epoch {
+            return;
+        }
+        let entry = CacheEntry { value, epoch };
+        if entry.size(key) > self.capacity / 2 {
+            return;
+        }
+        self.current.insert(name, key.to_vec(), entry);
+        self.switch_generation_if_full();
+    }
+
+    fn switch_generation_if_full(&mut self) {
+        if self.current.size > self.capacity / 2 {
+            self.previous = mem::replace(&mut self.current, Generation::default());
+        }
+    }
+
+    fn invalidate(&mut self, invalidated: &[(ResolvedAddress, Invalidation)]) {
+        for (name, invalidation) in invalidated {
+            self.current.invalidate(name, invalidation);
+            self.previous.invalidate(name, invalidation);
+        }
+        self.epoch += 1;
+        self.stats.invalidations += 1;
+    }
+
+    fn clear(&mut self) {
+        self.current = Generation::default();
+        self.previous = Generation::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{access::AccessExt, TemporaryDB};
+
+    #[test]
+    fn cache_hits_and_misses() {
+        let db = CachedDB::new(TemporaryDB::new(), 1 << 20);
+        let fork = db.fork();
+        fork.get_entry("entry").set(1_u64);
+        db.merge(fork.into_patch()).unwrap();
+        let stats_after_merge = db.stats();
+
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get_entry::<_, u64>("entry").get(), Some(1));
+        let stats = db.stats();
+        assert!(stats.misses > stats_after_merge.misses);
+        assert!(stats.size > 0);
+        assert_eq!(stats.invalidations, 1);
+
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get_entry::<_, u64>("entry").get(), Some(1));
+        let new_stats = db.stats();
+        assert_eq!(new_stats.misses, stats.misses);
+        assert!(new_stats.hits > stats.hits);
+
+        // Absent values are cached as well.
+        let name = ResolvedAddress::system("absent");
+        assert_eq!(snapshot.get(&name, &[1]), None);
+        assert!(!snapshot.contains(&name, &[1]));
+        assert_eq!(db.stats().misses, new_stats.misses + 1);
+    }
+
+    #[test]
+    fn cache_is_invalidated_on_merge() {
+        let db = CachedDB::new(TemporaryDB::new(), 1 << 20);
+        let fork = db.fork();
+        fork.get_map("map").put(&1_u8, 1_u64);
+        fork.get_map("map").put(&2_u8, 2_u64);
+        fork.get_entry("entry").set(1_u64);
+        db.merge(fork.into_patch()).unwrap();
+
+        let old_snapshot = db.snapshot();
+        assert_eq!(old_snapshot.get_entry::<_, u64>("entry").get(), Some(1));
+        assert_eq!(old_snapshot.get_map::<_, u8, u64>("map").get(&1), Some(1));
+
+        let fork = db.fork();
+        fork.get_entry("entry").set(2_u64);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get_entry::<_, u64>("entry").get(), Some(2));
+        assert_eq!(snapshot.get_map::<_, u8, u64>("map").get(&1), Some(1));
+        // The old snapshot must not observe values cached by newer snapshots.
+        assert_eq!(old_snapshot.get_entry::<_, u64>("entry").get(), Some(1));
+
+        let fork = db.fork();
+        fork.get_map::<_, u8, u64>("map").clear();
+        db.merge(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get_map::<_, u8, u64>("map").get(&1), None);
+        assert_eq!(old_snapshot.get_map::<_, u8, u64>("map").get(&2), Some(2));
+        assert_eq!(snapshot.get_entry::<_, u64>("entry").get(), Some(2));
+    }
+
+    #[test]
+    fn cache_size_is_bounded() {
+        const CAPACITY: usize = 4_096;
+
+        let db = CachedDB::new(TemporaryDB::new(), CAPACITY);
+        let fork = db.fork();
+        {
+            let mut map = fork.get_map("map");
+            for i in 0..1_000_u32 {
+                map.put(&i, vec![0_u8; 32]);
+            }
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map = snapshot.get_map::<_, u32, Vec<u8>>("map");
+        for i in 0..1_000 {
+            assert_eq!(map.get(&i), Some(vec![0; 32]));
+            assert!(db.stats().size <= CAPACITY);
+        }
+
+        // Recently read entries are retained in the cache.
+        let misses = db.stats().misses;
+        assert_eq!(map.get(&999), Some(vec![0; 32]));
+        assert_eq!(db.stats().misses, misses);
+
+        db.clear_cache();
+        assert_eq!(db.stats().size, 0);
+        assert_eq!(map.get(&999), Some(vec![0; 32]));
+        assert_eq!(map.get(&999), Some(vec![0; 32]));
+        assert_eq!(db.stats().misses, misses + 1);
+    }
+}
@@ -16,16 +16,27 @@
 
 pub use rocksdb::{BlockBasedOptions as RocksBlockOptions, WriteOptions as RocksDBWriteOptions};
 
-use std::{fmt, iter::Peekable, mem, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    iter::Peekable,
+    mem,
+    ops::Bound,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
 use rocksdb::{
-    self, checkpoint::Checkpoint, ColumnFamily, DBIterator, Options as RocksDbOptions, WriteBatch,
+    self, checkpoint::Checkpoint, ColumnFamilyDescriptor, ColumnFamilyRef, DBIterator, Direction,
+    IteratorMode, Options as RocksDbOptions, WriteBatch,
 };
 use smallvec::SmallVec;
 
 use crate::{
-    db::{check_database, Change},
-    Database, DbOptions, Iter, Iterator, Patch, ResolvedAddress, Snapshot,
+    db::{check_database, check_readonly_database, Change},
+    options::ColumnFamilyOptions,
+    views::IndexesPool,
+    Database, DbOptions, IndexType, Iter, Iterator, Patch, ResolvedAddress, Snapshot,
 };
 
 /// Size of a byte representation of an index ID, which is used to prefix index keys
@@ -38,9 +49,28 @@ const ID_SIZE: usize = mem::size_of::<u64>();
 /// `RocksDB` is an embedded database for key-value data, which is optimized for fast storage.
 /// This structure is required to potentially adapt the interface to
 /// use different databases.
+///
+/// Besides the usual read-write mode, the database can be opened in the [read-only]
+/// or [secondary] mode. These modes allow other processes (e.g., explorers or exporters)
+/// to read the data of a database used by an Exonum node without stopping the node.
+///
+/// [read-only]: #method.open_read_only
+/// [secondary]: #method.open_as_secondary
 pub struct RocksDB {
-    db: Arc<rocksdb::DB>,
+    /// Database handle, which is shared with each snapshot of the database. The lock is held
+    /// only while creating snapshots or column families; snapshots do not hold it.
+    /// The number of strong references to the handle tells whether there are live snapshots.
+    db: Mutex<Arc<rocksdb::DB>>,
     options: DbOptions,
+    access_mode: AccessMode,
+}
+
+/// Mode in which a `RocksDB` instance is opened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AccessMode {
+    ReadWrite,
+    ReadOnly,
+    Secondary,
 }
 
 impl From<DbOptions> for RocksDbOptions {
@@ -53,18 +83,63 @@ impl From<&DbOptions> for RocksDbOptions {
     fn from(opts: &DbOptions) -> Self {
         let mut defaults = Self::default();
         defaults.create_if_missing(opts.create_if_missing);
-        defaults.set_compression_type(opts.compression_type.into());
         defaults.set_max_open_files(opts.max_open_files.unwrap_or(-1));
+        if let Some(jobs) = opts.max_background_compactions {
+            defaults.set_max_background_compactions(jobs);
+        }
+        if let Some(jobs) = opts.max_background_flushes {
+            defaults.set_max_background_flushes(jobs);
+        }
+        if let Some(size) = opts.max_total_wal_size {
+            defaults.set_max_total_wal_size(size);
+        }
+        if let Some(ref wal_dir) = opts.wal_dir {
+            defaults.set_wal_dir(wal_dir);
+        }
+        // Options for column families not covered by overrides (e.g., the default one).
+        opts.column_family_options("", None).apply_to(&mut defaults);
         defaults
     }
 }
 
+impl ColumnFamilyOptions {
+    fn apply_to(self, options: &mut RocksDbOptions) {
+        options.set_compression_type(self.compression_type.into());
+        if let Some(size) = self.write_buffer_size {
+            options.set_write_buffer_size(size);
+        }
+        if let Some(number) = self.max_write_buffer_number {
+            options.set_max_write_buffer_number(number);
+        }
+        if self.block_cache_size.is_some() || self.bloom_filter_bits_per_key.is_some() {
+            let mut block_options = RocksBlockOptions::default();
+            if let Some(size) = self.block_cache_size {
+                block_options.set_lru_cache(size);
+            }
+            if let Some(bits_per_key) = self.bloom_filter_bits_per_key {
+                block_options.set_bloom_filter(bits_per_key, false);
+            }
+            options.set_block_based_table_factory(&block_options);
+        }
+    }
+}
+
 /// A snapshot of a `RocksDB`.
 pub struct RocksDBSnapshot {
-    snapshot: rocksdb::Snapshot<'static>,
+    // `inner` is declared before `db`, so that it is dropped first.
+    inner: SnapshotInner,
     db: Arc<rocksdb::DB>,
 }
 
+/// Source of data for a `RocksDBSnapshot`.
+enum SnapshotInner {
+    /// Native snapshot of a database opened in the read-write mode.
+    Native(rocksdb::Snapshot<'static>),
+    /// The database itself, which cannot change while the snapshot is alive. Used for
+    /// read-only and secondary instances; the latter do not support native snapshots.
+    Database,
+}
+
 /// An iterator over the entries of a `RocksDB`.
 struct RocksDBIterator<'a> {
     iter: Peekable<DBIterator<'a>>,
@@ -81,24 +156,174 @@ impl RocksDB {
     /// `create_if_missing` is switched on in `DbOptions`, a new database will
     /// be created at the indicated path.
     pub fn open<P: AsRef<Path>>(path: P, options: &DbOptions) -> crate::Result<Self> {
+        let path = path.as_ref();
         let inner = {
-            if let Ok(names) = rocksdb::DB::list_cf(&RocksDbOptions::default(), &path) {
-                let cf_names = names.iter().map(String::as_str).collect::<Vec<_>>();
-                rocksdb::DB::open_cf(&options.into(), path, cf_names)?
+            if let Ok(names) = rocksdb::DB::list_cf(&RocksDbOptions::default(), path) {
+                let index_types = if options.has_index_type_overrides() {
+                    Self::read_index_types(path, options)?
+                } else {
+                    HashMap::new()
+                };
+                let descriptors = names
+                    .iter()
+                    .map(|name| {
+                        let index_type = index_types.get(name).cloned();
+                        let cf_options = Self::column_family_options(options, name, index_type);
+                        ColumnFamilyDescriptor::new(name.as_str(), cf_options)
+                    })
+                    .collect::<Vec<_>>();
+                rocksdb::DB::open_cf_descriptors(&options.into(), path, descriptors)?
             } else {
                 rocksdb::DB::open(&options.into(), path)?
             }
         };
-        let mut db = Self {
-            db: Arc::new(inner),
-            options: *options,
-        };
+        let mut db = Self::new(inner, options, AccessMode::ReadWrite);
         check_database(&mut db)?;
         Ok(db)
     }
 
-    pub(super) fn rocksdb(&self) -> &rocksdb::DB {
-        self.db.as_ref()
+    /// Opens an existing database stored at the specified path in the read-only mode.
+    ///
+    /// The database may be concurrently used by another process (e.g., an Exonum node)
+    /// in the read-write mode. The opened instance observes the state of the database
+    /// at the moment of opening; changes made afterwards by other processes are not visible.
+    /// Merging patches into the database returns an error.
+    pub fn open_read_only<P: AsRef<Path>>(path: P, options: &DbOptions) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let names = rocksdb::DB::list_cf(&RocksDbOptions::default(), path)?;
+        let inner = rocksdb::DB::open_cf_for_read_only(&options.into(), path, &names, false)?;
+        let db = Self::new(inner, options, AccessMode::ReadOnly);
+        check_readonly_database(&db)?;
+        Ok(db)
+    }
+
+    /// Opens an existing database stored at `primary_path` as a secondary instance.
+    /// The secondary instance stores its information logs at `secondary_path`.
+    ///
+    /// Similar to the [read-only] mode, the database may be concurrently used by the primary
+    /// instance (e.g., an Exonum node), and merging patches into the secondary instance
+    /// returns an error. Unlike the read-only mode, the secondary instance can
+    /// [catch up] with the primary.
+    ///
+    /// Secondary instances require the `max_open_files` option to be set to `None`
+    /// (i.e., the number of open files is not limited), which is the default.
+    ///
+    /// [read-only]: #method.open_read_only
+    /// [catch up]: #method.try_catch_up_with_primary
+    pub fn open_as_secondary<P, Q>(
+        primary_path: P,
+        secondary_path: Q,
+        options: &DbOptions,
+    ) -> crate::Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let primary_path = primary_path.as_ref();
+        let names = rocksdb::DB::list_cf(&RocksDbOptions::default(), primary_path)?;
+        let inner = rocksdb::DB::open_cf_as_secondary(
+            &options.into(),
+            primary_path,
+            secondary_path.as_ref(),
+            &names,
+        )?;
+        let db = Self::new(inner, options, AccessMode::Secondary);
+        check_readonly_database(&db)?;
+        Ok(db)
+    }
+
+    fn new(inner: rocksdb::DB, options: &DbOptions, access_mode: AccessMode) -> Self {
+        Self {
+            db: Mutex::new(Arc::new(inner)),
+            options: options.clone(),
+            access_mode,
+        }
+    }
+
+    /// Returns `true` if the database was opened in the read-only or secondary mode,
+    /// i.e., patches cannot be merged into it.
+    pub fn is_read_only(&self) -> bool {
+        self.access_mode != AccessMode::ReadWrite
+    }
+
+    /// Makes changes written by the primary instance visible to this secondary instance.
+    /// Snapshots created after this call will reflect these changes.
+    ///
+    /// Column families created by the primary after the secondary instance was opened
+    /// are not picked up; the indexes stored in them will appear empty until the secondary
+    /// instance is reopened.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database was not [opened as a secondary instance], or if there
+    /// are live snapshots of the database. (The secondary instance cannot provide snapshot
+    /// isolation otherwise.) An error is also returned if the underlying `RocksDB`
+    /// call fails.
+    ///
+    /// [opened as a secondary instance]: #method.open_as_secondary
+    pub fn try_catch_up_with_primary(&self) -> crate::Result<()> {
+        if self.access_mode != AccessMode::Secondary {
+            return Err(crate::Error::new(
+                "Only secondary database instances can catch up with the primary",
+            ));
+        }
+
+        // Holding the lock ensures that no snapshots are created during the catch-up.
+        let db = self.lock_db();
+        if Arc::strong_count(&db) > 1 {
+            return Err(crate::Error::new(
+                "Cannot catch up with the primary while there are live database snapshots",
+            ));
+        }
+        db.try_catch_up_with_primary()?;
+        Ok(())
+    }
+
+    /// Reads the types of indexes stored in each column family of an existing database.
+    /// The database is temporarily opened in the read-only mode.
+    ///
+    /// If indexes of different types share a column family, the type of an arbitrary index
+    /// is returned.
+    fn read_index_types(
+        path: &Path,
+        options: &DbOptions,
+    ) -> crate::Result<HashMap<String, IndexType>> {
+        let db = Self::open_read_only(path, options)?;
+        let snapshot = db.snapshot();
+
+        let mut index_types = HashMap::new();
+        for (address, metadata) in IndexesPool::new(&*snapshot).iter() {
+            if metadata.index_type() != IndexType::Tombstone {
+                index_types
+                    .entry(metadata.resolve(&address).name)
+                    .or_insert_with(|| metadata.index_type());
+            }
+        }
+        Ok(index_types)
+    }
+
+    /// Returns `RocksDB` options for a column family with the specified name, which stores
+    /// indexes of the specified type.
+    fn column_family_options(
+        options: &DbOptions,
+        name: &str,
+        index_type: Option<IndexType>,
+    ) -> RocksDbOptions {
+        let mut cf_options = RocksDbOptions::from(options);
+        options
+            .column_family_options(name, index_type)
+            .apply_to(&mut cf_options);
+        cf_options
+    }
+
+    pub(super) fn rocksdb(&self) -> Arc<rocksdb::DB> {
+        Arc::clone(&self.lock_db())
+    }
+
+    fn lock_db(&self) -> MutexGuard<'_, Arc<rocksdb::DB>> {
+        self.db
+            .lock()
+            .expect("Failed to acquire lock on the database")
     }
 
     /// Creates checkpoint of this database in the given directory. See [RocksDB docs] for
@@ -108,24 +333,46 @@ impl RocksDB {
     ///
     /// [RocksDB docs]: https://github.com/facebook/rocksdb/wiki/Checkpoints
     pub fn create_checkpoint<T: AsRef<Path>>(&self, path: T) -> crate::Result<()> {
-        let checkpoint = Checkpoint::new(&*self.db)?;
+        let db = self.rocksdb();
+        let checkpoint = Checkpoint::new(&*db)?;
         checkpoint.create_checkpoint(path)?;
         Ok(())
     }
 
     fn do_merge(&self, patch: Patch, w_opts: &RocksDBWriteOptions) -> crate::Result<()> {
+        if self.is_read_only() {
+            return Err(crate::Error::new(
+                "Cannot merge changes into a database opened in the read-only mode",
+            ));
+        }
+
+        let changes = patch.into_changes();
+        let db = {
+            // The lock prevents concurrent merges from creating the same column family.
+            let db = self.lock_db();
+            for (resolved, changes) in &changes {
+                if db.cf_handle(&resolved.name).is_none() {
+                    let cf_options = Self::column_family_options(
+                        &self.options,
+                        &resolved.name,
+                        changes.index_type(),
+                    );
+                    db.create_cf(&resolved.name, &cf_options)?;
+                }
+            }
+            Arc::clone(&db)
+        };
+
+        let db: &rocksdb::DB = &db;
         let mut batch = WriteBatch::default();
-        for (resolved, changes) in patch.into_changes() {
-            let cf = match self.db.cf_handle(&resolved.name) {
-                Some(cf) => cf,
-                None => self
-                    .db
-                    .create_cf(&resolved.name, &self.options.into())
-                    .unwrap(),
-            };
+        for (resolved, changes) in changes {
+            let cf = db.cf_handle(&resolved.name).unwrap();
 
             if changes.is_cleared() {
-                self.clear_prefix(&mut batch, cf, &resolved)?;
+                Self::clear_prefix(db, &mut batch, cf, &resolved);
+            }
+            for (start, end) in changes.removed_ranges() {
+                Self::delete_range(db, &mut batch, cf, &resolved, start, end);
             }
 
             if let Some(id_bytes) = resolved.id_to_bytes() {
@@ -141,71 +388,134 @@ impl RocksDB {
                     buffer.truncate(ID_SIZE);
                     buffer.extend_from_slice(&key);
                     match change {
-                        Change::Put(ref value) => batch.put_cf(cf, &buffer, value)?,
-                        Change::Delete => batch.delete_cf(cf, &buffer)?,
+                        Change::Put(ref value) => batch.put_cf(cf, &buffer, value),
+                        Change::Delete => batch.delete_cf(cf, &buffer),
                     }
                 }
             } else {
                 // Write changes to the column family as-is.
                 for (key, change) in changes.into_data() {
                     match change {
-                        Change::Put(ref value) => batch.put_cf(cf, &key, value)?,
-                        Change::Delete => batch.delete_cf(cf, &key)?,
+                        Change::Put(ref value) => batch.put_cf(cf, &key, value),
+                        Change::Delete => batch.delete_cf(cf, &key),
                     }
                 }
             }
         }
 
-        self.db.write_opt(batch, w_opts).map_err(Into::into)
+        db.write_opt(batch, w_opts).map_err(Into::into)
     }
 
     /// Removes all keys with a specified prefix from a column family.
     fn clear_prefix(
-        &self,
+        db: &rocksdb::DB,
         batch: &mut WriteBatch,
-        cf: ColumnFamily<'_>,
+        cf: ColumnFamilyRef<'_>,
         resolved: &ResolvedAddress,
-    ) -> crate::Result<()> {
-        let snapshot = self.rocksdb_snapshot();
-        let mut iterator = snapshot.rocksdb_iter(resolved, &[]);
+    ) {
+        let from = resolved.keyed(&[]);
+        let iter = db.iterator_cf(cf, IteratorMode::From(from.as_ref(), Direction::Forward));
+        let mut iterator = RocksDBIterator::new(iter.peekable(), resolved);
         while iterator.next().is_some() {
             // The ID prefix is already included to the keys yielded by the iterator.
-            batch.delete_cf(cf, iterator.key.as_ref().unwrap())?;
+            batch.delete_cf(cf, iterator.key.as_ref().unwrap());
+        }
+    }
+
+    /// Removes keys within the range `start..end` from a column family. If `end` is `None`,
+    /// the range is unbounded.
+    fn delete_range(
+        db: &rocksdb::DB,
+        batch: &mut WriteBatch,
+        cf: ColumnFamilyRef<'_>,
+        resolved: &ResolvedAddress,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) {
+        let end = match end {
+            Some(end) => Bound::Excluded(end),
+            None => Bound::Unbounded,
+        };
+        let start = resolved.keyed(start);
+        if let Bound::Excluded(end) = resolved.keyed_upper_bound(end) {
+            batch.delete_range_cf(cf, &start[..], &end[..]);
+        } else {
+            // There is no upper bound for keys in the range, so we remove them one by one.
+            let iter = db.iterator_cf(cf, IteratorMode::From(start.as_ref(), Direction::Forward));
+            let mut iterator = RocksDBIterator::new(iter.peekable(), resolved);
+            while iterator.next().is_some() {
+                // The ID prefix is already included to the keys yielded by the iterator.
+                batch.delete_cf(cf, iterator.key.as_ref().unwrap());
+            }
         }
-        Ok(())
     }
 
     #[allow(unsafe_code)]
     fn rocksdb_snapshot(&self) -> RocksDBSnapshot {
-        RocksDBSnapshot {
+        let db = self.rocksdb();
+        let inner = if self.is_read_only() {
+            SnapshotInner::Database
+        } else {
             // SAFETY:
             // The snapshot carries an `Arc` to the database to make sure that database
-            // is not dropped before the snapshot.
-            snapshot: unsafe { mem::transmute(self.db.snapshot()) },
-            db: Arc::clone(&self.db),
-        }
+            // is not dropped before the snapshot. The database is never borrowed mutably;
+            // column families are created through a shared reference.
+            SnapshotInner::Native(unsafe { mem::transmute(db.snapshot()) })
+        };
+        RocksDBSnapshot { inner, db }
     }
 }
 
 impl RocksDBSnapshot {
-    fn rocksdb_iter(&self, name: &ResolvedAddress, from: &[u8]) -> RocksDBIterator<'_> {
-        use rocksdb::{Direction, IteratorMode};
+    fn iterator_cf(&self, cf: ColumnFamilyRef<'_>, mode: IteratorMode<'_>) -> DBIterator<'_> {
+        match self.inner {
+            SnapshotInner::Native(ref snapshot) => snapshot.iterator_cf(cf, mode),
+            SnapshotInner::Database => self.db.iterator_cf(cf, mode),
+        }
+    }
+
+    fn iterator(&self, mode: IteratorMode<'_>) -> DBIterator<'_> {
+        match self.inner {
+            SnapshotInner::Native(ref snapshot) => snapshot.iterator(mode),
+            SnapshotInner::Database => self.db.iterator(mode),
+        }
+    }
 
+    fn rocksdb_iter(&self, name: &ResolvedAddress, from: &[u8]) -> RocksDBIterator<'_> {
         let from = name.keyed(from);
         let iter = match self.db.cf_handle(&name.name) {
-            Some(cf) => self
-                .snapshot
-                .iterator_cf(cf, IteratorMode::From(from.as_ref(), Direction::Forward))
-                .unwrap(),
-            None => self.snapshot.iterator(IteratorMode::Start),
+            Some(cf) => self.iterator_cf(cf, IteratorMode::From(from.as_ref(), Direction::Forward)),
+            None => self.iterator(IteratorMode::Start),
         };
-        RocksDBIterator {
-            iter: iter.peekable(),
-            prefix: name.id_to_bytes(),
-            key: None,
-            value: None,
-            ended: false,
+        RocksDBIterator::new(iter.peekable(), name)
+    }
+
+    fn rocksdb_iter_rev(
+        &self,
+        name: &ResolvedAddress,
+        upper_bound: Bound<&[u8]>,
+    ) -> RocksDBIterator<'_> {
+        let upper_bound = name.keyed_upper_bound(upper_bound);
+        let mode = match upper_bound {
+            Bound::Included(ref key) | Bound::Excluded(ref key) => {
+                IteratorMode::From(key, Direction::Reverse)
+            }
+            Bound::Unbounded => IteratorMode::End,
+        };
+        let iter = match self.db.cf_handle(&name.name) {
+            Some(cf) => self.iterator_cf(cf, mode),
+            None => self.iterator(IteratorMode::End),
+        };
+
+        let mut iter = iter.peekable();
+        // The iterator is positioned at the last key not greater than the bound,
+        // so we need to skip this key if the bound is exclusive.
+        if let Bound::Excluded(ref key) = upper_bound {
+            if iter.peek().map_or(false, |(k, _)| k[..] == key[..]) {
+                iter.next();
+            }
         }
+        RocksDBIterator::new(iter, name)
     }
 }
 
@@ -228,19 +538,37 @@ impl Database for RocksDB {
 
 impl Snapshot for RocksDBSnapshot {
     fn get(&self, resolved_addr: &ResolvedAddress, key: &[u8]) -> Option<Vec<u8>> {
-        if let Some(cf) = self.db.cf_handle(&resolved_addr.name) {
-            match self.snapshot.get_cf(cf, resolved_addr.keyed(key)) {
-                Ok(value) => value.map(|v| v.to_vec()),
-                Err(e) => panic!(e),
-            }
-        } else {
-            None
+        let cf = self.db.cf_handle(&resolved_addr.name)?;
+        let key = resolved_addr.keyed(key);
+        let value = match self.inner {
+            SnapshotInner::Native(ref snapshot) => snapshot.get_cf(cf, key),
+            SnapshotInner::Database => self.db.get_cf(cf, key),
+        };
+        match value {
+            Ok(value) => value,
+            Err(e) => panic!(e),
         }
     }
 
     fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_> {
         Box::new(self.rocksdb_iter(name, from))
     }
+
+    fn iter_rev(&self, name: &ResolvedAddress, upper_bound: Bound<&[u8]>) -> Iter<'_> {
+        Box::new(self.rocksdb_iter_rev(name, upper_bound))
+    }
+}
+
+impl<'a> RocksDBIterator<'a> {
+    fn new(iter: Peekable<DBIterator<'a>>, name: &ResolvedAddress) -> Self {
+        Self {
+            iter,
+            prefix: name.id_to_bytes(),
+            key: None,
+            value: None,
+            ended: false,
+        }
+    }
 }
 
 impl<'a> Iterator for RocksDBIterator<'a> {
@@ -0,0 +1,597 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of `SledDB` database.
+
+use sled::{transaction::TransactionError, Batch, Transactional};
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    iter::Peekable,
+    mem,
+    ops::Bound,
+    path::Path,
+    sync::{Arc, Mutex, RwLock, Weak},
+};
+
+use crate::{
+    db::{check_database, Change},
+    Database, DbOptions, Iter, Iterator, Patch, ResolvedAddress, Snapshot,
+};
+
+/// Size of a byte representation of an index ID, which is used to prefix index keys
+/// in a tree.
+const ID_SIZE: usize = mem::size_of::<u64>();
+
+/// Values of the keys changed after the creation of a snapshot, as they were at the moment
+/// of the snapshot creation. Values are grouped by the tree name; `None` corresponds
+/// to a key absent at the moment of the snapshot creation.
+type Overlay = HashMap<String, BTreeMap<Vec<u8>, Option<Vec<u8>>>>;
+
+/// Iterator over the entries of a `sled` tree in either direction.
+type TreeIter = Box<dyn std::iter::Iterator<Item = sled::Result<(sled::IVec, sled::IVec)>>>;
+
+/// Database implementation on top of [`sled`](https://github.com/spacejam/sled),
+/// an embedded key-value store written in pure Rust.
+///
+/// The data layout mirrors [`RocksDB`]: each column family is mapped to a separate `sled` tree,
+/// and keys of indexes sharing a tree are prefixed with the index ID.
+///
+/// `sled` does not provide point-in-time snapshots. To provide read isolation, each
+/// snapshot of a `SledDB` keeps the original values of the keys changed by the patches
+/// merged after the snapshot was created. Thus, long-living snapshots may consume a lot
+/// of memory if the database is actively written to.
+///
+/// [`RocksDB`]: struct.RocksDB.html
+pub struct SledDB {
+    inner: Arc<Inner>,
+}
+
+/// Database state shared among the database and its snapshots.
+struct Inner {
+    db: sled::Db,
+    state: RwLock<State>,
+}
+
+/// Mutable database state.
+///
+/// Merging a patch requires a write lock on the state, while snapshot reads require
+/// a read lock. This guarantees that reads never observe a partially merged patch.
+struct State {
+    /// Trees known to the database.
+    trees: HashMap<String, sled::Tree>,
+    /// Overlays of the snapshots that may be alive.
+    overlays: Vec<Weak<Mutex<Overlay>>>,
+}
+
+/// A snapshot of a `SledDB`.
+pub struct SledDBSnapshot {
+    inner: Arc<Inner>,
+    overlay: Arc<Mutex<Overlay>>,
+}
+
+/// An iterator over the entries of a `SledDB`.
+struct SledDBIterator {
+    inner: Arc<Inner>,
+    overlay: Arc<Mutex<Overlay>>,
+    tree_name: String,
+    iter: Option<Peekable<TreeIter>>,
+    prefix: Option<[u8; ID_SIZE]>,
+    /// Bound on the keys that were not yet yielded by the iterator: the lower bound
+    /// for ascending iterators and the upper bound for descending ones.
+    bound: Bound<Vec<u8>>,
+    reversed: bool,
+    item: Option<(Vec<u8>, Vec<u8>)>,
+    peeked: Option<Option<(Vec<u8>, Vec<u8>)>>,
+}
+
+impl SledDB {
+    /// Opens a database stored at the specified path with the specified options.
+    ///
+    /// If the database does not exist at the indicated path and the option
+    /// `create_if_missing` is switched on in `DbOptions`, a new database will
+    /// be created at the indicated path. Other options in `DbOptions` are specific to `RocksDB`
+    /// and are ignored.
+    pub fn open<P: AsRef<Path>>(path: P, options: &DbOptions) -> crate::Result<Self> {
+        let path = path.as_ref();
+        if !options.create_if_missing && !path.exists() {
+            let message = format!("Database does not exist at {}", path.display());
+            return Err(crate::Error::new(message));
+        }
+
+        let db = sled::Config::new().path(path).open()?;
+        let mut trees = HashMap::new();
+        for name in db.tree_names() {
+            let tree = db.open_tree(&name)?;
+            trees.insert(String::from_utf8_lossy(&name).into_owned(), tree);
+        }
+
+        let state = State {
+            trees,
+            overlays: vec![],
+        };
+        let mut db = Self {
+            inner: Arc::new(Inner {
+                db,
+                state: RwLock::new(state),
+            }),
+        };
+        check_database(&mut db)?;
+        Ok(db)
+    }
+
+    fn do_merge(&self, patch: Patch, sync: bool) -> crate::Result<()> {
+        let mut state = self
+            .inner
+            .state
+            .write()
+            .expect("Cannot lock database state");
+        let overlays = state.live_overlays();
+
+        let mut batches: BTreeMap<String, (sled::Tree, Batch)> = BTreeMap::new();
+        for (resolved, changes) in patch.into_changes() {
+            let tree = state.tree(&self.inner.db, &resolved.name)?;
+            let (_, batch) = batches
+                .entry(resolved.name.clone())
+                .or_insert_with(|| (tree.clone(), Batch::default()));
+
+            if changes.is_cleared() {
+                let prefix = resolved.id_to_bytes();
+                let prefix = prefix.as_ref().map_or(&[][..], |bytes| &bytes[..]);
+                for entry in tree.range(prefix..) {
+                    let (key, value) = entry?;
+                    if !key.starts_with(prefix) {
+                        break;
+                    }
+                    record_value(&overlays, &resolved.name, &key, Some(&value[..]));
+                    batch.remove(key);
+                }
+            }
+
+            for (start, end) in changes.removed_ranges() {
+                // Sled does not support range deletions, so the keys are removed one by one.
+                let prefix = resolved.id_to_bytes();
+                let prefix = prefix.as_ref().map_or(&[][..], |bytes| &bytes[..]);
+                let end = end.map(|end| resolved.keyed(end).into_owned());
+                for entry in tree.range(resolved.keyed(start).into_owned()..) {
+                    let (key, value) = entry?;
+                    let is_in_range = end.as_ref().map_or(true, |end| key[..] < end[..]);
+                    if !key.starts_with(prefix) || !is_in_range {
+                        break;
+                    }
+                    record_value(&overlays, &resolved.name, &key, Some(&value[..]));
+                    batch.remove(key);
+                }
+            }
+
+            for (key, change) in changes.into_data() {
+                let key = resolved.keyed(&key).into_owned();
+                if !overlays.is_empty() {
+                    let value = tree.get(&key)?;
+                    record_value(
+                        &overlays,
+                        &resolved.name,
+                        &key,
+                        value.as_ref().map(|v| &v[..]),
+                    );
+                }
+                match change {
+                    Change::Put(value) => batch.insert(key, value),
+                    Change::Delete => batch.remove(key),
+                }
+            }
+        }
+
+        let (trees, batches): (Vec<_>, Vec<_>) = batches.into_iter().map(|(_, pair)| pair).unzip();
+        // Since all writes are performed under the write lock on `state`,
+        // the transaction never conflicts with other transactions.
+        let result: Result<(), TransactionError<()>> = trees.as_slice().transaction(|trees| {
+            for (tree, batch) in trees.iter().zip(&batches) {
+                tree.apply_batch(batch)?;
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => {}
+            Err(TransactionError::Storage(e)) => return Err(e.into()),
+            Err(TransactionError::Abort(())) => unreachable!("Transaction is never aborted"),
+        }
+
+        if sync {
+            self.inner.db.flush()?;
+        }
+        Ok(())
+    }
+
+    fn sled_snapshot(&self) -> SledDBSnapshot {
+        let overlay = Arc::new(Mutex::new(Overlay::new()));
+        let mut state = self
+            .inner
+            .state
+            .write()
+            .expect("Cannot lock database state");
+        state.overlays.retain(|overlay| overlay.upgrade().is_some());
+        state.overlays.push(Arc::downgrade(&overlay));
+        SledDBSnapshot {
+            inner: Arc::clone(&self.inner),
+            overlay,
+        }
+    }
+}
+
+impl State {
+    /// Gets a tree with the specified name, creating it if necessary.
+    fn tree(&mut self, db: &sled::Db, name: &str) -> crate::Result<sled::Tree> {
+        if let Some(tree) = self.trees.get(name) {
+            return Ok(tree.clone());
+        }
+        let tree = db.open_tree(name)?;
+        self.trees.insert(name.to_owned(), tree.clone());
+        Ok(tree)
+    }
+
+    /// Returns overlays of the snapshots which are currently alive.
+    fn live_overlays(&mut self) -> Vec<Arc<Mutex<Overlay>>> {
+        let overlays: Vec<_> = self.overlays.iter().filter_map(Weak::upgrade).collect();
+        self.overlays = overlays.iter().map(Arc::downgrade).collect();
+        overlays
+    }
+}
+
+/// Records the value of a key before it is changed in all snapshot overlays. If the key
+/// was already changed since a snapshot was created, the overlay is left intact.
+fn record_value(
+    overlays: &[Arc<Mutex<Overlay>>],
+    tree_name: &str,
+    key: &[u8],
+    value: Option<&[u8]>,
+) {
+    for overlay in overlays {
+        let mut overlay = overlay.lock().expect("Cannot lock snapshot overlay");
+        let values = overlay.entry(tree_name.to_owned()).or_default();
+        if !values.contains_key(key) {
+            values.insert(key.to_vec(), value.map(<[u8]>::to_vec));
+        }
+    }
+}
+
+impl SledDBSnapshot {
+    fn sled_iter(&self, name: &ResolvedAddress, from: &[u8]) -> SledDBIterator {
+        let state = self.inner.state.read().expect("Cannot lock database state");
+        let from = name.keyed(from).into_owned();
+        let iter = state.trees.get(&name.name).map(|tree| {
+            let iter: TreeIter = Box::new(tree.range(from.as_slice()..));
+            iter.peekable()
+        });
+
+        SledDBIterator {
+            inner: Arc::clone(&self.inner),
+            overlay: Arc::clone(&self.overlay),
+            tree_name: name.name.clone(),
+            iter,
+            prefix: name.id_to_bytes(),
+            bound: Bound::Included(from),
+            reversed: false,
+            item: None,
+            peeked: None,
+        }
+    }
+
+    fn sled_iter_rev(&self, name: &ResolvedAddress, upper_bound: Bound<&[u8]>) -> SledDBIterator {
+        let state = self.inner.state.read().expect("Cannot lock database state");
+        let upper_bound = name.keyed_upper_bound(upper_bound);
+        let iter = state.trees.get(&name.name).map(|tree| {
+            let range = (Bound::Unbounded, upper_bound.clone());
+            let iter: TreeIter = Box::new(tree.range::<Vec<u8>, _>(range).rev());
+            iter.peekable()
+        });
+
+        SledDBIterator {
+            inner: Arc::clone(&self.inner),
+            overlay: Arc::clone(&self.overlay),
+            tree_name: name.name.clone(),
+            iter,
+            prefix: name.id_to_bytes(),
+            bound: upper_bound,
+            reversed: true,
+            item: None,
+            peeked: None,
+        }
+    }
+}
+
+impl Database for SledDB {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        Box::new(self.sled_snapshot())
+    }
+
+    fn merge(&self, patch: Patch) -> crate::Result<()> {
+        self.do_merge(patch, false)
+    }
+
+    fn merge_sync(&self, patch: Patch) -> crate::Result<()> {
+        self.do_merge(patch, true)
+    }
+}
+
+impl Snapshot for SledDBSnapshot {
+    fn get(&self, resolved_addr: &ResolvedAddress, key: &[u8]) -> Option<Vec<u8>> {
+        let state = self.inner.state.read().expect("Cannot lock database state");
+        let key = resolved_addr.keyed(key);
+
+        let overlay = self.overlay.lock().expect("Cannot lock snapshot overlay");
+        let original_value = overlay
+            .get(&resolved_addr.name)
+            .and_then(|values| values.get(key.as_ref()));
+        if let Some(value) = original_value {
+            return value.clone();
+        }
+
+        let tree = state.trees.get(&resolved_addr.name)?;
+        match tree.get(key) {
+            Ok(value) => value.map(|v| v.to_vec()),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_> {
+        Box::new(self.sled_iter(name, from))
+    }
+
+    fn iter_rev(&self, name: &ResolvedAddress, upper_bound: Bound<&[u8]>) -> Iter<'_> {
+        Box::new(self.sled_iter_rev(name, upper_bound))
+    }
+}
+
+fn has_prefix(prefix: &Option<[u8; ID_SIZE]>, key: &[u8]) -> bool {
+    prefix.map_or(true, |prefix| key.starts_with(&prefix))
+}
+
+fn bound_as_slice(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key),
+        Bound::Excluded(key) => Bound::Excluded(key),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl SledDBIterator {
+    /// Finds the next entry in the snapshot, merging the current tree contents with
+    /// the original values of the keys changed after the snapshot creation.
+    fn advance(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        // Prevent patches from being merged while we are reading the data.
+        let _state = self.inner.state.read().expect("Cannot lock database state");
+        let overlay = self.overlay.lock().expect("Cannot lock snapshot overlay");
+        let original_values = overlay.get(&self.tree_name);
+
+        loop {
+            let db_key = match self.iter.as_mut().and_then(Peekable::peek) {
+                Some(Ok((key, _))) => Some(key.to_vec()),
+                Some(Err(e)) => panic!("{}", e),
+                None => None,
+            };
+            let db_key = db_key.filter(|key| has_prefix(&self.prefix, key));
+
+            let original_entry = original_values
+                .and_then(|values| {
+                    if self.reversed {
+                        let range = (Bound::Unbounded, bound_as_slice(&self.bound));
+                        values.range::<[u8], _>(range).next_back()
+                    } else {
+                        let range = (bound_as_slice(&self.bound), Bound::Unbounded);
+                        values.range::<[u8], _>(range).next()
+                    }
+                })
+                .filter(|(key, _)| has_prefix(&self.prefix, key))
+                .map(|(key, value)| (key.clone(), value.clone()));
+
+            // Original values take precedence over the current values in the tree.
+            let use_db_entry = match (&db_key, &original_entry) {
+                (None, None) => return None,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some(db_key), Some((key, _))) => match self.cmp_keys(db_key, key) {
+                    Ordering::Less => true,
+                    Ordering::Equal => {
+                        self.iter.as_mut().unwrap().next();
+                        false
+                    }
+                    Ordering::Greater => false,
+                },
+            };
+
+            let (key, value) = if use_db_entry {
+                let (key, value) = self.iter.as_mut().unwrap().next().unwrap().unwrap();
+                (key.to_vec(), Some(value.to_vec()))
+            } else {
+                original_entry.unwrap()
+            };
+
+            self.bound = Bound::Excluded(key.clone());
+            if let Some(value) = value {
+                return Some((key, value));
+            }
+        }
+    }
+
+    /// Compares keys in the iteration order.
+    fn cmp_keys(&self, db_key: &[u8], original_key: &[u8]) -> Ordering {
+        let ordering = db_key.cmp(original_key);
+        if self.reversed {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn key_offset(&self) -> usize {
+        if self.prefix.is_some() {
+            ID_SIZE
+        } else {
+            0
+        }
+    }
+}
+
+impl Iterator for SledDBIterator {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.item = match self.peeked.take() {
+            Some(item) => item,
+            None => self.advance(),
+        };
+        let offset = self.key_offset();
+        self.item
+            .as_ref()
+            .map(|(key, value)| (&key[offset..], &value[..]))
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.advance());
+        }
+        let offset = self.key_offset();
+        self.peeked
+            .as_ref()?
+            .as_ref()
+            .map(|(key, value)| (&key[offset..], &value[..]))
+    }
+}
+
+impl From<SledDB> for Arc<dyn Database> {
+    fn from(db: SledDB) -> Self {
+        Self::from(Box::new(db) as Box<dyn Database>)
+    }
+}
+
+impl fmt::Debug for SledDB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SledDB").finish()
+    }
+}
+
+impl fmt::Debug for SledDBSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SledDBSnapshot").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{access::AccessExt, ObjectHash};
+
+    #[test]
+    fn snapshots_are_isolated_from_merges() {
+        let dir = TempDir::new().unwrap();
+        let db = SledDB::open(&dir, &DbOptions::default()).unwrap();
+
+        let fork = db.fork();
+        fork.get_list("list").extend(vec![1_u32, 2, 3]);
+        fork.get_map(("map", &0_u8)).put(&1_u8, "foo".to_owned());
+        fork.get_map(("map", &0_u8)).put(&3_u8, "bar".to_owned());
+        db.merge(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+        let list_hash = snapshot
+            .get_proof_list::<_, u32>("proof_list")
+            .object_hash();
+
+        let fork = db.fork();
+        fork.get_list("list").clear();
+        fork.get_proof_list("proof_list").push(1_u32);
+        {
+            let mut map = fork.get_map(("map", &0_u8));
+            map.put(&0_u8, "baz".to_owned());
+            map.put(&2_u8, "?".to_owned());
+            map.remove(&3_u8);
+        }
+        fork.get_map(("map", &1_u8)).put(&1_u8, "!".to_owned());
+        db.merge(fork.into_patch()).unwrap();
+
+        let list = snapshot.get_list::<_, u32>("list");
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        let map = snapshot.get_map::<_, u8, String>(("map", &0_u8));
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(1, "foo".to_owned()), (3, "bar".to_owned())]
+        );
+        assert_eq!(map.get(&0), None);
+        assert_eq!(map.get(&3), Some("bar".to_owned()));
+        let proof_list = snapshot.get_proof_list::<_, u32>("proof_list");
+        assert_eq!(proof_list.object_hash(), list_hash);
+        assert!(snapshot.index_type(("map", &1_u8)).is_none());
+
+        let new_snapshot = db.snapshot();
+        let map = new_snapshot.get_map::<_, u8, String>(("map", &0_u8));
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![0, 1, 2],);
+        assert!(new_snapshot.get_list::<_, u32>("list").is_empty());
+    }
+
+    #[test]
+    fn iterators_are_isolated_from_merges() {
+        let dir = TempDir::new().unwrap();
+        let db = SledDB::open(&dir, &DbOptions::default()).unwrap();
+        let fork = db.fork();
+        fork.get_map("map").extend((0_u32..10).map(|i| (i, i)));
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map = snapshot.get_map::<_, u32, u32>("map");
+        let mut iter = map.iter_from(&3);
+        assert_eq!(iter.next(), Some((3, 3)));
+
+        let fork = db.fork();
+        {
+            let mut map = fork.get_map("map");
+            map.remove(&4_u32);
+            map.put(&5, 100);
+            map.extend((10_u32..15).map(|i| (i, i)));
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        assert!(iter.eq((4..10).map(|i| (i, i))));
+    }
+
+    #[test]
+    fn database_can_be_reopened() {
+        let dir = TempDir::new().unwrap();
+        {
+            let db = SledDB::open(&dir, &DbOptions::default()).unwrap();
+            let fork = db.fork();
+            fork.get_proof_list("list").extend(vec![1_u32, 2, 3]);
+            fork.get_entry(("entry", &1_u8)).set("!".to_owned());
+            db.merge_sync(fork.into_patch()).unwrap();
+        }
+
+        let db = SledDB::open(&dir, &DbOptions::default()).unwrap();
+        let snapshot = db.snapshot();
+        let list = snapshot.get_proof_list::<_, u32>("list");
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        let entry = snapshot.get_entry::<_, String>(("entry", &1_u8));
+        assert_eq!(entry.get().unwrap(), "!");
+    }
+
+    #[test]
+    fn missing_database_is_not_created_if_requested() {
+        let dir = TempDir::new().unwrap();
+        let options = DbOptions {
+            create_if_missing: false,
+            ..DbOptions::default()
+        };
+        assert!(SledDB::open(dir.path().join("db"), &options).is_err());
+    }
+}
@@ -51,17 +51,18 @@ impl TemporaryDB {
 
         let opts = rocksdb::Options::default();
         let names = rocksdb::DB::list_cf(&opts, self.dir.path())?;
+        let db = self.inner.rocksdb();
 
         // For some reason, using a `WriteBatch` is significantly faster than using `DB::drop_cf`,
         // both in debug and release modes.
         let mut batch = WriteBatch::default();
         for name in &names {
             if name != DEFAULT_CF && name != DB_METADATA {
-                let cf_handle = self.inner.rocksdb().cf_handle(name).ok_or_else(|| {
+                let cf_handle = db.cf_handle(name).ok_or_else(|| {
                     let message = format!("Cannot access column family {}", name);
                     crate::Error::new(message)
                 })?;
-                let mut iter = self.inner.rocksdb().raw_iterator_cf(cf_handle.clone())?;
+                let mut iter = db.raw_iterator_cf(cf_handle);
                 iter.seek_to_last();
                 if iter.valid() {
                     if let Some(key) = iter.key() {
@@ -71,10 +72,10 @@ impl TemporaryDB {
                         // is mostly used for testing, this optimization leads to practical
                         // performance improvement.
                         if key.len() < LARGER_KEY.len() {
-                            batch.delete_range_cf::<&[u8]>(cf_handle, &[], LARGER_KEY)?;
+                            batch.delete_range_cf(cf_handle, &[] as &[u8], LARGER_KEY);
                         } else {
-                            batch.delete_range_cf::<&[u8]>(cf_handle.clone(), &[], &key)?;
-                            batch.delete_cf(cf_handle, &key)?;
+                            batch.delete_range_cf(cf_handle, &[] as &[u8], key);
+                            batch.delete_cf(cf_handle, &key);
                         }
                     }
                 }
@@ -82,10 +83,7 @@ impl TemporaryDB {
         }
 
         let write_options = WriteOptions::default();
-        self.inner
-            .rocksdb()
-            .write_opt(batch, &write_options)
-            .map_err(Into::into)
+        db.write_opt(batch, &write_options).map_err(Into::into)
     }
 }
 
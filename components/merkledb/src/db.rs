@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::{BTreeMap, HashMap, HashSet},
     fmt,
     iter::{Iterator as StdIterator, Peekable},
@@ -25,11 +25,14 @@ use std::{
 };
 
 use crate::{
-    validation::assert_valid_name_component,
+    access::AccessError,
+    history::{HistoricalSnapshot, History},
+    validation::{assert_valid_name_component, check_index_valid_full_name},
     views::{
-        get_object_hash, AsReadonly, ChangesIter, IndexesPool, RawAccess, ResolvedAddress, View,
+        get_object_hash, AsReadonly, ChangesIter, IndexAddress, IndexesPool, RawAccess,
+        ResolvedAddress, View,
     },
-    Error, Result, SystemSchema,
+    Error, HistoryOptions, IndexType, Result, SizeLimitExceeded, SystemSchema,
 };
 
 /// Changes related to a specific `View`.
@@ -37,12 +40,48 @@ use crate::{
 pub struct ViewChanges {
     /// Changes within the view.
     pub(super) data: BTreeMap<Vec<u8>, Change>,
+    /// Key ranges removed from the view. The ranges do not overlap; each range is keyed
+    /// by its inclusive start and maps to its exclusive end (`None` for unbounded ranges).
+    /// Entries in `data` take precedence over the removed ranges.
+    removed_ranges: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
     /// Was the view cleared as a part of changes?
     is_cleared: bool,
+    /// Type of the index backed by the view, if known. The type is used by backends
+    /// to tune storage of newly created indexes.
+    index_type: Option<IndexType>,
     /// Is the view aggregated into `state_hash` of the database?
     /// Storing this information directly in the changes allows to avoid relatively expensive
     /// metadata lookups during state aggregator update in `Fork::into_patch()`.
-    namespace: Option<String>,
+    aggregation: Option<AggregationKey>,
+    /// Approximate size of the changes in bytes. See `ViewChanges::size()` for details.
+    size: usize,
+}
+
+/// Returns the approximate size of a change in bytes.
+fn change_size(key: &[u8], change: &Change) -> usize {
+    key.len() + value_size(change)
+}
+
+fn value_size(change: &Change) -> usize {
+    match change {
+        Change::Put(value) => value.len(),
+        Change::Delete => 0,
+    }
+}
+
+/// Returns the approximate size of a removed key range in bytes.
+fn range_size(start: &[u8], end: Option<&[u8]>) -> usize {
+    start.len() + end.map_or(0, <[u8]>::len)
+}
+
+/// Location of an aggregated index within state aggregators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregationKey {
+    /// Aggregation namespace of the index; the default namespace is denoted by an empty string.
+    pub namespace: String,
+    /// Name of the index, which serves as a key in the aggregator. The name may differ from
+    /// the name of the column family storing the index (e.g., if the index was moved).
+    pub name: String,
 }
 
 impl ViewChanges {
@@ -56,16 +95,223 @@ impl ViewChanges {
 
     #[cfg(test)]
     pub fn is_aggregated(&self) -> bool {
-        self.namespace.as_ref().map_or(false, String::is_empty)
+        self.aggregation
+            .as_ref()
+            .map_or(false, |key| key.namespace.is_empty())
     }
 
     pub fn clear(&mut self) {
         self.data.clear();
+        self.removed_ranges.clear();
         self.is_cleared = true;
+        self.size = 0;
+    }
+
+    /// Returns the approximate size of the changes in bytes. The size is computed as the total
+    /// length of changed keys and values and the bounds of removed key ranges; the memory
+    /// overhead of the containers is not taken into account. Thus, the size does not depend
+    /// on the platform.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Recomputes the size of the changes after `data` was modified directly.
+    pub(crate) fn update_size(&mut self) {
+        let data_size: usize = self
+            .data
+            .iter()
+            .map(|(key, change)| change_size(key, change))
+            .sum();
+        let ranges_size: usize = self
+            .removed_ranges()
+            .map(|(start, end)| range_size(start, end))
+            .sum();
+        self.size = data_size + ranges_size;
+    }
+
+    /// Returns the size of the changes after recording a change for the specified key.
+    pub(crate) fn size_after_put(&self, key: &[u8], change: &Change) -> usize {
+        let new_size = value_size(change);
+        if let Some(old_change) = self.data.get(key) {
+            self.size - value_size(old_change) + new_size
+        } else {
+            self.size + key.len() + new_size
+        }
+    }
+
+    /// Records a change for the specified key.
+    pub(crate) fn put(&mut self, key: Vec<u8>, change: Change) {
+        let new_size = value_size(&change);
+        if let Some(old_change) = self.data.get_mut(&key) {
+            self.size = self.size - value_size(old_change) + new_size;
+            *old_change = change;
+        } else {
+            self.size += key.len() + new_size;
+            self.data.insert(key, change);
+        }
+    }
+
+    /// Removes all keys in the range `start..end` (or `start..` if `end` is `None`).
+    pub fn remove_range(&mut self, start: Vec<u8>, end: Option<Vec<u8>>) {
+        if let Some(ref end) = end {
+            if *end <= start {
+                return;
+            }
+        }
+
+        // Forget changes within the range.
+        let mut removed = self.data.split_off(&start);
+        if let Some(ref end) = end {
+            let mut rest = removed.split_off(end);
+            self.data.append(&mut rest);
+        }
+        let removed_size: usize = removed
+            .iter()
+            .map(|(key, change)| change_size(key, change))
+            .sum();
+        self.size -= removed_size;
+        if self.is_cleared {
+            // Nothing to remove from the snapshot.
+            return;
+        }
+
+        // Merge the range with overlapping or adjacent removed ranges.
+        let merged: Vec<_> = self
+            .adjacent_ranges(&start, end.as_ref().map(Vec::as_slice))
+            .map(|(range_start, _)| range_start.to_owned())
+            .collect();
+        let (mut start, mut end) = (start, end);
+        for range_start in merged {
+            let range_end = self.removed_ranges.remove(&range_start).unwrap();
+            self.size -= range_size(&range_start, range_end.as_ref().map(Vec::as_slice));
+            if range_start < start {
+                start = range_start;
+            }
+            end = match (end, range_end) {
+                (Some(end), Some(range_end)) => Some(std::cmp::max(end, range_end)),
+                _ => None,
+            };
+        }
+        self.size += range_size(&start, end.as_ref().map(Vec::as_slice));
+        self.removed_ranges.insert(start, end);
+    }
+
+    /// Returns the size of the changes after removing all keys in the range `start..end`
+    /// (or `start..` if `end` is `None`). The changes themselves are not modified.
+    pub(crate) fn size_after_remove_range(&self, start: &[u8], end: Option<&[u8]>) -> usize {
+        let upper = match end {
+            Some(end) if end <= start => return self.size,
+            Some(end) => Bound::Excluded(end),
+            None => Bound::Unbounded,
+        };
+        let removed_size: usize = self
+            .data
+            .range::<[u8], _>((Bound::Included(start), upper))
+            .map(|(key, change)| change_size(key, change))
+            .sum();
+        let mut size = self.size - removed_size;
+        if self.is_cleared {
+            return size;
+        }
+
+        let (mut merged_start, mut merged_end) = (start, end);
+        for (range_start, range_end) in self.adjacent_ranges(start, end) {
+            size -= range_size(range_start, range_end);
+            merged_start = std::cmp::min(merged_start, range_start);
+            merged_end = match (merged_end, range_end) {
+                (Some(end), Some(range_end)) => Some(std::cmp::max(end, range_end)),
+                _ => None,
+            };
+        }
+        size + range_size(merged_start, merged_end)
+    }
+
+    /// Iterates over removed ranges overlapping with or adjacent to the range `start..end`,
+    /// in the descending order.
+    fn adjacent_ranges<'a>(
+        &'a self,
+        start: &'a [u8],
+        end: Option<&'a [u8]>,
+    ) -> impl StdIterator<Item = (&'a [u8], Option<&'a [u8]>)> {
+        let upper = end.map_or(Bound::Unbounded, Bound::Included);
+        self.removed_ranges
+            .range::<[u8], _>((Bound::Unbounded, upper))
+            .rev()
+            .take_while(move |(_, range_end)| {
+                range_end
+                    .as_ref()
+                    .map_or(true, |range_end| &range_end[..] >= start)
+            })
+            .map(|(range_start, range_end)| {
+                (&range_start[..], range_end.as_ref().map(Vec::as_slice))
+            })
+    }
+
+    /// Returns `true` if the changes contain removed key ranges.
+    pub fn has_removed_ranges(&self) -> bool {
+        !self.removed_ranges.is_empty()
+    }
+
+    /// Iterates over key ranges removed from the view, in the ascending order.
+    /// Each range is represented by its inclusive start and exclusive end (`None`
+    /// if the range is unbounded).
+    pub fn removed_ranges(&self) -> impl StdIterator<Item = (&[u8], Option<&[u8]>)> {
+        self.removed_ranges
+            .iter()
+            .map(|(start, end)| (&start[..], end.as_ref().map(Vec::as_slice)))
+    }
+
+    /// Returns the removed range containing the `key`, if any.
+    fn removed_range(&self, key: &[u8]) -> Option<(&[u8], Option<&[u8]>)> {
+        let (start, end) = self
+            .removed_ranges
+            .range::<[u8], _>((Bound::Unbounded, Bound::Included(key)))
+            .next_back()?;
+        match end {
+            Some(ref end) if &end[..] <= key => None,
+            _ => Some((&start[..], end.as_ref().map(Vec::as_slice))),
+        }
+    }
+
+    /// Checks whether the `key` is removed from the underlying snapshot, either because
+    /// the view is cleared or because the key belongs to a removed range.
+    fn is_removed(&self, key: &[u8]) -> bool {
+        self.is_cleared || self.removed_range(key).is_some()
+    }
+
+    /// Wraps an iterator over the snapshot underlying these changes so that it skips
+    /// entries from the removed ranges. `reversed` specifies the iteration direction.
+    pub(crate) fn skip_removed<'a>(
+        &'a self,
+        snapshot: &'a dyn Snapshot,
+        name: &'a ResolvedAddress,
+        iter: Iter<'a>,
+        reversed: bool,
+    ) -> Iter<'a> {
+        if self.removed_ranges.is_empty() {
+            iter
+        } else {
+            Box::new(SkipRemovedIter {
+                snapshot,
+                name,
+                changes: self,
+                inner: Some(iter),
+                reversed,
+            })
+        }
+    }
+
+    pub fn set_aggregation(&mut self, aggregation: Option<AggregationKey>) {
+        self.aggregation = aggregation;
+    }
+
+    pub(crate) fn set_index_type(&mut self, index_type: IndexType) {
+        self.index_type = Some(index_type);
     }
 
-    pub fn set_aggregation(&mut self, namespace: Option<String>) {
-        self.namespace = namespace;
+    /// Returns the type of the index backed by the view, if known.
+    pub fn index_type(&self) -> Option<IndexType> {
+        self.index_type
     }
 
     pub fn into_data(self) -> BTreeMap<Vec<u8>, Change> {
@@ -81,7 +327,7 @@ impl ViewChanges {
                 Change::Delete => None,
             });
         }
-        if self.is_cleared() {
+        if self.is_removed(key) {
             return Ok(None);
         }
         Err(())
@@ -97,13 +343,59 @@ impl ViewChanges {
             });
         }
 
-        if self.is_cleared() {
+        if self.is_removed(key) {
             return Ok(false);
         }
         Err(())
     }
 }
 
+/// Iterator over a snapshot that skips entries in the key ranges removed by `ViewChanges`.
+/// When the iterator encounters a removed key, it re-seeks the snapshot past the removed range.
+struct SkipRemovedIter<'a> {
+    snapshot: &'a dyn Snapshot,
+    name: &'a ResolvedAddress,
+    changes: &'a ViewChanges,
+    inner: Option<Iter<'a>>,
+    reversed: bool,
+}
+
+impl SkipRemovedIter<'_> {
+    fn skip_removed(&mut self) {
+        loop {
+            let next_inner = {
+                let key = match self.inner.as_mut().and_then(|iter| iter.peek()) {
+                    Some((key, _)) => key,
+                    None => return,
+                };
+                let (start, end) = match self.changes.removed_range(key) {
+                    Some(range) => range,
+                    None => return,
+                };
+
+                if self.reversed {
+                    Some(self.snapshot.iter_rev(self.name, Bound::Excluded(start)))
+                } else {
+                    end.map(|end| self.snapshot.iter(self.name, end))
+                }
+            };
+            self.inner = next_inner;
+        }
+    }
+}
+
+impl Iterator for SkipRemovedIter<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.skip_removed();
+        self.inner.as_mut()?.next()
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        self.skip_removed();
+        self.inner.as_mut()?.peek()
+    }
+}
+
 /// Cell holding changes for a specific view. Mutable view borrows take changes out
 /// of the `Option` and unwraps `Rc` into inner data, while immutable borrows clone inner `Rc`.
 type ChangesCell = Option<Rc<ViewChanges>>;
@@ -111,6 +403,11 @@ type ChangesCell = Option<Rc<ViewChanges>>;
 #[derive(Debug, Default)]
 struct WorkingPatch {
     changes: RefCell<HashMap<ResolvedAddress, ChangesCell>>,
+    /// Approximate size of the fork changes in bytes, including the flushed changes.
+    /// The size is updated by `ChangesMut` instances on each write.
+    size: Cell<usize>,
+    /// Soft limit on the size of the fork changes.
+    size_limit: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -156,6 +453,49 @@ pub struct ChangesMut<'a> {
     parent: WorkingPatchRef<'a>,
     key: ResolvedAddress,
     changes: Option<Rc<ViewChanges>>,
+    /// Size of the changes accounted in the parent patch.
+    accounted_size: usize,
+}
+
+impl<'a> ChangesMut<'a> {
+    fn new(parent: WorkingPatchRef<'a>, key: ResolvedAddress) -> Self {
+        let changes = parent.patch().take_view_changes(&key);
+        let accounted_size = changes.as_ref().map_or(0, |changes| changes.size());
+        Self {
+            parent,
+            key,
+            changes,
+            accounted_size,
+        }
+    }
+
+    /// Checks whether the changes may be modified so that their size becomes `new_size`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the changes would grow and the size of the parent patch
+    /// would exceed its limit.
+    pub(crate) fn check_size(&self, new_size: usize) -> StdResult<(), SizeLimitExceeded> {
+        let patch = self.parent.patch();
+        let total_size = patch.size.get() - self.accounted_size + new_size;
+        match patch.size_limit {
+            Some(limit) if new_size > self.size() && total_size > limit => Err(SizeLimitExceeded {
+                limit,
+                size: total_size,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Accounts modifications of the changes in the size of the parent patch.
+    pub(crate) fn account_size(&mut self) {
+        let size = self.changes.as_ref().map_or(0, |changes| changes.size());
+        let patch = self.parent.patch();
+        patch
+            .size
+            .set(patch.size.get() - self.accounted_size + size);
+        self.accounted_size = size;
+    }
 }
 
 impl Deref for ChangesMut<'_> {
@@ -180,6 +520,7 @@ impl DerefMut for ChangesMut<'_> {
 
 impl Drop for ChangesMut<'_> {
     fn drop(&mut self) {
+        self.account_size();
         let mut change_map = self.parent.patch().changes.borrow_mut();
         let changes = change_map.get_mut(&self.key).unwrap_or_else(|| {
             panic!("insertion point for changes disappeared at {:?}", self.key);
@@ -191,10 +532,12 @@ impl Drop for ChangesMut<'_> {
 }
 
 impl WorkingPatch {
-    /// Creates a new empty patch.
-    fn new() -> Self {
+    /// Creates a new empty patch on top of changes with the specified size.
+    fn new(base_size: usize, size_limit: Option<usize>) -> Self {
         Self {
             changes: RefCell::new(HashMap::new()),
+            size: Cell::new(base_size),
+            size_limit,
         }
     }
 
@@ -271,10 +614,8 @@ impl WorkingPatch {
                 );
             });
 
-            if let Some(namespace) = mem::replace(&mut changes.namespace, None) {
-                patch
-                    .changed_aggregated_addrs
-                    .insert(address.clone(), namespace);
+            if let Some(key) = mem::replace(&mut changes.aggregation, None) {
+                patch.changed_aggregated_addrs.insert(address.clone(), key);
             }
 
             // The patch may already contain changes related to the `address`. If it does,
@@ -288,7 +629,16 @@ impl WorkingPatch {
             if changes.is_cleared() {
                 *patch_changes = changes;
             } else {
-                patch_changes.data.extend(changes.data);
+                if changes.index_type.is_some() {
+                    patch_changes.index_type = changes.index_type;
+                }
+                // Removed ranges precede the changes in `data`.
+                for (start, end) in changes.removed_ranges {
+                    patch_changes.remove_range(start, end);
+                }
+                for (key, change) in changes.data {
+                    patch_changes.put(key, change);
+                }
             }
         }
     }
@@ -424,7 +774,7 @@ pub struct Patch {
     changes: HashMap<ResolvedAddress, ViewChanges>,
     /// Addresses of aggregated indexes that were changed within this patch. This information
     /// is used to update the state aggregator in `Fork::into_patch()`.
-    changed_aggregated_addrs: HashMap<ResolvedAddress, String>,
+    changed_aggregated_addrs: HashMap<ResolvedAddress, AggregationKey>,
     /// Names of removed aggregated indexes.
     removed_aggregated_addrs: HashSet<String>,
 }
@@ -432,6 +782,7 @@ pub struct Patch {
 pub(super) struct ForkIter<'a, T: StdIterator> {
     snapshot: Iter<'a>,
     changes: Option<Peekable<T>>,
+    reversed: bool,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -487,7 +838,7 @@ pub trait Database: Send + Sync + 'static {
                 changed_aggregated_addrs: HashMap::new(),
                 removed_aggregated_addrs: HashSet::new(),
             },
-            working_patch: WorkingPatch::new(),
+            working_patch: WorkingPatch::new(0, None),
         }
     }
 
@@ -571,49 +922,142 @@ pub trait DatabaseExt: Database {
     fn merge_with_backup(&self, patch: Patch) -> Result<Patch> {
         // FIXME: does this work with migrations? (ECR-4005)
 
-        let snapshot = self.snapshot();
         let changed_aggregated_addrs = patch.changed_aggregated_addrs.clone();
-        let mut rev_changes = HashMap::with_capacity(patch.changes.len());
+        let rev_changes = reverse_changes(&*self.snapshot(), &patch);
+        self.merge(patch)?;
+        Ok(Patch {
+            snapshot: self.snapshot(),
+            changes: rev_changes,
+            changed_aggregated_addrs,
+            removed_aggregated_addrs: HashSet::new(),
+        })
+    }
 
-        for (name, changes) in &patch.changes {
-            let mut view_changes = changes.data.clone();
-            for (key, change) in &mut view_changes {
-                *change = if let Some(value) = snapshot.get(name, key) {
-                    Change::Put(value)
-                } else {
-                    Change::Delete
-                };
-            }
+    /// Merges a patch into the database, recording the changes necessary to restore
+    /// the database state preceding the merge. The recorded changes are labeled with
+    /// the specified `version`, which allows to restore the database state as of a past version
+    /// with [`snapshot_at`].
+    ///
+    /// Versions of the consecutive merges must not decrease. Several merges may have
+    /// the same version; in this case, the database state as of the version corresponds
+    /// to the state after the last of these merges. The database should not be changed
+    /// by the means other than this method once history recording is started;
+    /// otherwise, restored historical states will reflect such changes.
+    ///
+    /// If `options` restrict the number of retained versions, history records for the older
+    /// versions are removed within the same merge.
+    ///
+    /// # Performance notes
+    ///
+    /// This method has the same performance characteristics as [`merge_with_backup`]. Besides,
+    /// history records are stored in the database, which increases its disk usage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `version` is less than the version of the previous merge
+    /// performed with this method, or in the same situations as `Database::merge()`.
+    ///
+    /// [`snapshot_at`]: #method.snapshot_at
+    /// [`merge_with_backup`]: #method.merge_with_backup
+    fn merge_with_history(
+        &self,
+        patch: Patch,
+        version: u64,
+        options: &HistoryOptions,
+    ) -> Result<()> {
+        let rev_changes = reverse_changes(&*self.snapshot(), &patch);
+        let fork = Fork::from(patch);
+        History::new(&fork).push(version, rev_changes, options)?;
+        self.merge(fork.into_patch())
+    }
+
+    /// Returns the snapshot of the database state as of the specified `version`, i.e.,
+    /// after the last merge performed with [`merge_with_history`] with the version
+    /// not exceeding `version`.
+    ///
+    /// Returns `None` if the state cannot be restored: either history was not recorded
+    /// for the specified version, or it was removed according to the retention options.
+    /// If `version` is greater or equal to the latest recorded version, the current
+    /// database state is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, Database, DatabaseExt, HistoryOptions, TemporaryDB};
+    /// let db = TemporaryDB::new();
+    /// let options = HistoryOptions::default();
+    /// for version in 0..3 {
+    ///     let fork = db.fork();
+    ///     fork.get_list("list").push(version);
+    ///     db.merge_with_history(fork.into_patch(), version, &options).unwrap();
+    /// }
+    ///
+    /// let snapshot = db.snapshot_at(1).unwrap();
+    /// let list = snapshot.get_list::<_, u64>("list");
+    /// assert_eq!(list.iter().collect::<Vec<_>>(), vec![0, 1]);
+    /// ```
+    ///
+    /// [`merge_with_history`]: #method.merge_with_history
+    fn snapshot_at(&self, version: u64) -> Option<Box<dyn Snapshot>> {
+        let snapshot = HistoricalSnapshot::new(self.snapshot(), version)?;
+        Some(Box::new(snapshot))
+    }
+}
 
-            // Remember all elements that will be deleted.
-            if changes.is_cleared() {
-                let mut iter = snapshot.iter(name, &[]);
+/// Computes the changes reverting the `patch` if it is merged on top of the `snapshot`.
+fn reverse_changes(
+    snapshot: &dyn Snapshot,
+    patch: &Patch,
+) -> HashMap<ResolvedAddress, ViewChanges> {
+    let mut rev_changes = HashMap::with_capacity(patch.changes.len());
+
+    for (name, changes) in &patch.changes {
+        let mut view_changes = changes.data.clone();
+        for (key, change) in &mut view_changes {
+            *change = if let Some(value) = snapshot.get(name, key) {
+                Change::Put(value)
+            } else {
+                Change::Delete
+            };
+        }
+
+        // Remember all elements that will be deleted.
+        if changes.is_cleared() {
+            let mut iter = snapshot.iter(name, &[]);
+            while let Some((key, value)) = iter.next() {
+                view_changes.insert(key.to_vec(), Change::Put(value.to_vec()));
+            }
+        } else {
+            for (start, end) in changes.removed_ranges() {
+                let mut iter = snapshot.iter(name, start);
                 while let Some((key, value)) = iter.next() {
+                    if end.map_or(false, |end| key >= end) {
+                        break;
+                    }
                     view_changes.insert(key.to_vec(), Change::Put(value.to_vec()));
                 }
             }
-
-            rev_changes.insert(
-                name.to_owned(),
-                ViewChanges {
-                    data: view_changes,
-                    is_cleared: false,
-                    namespace: changes.namespace.clone(),
-                },
-            );
         }
 
-        self.merge(patch)?;
-        Ok(Patch {
-            snapshot: self.snapshot(),
-            changes: rev_changes,
-            changed_aggregated_addrs,
-            removed_aggregated_addrs: HashSet::new(),
-        })
+        rev_changes.insert(
+            name.to_owned(),
+            ViewChanges {
+                data: view_changes,
+                removed_ranges: BTreeMap::new(),
+                is_cleared: false,
+                index_type: changes.index_type,
+                aggregation: changes.aggregation.clone(),
+                size: 0,
+            },
+        );
     }
+    for changes in rev_changes.values_mut() {
+        changes.update_size();
+    }
+    rev_changes
 }
 
-impl<T: Database> DatabaseExt for T {}
+impl<T: Database + ?Sized> DatabaseExt for T {}
 
 /// A read-only snapshot of a storage backend.
 ///
@@ -635,6 +1079,34 @@ pub trait Snapshot: Send + Sync + 'static {
     /// Returns an iterator over the entries of the snapshot in ascending order starting from
     /// the specified key. The iterator element type is `(&[u8], &[u8])`.
     fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_>;
+
+    /// Returns an iterator over the entries of the snapshot in descending order starting from
+    /// the specified upper bound. The iterator element type is `(&[u8], &[u8])`.
+    ///
+    /// `Bound::Unbounded` means that the iteration starts from the last key of the view.
+    ///
+    /// The default implementation collects the entries of the view using [`iter`](#tymethod.iter)
+    /// and thus consumes memory linear w.r.t. the view size. Backends should override it
+    /// with a more efficient implementation.
+    fn iter_rev(&self, name: &ResolvedAddress, upper_bound: Bound<&[u8]>) -> Iter<'_> {
+        let mut entries = vec![];
+        let mut iter = self.iter(name, &[]);
+        while let Some((key, value)) = iter.next() {
+            let is_in_bound = match upper_bound {
+                Bound::Included(bound) => key <= bound,
+                Bound::Excluded(bound) => key < bound,
+                Bound::Unbounded => true,
+            };
+            if !is_in_bound {
+                break;
+            }
+            entries.push((key.to_vec(), value.to_vec()));
+        }
+        Box::new(BufferedRevIter {
+            entries,
+            current: None,
+        })
+    }
 }
 
 /// A trait that defines a streaming iterator over storage view entries. Unlike
@@ -653,6 +1125,37 @@ impl Patch {
     pub(crate) fn into_changes(self) -> HashMap<ResolvedAddress, ViewChanges> {
         self.changes
     }
+
+    /// Returns the snapshot this patch is based on.
+    pub(crate) fn base_snapshot(&self) -> &dyn Snapshot {
+        &*self.snapshot
+    }
+
+    /// Iterates over addresses of the views changed in this patch and the corresponding changes.
+    pub(crate) fn changes(&self) -> impl StdIterator<Item = (&ResolvedAddress, &ViewChanges)> {
+        self.changes.iter()
+    }
+
+    /// Returns the approximate size of changes in this patch in bytes. The size is computed
+    /// in the same way as for [`Fork`].
+    ///
+    /// [`Fork`]: struct.Fork.html#method.size
+    pub fn size(&self) -> usize {
+        self.changes.values().map(ViewChanges::size).sum()
+    }
+
+    /// Iterates over the approximate sizes of changes in individual indexes of this patch.
+    /// The order of iteration is not specified.
+    pub fn index_sizes(&self) -> impl StdIterator<Item = (&ResolvedAddress, usize)> {
+        self.changes
+            .iter()
+            .map(|(address, changes)| (address, changes.size()))
+    }
+
+    /// Returns changes in the view with the specified address, if any.
+    pub(crate) fn view_changes(&self, address: &ResolvedAddress) -> Option<&ViewChanges> {
+        self.changes.get(address)
+    }
 }
 
 impl Snapshot for Patch {
@@ -685,7 +1188,35 @@ impl Snapshot for Patch {
             // Ignore all changes from the snapshot.
             Box::new(ChangesIter::new(changes_iter.unwrap()))
         } else {
-            Box::new(ForkIter::new(self.snapshot.iter(name, from), changes_iter))
+            let snapshot_iter = self.snapshot.iter(name, from);
+            let snapshot_iter = match maybe_changes {
+                Some(changes) => changes.skip_removed(&*self.snapshot, name, snapshot_iter, false),
+                None => snapshot_iter,
+            };
+            Box::new(ForkIter::new(snapshot_iter, changes_iter))
+        }
+    }
+
+    fn iter_rev(&self, name: &ResolvedAddress, upper_bound: Bound<&[u8]>) -> Iter<'_> {
+        let maybe_changes = self.changes.get(name);
+        let changes_iter = maybe_changes.map(|changes| {
+            changes
+                .data
+                .range::<[u8], _>((Bound::Unbounded, upper_bound))
+                .rev()
+        });
+
+        let is_cleared = maybe_changes.map_or(false, ViewChanges::is_cleared);
+        if is_cleared {
+            // Ignore all changes from the snapshot.
+            Box::new(ChangesIter::new(changes_iter.unwrap()))
+        } else {
+            let snapshot_iter = self.snapshot.iter_rev(name, upper_bound);
+            let snapshot_iter = match maybe_changes {
+                Some(changes) => changes.skip_removed(&*self.snapshot, name, snapshot_iter, true),
+                None => snapshot_iter,
+            };
+            Box::new(ForkIter::new_rev(snapshot_iter, changes_iter))
         }
     }
 }
@@ -713,27 +1244,107 @@ impl Fork {
     /// If no `flush` method had been called before, finalizes all changes that were
     /// made after creation of `Fork`.
     pub fn flush(&mut self) {
-        let working_patch = mem::replace(&mut self.working_patch, WorkingPatch::new());
+        let size_limit = self.working_patch.size_limit;
+        let working_patch = mem::replace(&mut self.working_patch, WorkingPatch::default());
         working_patch.merge_into(&mut self.patch);
+        self.working_patch = WorkingPatch::new(self.patch.size(), size_limit);
+    }
+
+    /// Returns the approximate size of changes in this fork in bytes.
+    ///
+    /// The size is computed as the total length of changed keys and values
+    /// (plus the bounds of removed key ranges); it does not depend on the platform
+    /// or on the memory allocator. Changes made since the last [`flush`] may be counted
+    /// more than once if they override the flushed changes.
+    ///
+    /// [`flush`]: #method.flush
+    pub fn size(&self) -> usize {
+        self.working_patch.size.get()
+    }
+
+    /// Returns the soft limit on the size of changes in this fork, if any.
+    pub fn size_limit(&self) -> Option<usize> {
+        self.working_patch.size_limit
+    }
+
+    /// Sets a soft limit on the size of changes in this fork (as per [`size`]),
+    /// or removes the limit if `None` is passed. The limit persists across
+    /// [`flush`]es and [`rollback`]s.
+    ///
+    /// If a write to an index makes the fork size exceed the limit, the write panics
+    /// with a [`SizeLimitExceeded`] payload; writes that do not increase the size
+    /// (e.g., clearing an index) are not affected. Since the size is platform-independent,
+    /// exceeding the limit is deterministic. The limit is checked before the write is applied,
+    /// so the failed write does not change the fork. However, an index operation may consist
+    /// of several writes (e.g., pushing to a list), so the panic should be caught with
+    /// [`catch_unwind`] and the changes since the last `flush` discarded with [`rollback`].
+    ///
+    /// System writes, such as updates of the index metadata or the state aggregator
+    /// performed by [`into_patch`], [`move_index`] or migration-related methods, are
+    /// not subject to the limit, so these methods never panic because of it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, Database, SizeLimitExceeded, TemporaryDB};
+    /// # use std::panic::{self, AssertUnwindSafe};
+    /// let db = TemporaryDB::new();
+    /// let mut fork = db.fork();
+    /// fork.set_size_limit(Some(1_024));
+    /// fork.get_list("list").push(vec![0_u8; 512]);
+    /// fork.flush();
+    ///
+    /// let res = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     fork.get_list("list").push(vec![0_u8; 1_024]);
+    /// }));
+    /// let err = res.unwrap_err();
+    /// assert!(err.downcast_ref::<SizeLimitExceeded>().is_some());
+    /// fork.rollback();
+    /// assert_eq!(fork.get_list::<_, Vec<u8>>("list").len(), 1);
+    /// ```
+    ///
+    /// [`size`]: #method.size
+    /// [`flush`]: #method.flush
+    /// [`rollback`]: #method.rollback
+    /// [`into_patch`]: #method.into_patch
+    /// [`move_index`]: #method.move_index
+    /// [`SizeLimitExceeded`]: struct.SizeLimitExceeded.html
+    /// [`catch_unwind`]: https://doc.rust-lang.org/std/panic/fn.catch_unwind.html
+    pub fn set_size_limit(&mut self, limit: Option<usize>) {
+        self.working_patch.size_limit = limit;
+    }
+
+    /// Runs the provided closure with the size limit of the fork lifted. This is used
+    /// for system writes (e.g., updates of the index metadata or the state aggregator),
+    /// which must not fail regardless of the fork size.
+    fn without_size_limit<R>(&mut self, action: impl FnOnce(&mut Self) -> R) -> R {
+        let size_limit = self.working_patch.size_limit.take();
+        let output = action(self);
+        self.working_patch.size_limit = size_limit;
+        output
     }
 
     /// Finishes a migration of indexes with the specified prefix.
     pub fn flush_migration(&mut self, prefix: &str) {
         assert_valid_name_component(prefix);
+        self.without_size_limit(|fork| fork.flush_migration_inner(prefix));
+    }
 
+    fn flush_migration_inner(&mut self, prefix: &str) {
         // Mutable `self` reference ensures that no indexes are instantiated in the client code.
         self.flush(); // Flushing is necessary to keep `self.patch` up to date.
 
         // Update aggregation attribution of indexes.
-        for namespace in self.patch.changed_aggregated_addrs.values_mut() {
-            if namespace == prefix {
-                namespace.clear();
+        for key in self.patch.changed_aggregated_addrs.values_mut() {
+            if key.namespace == prefix {
+                key.namespace.clear();
             }
         }
         // Move aggregated indexes info from the `prefix` namespace into the default namespace.
         SystemSchema::new(&*self).merge_namespace(prefix);
 
         let removed_addrs = IndexesPool::new(&*self).flush_migration(&prefix);
+        let patch_size = self.patch.size();
         for (addr, is_removed_from_aggregation) in removed_addrs {
             self.patch.changed_aggregated_addrs.remove(&addr);
             if is_removed_from_aggregation {
@@ -743,12 +1354,134 @@ impl Fork {
             }
             self.patch.changes.entry(addr).or_default().clear();
         }
+        self.update_size(patch_size);
+    }
+
+    /// Updates the fork size after the flushed changes were modified directly.
+    fn update_size(&mut self, old_patch_size: usize) {
+        let size = self.working_patch.size.get() - old_patch_size + self.patch.size();
+        self.working_patch.size.set(size);
+    }
+
+    /// Enables hierarchical state aggregation for indexes with the specified prefix, i.e.,
+    /// indexes with the name equal to `prefix` or starting with `prefix.`. Such indexes
+    /// are aggregated in a separate aggregator, the hash of which is placed into the default
+    /// state aggregator under the `prefix` key. Enabling aggregation for the prefix multiple
+    /// times has no effect; once enabled, hierarchical aggregation cannot be disabled.
+    ///
+    /// Enabling hierarchical aggregation changes the database state hash, so all nodes
+    /// sharing the state must enable it for the same prefixes at the same point.
+    ///
+    /// See [`SystemSchema`] for more details.
+    ///
+    /// [`SystemSchema`]: struct.SystemSchema.html#hierarchical-aggregation
+    ///
+    /// # Panics
+    ///
+    /// Panics if the prefix is not a valid name component (e.g., contains a dot `.`).
+    pub fn enable_prefix_aggregation(&mut self, prefix: &str) {
+        assert_valid_name_component(prefix);
+        // Mutable `self` reference ensures that the state aggregator is not instantiated
+        // in the client code.
+        self.without_size_limit(|fork| {
+            SystemSchema::new(&*fork).enable_prefix_aggregation(prefix);
+        });
+    }
+
+    /// Moves an index to a new address. If `from` does not contain a key part, all indexes
+    /// in the group with the address `from` are moved as well; the keys of the moved indexes
+    /// within the group are appended to `to`.
+    ///
+    /// Moving an index does not copy its data, so the operation takes constant time
+    /// regardless of the index size. The state aggregator is updated accordingly
+    /// when the fork is converted into a patch: an aggregated index is removed from
+    /// aggregation under its old name and is aggregated under the new one.
+    ///
+    /// Like `flush_migration`, this method borrows the fork mutably, which ensures that
+    /// no indexes are instantiated in the client code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the addresses is invalid or reserved, if there is no index
+    /// to move, or if an index already exists at one of the target addresses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, Database, SystemSchema, TemporaryDB};
+    /// let db = TemporaryDB::new();
+    /// let mut fork = db.fork();
+    /// fork.get_proof_list("old_list").extend(vec![1_u32, 2, 3]);
+    /// fork.get_map(("group", &1_u8)).put(&1_u8, "!".to_owned());
+    ///
+    /// fork.move_index("old_list", "new_list").unwrap();
+    /// fork.move_index("group", "new_group").unwrap();
+    /// let list = fork.get_proof_list::<_, u32>("new_list");
+    /// assert_eq!(list.len(), 3);
+    /// let map = fork.get_map::<_, u8, String>(("new_group", &1_u8));
+    /// assert_eq!(map.get(&1).unwrap(), "!");
+    ///
+    /// let patch = fork.into_patch();
+    /// let aggregator = SystemSchema::new(&patch).state_aggregator();
+    /// assert!(!aggregator.contains("old_list"));
+    /// assert!(aggregator.contains("new_list"));
+    /// ```
+    pub fn move_index(
+        &mut self,
+        from: impl Into<IndexAddress>,
+        to: impl Into<IndexAddress>,
+    ) -> StdResult<(), AccessError> {
+        let from = from.into();
+        let to = to.into();
+        for addr in &[&from, &to] {
+            check_index_valid_full_name(addr.name()).map_err(|kind| AccessError {
+                addr: (*addr).clone(),
+                kind,
+            })?;
+        }
+
+        self.without_size_limit(|fork| fork.move_index_inner(&from, &to))
+    }
+
+    fn move_index_inner(
+        &mut self,
+        from: &IndexAddress,
+        to: &IndexAddress,
+    ) -> StdResult<(), AccessError> {
+        // Flushing is necessary to keep `self.patch` up to date.
+        self.flush();
+
+        let moved_indexes = IndexesPool::new(&*self).move_index(from, to)?;
+        for (old_address, new_address, metadata) in moved_indexes {
+            let resolved = metadata.resolve(&new_address);
+            let index_type = metadata.index_type();
+            let was_aggregated = old_address.id_in_group().is_none() && index_type.is_merkelized();
+            let is_aggregated = new_address.id_in_group().is_none() && index_type.is_merkelized();
+
+            if was_aggregated {
+                self.patch
+                    .removed_aggregated_addrs
+                    .insert(old_address.name().to_owned());
+            }
+            if is_aggregated {
+                let new_name = new_address.name().to_owned();
+                self.patch.removed_aggregated_addrs.remove(&new_name);
+                let key = AggregationKey {
+                    namespace: String::new(),
+                    name: new_name,
+                };
+                self.patch.changed_aggregated_addrs.insert(resolved, key);
+            } else {
+                self.patch.changed_aggregated_addrs.remove(&resolved);
+            }
+        }
+        Ok(())
     }
 
     /// Rolls back all changes that were made after the latest execution
     /// of the `flush` method.
     pub fn rollback(&mut self) {
-        self.working_patch = WorkingPatch::new();
+        self.working_patch = WorkingPatch::new(self.patch.size(), self.working_patch.size_limit);
     }
 
     /// Rolls back the migration with the specified name. This will remove all indexes
@@ -756,16 +1489,23 @@ impl Fork {
     pub fn rollback_migration(&mut self, prefix: &str) {
         assert_valid_name_component(prefix);
         self.flush();
-        SystemSchema::new(&*self).remove_namespace(prefix);
-        let removed_addrs = IndexesPool::new(&*self).rollback_migration(&prefix);
+        let removed_addrs = self.without_size_limit(|fork| {
+            SystemSchema::new(&*fork).remove_namespace(prefix);
+            IndexesPool::new(&*fork).rollback_migration(&prefix)
+        });
+        let patch_size = self.patch.size();
         for addr in &removed_addrs {
             self.patch.changed_aggregated_addrs.remove(addr);
             self.patch.changes.remove(addr);
         }
+        self.update_size(patch_size);
     }
 
     /// Converts the fork into `Patch` consuming the fork instance.
     pub fn into_patch(mut self) -> Patch {
+        // Updates of the state aggregator are system writes, so they are not subject
+        // to the size limit.
+        self.working_patch.size_limit = None;
         self.flush();
 
         // Replacing `changed_aggregated_addrs` has a beneficial side-effect: if the patch
@@ -773,18 +1513,19 @@ impl Fork {
         // its state aggregator unless the *new* changes in the `Fork` concern aggregated indexes.
         let changed_aggregated_addrs =
             mem::replace(&mut self.patch.changed_aggregated_addrs, HashMap::new());
-        let updated_entries = changed_aggregated_addrs.into_iter().map(|(addr, ns)| {
-            let index_name = addr.name.clone();
-            let is_in_migration = !ns.is_empty();
-            let index_hash = get_object_hash(&self.patch, addr, is_in_migration);
-            (ns, index_name, index_hash)
-        });
-        SystemSchema::new(&self).update_state_aggregators(updated_entries);
-
+        // Removals are applied first, since an index may be aggregated under the name
+        // of a removed index (e.g., if the removed index was moved to another address).
         let removed_aggregated_addrs =
             mem::replace(&mut self.patch.removed_aggregated_addrs, HashSet::new());
         SystemSchema::new(&self).remove_aggregated_indexes(removed_aggregated_addrs);
 
+        let updated_entries = changed_aggregated_addrs.into_iter().map(|(_, key)| {
+            let is_in_migration = !key.namespace.is_empty();
+            let index_hash = get_object_hash(&self.patch, &key.name, is_in_migration);
+            (key.namespace, key.name, index_hash)
+        });
+        SystemSchema::new(&self).update_state_aggregators(updated_entries);
+
         self.flush(); // flushes changes in the state aggregator
         self.patch
     }
@@ -804,9 +1545,10 @@ impl From<Patch> for Fork {
     /// to an inconsistent database state. Hence, this method is useful only if you
     /// are sure that the fork and `patch` interacted with different indexes.
     fn from(patch: Patch) -> Self {
+        let size = patch.size();
         Self {
             patch,
-            working_patch: WorkingPatch::new(),
+            working_patch: WorkingPatch::new(size, None),
         }
     }
 }
@@ -819,12 +1561,10 @@ impl<'a> RawAccess for &'a Fork {
     }
 
     fn changes(&self, address: &ResolvedAddress) -> Self::Changes {
-        let changes = self.working_patch.take_view_changes(address);
-        ChangesMut {
-            changes,
-            key: address.clone(),
-            parent: WorkingPatchRef::Borrowed(&self.working_patch),
-        }
+        ChangesMut::new(
+            WorkingPatchRef::Borrowed(&self.working_patch),
+            address.clone(),
+        )
     }
 }
 
@@ -836,12 +1576,7 @@ impl RawAccess for Rc<Fork> {
     }
 
     fn changes(&self, address: &ResolvedAddress) -> Self::Changes {
-        let changes = self.working_patch.take_view_changes(address);
-        ChangesMut {
-            changes,
-            key: address.clone(),
-            parent: WorkingPatchRef::Owned(Self::clone(self)),
-        }
+        ChangesMut::new(WorkingPatchRef::Owned(Self::clone(self)), address.clone())
     }
 }
 
@@ -938,6 +1673,10 @@ impl Snapshot for Box<dyn Snapshot> {
     fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_> {
         self.as_ref().iter(name, from)
     }
+
+    fn iter_rev(&self, name: &ResolvedAddress, upper_bound: Bound<&[u8]>) -> Iter<'_> {
+        self.as_ref().iter_rev(name, upper_bound)
+    }
 }
 
 impl<'a, T> ForkIter<'a, T>
@@ -948,22 +1687,44 @@ where
         ForkIter {
             snapshot,
             changes: changes.map(StdIterator::peekable),
+            reversed: false,
+        }
+    }
+
+    /// Creates an iterator over descending entries. Both `snapshot` and `changes`
+    /// must yield entries in descending order.
+    pub fn new_rev(snapshot: Iter<'a>, changes: Option<T>) -> Self {
+        ForkIter {
+            snapshot,
+            changes: changes.map(StdIterator::peekable),
+            reversed: true,
         }
     }
 
     fn step(&mut self) -> NextIterValue {
         use std::cmp::Ordering::*;
 
+        let reversed = self.reversed;
+        // Compares keys in the iteration order.
+        let cmp_keys = |change_key: &[u8], snapshot_key: &[u8]| {
+            let ordering = change_key.cmp(snapshot_key);
+            if reversed {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+
         if let Some(ref mut changes) = self.changes {
             match changes.peek() {
                 Some(&(k, change)) => match self.snapshot.peek() {
                     Some((key, ..)) => match *change {
-                        Change::Put(..) => match k[..].cmp(key) {
+                        Change::Put(..) => match cmp_keys(&k[..], key) {
                             Equal => NextIterValue::Replaced,
                             Less => NextIterValue::Inserted,
                             Greater => NextIterValue::Stored,
                         },
-                        Change::Delete => match k[..].cmp(key) {
+                        Change::Delete => match cmp_keys(&k[..], key) {
                             Equal => NextIterValue::Deleted,
                             Less => NextIterValue::MissDeleted,
                             Greater => NextIterValue::Stored,
@@ -1059,6 +1820,28 @@ where
     }
 }
 
+/// Iterator over the buffered entries in descending order.
+struct BufferedRevIter {
+    /// Remaining entries in ascending order.
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    current: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Iterator for BufferedRevIter {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.current = self.entries.pop();
+        self.current
+            .as_ref()
+            .map(|(key, value)| (key.as_slice(), value.as_slice()))
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        self.entries
+            .last()
+            .map(|(key, value)| (key.as_slice(), value.as_slice()))
+    }
+}
+
 impl fmt::Debug for dyn Database {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Database").finish()
@@ -1091,14 +1874,7 @@ pub fn check_database(db: &mut dyn Database) -> Result<()> {
         let addr = ResolvedAddress::system(DB_METADATA);
         let mut view = View::new(&fork, addr);
         if let Some(saved_version) = view.get::<_, u8>(VERSION_NAME) {
-            if saved_version != DB_VERSION {
-                return Err(Error::new(format!(
-                    "Database version doesn't match: actual {}, expected {}",
-                    saved_version, DB_VERSION
-                )));
-            }
-
-            return Ok(());
+            return check_version(saved_version);
         } else {
             view.put(VERSION_NAME, DB_VERSION);
         }
@@ -1106,6 +1882,27 @@ pub fn check_database(db: &mut dyn Database) -> Result<()> {
     db.merge(fork.into_patch())
 }
 
+/// Checks the version of a database which cannot be written to. Unlike `check_database`,
+/// a missing version is not considered an error.
+pub fn check_readonly_database(db: &dyn Database) -> Result<()> {
+    let snapshot = db.snapshot();
+    let addr = ResolvedAddress::system(DB_METADATA);
+    let view = View::new(&*snapshot, addr);
+    view.get::<_, u8>(VERSION_NAME)
+        .map_or(Ok(()), check_version)
+}
+
+fn check_version(saved_version: u8) -> Result<()> {
+    if saved_version == DB_VERSION {
+        Ok(())
+    } else {
+        Err(Error::new(format!(
+            "Database version doesn't match: actual {}, expected {}",
+            saved_version, DB_VERSION
+        )))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1292,6 +2089,281 @@ mod tests {
         assert!(backup.get_list::<_, u32>(("foo", &1_u8)).is_empty());
     }
 
+    #[test]
+    fn historical_snapshots() {
+        let db = TemporaryDB::new();
+        let options = HistoryOptions::default();
+        assert!(db.snapshot_at(0).is_none());
+
+        let mut state_hashes = vec![];
+        for version in 0..5_u64 {
+            // Perform several merges with the same version.
+            for i in 0..version {
+                let fork = db.fork();
+                fork.get_map(("map", &i)).put(&version, version.to_string());
+                db.merge_with_history(fork.into_patch(), version, &options)
+                    .unwrap();
+            }
+
+            let fork = db.fork();
+            {
+                let mut list = fork.get_proof_list("list");
+                list.push(version);
+                if version == 3 {
+                    list.clear();
+                }
+            }
+            fork.get_proof_entry("entry").set(version);
+            let patch = fork.into_patch();
+            state_hashes.push(SystemSchema::new(&patch).state_hash());
+            db.merge_with_history(patch, version, &options).unwrap();
+        }
+
+        for version in 0..5_u64 {
+            let snapshot = db.snapshot_at(version).unwrap();
+            assert_eq!(
+                SystemSchema::new(&snapshot).state_hash(),
+                state_hashes[version as usize]
+            );
+
+            let list = snapshot.get_proof_list::<_, u64>("list");
+            let expected_items: Vec<_> = if version < 3 {
+                (0..=version).collect()
+            } else {
+                (4..=version).collect()
+            };
+            assert_eq!(list.iter().collect::<Vec<_>>(), expected_items);
+            let entry = snapshot.get_proof_entry::<_, u64>("entry");
+            assert_eq!(entry.get(), Some(version));
+
+            for i in 0..5 {
+                let map = snapshot.get_map::<_, u64, String>(("map", &i));
+                let expected_keys: Vec<_> = (i + 1..=version).collect();
+                assert_eq!(map.keys().collect::<Vec<_>>(), expected_keys);
+                if expected_keys.is_empty() {
+                    assert!(snapshot.index_type(("map", &i)).is_none());
+                }
+            }
+        }
+
+        // Versions exceeding the latest merged version correspond to the current state.
+        let snapshot = db.snapshot_at(10).unwrap();
+        assert_eq!(SystemSchema::new(&snapshot).state_hash(), state_hashes[4]);
+    }
+
+    #[test]
+    fn removed_ranges_are_merged() {
+        let mut changes = ViewChanges::new();
+        changes.put(vec![1], Change::Put(vec![1]));
+        changes.put(vec![5], Change::Put(vec![5]));
+        changes.remove_range(vec![3], Some(vec![6]));
+        changes.remove_range(vec![5], Some(vec![8]));
+        changes.remove_range(vec![8], Some(vec![9]));
+        changes.remove_range(vec![10], None);
+        changes.remove_range(vec![0], Some(vec![1]));
+
+        assert_eq!(changes.data.keys().collect::<Vec<_>>(), vec![&vec![1]]);
+        assert_eq!(
+            changes.removed_ranges().collect::<Vec<_>>(),
+            vec![
+                (&[0_u8][..], Some(&[1_u8][..])),
+                (&[3][..], Some(&[9][..])),
+                (&[10][..], None),
+            ]
+        );
+        assert_eq!(changes.get(&[1]), Ok(Some(vec![1])));
+        assert_eq!(changes.get(&[0]), Ok(None));
+        assert_eq!(changes.get(&[8]), Ok(None));
+        assert_eq!(changes.get(&[9]), Err(()));
+        assert_eq!(changes.get(&[42]), Ok(None));
+    }
+
+    #[test]
+    fn backup_and_history_with_removed_ranges() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        {
+            let mut map = fork.get_map("map");
+            for i in 0..10_u64 {
+                map.put(&i, i);
+            }
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        fork.get_map::<_, u64, u64>("map").remove_range(3..7);
+        let backup = db.merge_with_backup(fork.into_patch()).unwrap();
+        let map = backup.get_map::<_, u64, u64>("map");
+        assert_eq!(map.keys().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        db.merge(backup).unwrap();
+
+        let options = HistoryOptions::default();
+        db.merge_with_history(db.fork().into_patch(), 0, &options)
+            .unwrap();
+        let fork = db.fork();
+        fork.get_map::<_, u64, u64>("map").remove_range(5..);
+        db.merge_with_history(fork.into_patch(), 1, &options)
+            .unwrap();
+
+        let snapshot = db.snapshot();
+        let map = snapshot.get_map::<_, u64, u64>("map");
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        let snapshot = db.snapshot_at(0).unwrap();
+        let map = snapshot.get_map::<_, u64, u64>("map");
+        assert_eq!(map.keys().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn historical_snapshots_with_retention() {
+        let db = TemporaryDB::new();
+        let options = HistoryOptions {
+            retained_versions: Some(2),
+        };
+        for version in 5..10_u64 {
+            let fork = db.fork();
+            fork.get_list("list").push(version);
+            db.merge_with_history(fork.into_patch(), version, &options)
+                .unwrap();
+        }
+
+        for version in 0..7 {
+            assert!(db.snapshot_at(version).is_none());
+        }
+        for version in 7..10 {
+            let snapshot = db.snapshot_at(version).unwrap();
+            let list = snapshot.get_list::<_, u64>("list");
+            assert_eq!(
+                list.iter().collect::<Vec<_>>(),
+                (5..=version).collect::<Vec<_>>()
+            );
+        }
+
+        let snapshot = db.snapshot();
+        let records = View::new(&snapshot, ResolvedAddress::system("__HISTORY_RECORDS__"));
+        assert_eq!(records.iter::<_, [u8], ()>(&()).count(), 2);
+    }
+
+    #[test]
+    fn historical_snapshots_with_overlapping_keys() {
+        let db = TemporaryDB::new();
+        let options = HistoryOptions::default();
+        let keys = vec![
+            vec![0_u8],
+            vec![0, 0],
+            vec![0, 1],
+            vec![1],
+            vec![1, 0],
+            vec![1, 0, 0],
+            vec![255],
+        ];
+
+        let fork = db.fork();
+        {
+            let mut map = fork.get_map("map");
+            for key in &keys {
+                map.put(key, 0_u32);
+            }
+        }
+        db.merge_with_history(fork.into_patch(), 0, &options)
+            .unwrap();
+        for version in 1..3 {
+            let fork = db.fork();
+            {
+                let mut map = fork.get_map::<_, Vec<u8>, u32>("map");
+                map.put(&vec![0], version);
+                map.remove(&vec![0, 0]);
+                map.put(&vec![1, 0, 0, 0], version);
+                map.put(&vec![2], version);
+            }
+            db.merge_with_history(fork.into_patch(), version.into(), &options)
+                .unwrap();
+        }
+
+        let snapshot = db.snapshot_at(0).unwrap();
+        let map = snapshot.get_map::<_, Vec<u8>, u32>("map");
+        let expected: Vec<_> = keys.iter().map(|key| (key.clone(), 0)).collect();
+        assert_eq!(map.iter().collect::<Vec<_>>(), expected);
+        let mut expected_rev = expected.clone();
+        expected_rev.reverse();
+        assert_eq!(map.iter_rev().collect::<Vec<_>>(), expected_rev);
+        assert_eq!(
+            map.iter_from(&vec![0, 0]).collect::<Vec<_>>(),
+            expected[1..].to_vec()
+        );
+        assert_eq!(
+            map.iter_rev_from(&vec![1, 0]).collect::<Vec<_>>(),
+            expected_rev[2..].to_vec()
+        );
+        assert_eq!(map.get(&vec![0, 0]), Some(0));
+        assert_eq!(map.get(&vec![2]), None);
+
+        let snapshot = db.snapshot_at(1).unwrap();
+        let map = snapshot.get_map::<_, Vec<u8>, u32>("map");
+        assert_eq!(map.get(&vec![0]), Some(1));
+        assert_eq!(map.get(&vec![0, 0]), None);
+        assert_eq!(
+            map.keys().collect::<Vec<_>>(),
+            vec![
+                vec![0],
+                vec![0, 1],
+                vec![1],
+                vec![1, 0],
+                vec![1, 0, 0],
+                vec![1, 0, 0, 0],
+                vec![2],
+                vec![255],
+            ]
+        );
+    }
+
+    #[test]
+    fn default_reverse_iteration() {
+        /// Snapshot relying on the default implementation of `iter_rev`.
+        struct ForwardOnly(Box<dyn Snapshot>);
+
+        impl Snapshot for ForwardOnly {
+            fn get(&self, name: &ResolvedAddress, key: &[u8]) -> Option<Vec<u8>> {
+                self.0.get(name, key)
+            }
+
+            fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_> {
+                self.0.iter(name, from)
+            }
+        }
+
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_list("list").extend(0_u32..5);
+        fork.get_list("other").push(10_u32);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot: Box<dyn Snapshot> = Box::new(ForwardOnly(db.snapshot()));
+        let list = snapshot.get_list::<_, u32>("list");
+        assert_eq!(list.iter_rev().collect::<Vec<_>>(), vec![4, 3, 2, 1, 0]);
+        assert_eq!(list.iter_rev_from(2).collect::<Vec<_>>(), vec![2, 1, 0]);
+        assert_eq!(list.iter_rev().take(2).collect::<Vec<_>>(), vec![4, 3]);
+    }
+
+    #[test]
+    fn history_with_decreasing_versions() {
+        let db = TemporaryDB::new();
+        let options = HistoryOptions::default();
+        let fork = db.fork();
+        fork.get_list("list").push(1_u32);
+        db.merge_with_history(fork.into_patch(), 1, &options)
+            .unwrap();
+        let fork = db.fork();
+        fork.get_list("list").push(2_u32);
+        let err = db
+            .merge_with_history(fork.into_patch(), 0, &options)
+            .unwrap_err();
+        assert!(err.to_string().contains("must not decrease"));
+
+        // The failed merge does not affect the database.
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get_list::<_, u32>("list").len(), 1);
+    }
+
     #[test]
     fn updated_addrs_are_efficiently_updated() {
         let db = TemporaryDB::new();
@@ -1305,8 +2377,8 @@ mod tests {
             .patch
             .changed_aggregated_addrs
             .iter()
-            .map(|(addr, ns)| {
-                assert!(ns.is_empty());
+            .map(|(addr, key)| {
+                assert!(key.namespace.is_empty());
                 addr.name.as_str()
             })
             .collect();
@@ -1380,4 +2452,246 @@ mod tests {
         // Since the index is already created, this should lead to a panic.
         let _readonly_entry = fork.readonly().get_entry::<_, u32>("entry");
     }
+
+    #[test]
+    fn moving_indexes() {
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        fork.get_proof_list("list").extend(vec![1_u32, 2, 3]);
+        fork.get_proof_entry("entry").set("!".to_owned());
+        fork.get_map("map").put(&1_u32, 2_u32);
+        db.merge(fork.into_patch()).unwrap();
+
+        let mut fork = db.fork();
+        fork.move_index("list", "moved.list").unwrap();
+        fork.move_index("entry", "moved.entry").unwrap();
+        fork.move_index("map", ("moved.map", &1_u8)).unwrap();
+        assert_eq!(fork.index_type("list"), None);
+        assert_eq!(fork.get_proof_list::<_, u32>("moved.list").len(), 3);
+        // Moved indexes can be modified.
+        fork.get_proof_list("moved.list").push(4_u32);
+        // The old address can be reused.
+        fork.get_proof_list("list").push(5_u32);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let list = snapshot.get_proof_list::<_, u32>("moved.list");
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        let new_list = snapshot.get_proof_list::<_, u32>("list");
+        assert_eq!(new_list.iter().collect::<Vec<_>>(), vec![5]);
+        let entry = snapshot.get_proof_entry::<_, String>("moved.entry");
+        assert_eq!(entry.get().unwrap(), "!");
+        let map = snapshot.get_map::<_, u32, u32>(("moved.map", &1_u8));
+        assert_eq!(map.get(&1), Some(2));
+
+        let aggregator = SystemSchema::new(&snapshot).state_aggregator();
+        assert_eq!(
+            aggregator.keys().collect::<Vec<_>>(),
+            vec!["list", "moved.entry", "moved.list"]
+        );
+        assert_eq!(aggregator.get("moved.list"), Some(list.object_hash()));
+        assert_eq!(aggregator.get("list"), Some(new_list.object_hash()));
+        assert_eq!(aggregator.get("moved.entry"), Some(entry.object_hash()));
+
+        // Check that the state hash is the same as if the indexes were created
+        // at the new addresses.
+        let other_db = TemporaryDB::new();
+        let fork = other_db.fork();
+        fork.get_proof_list("moved.list")
+            .extend(vec![1_u32, 2, 3, 4]);
+        fork.get_proof_list("list").push(5_u32);
+        fork.get_proof_entry("moved.entry").set("!".to_owned());
+        let patch = fork.into_patch();
+        assert_eq!(
+            SystemSchema::new(&patch).state_hash(),
+            SystemSchema::new(&snapshot).state_hash()
+        );
+    }
+
+    #[test]
+    fn moving_index_groups() {
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        for i in 0_u8..3 {
+            fork.get_proof_list(("group", &i)).push(u32::from(i));
+        }
+        fork.get_proof_list("group").push(42_u32);
+        fork.get_proof_list("group_sibling").push(100_u32);
+
+        fork.move_index("group", "new_group").unwrap();
+        assert_eq!(fork.index_type("group"), None);
+        assert_eq!(fork.index_type(("group", &0_u8)), None);
+        let patch = fork.into_patch();
+        for i in 0_u8..3 {
+            let list = patch.get_proof_list::<_, u32>(("new_group", &i));
+            assert_eq!(list.get(0), Some(u32::from(i)));
+        }
+        assert_eq!(patch.get_proof_list::<_, u32>("new_group").len(), 1);
+        assert_eq!(patch.get_proof_list::<_, u32>("group_sibling").len(), 1);
+
+        let aggregator = SystemSchema::new(&patch).state_aggregator();
+        assert_eq!(
+            aggregator.keys().collect::<Vec<_>>(),
+            vec!["group_sibling", "new_group"]
+        );
+
+        // Move the index back.
+        let mut fork = Fork::from(patch);
+        fork.move_index("new_group", "group").unwrap();
+        let patch = fork.into_patch();
+        assert_eq!(patch.get_proof_list::<_, u32>(("group", &2_u8)).len(), 1);
+        let aggregator = SystemSchema::new(&patch).state_aggregator();
+        assert_eq!(
+            aggregator.keys().collect::<Vec<_>>(),
+            vec!["group", "group_sibling"]
+        );
+    }
+
+    #[test]
+    fn moving_index_errors() {
+        use crate::access::AccessErrorKind;
+        use assert_matches::assert_matches;
+
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        fork.get_list("list").push(1_u32);
+        fork.get_list("other_list").push(2_u32);
+
+        let err = fork.move_index("non_existing", "foo").unwrap_err();
+        assert_matches!(err.kind, AccessErrorKind::DoesNotExist);
+        let err = fork.move_index("list", "other_list").unwrap_err();
+        assert_matches!(err.kind, AccessErrorKind::AlreadyExists);
+        let err = fork.move_index("list", "__reserved").unwrap_err();
+        assert_matches!(err.kind, AccessErrorKind::ReservedName);
+        let err = fork.move_index("list", "invalid name").unwrap_err();
+        assert_matches!(err.kind, AccessErrorKind::InvalidCharsInName { .. });
+
+        // Failed moves do not change the fork.
+        assert_eq!(fork.get_list::<_, u32>("list").len(), 1);
+        assert_eq!(fork.get_list::<_, u32>("other_list").len(), 1);
+    }
+
+    #[test]
+    fn view_changes_size() {
+        let mut changes = ViewChanges::new();
+        changes.put(vec![1], Change::Put(vec![0; 10]));
+        changes.put(vec![2, 2], Change::Delete);
+        assert_eq!(changes.size(), 1 + 10 + 2);
+        changes.put(vec![1], Change::Put(vec![0; 5]));
+        assert_eq!(changes.size(), 1 + 5 + 2);
+
+        changes.remove_range(vec![0], Some(vec![2]));
+        assert_eq!(changes.size(), 2 + 1 + 1);
+        changes.remove_range(vec![1], None);
+        assert_eq!(changes.size(), 1);
+        let mut recomputed = changes.clone();
+        recomputed.update_size();
+        assert_eq!(recomputed.size(), changes.size());
+
+        changes.clear();
+        assert_eq!(changes.size(), 0);
+    }
+
+    #[test]
+    fn fork_size_is_tracked() {
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        assert_eq!(fork.size(), 0);
+        {
+            let mut list = fork.get_list("list");
+            list.push(vec![0_u8; 100]);
+            list.push(vec![0_u8; 100]);
+        }
+        let size = fork.size();
+        assert!(size > 200);
+        fork.flush();
+        assert_eq!(fork.size(), size);
+        assert_eq!(fork.size(), fork.patch.size());
+
+        fork.get_list::<_, Vec<u8>>("list").clear();
+        assert!(fork.size() < size);
+        fork.rollback();
+        assert_eq!(fork.size(), size);
+
+        let patch = fork.into_patch();
+        let list_size = patch.index_sizes().map(|(_, size)| size).max().unwrap();
+        assert!(list_size > 200);
+        assert!(patch.size() > list_size);
+        let fork = Fork::from(patch);
+        assert!(fork.size() > list_size);
+    }
+
+    #[test]
+    fn fork_size_limit() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        fork.set_size_limit(Some(1_000));
+        fork.get_list("list").extend(vec![vec![0_u8; 100]; 5]);
+        fork.flush();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            fork.get_list("list").extend(vec![vec![0_u8; 100]; 5]);
+        }));
+        let err = *res.unwrap_err().downcast::<SizeLimitExceeded>().unwrap();
+        assert_eq!(err.limit, 1_000);
+        assert!(err.size > 1_000);
+        // The failed write is not applied.
+        assert!(fork.size() <= 1_000);
+        let len = fork.get_list::<_, Vec<u8>>("list").len();
+        assert!(len > 5 && len < 10);
+
+        fork.rollback();
+        assert_eq!(fork.size_limit(), Some(1_000));
+        assert!(fork.size() < 1_000);
+        assert_eq!(fork.get_list::<_, Vec<u8>>("list").len(), 5);
+
+        // Writes that do not increase the size are allowed even if the limit is exceeded.
+        fork.set_size_limit(Some(10));
+        fork.get_list::<_, Vec<u8>>("list").clear();
+        fork.set_size_limit(None);
+        fork.get_list("list").extend(vec![vec![0_u8; 100]; 20]);
+    }
+
+    #[test]
+    fn failed_writes_are_not_applied() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        fork.get_entry("entry").set(vec![0_u8; 10]);
+        fork.flush();
+        let size = fork.size();
+        fork.set_size_limit(Some(size + 10));
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            fork.get_entry("entry").set(vec![1_u8; 100]);
+        }));
+        assert!(res.unwrap_err().is::<SizeLimitExceeded>());
+        assert_eq!(fork.size(), size);
+        let entry = fork.get_entry::<_, Vec<u8>>("entry");
+        assert_eq!(entry.get(), Some(vec![0_u8; 10]));
+    }
+
+    #[test]
+    fn system_writes_ignore_size_limit() {
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        fork.get_proof_list("list").extend(vec![vec![0_u8; 100]; 5]);
+        fork.flush();
+        let size = fork.size();
+        fork.set_size_limit(Some(size));
+
+        fork.move_index("list", "moved_list").unwrap();
+        assert_eq!(fork.size_limit(), Some(size));
+        fork.enable_prefix_aggregation("prefix");
+        fork.flush_migration("prefix");
+        assert_eq!(fork.size_limit(), Some(size));
+
+        let patch = fork.into_patch();
+        let aggregator = SystemSchema::new(&patch).state_aggregator();
+        assert!(aggregator.contains("moved_list"));
+        assert!(!aggregator.contains("list"));
+    }
 }
@@ -0,0 +1,742 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differences between database states.
+//!
+//! [`StateDiff`] lists indexes which differ between two database states, together with
+//! the changed entries of each index. A diff can be computed between two arbitrary snapshots,
+//! or between a [`Patch`] and the snapshot it is based on; the latter is useful to find out
+//! changes introduced by a block.
+//!
+//! # Entry keys
+//!
+//! Changes within an index are keyed by the index-level keys serialized with [`BinaryKey`]
+//! rather than by the keys of the underlying key-value storage:
+//!
+//! - For maps and `KeySetIndex`, the keys are the map keys / set elements
+//! - For `ValueSetIndex`, the keys are hashes of the set elements, and values are the elements
+//! - For lists, the keys are `u64` positions of the list elements
+//! - For entries, the key is empty (i.e., corresponds to `()`)
+//!
+//! Auxiliary data of Merkelized indexes (such as Merkle tree nodes) is not reported.
+//!
+//! [`StateDiff`]: struct.StateDiff.html
+//! [`Patch`]: ../struct.Patch.html
+//! [`BinaryKey`]: ../trait.BinaryKey.html
+//!
+//! # Examples
+//!
+//! ```
+//! # use exonum_merkledb::{access::AccessExt, diff::{EntryChange, StateDiff}, Database, TemporaryDB};
+//! let db = TemporaryDB::new();
+//! let fork = db.fork();
+//! fork.get_list("list").extend(vec![1_u32, 2]);
+//! db.merge(fork.into_patch()).unwrap();
+//!
+//! let fork = db.fork();
+//! fork.get_list("list").set(1, 3_u32);
+//! fork.get_entry("entry").set("foo".to_owned());
+//! let patch = fork.into_patch();
+//!
+//! let diff = StateDiff::from_patch(&patch);
+//! let index_names: Vec<_> = diff.iter().map(|index| index.address().name()).collect();
+//! assert_eq!(index_names, vec!["entry", "list"]);
+//!
+//! let list_diff = diff.index("list").unwrap();
+//! let changes = list_diff.typed_changes::<u64, u32>().unwrap();
+//! assert_eq!(changes, vec![(1, EntryChange::Modified { old: 2, new: 3 })]);
+//! ```
+
+use exonum_crypto::Hash;
+use failure::Error;
+
+use std::{borrow::Cow, cmp::Ordering, collections::BTreeMap, iter::Peekable, slice};
+
+use crate::{
+    db::Change,
+    indexes::{indexed_map::VALUES_PREFIX, proof_map::VALUE_KEY_PREFIX},
+    views::{IndexMetadata, IndexType, IndexesPool, View, ViewWithMetadata},
+    BinaryKey, BinaryValue, IndexAddress, ObjectHash, Patch, ProofListIndex, ProofMapIndex,
+    Snapshot,
+};
+
+/// Change of a single index entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryChange<V = Vec<u8>> {
+    /// The entry was added with the specified value.
+    Added(V),
+    /// The entry with the specified value was removed.
+    Removed(V),
+    /// The entry value was modified.
+    Modified {
+        /// Value before the change.
+        old: V,
+        /// Value after the change.
+        new: V,
+    },
+}
+
+impl<V> EntryChange<V> {
+    /// Returns the value of the entry before the change, or `None` if the entry was added.
+    pub fn old_value(&self) -> Option<&V> {
+        match self {
+            EntryChange::Added(_) => None,
+            EntryChange::Removed(old) | EntryChange::Modified { old, .. } => Some(old),
+        }
+    }
+
+    /// Returns the value of the entry after the change, or `None` if the entry was removed.
+    pub fn new_value(&self) -> Option<&V> {
+        match self {
+            EntryChange::Removed(_) => None,
+            EntryChange::Added(new) | EntryChange::Modified { new, .. } => Some(new),
+        }
+    }
+}
+
+impl EntryChange {
+    fn from_values(old: Option<Vec<u8>>, new: Option<Vec<u8>>) -> Option<Self> {
+        match (old, new) {
+            (None, None) => None,
+            (None, Some(new)) => Some(EntryChange::Added(new)),
+            (Some(old), None) => Some(EntryChange::Removed(old)),
+            (Some(old), Some(new)) => {
+                if old == new {
+                    None
+                } else {
+                    Some(EntryChange::Modified { old, new })
+                }
+            }
+        }
+    }
+
+    /// Decodes values in this change.
+    pub fn decode<V: BinaryValue>(&self) -> Result<EntryChange<V>, Error> {
+        let decode = |bytes: &Vec<u8>| V::from_bytes(Cow::Borrowed(bytes));
+        Ok(match self {
+            EntryChange::Added(new) => EntryChange::Added(decode(new)?),
+            EntryChange::Removed(old) => EntryChange::Removed(decode(old)?),
+            EntryChange::Modified { old, new } => EntryChange::Modified {
+                old: decode(old)?,
+                new: decode(new)?,
+            },
+        })
+    }
+}
+
+/// Differences in a single index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexDiff {
+    address: IndexAddress,
+    old_type: Option<IndexType>,
+    new_type: Option<IndexType>,
+    changes: BTreeMap<Vec<u8>, EntryChange>,
+}
+
+impl IndexDiff {
+    /// Returns the address of the index.
+    pub fn address(&self) -> &IndexAddress {
+        &self.address
+    }
+
+    /// Returns the type of the index in the old state, or `None` if the index did not exist.
+    pub fn old_type(&self) -> Option<IndexType> {
+        self.old_type
+    }
+
+    /// Returns the type of the index in the new state, or `None` if the index does not exist.
+    pub fn new_type(&self) -> Option<IndexType> {
+        self.new_type
+    }
+
+    /// Returns changed entries of the index keyed by raw index-level keys. See the
+    /// [module docs](index.html#entry-keys) for details on the key format.
+    pub fn changes(&self) -> &BTreeMap<Vec<u8>, EntryChange> {
+        &self.changes
+    }
+
+    /// Returns changed entries of the index with decoded keys and values. The entries
+    /// are ordered by raw keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the values cannot be decoded.
+    pub fn typed_changes<K, V>(&self) -> Result<Vec<(K::Owned, EntryChange<V>)>, Error>
+    where
+        K: BinaryKey + ?Sized,
+        V: BinaryValue,
+    {
+        self.changes
+            .iter()
+            .map(|(key, change)| Ok((K::read(key), change.decode()?)))
+            .collect()
+    }
+
+    fn new(
+        address: IndexAddress,
+        old: Option<IndexVersion<'_>>,
+        new: Option<IndexVersion<'_>>,
+        keys: Keys<'_>,
+    ) -> Option<Self> {
+        let old_type = old.as_ref().map(IndexVersion::index_type);
+        let new_type = new.as_ref().map(IndexVersion::index_type);
+
+        let changes = match (old, new) {
+            (Some(old), Some(new)) if old_type == new_type => match keys {
+                Keys::All => old.diff(&new),
+                Keys::Changed(keys) => old.diff_keys(&new, keys),
+            },
+            (old, new) => {
+                let old_view = old.as_ref().map(IndexVersion::view);
+                let new_view = new.as_ref().map(IndexVersion::view);
+                let old_entries = old_view
+                    .iter()
+                    .zip(old_type)
+                    .flat_map(|(view, index_type)| entries(view, index_type));
+                let new_entries = new_view
+                    .iter()
+                    .zip(new_type)
+                    .flat_map(|(view, index_type)| entries(view, index_type));
+                merge_entries(old_entries, new_entries)
+            }
+        };
+
+        if changes.is_empty() && old_type == new_type {
+            None
+        } else {
+            Some(Self {
+                address,
+                old_type,
+                new_type,
+                changes,
+            })
+        }
+    }
+}
+
+/// Differences between two database states.
+///
+/// See the [module docs](index.html) for more details.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StateDiff {
+    indexes: Vec<IndexDiff>,
+}
+
+impl StateDiff {
+    /// Computes differences between the `old` and `new` database states.
+    ///
+    /// # Performance notes
+    ///
+    /// This method iterates over all indexes in both states. For `ProofListIndex`es,
+    /// unchanged subtrees of the Merkle tree are skipped based on their hashes. `ProofMapIndex`es
+    /// and `ProofKeySetIndex`es with equal hashes are skipped entirely; otherwise, they are
+    /// compared entry by entry, like non-Merkelized indexes, since map keys cannot be recovered
+    /// from the paths in the Merkle Patricia tree. Thus, the method may be inappropriate to use
+    /// with large databases; consider using [`from_patch`] if possible.
+    ///
+    /// [`from_patch`]: #method.from_patch
+    pub fn new(old: &dyn Snapshot, new: &dyn Snapshot) -> Self {
+        Self::compute(old, new, |_, _| Some(Keys::All))
+    }
+
+    /// Computes differences introduced by the `patch` with respect to the snapshot
+    /// it is based on.
+    ///
+    /// Unlike [`new`], this method only compares entries changed in the `patch`, and thus
+    /// is linear w.r.t. the patch size (plus the number of indexes in the database, and sizes
+    /// of the indexes cleared or having key ranges removed in the patch).
+    ///
+    /// [`new`]: #method.new
+    pub fn from_patch(patch: &Patch) -> Self {
+        Self::compute(patch.base_snapshot(), patch, move |address, metadata| {
+            let view_changes = patch.view_changes(&metadata.resolve(address))?;
+            if view_changes.is_cleared() || view_changes.has_removed_ranges() {
+                Some(Keys::All)
+            } else {
+                Some(Keys::Changed(&view_changes.data))
+            }
+        })
+    }
+
+    fn compute<'a, F>(old: &'a dyn Snapshot, new: &'a dyn Snapshot, touched_keys: F) -> Self
+    where
+        F: Fn(&IndexAddress, &IndexMetadata) -> Option<Keys<'a>>,
+    {
+        let old_pool = IndexesPool::new(old);
+        let new_pool = IndexesPool::new(new);
+        let mut old_indexes = old_pool.iter().peekable();
+        let mut new_indexes = new_pool.iter().peekable();
+        let mut indexes = vec![];
+
+        while let Some((address, old_metadata, new_metadata)) =
+            next_index(&mut old_indexes, &mut new_indexes)
+        {
+            if address.is_system() {
+                continue;
+            }
+
+            let keys = match (&old_metadata, &new_metadata) {
+                (Some(old_metadata), Some(new_metadata))
+                    if old_metadata.index_type() == new_metadata.index_type()
+                        && old_metadata.resolve(&address) == new_metadata.resolve(&address) =>
+                {
+                    match touched_keys(&address, new_metadata) {
+                        Some(keys) => keys,
+                        // The index was not changed.
+                        None => continue,
+                    }
+                }
+                _ => Keys::All,
+            };
+
+            let old_index = old_metadata.map(|metadata| IndexVersion::new(old, &address, metadata));
+            let new_index = new_metadata.map(|metadata| IndexVersion::new(new, &address, metadata));
+            let diff = IndexDiff::new(address.clone(), old_index, new_index, keys);
+            indexes.extend(diff);
+        }
+        Self { indexes }
+    }
+
+    /// Checks if the compared database states are equal.
+    pub fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    /// Iterates over the changed indexes in the order of their addresses.
+    pub fn iter(&self) -> slice::Iter<'_, IndexDiff> {
+        self.indexes.iter()
+    }
+
+    /// Returns the diff for the index with the specified address, or `None` if the index
+    /// is unchanged.
+    pub fn index<I: Into<IndexAddress>>(&self, address: I) -> Option<&IndexDiff> {
+        let address = address.into();
+        self.indexes.iter().find(|index| index.address == address)
+    }
+}
+
+impl<'a> IntoIterator for &'a StateDiff {
+    type Item = &'a IndexDiff;
+    type IntoIter = slice::Iter<'a, IndexDiff>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Keys of an index view that need to be compared.
+#[derive(Debug, Clone, Copy)]
+enum Keys<'a> {
+    /// All keys of the view.
+    All,
+    /// Keys changed in a patch. Other keys are known to be unchanged.
+    Changed(&'a BTreeMap<Vec<u8>, Change>),
+}
+
+/// Index as of a certain database state.
+struct IndexVersion<'a> {
+    access: &'a dyn Snapshot,
+    address: &'a IndexAddress,
+    metadata: IndexMetadata,
+}
+
+impl<'a> IndexVersion<'a> {
+    fn new(access: &'a dyn Snapshot, address: &'a IndexAddress, metadata: IndexMetadata) -> Self {
+        Self {
+            access,
+            address,
+            metadata,
+        }
+    }
+
+    fn index_type(&self) -> IndexType {
+        self.metadata.index_type()
+    }
+
+    fn view_with_metadata(&self) -> ViewWithMetadata<&'a dyn Snapshot> {
+        ViewWithMetadata::from_metadata(self.access, self.address, self.metadata.clone())
+    }
+
+    fn view(&self) -> View<&'a dyn Snapshot> {
+        self.view_with_metadata().into()
+    }
+
+    /// Compares all entries of two versions of the index with the same type.
+    fn diff(&self, other: &Self) -> BTreeMap<Vec<u8>, EntryChange> {
+        match self.index_type() {
+            IndexType::ProofList => {
+                let old_list = ProofListIndex::<_, Vec<u8>>::new(self.view_with_metadata());
+                let new_list = ProofListIndex::<_, Vec<u8>>::new(other.view_with_metadata());
+                old_list
+                    .changed_positions(&new_list)
+                    .into_iter()
+                    .filter_map(|position| {
+                        let change = EntryChange::from_values(
+                            old_list.get(position),
+                            new_list.get(position),
+                        )?;
+                        Some((key_bytes(&position), change))
+                    })
+                    .collect()
+            }
+
+            IndexType::ProofMap | IndexType::ProofKeySet
+                if self.proof_map_hash() == other.proof_map_hash() =>
+            {
+                BTreeMap::new()
+            }
+
+            index_type => {
+                let (old_view, new_view) = (self.view(), other.view());
+                merge_entries(
+                    entries(&old_view, index_type),
+                    entries(&new_view, index_type),
+                )
+            }
+        }
+    }
+
+    /// Compares the specified raw `keys` in two versions of the index with the same type.
+    fn diff_keys(
+        &self,
+        other: &Self,
+        keys: &BTreeMap<Vec<u8>, Change>,
+    ) -> BTreeMap<Vec<u8>, EntryChange> {
+        let index_type = self.index_type();
+        if index_type == IndexType::ProofList {
+            // Skipping unchanged subtrees is efficient enough.
+            return self.diff(other);
+        }
+
+        let (old_view, new_view) = (self.view(), other.view());
+        keys.keys()
+            .filter_map(|raw_key| {
+                let key = entry_key(raw_key, index_type)?;
+                let old_value = old_view.get::<_, Vec<u8>>(raw_key.as_slice());
+                let new_value = new_view.get::<_, Vec<u8>>(raw_key.as_slice());
+                let change = EntryChange::from_values(old_value, new_value)?;
+                Some((key.to_vec(), change))
+            })
+            .collect()
+    }
+
+    fn proof_map_hash(&self) -> Hash {
+        // We don't access map entries, so the key / value types don't matter.
+        ProofMapIndex::<_, (), ()>::new(self.view_with_metadata()).object_hash()
+    }
+}
+
+/// Returns the next index in the merged sequence of indexes in two states.
+fn next_index<I, J>(
+    old_indexes: &mut Peekable<I>,
+    new_indexes: &mut Peekable<J>,
+) -> Option<(IndexAddress, Option<IndexMetadata>, Option<IndexMetadata>)>
+where
+    I: Iterator<Item = (IndexAddress, IndexMetadata)>,
+    J: Iterator<Item = (IndexAddress, IndexMetadata)>,
+{
+    let ordering = match (old_indexes.peek(), new_indexes.peek()) {
+        (None, None) => return None,
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some((old_address, _)), Some((new_address, _))) => old_address
+            .fully_qualified_name()
+            .cmp(&new_address.fully_qualified_name()),
+    };
+
+    Some(match ordering {
+        Ordering::Less => {
+            let (address, metadata) = old_indexes.next().unwrap();
+            (address, Some(metadata), None)
+        }
+        Ordering::Greater => {
+            let (address, metadata) = new_indexes.next().unwrap();
+            (address, None, Some(metadata))
+        }
+        Ordering::Equal => {
+            let (address, old_metadata) = old_indexes.next().unwrap();
+            let (_, new_metadata) = new_indexes.next().unwrap();
+            (address, Some(old_metadata), Some(new_metadata))
+        }
+    })
+}
+
+fn key_bytes<K: BinaryKey + ?Sized>(key: &K) -> Vec<u8> {
+    let mut buffer = vec![0; key.size()];
+    key.write(&mut buffer);
+    buffer
+}
+
+/// Converts a raw key of the index view to the index-level key, or returns `None`
+/// if the raw key corresponds to auxiliary index data.
+fn entry_key(raw_key: &[u8], index_type: IndexType) -> Option<&[u8]> {
+    match index_type {
+        IndexType::ProofMap | IndexType::ProofKeySet => match raw_key.split_first() {
+            Some((&VALUE_KEY_PREFIX, key)) => Some(key),
+            _ => None,
+        },
+        // Entries of secondary indexes are derived from the values, so they are skipped.
+        IndexType::IndexedMap => match raw_key.split_first() {
+            Some((&VALUES_PREFIX, key)) => Some(key),
+            _ => None,
+        },
+        // Elements are stored at the zero height of the Merkle tree, with the height
+        // being the most significant byte of the key.
+        IndexType::ProofList if raw_key.first() != Some(&0) => None,
+        _ => Some(raw_key),
+    }
+}
+
+/// Iterates over index entries with index-level keys.
+fn entries<'a>(
+    view: &'a View<&dyn Snapshot>,
+    index_type: IndexType,
+) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a {
+    view.iter::<_, [u8], Vec<u8>>(&())
+        .filter_map(move |(raw_key, value)| {
+            let key = entry_key(&raw_key, index_type)?.to_vec();
+            Some((key, value))
+        })
+}
+
+/// Compares two sequences of entries ordered by keys.
+fn merge_entries(
+    old_entries: impl Iterator<Item = (Vec<u8>, Vec<u8>)>,
+    new_entries: impl Iterator<Item = (Vec<u8>, Vec<u8>)>,
+) -> BTreeMap<Vec<u8>, EntryChange> {
+    let mut old_entries = old_entries.peekable();
+    let mut new_entries = new_entries.peekable();
+    let mut changes = BTreeMap::new();
+
+    loop {
+        let ordering = match (old_entries.peek(), new_entries.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((old_key, _)), Some((new_key, _))) => old_key.cmp(new_key),
+        };
+
+        let (key, change) = match ordering {
+            Ordering::Less => {
+                let (key, old_value) = old_entries.next().unwrap();
+                (key, EntryChange::from_values(Some(old_value), None))
+            }
+            Ordering::Greater => {
+                let (key, new_value) = new_entries.next().unwrap();
+                (key, EntryChange::from_values(None, Some(new_value)))
+            }
+            Ordering::Equal => {
+                let (key, old_value) = old_entries.next().unwrap();
+                let (_, new_value) = new_entries.next().unwrap();
+                (
+                    key,
+                    EntryChange::from_values(Some(old_value), Some(new_value)),
+                )
+            }
+        };
+        if let Some(change) = change {
+            changes.insert(key, change);
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{access::AccessExt, Database, TemporaryDB};
+
+    #[test]
+    fn diff_of_equal_states_is_empty() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_list("list").extend(vec![1_u32, 2, 3]);
+        fork.get_proof_map("map").put(&1_u64, "foo".to_owned());
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        assert!(StateDiff::new(&*snapshot, &*snapshot).is_empty());
+        assert!(StateDiff::from_patch(&db.fork().into_patch()).is_empty());
+    }
+
+    #[test]
+    fn diff_with_created_and_removed_entries() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_map("map").put(&1_u64, "foo".to_owned());
+        fork.get_map("map").put(&2_u64, "bar".to_owned());
+        fork.get_entry(("entry", &1_u8)).set(1_u32);
+        db.merge(fork.into_patch()).unwrap();
+        let old_snapshot = db.snapshot();
+
+        let fork = db.fork();
+        fork.get_map("map").remove(&1_u64);
+        fork.get_map("map").put(&2_u64, "baz".to_owned());
+        fork.get_map("map").put(&3_u64, "qux".to_owned());
+        fork.get_entry(("entry", &2_u8)).set(2_u32);
+        fork.get_key_set::<_, u8>("empty_set");
+        let patch = fork.into_patch();
+
+        let diff = StateDiff::from_patch(&patch);
+        assert_eq!(diff, StateDiff::new(&*old_snapshot, &patch));
+
+        let addresses: Vec<_> = diff.iter().map(IndexDiff::address).cloned().collect();
+        assert_eq!(
+            addresses,
+            vec![
+                IndexAddress::from_root("empty_set"),
+                IndexAddress::from(("entry", &2_u8)),
+                IndexAddress::from_root("map"),
+            ]
+        );
+
+        let set_diff = diff.index("empty_set").unwrap();
+        assert_eq!(set_diff.old_type(), None);
+        assert_eq!(set_diff.new_type(), Some(IndexType::KeySet));
+        assert!(set_diff.changes().is_empty());
+
+        let entry_diff = diff.index(("entry", &2_u8)).unwrap();
+        assert_eq!(
+            entry_diff.typed_changes::<(), u32>().unwrap(),
+            vec![((), EntryChange::Added(2))]
+        );
+
+        let map_diff = diff.index("map").unwrap();
+        assert_eq!(map_diff.old_type(), Some(IndexType::Map));
+        assert_eq!(map_diff.new_type(), Some(IndexType::Map));
+        assert_eq!(
+            map_diff.typed_changes::<u64, String>().unwrap(),
+            vec![
+                (1, EntryChange::Removed("foo".to_owned())),
+                (
+                    2,
+                    EntryChange::Modified {
+                        old: "bar".to_owned(),
+                        new: "baz".to_owned(),
+                    }
+                ),
+                (3, EntryChange::Added("qux".to_owned())),
+            ]
+        );
+
+        // Reverse diff.
+        db.merge(patch).unwrap();
+        let diff = StateDiff::new(&*db.snapshot(), &*old_snapshot);
+        let set_diff = diff.index("empty_set").unwrap();
+        assert_eq!(set_diff.old_type(), Some(IndexType::KeySet));
+        assert_eq!(set_diff.new_type(), None);
+        let map_diff = diff.index("map").unwrap();
+        assert_eq!(
+            map_diff.changes()[&key_bytes(&3_u64)],
+            EntryChange::Removed("qux".to_owned().into_bytes())
+        );
+    }
+
+    #[test]
+    fn diff_for_merkelized_indexes() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_list("list").extend(0_u64..100);
+        fork.get_proof_map("map").put(&1_u64, 1_u64);
+        fork.get_proof_map("other_map").put(&1_u64, 1_u64);
+        fork.get_proof_entry("entry").set(1_u64);
+        db.merge(fork.into_patch()).unwrap();
+        let old_snapshot = db.snapshot();
+
+        let fork = db.fork();
+        {
+            let mut list = fork.get_proof_list("list");
+            list.set(10, 1_000);
+            list.set(50, 5_000);
+            list.truncate(98);
+            list.extend(vec![1, 2, 3]);
+        }
+        fork.get_proof_map("map").put(&2_u64, 2_u64);
+        fork.get_proof_map("map").remove(&1_u64);
+        // Changes to `other_map` cancel each other.
+        fork.get_proof_map("other_map").put(&1_u64, 2_u64);
+        fork.get_proof_map("other_map").put(&1_u64, 1_u64);
+        fork.get_proof_entry("entry").set(2_u64);
+        let patch = fork.into_patch();
+
+        let diff = StateDiff::from_patch(&patch);
+        assert_eq!(diff, StateDiff::new(&*old_snapshot, &patch));
+        let names: Vec<_> = diff.iter().map(|index| index.address().name()).collect();
+        assert_eq!(names, vec!["entry", "list", "map"]);
+
+        let list_changes = diff.index("list").unwrap().typed_changes::<u64, u64>();
+        assert_eq!(
+            list_changes.unwrap(),
+            vec![
+                (
+                    10,
+                    EntryChange::Modified {
+                        old: 10,
+                        new: 1_000
+                    }
+                ),
+                (
+                    50,
+                    EntryChange::Modified {
+                        old: 50,
+                        new: 5_000
+                    }
+                ),
+                (98, EntryChange::Modified { old: 98, new: 1 }),
+                (99, EntryChange::Modified { old: 99, new: 2 }),
+                (100, EntryChange::Added(3)),
+            ]
+        );
+
+        let map_changes = diff.index("map").unwrap().typed_changes::<u64, u64>();
+        assert_eq!(
+            map_changes.unwrap(),
+            vec![(1, EntryChange::Removed(1)), (2, EntryChange::Added(2))]
+        );
+
+        let entry_changes = diff.index("entry").unwrap().typed_changes::<(), u64>();
+        assert_eq!(
+            entry_changes.unwrap(),
+            vec![((), EntryChange::Modified { old: 1, new: 2 })]
+        );
+    }
+
+    #[test]
+    fn diff_with_cleared_index() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_value_set("set").insert(1_u32);
+        fork.get_proof_map("map").put(&1_u64, 1_u64);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        fork.get_value_set::<_, u32>("set").clear();
+        fork.get_value_set("set").insert(2_u32);
+        fork.get_proof_map::<_, u64, u64>("map").clear();
+        let patch = fork.into_patch();
+
+        let diff = StateDiff::from_patch(&patch);
+        let set_changes = diff.index("set").unwrap().typed_changes::<Hash, u32>();
+        assert_eq!(set_changes.unwrap(), {
+            let mut changes = vec![
+                (1_u32.object_hash(), EntryChange::Removed(1)),
+                (2_u32.object_hash(), EntryChange::Added(2)),
+            ];
+            changes.sort_by(|(x, _), (y, _)| x.cmp(y));
+            changes
+        });
+        let map_changes = diff.index("map").unwrap().typed_changes::<u64, u64>();
+        assert_eq!(map_changes.unwrap(), vec![(1, EntryChange::Removed(1))]);
+    }
+}
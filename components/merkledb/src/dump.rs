@@ -0,0 +1,595 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Portable database dumps.
+//!
+//! A dump is a backend-independent serialization of the database state. It can be used
+//! to move the database between machines or storage backends, or to create backups.
+//!
+//! # Format
+//!
+//! A dump starts with a fixed header (magic bytes and the format version). The header
+//! is followed by a stream of [`bincode`]-serialized records:
+//!
+//! - An index record containing the index address, type and state
+//! - Entry records with the contents of the preceding index
+//! - Records with prefixes for which [hierarchical aggregation] is enabled
+//! - The final record with the state hash of the exported database
+//!
+//! Indexes are exported together with their auxiliary data (e.g., Merkle tree nodes),
+//! so that importing a dump does not require recomputing any hashes. System indexes,
+//! such as the state aggregator, are not exported; they are rebuilt during import.
+//! The importer compares the state hash of the imported data with the one recorded
+//! in the final record. Note that this check covers the hashes of aggregated indexes;
+//! it does not verify that the Merkle tree nodes are consistent with the index values.
+//! Use [`integrity::check`] to perform the full verification.
+//!
+//! [`bincode`]: https://docs.rs/bincode/
+//! [hierarchical aggregation]: ../struct.SystemSchema.html#hierarchical-aggregation
+//! [`integrity::check`]: ../integrity/fn.check.html
+//!
+//! # Examples
+//!
+//! ```
+//! # use exonum_merkledb::{access::AccessExt, dump, Database, SystemSchema, TemporaryDB};
+//! let db = TemporaryDB::new();
+//! let fork = db.fork();
+//! fork.get_proof_list("list").extend(vec![1_u32, 2, 3]);
+//! fork.get_map(("map", &1_u8)).put(&1_u8, "foo".to_owned());
+//! db.merge(fork.into_patch()).unwrap();
+//!
+//! let mut buffer = vec![];
+//! let state_hash = dump::export(&*db.snapshot(), &mut buffer).unwrap();
+//!
+//! let other_db = TemporaryDB::new();
+//! let imported_hash = dump::import(&other_db, &buffer[..]).unwrap();
+//! assert_eq!(imported_hash, state_hash);
+//! let snapshot = other_db.snapshot();
+//! assert_eq!(SystemSchema::new(&snapshot).state_hash(), state_hash);
+//! let map = snapshot.get_map::<_, u8, String>(("map", &1_u8));
+//! assert_eq!(map.get(&1), Some("foo".to_owned()));
+//! ```
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use exonum_crypto::Hash;
+use failure::Fail;
+use serde_derive::{Deserialize, Serialize};
+
+use std::{
+    io::{self, Read, Write},
+    mem,
+};
+
+use crate::{
+    access::AccessError,
+    validation::is_valid_index_name_component,
+    views::{get_aggregated_prefixes, IndexType, IndexesPool, View, ViewWithMetadata},
+    Database, Fork, IndexAddress, Snapshot, SystemSchema, ValueCompression,
+};
+
+/// Magic bytes at the start of a dump.
+const MAGIC: &[u8; 8] = b"MERKLEDB";
+/// Version of the dump format.
+const FORMAT_VERSION: u32 = 1;
+/// Maximum size of a single record in the dump.
+const MAX_RECORD_SIZE: u64 = 1 << 30;
+/// Number of entries written into a single fork during import.
+const MAX_FORK_SIZE: usize = 100_000;
+
+/// Errors that can occur during export or import of a database dump.
+#[derive(Debug, Fail)]
+pub enum DumpError {
+    /// I/O error.
+    #[fail(display = "I/O error: {}", _0)]
+    Io(#[fail(cause)] io::Error),
+
+    /// The dump has invalid format.
+    #[fail(display = "Invalid dump format: {}", _0)]
+    InvalidFormat(String),
+
+    /// The dump has an unsupported format version.
+    #[fail(display = "Unsupported dump format version: {}", _0)]
+    UnsupportedVersion(u32),
+
+    /// An index in the dump cannot be imported.
+    #[fail(display = "Cannot import index: {}", _0)]
+    InvalidIndex(#[fail(cause)] AccessError),
+
+    /// The database to import the dump into is not empty.
+    #[fail(display = "Database to import the dump into is not empty")]
+    NonEmptyDatabase,
+
+    /// The state hash of the imported database differs from the one recorded in the dump.
+    #[fail(
+        display = "State hash mismatch: expected {:?}, got {:?}",
+        expected, actual
+    )]
+    StateHashMismatch {
+        /// State hash recorded in the dump.
+        expected: Hash,
+        /// Actual state hash of the imported data.
+        actual: Hash,
+    },
+
+    /// Error in the underlying database.
+    #[fail(display = "Storage error: {}", _0)]
+    Storage(#[fail(cause)] crate::Error),
+}
+
+impl From<io::Error> for DumpError {
+    fn from(err: io::Error) -> Self {
+        DumpError::Io(err)
+    }
+}
+
+impl From<crate::Error> for DumpError {
+    fn from(err: crate::Error) -> Self {
+        DumpError::Storage(err)
+    }
+}
+
+impl From<AccessError> for DumpError {
+    fn from(err: AccessError) -> Self {
+        DumpError::InvalidIndex(err)
+    }
+}
+
+impl From<bincode::Error> for DumpError {
+    fn from(err: bincode::Error) -> Self {
+        match *err {
+            bincode::ErrorKind::Io(err) => DumpError::Io(err),
+            err => DumpError::InvalidFormat(err.to_string()),
+        }
+    }
+}
+
+/// Record in a database dump.
+#[derive(Debug, Serialize, Deserialize)]
+enum Record {
+    /// Start of an index. Subsequent entry records belong to this index.
+    Index {
+        name: String,
+        id_in_group: Option<Vec<u8>>,
+        in_migration: bool,
+        index_type: IndexType,
+        state: Option<Vec<u8>>,
+    },
+    /// Entry of the index view.
+    Entry { key: Vec<u8>, value: Vec<u8> },
+    /// End of the dump.
+    End { state_hash: Hash },
+    /// Prefix for which hierarchical aggregation is enabled.
+    AggregatedPrefix { prefix: String },
+    /// Compression of values in the current index. If present, this record immediately
+    /// follows the `Index` record.
+    IndexCompression { compression: ValueCompression },
+}
+
+impl Record {
+    fn write(&self, writer: &mut impl Write) -> Result<(), DumpError> {
+        bincode::serialize_into(writer, self).map_err(From::from)
+    }
+
+    fn read(reader: &mut impl Read) -> Result<Self, DumpError> {
+        bincode::config()
+            .limit(MAX_RECORD_SIZE)
+            .deserialize_from(reader)
+            .map_err(From::from)
+    }
+}
+
+/// Writes a dump of the database state represented by `snapshot` into `writer`.
+///
+/// Returns the state hash of the exported database.
+///
+/// # Errors
+///
+/// Returns an error if writing to `writer` fails.
+pub fn export<W: Write>(snapshot: &dyn Snapshot, mut writer: W) -> Result<Hash, DumpError> {
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<LittleEndian>(FORMAT_VERSION)?;
+
+    for (address, metadata) in IndexesPool::new(snapshot).iter() {
+        if address.is_system() {
+            continue;
+        }
+
+        let index_type = metadata.index_type();
+        let view: View<_> =
+            ViewWithMetadata::from_metadata(snapshot, &address, metadata.clone()).into();
+        Record::Index {
+            name: address.name().to_owned(),
+            id_in_group: address.id_in_group().map(<[u8]>::to_vec),
+            in_migration: address.in_migration(),
+            index_type,
+            state: metadata.raw_state().map(<[u8]>::to_vec),
+        }
+        .write(&mut writer)?;
+        if metadata.compression() != ValueCompression::None {
+            Record::IndexCompression {
+                compression: metadata.compression(),
+            }
+            .write(&mut writer)?;
+        }
+
+        for (key, value) in view.iter::<_, [u8], Vec<u8>>(&()) {
+            Record::Entry { key, value }.write(&mut writer)?;
+        }
+    }
+
+    for prefix in &get_aggregated_prefixes(snapshot) {
+        Record::AggregatedPrefix { prefix }.write(&mut writer)?;
+    }
+
+    let state_hash = SystemSchema::new(snapshot).state_hash();
+    Record::End { state_hash }.write(&mut writer)?;
+    writer.flush()?;
+    Ok(state_hash)
+}
+
+/// Imports a dump from `reader` into `db`. The database must not contain any indexes.
+///
+/// Returns the state hash of the imported database. The state hash is checked against
+/// the one recorded in the dump.
+///
+/// # Errors
+///
+/// Returns an error if the dump is malformed, or the state hash of the imported data
+/// does not match the one recorded in the dump. Note that large dumps are imported
+/// in several merges; thus, the database may contain a part of the imported data
+/// after an error and should be discarded.
+pub fn import<R: Read>(db: &dyn Database, mut reader: R) -> Result<Hash, DumpError> {
+    let mut magic = [0_u8; 8];
+    reader.read_exact(&mut magic)?;
+    if magic != *MAGIC {
+        return Err(DumpError::InvalidFormat("invalid magic bytes".to_owned()));
+    }
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != FORMAT_VERSION {
+        return Err(DumpError::UnsupportedVersion(version));
+    }
+
+    if IndexesPool::new(&*db.snapshot()).iter().next().is_some() {
+        return Err(DumpError::NonEmptyDatabase);
+    }
+
+    let mut importer = Importer::new(db);
+    loop {
+        match Record::read(&mut reader)? {
+            Record::Index {
+                name,
+                id_in_group,
+                in_migration,
+                index_type,
+                state,
+            } => {
+                let mut address = IndexAddress::from_root(name);
+                if let Some(id_in_group) = id_in_group {
+                    address = address.append_key(id_in_group.as_slice());
+                }
+                if in_migration {
+                    address.set_in_migration();
+                }
+                importer.start_index(address, index_type, state)?;
+            }
+            Record::Entry { key, value } => importer.add_entry(key, value)?,
+            Record::AggregatedPrefix { prefix } => importer.enable_prefix_aggregation(&prefix)?,
+            Record::IndexCompression { compression } => importer.set_compression(compression)?,
+            Record::End { state_hash } => return importer.finish(state_hash),
+        }
+    }
+}
+
+/// Index being imported.
+#[derive(Debug)]
+struct ImportedIndex {
+    address: IndexAddress,
+    index_type: IndexType,
+    compression: ValueCompression,
+    /// Index state; taken when the index is created in the fork.
+    state: Option<Vec<u8>>,
+    /// Was the index created in the fork? Index creation is postponed until the first write
+    /// in order to account for records modifying index metadata (e.g., compression).
+    is_created: bool,
+}
+
+/// Helper for importing a dump in several merges.
+struct Importer<'a> {
+    db: &'a dyn Database,
+    fork: Fork,
+    /// Currently imported index.
+    index: Option<ImportedIndex>,
+    /// Entries of the current index which are not written to the fork yet.
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Number of entries written to the fork.
+    fork_size: usize,
+}
+
+impl<'a> Importer<'a> {
+    fn new(db: &'a dyn Database) -> Self {
+        Self {
+            db,
+            fork: db.fork(),
+            index: None,
+            entries: vec![],
+            fork_size: 0,
+        }
+    }
+
+    fn start_index(
+        &mut self,
+        address: IndexAddress,
+        index_type: IndexType,
+        state: Option<Vec<u8>>,
+    ) -> Result<(), DumpError> {
+        self.write_entries()?;
+
+        if address.is_system() {
+            let msg = format!("unexpected system index {:?}", address);
+            return Err(DumpError::InvalidFormat(msg));
+        }
+        if ViewWithMetadata::get_metadata(&self.fork, &address)?.is_some() {
+            let msg = format!("duplicate index {:?}", address);
+            return Err(DumpError::InvalidFormat(msg));
+        }
+
+        self.index = Some(ImportedIndex {
+            address,
+            index_type,
+            compression: ValueCompression::None,
+            state,
+            is_created: false,
+        });
+        Ok(())
+    }
+
+    fn set_compression(&mut self, compression: ValueCompression) -> Result<(), DumpError> {
+        match self.index {
+            Some(ref mut index) if !index.is_created && self.entries.is_empty() => {
+                index.compression = compression;
+                Ok(())
+            }
+            _ => {
+                let msg = "index compression not immediately following an index".to_owned();
+                Err(DumpError::InvalidFormat(msg))
+            }
+        }
+    }
+
+    fn add_entry(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<(), DumpError> {
+        if self.index.is_none() {
+            let msg = "index entry without a preceding index".to_owned();
+            return Err(DumpError::InvalidFormat(msg));
+        }
+        self.entries.push((key, value));
+        if self.entries.len() >= MAX_FORK_SIZE {
+            self.write_entries()?;
+        }
+        Ok(())
+    }
+
+    fn write_entries(&mut self) -> Result<(), DumpError> {
+        let index = match self.index {
+            Some(ref mut index) => index,
+            None => return Ok(()),
+        };
+
+        if !index.is_created {
+            let view = ViewWithMetadata::get_or_create_compressed(
+                &self.fork,
+                &index.address,
+                index.index_type,
+                index.compression,
+            )?;
+            let (_, mut index_state) = view.into_parts::<Vec<u8>>();
+            if let Some(state) = index.state.take() {
+                index_state.set(state);
+            }
+            index.is_created = true;
+        }
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+
+        self.fork_size += self.entries.len();
+        {
+            let mut view: View<_> =
+                ViewWithMetadata::get_or_create(&self.fork, &index.address, index.index_type)?
+                    .into();
+            for (key, value) in self.entries.drain(..) {
+                view.put(key.as_slice(), value);
+            }
+        }
+
+        if self.fork_size >= MAX_FORK_SIZE {
+            let fork = mem::replace(&mut self.fork, self.db.fork());
+            self.db.merge(fork.into_patch())?;
+            // The fork needs to be recreated in order to observe the merged changes.
+            self.fork = self.db.fork();
+            self.fork_size = 0;
+        }
+        Ok(())
+    }
+
+    fn enable_prefix_aggregation(&mut self, prefix: &str) -> Result<(), DumpError> {
+        self.write_entries()?;
+        self.index = None;
+
+        if prefix.is_empty() || !is_valid_index_name_component(prefix) {
+            let msg = format!("invalid aggregated prefix {:?}", prefix);
+            return Err(DumpError::InvalidFormat(msg));
+        }
+        self.fork.enable_prefix_aggregation(prefix);
+        Ok(())
+    }
+
+    fn finish(mut self, expected_hash: Hash) -> Result<Hash, DumpError> {
+        self.write_entries()?;
+        let patch = self.fork.into_patch();
+        let state_hash = SystemSchema::new(&patch).state_hash();
+        if state_hash != expected_hash {
+            return Err(DumpError::StateHashMismatch {
+                expected: expected_hash,
+                actual: state_hash,
+            });
+        }
+        self.db.merge(patch)?;
+        Ok(state_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::{
+        access::{AccessExt, Compressed},
+        migration::Migration,
+        ObjectHash, TemporaryDB,
+    };
+
+    fn create_db() -> TemporaryDB {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_list("list").extend(0_u64..1_000);
+        fork.get_proof_map("map").put(&1_u64, "foo".to_owned());
+        fork.get_proof_entry("entry").set(42_u64);
+        fork.get_value_set(("set", &1_u8)).insert(1_u32);
+        fork.get_list::<_, u64>("empty_list");
+        Migration::new("test", &fork)
+            .get_proof_entry("entry")
+            .set(1_u64);
+        db.merge(fork.into_patch()).unwrap();
+        db
+    }
+
+    #[test]
+    fn export_and_import_roundtrip() {
+        let db = create_db();
+        let mut buffer = vec![];
+        let state_hash = export(&*db.snapshot(), &mut buffer).unwrap();
+
+        let imported_db = TemporaryDB::new();
+        assert_eq!(import(&imported_db, &buffer[..]).unwrap(), state_hash);
+
+        let snapshot = imported_db.snapshot();
+        assert_eq!(SystemSchema::new(&snapshot).state_hash(), state_hash);
+        let list = snapshot.get_proof_list::<_, u64>("list");
+        assert_eq!(list.len(), 1_000);
+        assert_eq!(list.get(500), Some(500));
+        assert_eq!(list.object_hash(), {
+            let old_snapshot = db.snapshot();
+            let old_list = old_snapshot.get_proof_list::<_, u64>("list");
+            old_list.object_hash()
+        });
+        let set = snapshot.get_value_set::<_, u32>(("set", &1_u8));
+        assert!(set.contains(&1));
+        assert_eq!(snapshot.index_type("empty_list"), Some(IndexType::List));
+        let migration = Migration::new("test", &snapshot);
+        assert_eq!(migration.get_proof_entry::<_, u64>("entry").get(), Some(1));
+
+        // Importing into a non-empty database should fail.
+        let err = import(&imported_db, &buffer[..]).unwrap_err();
+        assert_matches!(err, DumpError::NonEmptyDatabase);
+    }
+
+    #[test]
+    fn export_and_import_with_prefix_aggregation() {
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        fork.get_proof_list("service.list").extend(0_u64..10);
+        fork.enable_prefix_aggregation("service");
+        fork.enable_prefix_aggregation("empty");
+        fork.get_proof_entry("entry").set(42_u64);
+        db.merge(fork.into_patch()).unwrap();
+
+        let mut buffer = vec![];
+        let state_hash = export(&*db.snapshot(), &mut buffer).unwrap();
+        let imported_db = TemporaryDB::new();
+        assert_eq!(import(&imported_db, &buffer[..]).unwrap(), state_hash);
+
+        let snapshot = imported_db.snapshot();
+        let schema = SystemSchema::new(&snapshot);
+        assert!(schema.is_prefix_aggregated("service"));
+        assert!(schema.is_prefix_aggregated("empty"));
+        let aggregator = schema.prefix_state_aggregator("service");
+        assert_eq!(
+            aggregator.keys().collect::<Vec<_>>(),
+            vec!["service.list".to_owned()]
+        );
+    }
+
+    #[test]
+    fn export_and_import_with_compression() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let compressed = Compressed::new(&fork, ValueCompression::Zstd);
+        compressed.get_map("map").put(&1_u64, "foo".repeat(100));
+        compressed
+            .get_proof_list("list")
+            .extend(vec![vec![1_u8; 256]; 10]);
+        fork.get_entry("entry").set(42_u64);
+        db.merge(fork.into_patch()).unwrap();
+
+        let mut buffer = vec![];
+        let state_hash = export(&*db.snapshot(), &mut buffer).unwrap();
+        let imported_db = TemporaryDB::new();
+        assert_eq!(import(&imported_db, &buffer[..]).unwrap(), state_hash);
+
+        let snapshot = imported_db.snapshot();
+        let map = snapshot.get_map::<_, u64, String>("map");
+        assert_eq!(map.get(&1), Some("foo".repeat(100)));
+        let list = snapshot.get_proof_list::<_, Vec<u8>>("list");
+        assert_eq!(list.get(5), Some(vec![1; 256]));
+
+        for &(name, compression) in &[
+            ("map", ValueCompression::Zstd),
+            ("list", ValueCompression::Zstd),
+            ("entry", ValueCompression::None),
+        ] {
+            let metadata = ViewWithMetadata::get_metadata(&snapshot, &name.into())
+                .unwrap()
+                .unwrap();
+            assert_eq!(metadata.compression(), compression);
+        }
+    }
+
+    #[test]
+    fn import_with_corrupted_data() {
+        let db = create_db();
+        let mut buffer = vec![];
+        let state_hash = export(&*db.snapshot(), &mut buffer).unwrap();
+
+        let err = import(&TemporaryDB::new(), &buffer[1..]).unwrap_err();
+        assert_matches!(err, DumpError::InvalidFormat(_));
+        let err = import(&TemporaryDB::new(), &buffer[..buffer.len() - 1]).unwrap_err();
+        assert_matches!(err, DumpError::Io(_));
+
+        // Replace the state hash in the final record.
+        let end_record = Record::End { state_hash };
+        let end_record_len = bincode::serialized_size(&end_record).unwrap() as usize;
+        buffer.truncate(buffer.len() - end_record_len);
+        let end_record = Record::End {
+            state_hash: Hash::zero(),
+        };
+        end_record.write(&mut buffer).unwrap();
+        let err = import(&TemporaryDB::new(), &buffer[..]).unwrap_err();
+        assert_matches!(
+            err,
+            DumpError::StateHashMismatch { expected, actual }
+                if expected == Hash::zero() && actual == state_hash
+        );
+    }
+}
@@ -37,8 +37,35 @@ impl Error {
     }
 }
 
+/// Error signalling that the size of changes in a `Fork` has exceeded the limit set via
+/// [`Fork::set_size_limit`]. The error is returned by the write path of the fork;
+/// since write operations on indexes are infallible, they raise it as a panic payload.
+/// System writes (e.g., updates of the state aggregator) are not subject to the limit.
+///
+/// [`Fork::set_size_limit`]: struct.Fork.html#method.set_size_limit
+/// [`Error`]: struct.Error.html
+#[derive(Fail, Debug, Clone, Copy, PartialEq, Eq)]
+#[fail(
+    display = "Size of fork changes ({} bytes) exceeds the limit ({} bytes)",
+    size, limit
+)]
+pub struct SizeLimitExceeded {
+    /// Size limit of the fork in bytes.
+    pub limit: usize,
+    /// Size the fork changes would have after the failed write.
+    pub size: usize,
+}
+
+#[cfg(feature = "rocksdb")]
 impl From<rocksdb::Error> for Error {
     fn from(err: rocksdb::Error) -> Self {
         Self::new(err.description())
     }
 }
+
+#[cfg(feature = "sled")]
+impl From<sled::Error> for Error {
+    fn from(err: sled::Error) -> Self {
+        Self::new(err.to_string())
+    }
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use byteorder::{ByteOrder, LittleEndian};
-use exonum_crypto::{hash, Hash, HashStream};
+use exonum_crypto::{hash, Hash, HashStream, HASH_SIZE};
 use failure::Fail;
 use hex::FromHex;
 
@@ -239,7 +239,7 @@ impl ObjectHash for [u8] {
 
 /// Errors that can occur while validating a `ListProof` or `MapProof` against
 /// a trusted collection hash.
-#[derive(Debug, Fail)]
+#[derive(Debug, Clone, PartialEq, Fail)]
 pub enum ValidationError<E: Fail> {
     /// The hash of the proof is not equal to the trusted root hash.
     #[fail(display = "hash of the proof is not equal to the trusted hash of the list")]
@@ -250,6 +250,30 @@ pub enum ValidationError<E: Fail> {
     Malformed(#[fail(cause)] E),
 }
 
+impl<E: Fail> From<exonum_proofs::ValidationError<E>> for ValidationError<E> {
+    fn from(err: exonum_proofs::ValidationError<E>) -> Self {
+        match err {
+            exonum_proofs::ValidationError::UnmatchedRootHash => ValidationError::UnmatchedRootHash,
+            exonum_proofs::ValidationError::Malformed(e) => ValidationError::Malformed(e),
+        }
+    }
+}
+
+/// Converts a hash into the hash type of `exonum-proofs`, which implements the verification
+/// of proofs.
+pub(crate) fn to_proofs_hash(hash: &Hash) -> exonum_proofs::Hash {
+    let mut bytes = [0; HASH_SIZE];
+    bytes.copy_from_slice(hash.as_ref());
+    exonum_proofs::Hash::new(bytes)
+}
+
+/// Converts a hash from the hash type of `exonum-proofs`.
+pub(crate) fn from_proofs_hash(hash: exonum_proofs::Hash) -> Hash {
+    let mut bytes = [0; HASH_SIZE];
+    bytes.copy_from_slice(hash.as_ref());
+    Hash::new(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use exonum_crypto::{Hash, HashStream};
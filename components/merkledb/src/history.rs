@@ -0,0 +1,481 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage of the changes reverting merged patches, which allows to restore historical
+//! database states.
+//!
+//! Each reverting change is stored as a separate history entry keyed by the address
+//! of the changed view, the changed key and the version of the merge. Thus, the historical
+//! value of a key can be retrieved with a single seek, and historical states never need
+//! to be loaded into memory as a whole.
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::HashMap,
+    convert::TryInto,
+    iter::{Iterator as StdIterator, Peekable},
+    ops::Bound,
+};
+
+use crate::{
+    db::{Change, ViewChanges, DB_METADATA},
+    views::{next_prefix, RawAccess, RawAccessMut, View},
+    BinaryValue, Error, HistoryOptions, Iter, Iterator, ResolvedAddress, Snapshot,
+};
+
+/// Name of the system view storing history entries.
+const HISTORY_ENTRIES: &str = "__HISTORY__";
+/// Name of the system view storing keys of the history entries added by each merged patch.
+const HISTORY_RECORDS: &str = "__HISTORY_RECORDS__";
+/// Name of the key in the database metadata storing the earliest version, the state
+/// for which can be restored.
+const HISTORY_START_NAME: &str = "history_start";
+/// Name of the key in the database metadata storing the latest merged version.
+const HISTORY_END_NAME: &str = "history_end";
+
+/// Size of a record key: a big-endian version followed by a big-endian sequence number
+/// of the record within the version.
+const RECORD_KEY_SIZE: usize = 12;
+
+/// Keys of the history entries added by a single merged patch.
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryRecord {
+    entry_keys: Vec<Vec<u8>>,
+}
+
+impl BinaryValue for HistoryRecord {
+    fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("Cannot serialize history record")
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Result<Self, failure::Error> {
+        bincode::deserialize(bytes.as_ref()).map_err(From::from)
+    }
+}
+
+fn record_key(version: u64, seq: u32) -> [u8; RECORD_KEY_SIZE] {
+    let mut key = [0; RECORD_KEY_SIZE];
+    key[..8].copy_from_slice(&version.to_be_bytes());
+    key[8..].copy_from_slice(&seq.to_be_bytes());
+    key
+}
+
+fn record_version(key: &[u8]) -> u64 {
+    u64::from_be_bytes(key[..8].try_into().expect("Invalid history record key"))
+}
+
+fn record_seq(key: &[u8]) -> u32 {
+    u32::from_be_bytes(key[8..].try_into().expect("Invalid history record key"))
+}
+
+/// Returns the common prefix of history entries for the view with the specified address.
+/// The prefix is self-delimiting, so prefixes for different views never overlap.
+fn entry_prefix(address: &ResolvedAddress) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(address.name.len() + 13);
+    prefix.extend_from_slice(&(address.name.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(address.name.as_bytes());
+    if let Some(id) = address.id_to_bytes() {
+        prefix.push(1);
+        prefix.extend_from_slice(&id);
+    } else {
+        prefix.push(0);
+    }
+    prefix
+}
+
+/// Appends an order-preserving encoding of `key` to `buffer`. Zero bytes are escaped
+/// as `[0, 0xff]`. If `terminate` is set, the encoding is finished with `[0, 0]`,
+/// so that the version following the key cannot be confused with the key bytes.
+fn encode_key(buffer: &mut Vec<u8>, key: &[u8], terminate: bool) {
+    for &byte in key {
+        buffer.push(byte);
+        if byte == 0 {
+            buffer.push(0xff);
+        }
+    }
+    if terminate {
+        buffer.extend_from_slice(&[0, 0]);
+    }
+}
+
+/// Splits the part of an entry key following the view prefix into the view key
+/// and the version of the merge.
+fn decode_entry_key(bytes: &[u8]) -> (Vec<u8>, u64) {
+    let (encoded_key, record_key) = bytes.split_at(bytes.len() - RECORD_KEY_SIZE);
+    let mut key = Vec::with_capacity(encoded_key.len());
+    // Skip the terminating `[0, 0]` bytes.
+    let mut encoded_bytes = encoded_key[..encoded_key.len() - 2].iter();
+    while let Some(&byte) = encoded_bytes.next() {
+        key.push(byte);
+        if byte == 0 {
+            encoded_bytes.next();
+        }
+    }
+    (key, record_version(record_key))
+}
+
+fn encode_value(value: Option<&[u8]>) -> Vec<u8> {
+    match value {
+        Some(value) => {
+            let mut bytes = Vec::with_capacity(value.len() + 1);
+            bytes.push(1);
+            bytes.extend_from_slice(value);
+            bytes
+        }
+        None => vec![0],
+    }
+}
+
+fn decode_value(bytes: &[u8]) -> Option<Vec<u8>> {
+    match bytes.split_first() {
+        Some((&0, _)) => None,
+        Some((_, value)) => Some(value.to_vec()),
+        None => panic!("Invalid history entry"),
+    }
+}
+
+/// Database history, i.e., entries with the changes reverting merged patches.
+#[derive(Debug)]
+pub(crate) struct History<T: RawAccess> {
+    access: T,
+}
+
+impl<T: RawAccess> History<T> {
+    pub fn new(access: T) -> Self {
+        Self { access }
+    }
+
+    fn entries(&self) -> View<T> {
+        View::new(
+            self.access.clone(),
+            ResolvedAddress::system(HISTORY_ENTRIES),
+        )
+    }
+
+    fn records(&self) -> View<T> {
+        View::new(
+            self.access.clone(),
+            ResolvedAddress::system(HISTORY_RECORDS),
+        )
+    }
+
+    fn metadata(&self) -> View<T> {
+        View::new(self.access.clone(), ResolvedAddress::system(DB_METADATA))
+    }
+
+    /// Returns the earliest version, the state for which can be restored, or `None`
+    /// if the history is not recorded.
+    pub fn start(&self) -> Option<u64> {
+        self.metadata().get(HISTORY_START_NAME)
+    }
+}
+
+impl<T: RawAccessMut> History<T> {
+    /// Adds history entries with the specified `version` and prunes obsolete entries
+    /// according to `options`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `version` is less than the version of the previously merged patch.
+    pub fn push(
+        &self,
+        version: u64,
+        changes: HashMap<ResolvedAddress, ViewChanges>,
+        options: &HistoryOptions,
+    ) -> crate::Result<()> {
+        let mut metadata = self.metadata();
+        if let Some(latest_version) = metadata.get::<_, u64>(HISTORY_END_NAME) {
+            if version < latest_version {
+                return Err(Error::new(format!(
+                    "Versions of patches merged with history must not decrease: \
+                     got {} after {}",
+                    version, latest_version
+                )));
+            }
+        }
+
+        let mut records = self.records();
+        let seq = records
+            .iter_rev::<_, [u8], ()>(&())
+            .next()
+            .filter(|(key, ())| record_version(key) == version)
+            .map_or(0, |(key, ())| record_seq(&key) + 1);
+        let record_key = record_key(version, seq);
+
+        let mut entries = self.entries();
+        let mut entry_keys = vec![];
+        for (address, view_changes) in changes {
+            let prefix = entry_prefix(&address);
+            for (key, change) in view_changes.into_data() {
+                let mut entry_key = prefix.clone();
+                encode_key(&mut entry_key, &key, true);
+                entry_key.extend_from_slice(&record_key);
+                let value = match change {
+                    Change::Put(ref value) => Some(value.as_slice()),
+                    Change::Delete => None,
+                };
+                entries.put(&entry_key[..], encode_value(value));
+                entry_keys.push(entry_key);
+            }
+        }
+        records.put(&record_key[..], HistoryRecord { entry_keys });
+
+        let mut history_start = metadata.get(HISTORY_START_NAME).unwrap_or(version);
+        if let Some(retained_versions) = options.retained_versions {
+            let cutoff = version.saturating_sub(retained_versions);
+            let obsolete_records: Vec<_> = records
+                .iter::<_, [u8], HistoryRecord>(&())
+                .take_while(|(key, _)| record_version(key) <= cutoff)
+                .collect();
+            for (key, record) in obsolete_records {
+                for entry_key in record.entry_keys {
+                    entries.remove(&entry_key[..]);
+                }
+                records.remove(&key[..]);
+            }
+            history_start = history_start.max(cutoff);
+        }
+        metadata.put(HISTORY_START_NAME, history_start);
+        metadata.put(HISTORY_END_NAME, version);
+        Ok(())
+    }
+}
+
+/// Snapshot of the database state as of a past version. Historical values are read
+/// from the history entries on demand; the keys not changed after the version are read
+/// from the current database state.
+#[derive(Debug)]
+pub(crate) struct HistoricalSnapshot {
+    snapshot: Box<dyn Snapshot>,
+    version: u64,
+}
+
+impl HistoricalSnapshot {
+    /// Creates a snapshot as of the specified `version`, or returns `None` if the history
+    /// for `version` is not available.
+    pub fn new(snapshot: Box<dyn Snapshot>, version: u64) -> Option<Self> {
+        let history_start = History::new(&snapshot).start()?;
+        if version < history_start {
+            return None;
+        }
+        Some(Self { snapshot, version })
+    }
+
+    fn entries(&self, start: &[u8]) -> Iter<'_> {
+        self.snapshot
+            .iter(&ResolvedAddress::system(HISTORY_ENTRIES), start)
+    }
+
+    fn entries_rev(&self, upper_bound: Bound<&[u8]>) -> Iter<'_> {
+        self.snapshot
+            .iter_rev(&ResolvedAddress::system(HISTORY_ENTRIES), upper_bound)
+    }
+}
+
+impl Snapshot for HistoricalSnapshot {
+    fn get(&self, name: &ResolvedAddress, key: &[u8]) -> Option<Vec<u8>> {
+        let next_version = match self.version.checked_add(1) {
+            Some(next_version) => next_version,
+            None => return self.snapshot.get(name, key),
+        };
+
+        let mut key_prefix = entry_prefix(name);
+        encode_key(&mut key_prefix, key, true);
+        let mut start = key_prefix.clone();
+        start.extend_from_slice(&next_version.to_be_bytes());
+        // The first entry for the key with a version exceeding `self.version` reverts the key
+        // to its state as of `self.version`.
+        match self.entries(&start).next() {
+            Some((entry_key, value)) if entry_key.starts_with(&key_prefix) => decode_value(value),
+            _ => self.snapshot.get(name, key),
+        }
+    }
+
+    fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_> {
+        let prefix = entry_prefix(name);
+        let mut start = prefix.clone();
+        encode_key(&mut start, from, false);
+        let changes = HistoryChanges {
+            entries: self.entries(&start),
+            prefix,
+            version: self.version,
+            reversed: false,
+        };
+        Box::new(HistoryIter::new(
+            self.snapshot.iter(name, from),
+            changes,
+            false,
+        ))
+    }
+
+    fn iter_rev(&self, name: &ResolvedAddress, upper_bound: Bound<&[u8]>) -> Iter<'_> {
+        let prefix = entry_prefix(name);
+        let entries_bound = match upper_bound {
+            Bound::Included(key) | Bound::Excluded(key) => {
+                let mut bound = prefix.clone();
+                encode_key(&mut bound, key, false);
+                // Entries for `key` continue with `[0, 0]`, and entries for the greater keys
+                // with `[0, 0xff]` or a non-zero byte.
+                if let Bound::Included(_) = upper_bound {
+                    bound.extend_from_slice(&[0, 1]);
+                } else {
+                    bound.extend_from_slice(&[0, 0]);
+                }
+                Some(bound)
+            }
+            Bound::Unbounded => next_prefix(&prefix),
+        };
+        let entries = match entries_bound {
+            Some(ref bound) => self.entries_rev(Bound::Excluded(bound.as_slice())),
+            None => self.entries_rev(Bound::Unbounded),
+        };
+
+        let changes = HistoryChanges {
+            entries,
+            prefix,
+            version: self.version,
+            reversed: true,
+        };
+        Box::new(HistoryIter::new(
+            self.snapshot.iter_rev(name, upper_bound),
+            changes,
+            true,
+        ))
+    }
+}
+
+/// Iterator over historical values of the keys in a single view. `None` values correspond
+/// to the keys absent in the historical state.
+struct HistoryChanges<'a> {
+    entries: Iter<'a>,
+    prefix: Vec<u8>,
+    version: u64,
+    reversed: bool,
+}
+
+impl StdIterator for HistoryChanges<'_> {
+    type Item = (Vec<u8>, Option<Vec<u8>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut current_key = None;
+            let mut historical_value = None;
+
+            while let Some((entry_key, value)) = self.entries.peek() {
+                if !entry_key.starts_with(&self.prefix) {
+                    break;
+                }
+                let (key, version) = decode_entry_key(&entry_key[self.prefix.len()..]);
+                if current_key
+                    .as_ref()
+                    .map_or(false, |current| *current != key)
+                {
+                    break;
+                }
+
+                // Entries for the same key are ordered by version. The historical value
+                // is provided by the entry with the least version exceeding `self.version`.
+                if version > self.version && (self.reversed || historical_value.is_none()) {
+                    historical_value = Some(decode_value(value));
+                }
+                current_key = Some(key);
+                self.entries.next();
+            }
+
+            let key = current_key?;
+            if let Some(value) = historical_value {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+/// Iterator over a view in a `HistoricalSnapshot`, which merges the current state of the view
+/// with historical values of the changed keys.
+struct HistoryIter<'a> {
+    snapshot: Iter<'a>,
+    changes: Peekable<HistoryChanges<'a>>,
+    reversed: bool,
+    peeked: Option<Option<(Vec<u8>, Vec<u8>)>>,
+    current: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl<'a> HistoryIter<'a> {
+    fn new(snapshot: Iter<'a>, changes: HistoryChanges<'a>, reversed: bool) -> Self {
+        Self {
+            snapshot,
+            changes: changes.peekable(),
+            reversed,
+            peeked: None,
+            current: None,
+        }
+    }
+
+    fn advance(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        loop {
+            let ordering = match (self.snapshot.peek(), self.changes.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((key, _)), Some((changed_key, _))) => {
+                    let ordering = key.cmp(&changed_key[..]);
+                    if self.reversed {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                }
+            };
+
+            if ordering == Ordering::Less {
+                return self
+                    .snapshot
+                    .next()
+                    .map(|(key, value)| (key.to_vec(), value.to_vec()));
+            }
+            if ordering == Ordering::Equal {
+                // The historical value overrides the current one.
+                self.snapshot.next();
+            }
+            if let Some((key, Some(value))) = self.changes.next() {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+impl Iterator for HistoryIter<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.current = match self.peeked.take() {
+            Some(item) => item,
+            None => self.advance(),
+        };
+        self.current
+            .as_ref()
+            .map(|(key, value)| (key.as_slice(), value.as_slice()))
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.advance());
+        }
+        self.peeked
+            .as_ref()
+            .and_then(Option::as_ref)
+            .map(|(key, value)| (key.as_slice(), value.as_slice()))
+    }
+}
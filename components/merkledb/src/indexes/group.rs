@@ -1,7 +1,10 @@
-use std::marker::PhantomData;
+//! Groups of indexes distinguished by a key, together with iterators over
+//! the existing members of a group.
+
+use std::{borrow::Borrow, fmt, marker::PhantomData};
 
 use crate::{
-    access::{Access, AccessError, FromAccess},
+    access::{Access, AccessError, AccessGroups, FromAccess, GroupKeys, RawAccess},
     views::IndexAddress,
     BinaryKey,
 };
@@ -64,6 +67,22 @@ use crate::{
 /// group.get("bar").push(42);
 /// # assert_eq!(fork.readonly().get_list::<_, u64>(("unsized_group", "bar")).len(), 1);
 /// ```
+///
+/// Existing members of the group can be enumerated:
+///
+/// ```
+/// # use exonum_merkledb::{access::AccessExt, Database, Group, ListIndex, TemporaryDB};
+/// # let db = TemporaryDB::new();
+/// # let fork = db.fork();
+/// let group: Group<_, u64, ListIndex<_, u64>> = fork.get_group("group");
+/// group.get(&3).push(1);
+/// group.get(&1).extend(vec![2, 3]);
+/// assert_eq!(group.keys().collect::<Vec<_>>(), vec![1, 3]);
+/// for (key, list) in group.iter_from(&2) {
+///     assert_eq!(key, 3);
+///     assert_eq!(list.len(), 1);
+/// }
+/// ```
 #[derive(Debug)]
 pub struct Group<T, K: ?Sized, I> {
     access: T,
@@ -106,14 +125,141 @@ where
     }
 }
 
+impl<T, K, I> Group<T, K, I>
+where
+    T: AccessGroups,
+    K: BinaryKey + ?Sized,
+    I: FromAccess<T>,
+{
+    /// Returns an iterator over keys of the existing indexes in the group. Keys are returned
+    /// in the lexicographic order of their binary serialization.
+    ///
+    /// An index is considered existing if it was accessed at least once in a `Fork`,
+    /// even if it is empty. Only indexes placed directly in the group are enumerated;
+    /// the method cannot be used for groups of components or nested groups, since the keys
+    /// of the contained indexes are not separated from other address components.
+    pub fn keys(&self) -> Keys<T::Base, K> {
+        Keys {
+            inner: self.access.clone().group_keys(self.prefix.clone(), &[]),
+            _key: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the existing indexes in the group together with their keys.
+    ///
+    /// See [`keys`](#method.keys) for the limitations applicable to the iteration.
+    ///
+    /// # Panics
+    ///
+    /// If any iterated index has a wrong type.
+    pub fn iter(&self) -> Iter<'_, T, K, I> {
+        Iter {
+            keys: self.keys(),
+            group: self,
+        }
+    }
+
+    /// Returns an iterator over the existing indexes in the group starting from the
+    /// specified key (inclusive).
+    ///
+    /// See [`keys`](#method.keys) for the limitations applicable to the iteration.
+    ///
+    /// # Panics
+    ///
+    /// If any iterated index has a wrong type.
+    pub fn iter_from(&self, from: &K) -> Iter<'_, T, K, I> {
+        let mut from_bytes = vec![0; from.size()];
+        from.write(&mut from_bytes);
+        let keys = Keys {
+            inner: self
+                .access
+                .clone()
+                .group_keys(self.prefix.clone(), &from_bytes),
+            _key: PhantomData,
+        };
+        Iter { keys, group: self }
+    }
+}
+
+/// Iterator over keys of the existing indexes in a `Group`.
+///
+/// This struct is created by the [`keys`] method on [`Group`].
+///
+/// [`keys`]: struct.Group.html#method.keys
+/// [`Group`]: struct.Group.html
+pub struct Keys<T: RawAccess, K: ?Sized> {
+    inner: GroupKeys<T>,
+    _key: PhantomData<K>,
+}
+
+impl<T, K> fmt::Debug for Keys<T, K>
+where
+    T: RawAccess,
+    K: ?Sized,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_tuple("Keys").field(&self.inner).finish()
+    }
+}
+
+impl<T, K> Iterator for Keys<T, K>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+{
+    type Item = K::Owned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|key| K::read(&key))
+    }
+}
+
+/// Iterator over the existing indexes in a `Group` together with their keys.
+///
+/// This struct is created by the [`iter`] or [`iter_from`] methods on [`Group`].
+///
+/// [`iter`]: struct.Group.html#method.iter
+/// [`iter_from`]: struct.Group.html#method.iter_from
+/// [`Group`]: struct.Group.html
+pub struct Iter<'a, T: Access, K: ?Sized, I> {
+    keys: Keys<T::Base, K>,
+    group: &'a Group<T, K, I>,
+}
+
+impl<T, K, I> fmt::Debug for Iter<'_, T, K, I>
+where
+    T: Access,
+    K: ?Sized,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_tuple("Iter").field(&self.keys).finish()
+    }
+}
+
+impl<T, K, I> Iterator for Iter<'_, T, K, I>
+where
+    T: Access,
+    K: BinaryKey + ?Sized,
+    I: FromAccess<T>,
+{
+    type Item = (K::Owned, I);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        let index = self.group.get(key.borrow());
+        Some((key, index))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{access::AccessExt, Database, ProofListIndex, TemporaryDB};
+    use crate::{
+        access::{AccessExt, Prefixed},
+        Database, ListIndex, ProofListIndex,
+    };
 
-    #[test]
-    fn group() {
-        let db = TemporaryDB::new();
+    fn group(db: &dyn Database) {
         let fork = db.fork();
 
         {
@@ -142,4 +288,75 @@ mod tests {
         // The next line fails to compile because `Snapshot` cannot be written to:
         // group.get(&3).push("quux".to_owned());
     }
+
+    fn group_iteration(db: &dyn Database) {
+        let fork = db.fork();
+        {
+            let group: Group<_, str, ProofListIndex<_, u64>> = fork.get_group("group");
+            group.get("foo").push(1);
+            group.get("bar").extend(vec![2, 3]);
+            group.get("baz");
+            // Indexes outside of the group should not be iterated over.
+            fork.get_list::<_, u64>("group_").push(4);
+            fork.get_list::<_, u64>(("group.other", "qux")).push(5);
+
+            let keys: Vec<_> = group.keys().collect();
+            assert_eq!(keys, vec!["bar", "baz", "foo"]);
+            let lengths: Vec<_> = group.iter().map(|(key, list)| (key, list.len())).collect();
+            assert_eq!(
+                lengths,
+                vec![
+                    ("bar".to_owned(), 2),
+                    ("baz".to_owned(), 0),
+                    ("foo".to_owned(), 1)
+                ]
+            );
+            let keys: Vec<_> = group.iter_from("bar_").map(|(key, _)| key).collect();
+            assert_eq!(keys, vec!["baz", "foo"]);
+
+            // Indexes can be accessed while iterating.
+            for key in group.keys() {
+                group.get(&key).push(0);
+            }
+        }
+
+        db.merge_sync(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+        let group: Group<_, str, ProofListIndex<_, u64>> = snapshot.get_group("group");
+        let lengths: Vec<_> = group.iter().map(|(_, list)| list.len()).collect();
+        assert_eq!(lengths, vec![3, 1, 2]);
+        let empty_group: Group<_, str, ProofListIndex<_, u64>> = snapshot.get_group("other");
+        assert_eq!(empty_group.keys().count(), 0);
+    }
+
+    fn group_iteration_with_many_keys(db: &dyn Database) {
+        let fork = db.fork();
+        let group: Group<_, u32, ListIndex<_, u32>> = fork.get_group("group");
+        for i in 0..1_000 {
+            group.get(&i).push(i);
+        }
+        assert!(group.keys().eq(0..1_000));
+        assert!(group.iter_from(&500).map(|(key, _)| key).eq(500..1_000));
+        assert!(group.iter().all(|(key, list)| list.get(0) == Some(key)));
+    }
+
+    fn group_iteration_with_prefixed_access(db: &dyn Database) {
+        let fork = db.fork();
+        let prefixed = Prefixed::new("prefixed", &fork);
+        let group: Group<_, u8, ListIndex<_, u8>> = prefixed.get_group("group");
+        group.get(&2).push(2);
+        group.get(&1).push(1);
+        fork.get_list::<_, u8>(("group", &3_u8)).push(3);
+
+        assert_eq!(group.keys().collect::<Vec<_>>(), vec![1, 2]);
+        let group: Group<_, u8, ListIndex<_, u8>> = fork.get_group("group");
+        assert_eq!(group.keys().collect::<Vec<_>>(), vec![3]);
+    }
+
+    backend_tests!(
+        group,
+        group_iteration,
+        group_iteration_with_many_keys,
+        group_iteration_with_prefixed_access,
+    );
 }
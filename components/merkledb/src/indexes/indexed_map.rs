@@ -0,0 +1,852 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a key-value map with secondary indexes.
+//!
+//! [`IndexedMap`] is a map with secondary indexes, which are updated automatically
+//! on each modification of the map. A secondary index is declared by implementing
+//! the [`SecondaryIndex`] trait, which extracts the secondary key from a map value;
+//! the set of indexes of the map is specified as a tuple of such types. The map allows to look up
+//! values by a secondary key and to iterate over a secondary index in the order of secondary keys.
+//!
+//! All data of the map, including secondary indexes, is stored in a single index with
+//! the [`IndexedMap`] type. Names of the secondary indexes are recorded in the index metadata;
+//! an attempt to access the map with a different set of secondary indexes fails.
+//! Thus, adding or removing a secondary index requires a data migration: a map with the new
+//! set of indexes should be created in the migration namespace and populated with
+//! the values from the old map, which automatically builds the new secondary indexes.
+//!
+//! [`IndexedMap`]: struct.IndexedMap.html
+//! [`SecondaryIndex`]: trait.SecondaryIndex.html
+//!
+//! # Examples
+//!
+//! ```
+//! use exonum_merkledb::{access::AccessExt, indexed_map::SecondaryIndex, Database, TemporaryDB};
+//!
+//! /// Indexes names by their length.
+//! struct ByLength;
+//!
+//! impl SecondaryIndex<String> for ByLength {
+//!     type Key = u64;
+//!     const NAME: &'static str = "by_length";
+//!
+//!     fn key(value: &String) -> Option<u64> {
+//!         Some(value.len() as u64)
+//!     }
+//! }
+//!
+//! let db = TemporaryDB::new();
+//! let fork = db.fork();
+//! let mut names = fork.get_indexed_map::<_, u64, String, (ByLength,)>("names");
+//! names.put(&1, "Alice".to_owned());
+//! names.put(&2, "Bob".to_owned());
+//! names.put(&3, "Carol".to_owned());
+//!
+//! let keys: Vec<_> = names.keys_by::<ByLength>(&5).collect();
+//! assert_eq!(keys, vec![1, 3]);
+//!
+//! // Secondary indexes are updated when the values are changed.
+//! names.put(&3, "Dave".to_owned());
+//! let entries: Vec<_> = names.iter_by::<ByLength>(&4).collect();
+//! assert_eq!(entries, vec![(3, "Dave".to_owned())]);
+//! ```
+
+use failure::format_err;
+
+use std::marker::PhantomData;
+
+use crate::{
+    access::{Access, AccessError, AccessErrorKind, FromAccess},
+    keys::FixedSizeKey,
+    validation::check_valid_name_component,
+    views::{
+        IndexAddress, IndexState, IndexType, Iter as ViewIter, RawAccess, RawAccessMut, View,
+        ViewWithMetadata,
+    },
+    BinaryKey, BinaryValue,
+};
+
+/// Prefix of the keys storing the map values. Entries of the secondary indexes are stored
+/// under the prefix equal to the one-based position of the index in the declaration.
+pub(crate) const VALUES_PREFIX: u8 = 0;
+
+/// Secondary index of an `IndexedMap` with values of type `V`.
+///
+/// Secondary keys must have a fixed size, so that entries of the index can be looked up
+/// by the secondary key alone. Several values may have the same secondary key.
+pub trait SecondaryIndex<V> {
+    /// Type of the secondary keys.
+    type Key: FixedSizeKey;
+    /// Name of the index, which is recorded in the metadata of the map. The name should
+    /// follow conventions for index name components and must be unique among the indexes
+    /// of the map.
+    const NAME: &'static str;
+
+    /// Returns the secondary key for the specified value, or `None` if the value
+    /// should not be included into the index.
+    fn key(value: &V) -> Option<Self::Key>;
+}
+
+/// Set of secondary indexes of an `IndexedMap`. This trait is implemented for tuples
+/// of up to 6 [`SecondaryIndex`]es and should not be implemented manually.
+///
+/// [`SecondaryIndex`]: trait.SecondaryIndex.html
+pub trait SecondaryIndexes<V> {
+    /// Returns names of the indexes in the order of their declaration.
+    fn names() -> Vec<&'static str>;
+    /// Returns serialized secondary keys of the value in the order of the index declaration.
+    fn raw_keys(value: &V) -> Vec<Option<Vec<u8>>>;
+}
+
+macro_rules! impl_secondary_indexes {
+    ($($index:ident),+) => {
+        impl<V, $($index),+> SecondaryIndexes<V> for ($($index,)+)
+        where
+            $($index: SecondaryIndex<V>,)+
+        {
+            fn names() -> Vec<&'static str> {
+                vec![$(<$index as SecondaryIndex<V>>::NAME),+]
+            }
+
+            fn raw_keys(value: &V) -> Vec<Option<Vec<u8>>> {
+                vec![$(<$index as SecondaryIndex<V>>::key(value).map(|key| key_bytes(&key))),+]
+            }
+        }
+    };
+}
+
+impl_secondary_indexes!(A);
+impl_secondary_indexes!(A, B);
+impl_secondary_indexes!(A, B, C);
+impl_secondary_indexes!(A, B, C, D);
+impl_secondary_indexes!(A, B, C, D, E);
+impl_secondary_indexes!(A, B, C, D, E, F);
+
+/// A map of keys and values with secondary indexes `S` over the values.
+///
+/// `IndexedMap` requires that keys implement the [`BinaryKey`] trait and values implement
+/// the [`BinaryValue`] trait. Secondary indexes are declared as a tuple of [`SecondaryIndex`]
+/// implementations.
+///
+/// [`BinaryKey`]: ../../trait.BinaryKey.html
+/// [`BinaryValue`]: ../../trait.BinaryValue.html
+/// [`SecondaryIndex`]: trait.SecondaryIndex.html
+#[derive(Debug)]
+pub struct IndexedMap<T: RawAccess, K: ?Sized, V, S> {
+    base: View<T>,
+    state: IndexState<T, Vec<String>>,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+    _s: PhantomData<S>,
+}
+
+/// Returns an iterator over the entries of an `IndexedMap`.
+///
+/// This struct is created by the [`iter`] or [`iter_from`] method on [`IndexedMap`].
+/// See its documentation for additional details.
+///
+/// [`iter`]: struct.IndexedMap.html#method.iter
+/// [`iter_from`]: struct.IndexedMap.html#method.iter_from
+/// [`IndexedMap`]: struct.IndexedMap.html
+#[derive(Debug)]
+pub struct Iter<'a, K: ?Sized, V> {
+    base_iter: ViewIter<'a, Vec<u8>, V>,
+    _k: PhantomData<K>,
+}
+
+/// Returns an iterator over the keys of an `IndexedMap`.
+///
+/// This struct is created by the [`keys`] or [`keys_by`] method on [`IndexedMap`].
+/// See its documentation for additional details.
+///
+/// [`keys`]: struct.IndexedMap.html#method.keys
+/// [`keys_by`]: struct.IndexedMap.html#method.keys_by
+/// [`IndexedMap`]: struct.IndexedMap.html
+#[derive(Debug)]
+pub struct Keys<'a, K: ?Sized> {
+    base_iter: ViewIter<'a, Vec<u8>, ()>,
+    key_offset: usize,
+    _k: PhantomData<K>,
+}
+
+/// Returns an iterator over the values of an `IndexedMap`.
+///
+/// This struct is created by the [`values`] method on [`IndexedMap`].
+/// See its documentation for additional details.
+///
+/// [`values`]: struct.IndexedMap.html#method.values
+/// [`IndexedMap`]: struct.IndexedMap.html
+#[derive(Debug)]
+pub struct Values<'a, V> {
+    base_iter: ViewIter<'a, (), V>,
+}
+
+/// Returns an iterator over the entries of an `IndexedMap` with the specified secondary key.
+///
+/// This struct is created by the [`iter_by`] method on [`IndexedMap`].
+/// See its documentation for additional details.
+///
+/// [`iter_by`]: struct.IndexedMap.html#method.iter_by
+/// [`IndexedMap`]: struct.IndexedMap.html
+#[derive(Debug)]
+pub struct IterBy<'a, T: RawAccess, K: ?Sized, V> {
+    base: &'a View<T>,
+    base_iter: ViewIter<'a, Vec<u8>, ()>,
+    key_offset: usize,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+}
+
+/// Returns an iterator over the entries of a secondary index of an `IndexedMap`.
+///
+/// This struct is created by the [`index_iter`] or [`index_iter_from`] method
+/// on [`IndexedMap`]. See its documentation for additional details.
+///
+/// [`index_iter`]: struct.IndexedMap.html#method.index_iter
+/// [`index_iter_from`]: struct.IndexedMap.html#method.index_iter_from
+/// [`IndexedMap`]: struct.IndexedMap.html
+#[derive(Debug)]
+pub struct IndexIter<'a, Q, K: ?Sized> {
+    base_iter: ViewIter<'a, Vec<u8>, ()>,
+    _q: PhantomData<Q>,
+    _k: PhantomData<K>,
+}
+
+fn key_bytes<K: BinaryKey + ?Sized>(key: &K) -> Vec<u8> {
+    let mut buffer = vec![0; key.size()];
+    key.write(&mut buffer);
+    buffer
+}
+
+/// Returns the serialized `key` prepended with the specified one-byte prefix.
+fn prefixed_key<K: BinaryKey + ?Sized>(prefix: u8, key: &K) -> Vec<u8> {
+    let mut buffer = vec![0; 1 + key.size()];
+    buffer[0] = prefix;
+    key.write(&mut buffer[1..]);
+    buffer
+}
+
+/// Checks that names of secondary indexes are valid and unique.
+fn check_index_names(names: &[&str]) -> Result<(), AccessErrorKind> {
+    for (i, name) in names.iter().enumerate() {
+        check_valid_name_component(name)?;
+        if names[..i].contains(name) {
+            let err = format_err!("Secondary index `{}` is declared several times", name);
+            return Err(AccessErrorKind::Custom(err));
+        }
+    }
+    Ok(())
+}
+
+impl<T, K, V, S> FromAccess<T> for IndexedMap<T::Base, K, V, S>
+where
+    T: Access,
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+    S: SecondaryIndexes<V>,
+{
+    fn from_access(access: T, addr: IndexAddress) -> Result<Self, AccessError> {
+        let names = S::names();
+        if let Err(kind) = check_index_names(&names) {
+            return Err(AccessError { addr, kind });
+        }
+
+        let view = access.get_or_create_view(addr.clone(), IndexType::IndexedMap)?;
+        let (base, state) = view.into_parts::<Vec<String>>();
+        if let Some(recorded_names) = state.get_ref() {
+            if !recorded_names
+                .iter()
+                .map(String::as_str)
+                .eq(names.iter().copied())
+            {
+                return Err(AccessError {
+                    addr,
+                    kind: AccessErrorKind::SecondaryIndexesMismatch {
+                        expected: names.into_iter().map(str::to_owned).collect(),
+                        actual: recorded_names.clone(),
+                    },
+                });
+            }
+        }
+
+        Ok(Self {
+            base,
+            state,
+            _k: PhantomData,
+            _v: PhantomData,
+            _s: PhantomData,
+        })
+    }
+}
+
+impl<T, K, V, S> IndexedMap<T, K, V, S>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+    S: SecondaryIndexes<V>,
+{
+    /// Returns the prefix of the entries of the secondary index `I`.
+    fn index_prefix<I: SecondaryIndex<V>>() -> u8 {
+        let position = S::names()
+            .into_iter()
+            .position(|name| name == I::NAME)
+            .unwrap_or_else(|| panic!("Secondary index `{}` is not declared", I::NAME));
+        position as u8 + 1
+    }
+
+    /// Returns a value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, indexed_map::SecondaryIndex, Database, TemporaryDB};
+    /// # struct ByLength;
+    /// # impl SecondaryIndex<String> for ByLength {
+    /// #     type Key = u64;
+    /// #     const NAME: &'static str = "by_length";
+    /// #     fn key(value: &String) -> Option<u64> { Some(value.len() as u64) }
+    /// # }
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_indexed_map::<_, u8, String, (ByLength,)>("name");
+    /// assert!(index.get(&1).is_none());
+    ///
+    /// index.put(&1, "foo".to_owned());
+    /// assert_eq!(index.get(&1), Some("foo".to_owned()));
+    /// ```
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.base.get(&prefixed_key(VALUES_PREFIX, key))
+    }
+
+    /// Returns `true` if the map contains a value corresponding to the specified key.
+    pub fn contains(&self, key: &K) -> bool {
+        self.base.contains(&prefixed_key(VALUES_PREFIX, key))
+    }
+
+    /// Returns an iterator over the entries of the map in ascending order of keys.
+    /// The iterator element type is (K, V).
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            base_iter: self.base.iter(&VALUES_PREFIX),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the map in ascending order starting from
+    /// the specified key. The iterator element type is (K, V).
+    pub fn iter_from(&self, from: &K) -> Iter<'_, K, V> {
+        Iter {
+            base_iter: self
+                .base
+                .iter_from(&VALUES_PREFIX, &prefixed_key(VALUES_PREFIX, from)),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the keys of the map in ascending order. The iterator element
+    /// type is K.
+    pub fn keys(&self) -> Keys<'_, K> {
+        Keys {
+            base_iter: self.base.iter(&VALUES_PREFIX),
+            key_offset: 1,
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the values of the map in ascending order of keys.
+    /// The iterator element type is V.
+    pub fn values(&self) -> Values<'_, V> {
+        Values {
+            base_iter: self.base.iter(&VALUES_PREFIX),
+        }
+    }
+
+    /// Returns an iterator over the keys of the values with the specified key in the secondary
+    /// index `I`. The keys are returned in ascending order.
+    ///
+    /// # Panics
+    ///
+    /// If `I` is not one of the secondary indexes of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, indexed_map::SecondaryIndex, Database, TemporaryDB};
+    /// /// Indexes strings by their first char.
+    /// struct ByFirstChar;
+    ///
+    /// impl SecondaryIndex<String> for ByFirstChar {
+    ///     type Key = u8;
+    ///     const NAME: &'static str = "by_first_char";
+    ///
+    ///     fn key(value: &String) -> Option<u8> {
+    ///         // Empty strings are not indexed.
+    ///         value.bytes().next()
+    ///     }
+    /// }
+    ///
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_indexed_map::<_, u8, String, (ByFirstChar,)>("name");
+    /// index.put(&1, "foo".to_owned());
+    /// index.put(&2, "bar".to_owned());
+    /// index.put(&3, "fizz".to_owned());
+    /// index.put(&4, String::new());
+    ///
+    /// let keys: Vec<_> = index.keys_by::<ByFirstChar>(&b'f').collect();
+    /// assert_eq!(keys, vec![1, 3]);
+    /// assert_eq!(index.keys_by::<ByFirstChar>(&b'q').count(), 0);
+    /// ```
+    pub fn keys_by<I>(&self, secondary_key: &I::Key) -> Keys<'_, K>
+    where
+        I: SecondaryIndex<V>,
+    {
+        let prefix = prefixed_key(Self::index_prefix::<I>(), secondary_key);
+        Keys {
+            key_offset: prefix.len(),
+            base_iter: self.base.iter(&prefix),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries with the specified key in the secondary index `I`.
+    /// The entries are returned in ascending order of their keys. The iterator element
+    /// type is (K, V).
+    ///
+    /// # Panics
+    ///
+    /// If `I` is not one of the secondary indexes of the map.
+    pub fn iter_by<I>(&self, secondary_key: &I::Key) -> IterBy<'_, T, K, V>
+    where
+        I: SecondaryIndex<V>,
+    {
+        let prefix = prefixed_key(Self::index_prefix::<I>(), secondary_key);
+        IterBy {
+            base: &self.base,
+            key_offset: prefix.len(),
+            base_iter: self.base.iter(&prefix),
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the secondary index `I` in ascending order of secondary keys.
+    /// Entries with the same secondary key are ordered by the key in the map.
+    /// The iterator element type is (secondary key, K).
+    ///
+    /// # Panics
+    ///
+    /// If `I` is not one of the secondary indexes of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, indexed_map::SecondaryIndex, Database, TemporaryDB};
+    /// # struct ByLength;
+    /// # impl SecondaryIndex<String> for ByLength {
+    /// #     type Key = u64;
+    /// #     const NAME: &'static str = "by_length";
+    /// #     fn key(value: &String) -> Option<u64> { Some(value.len() as u64) }
+    /// # }
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_indexed_map::<_, u8, String, (ByLength,)>("name");
+    /// index.put(&1, "fizz".to_owned());
+    /// index.put(&2, "foo".to_owned());
+    /// index.put(&3, "buzz".to_owned());
+    ///
+    /// let entries: Vec<_> = index.index_iter::<ByLength>().collect();
+    /// assert_eq!(entries, vec![(3, 2), (4, 1), (4, 3)]);
+    /// let entries: Vec<_> = index.index_iter_from::<ByLength>(&4).collect();
+    /// assert_eq!(entries, vec![(4, 1), (4, 3)]);
+    /// ```
+    pub fn index_iter<I>(&self) -> IndexIter<'_, I::Key, K>
+    where
+        I: SecondaryIndex<V>,
+    {
+        IndexIter {
+            base_iter: self.base.iter(&Self::index_prefix::<I>()),
+            _q: PhantomData,
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the secondary index `I` in ascending order of secondary keys
+    /// starting from the specified secondary key. The iterator element type is
+    /// (secondary key, K).
+    ///
+    /// # Panics
+    ///
+    /// If `I` is not one of the secondary indexes of the map.
+    pub fn index_iter_from<I>(&self, from: &I::Key) -> IndexIter<'_, I::Key, K>
+    where
+        I: SecondaryIndex<V>,
+    {
+        let prefix = Self::index_prefix::<I>();
+        IndexIter {
+            base_iter: self.base.iter_from(&prefix, &prefixed_key(prefix, from)),
+            _q: PhantomData,
+            _k: PhantomData,
+        }
+    }
+}
+
+impl<T, K, V, S> IndexedMap<T, K, V, S>
+where
+    T: RawAccessMut,
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+    S: SecondaryIndexes<V>,
+{
+    /// Records the names of the secondary indexes in the index metadata if they are
+    /// not recorded yet.
+    fn record_index_names(&mut self) {
+        if self.state.get_ref().is_none() {
+            let names = S::names().into_iter().map(str::to_owned).collect();
+            self.state.set(names);
+        }
+    }
+
+    /// Returns the keys of the secondary index entries for the specified value.
+    fn index_entry_keys(value: &V, raw_key: &[u8]) -> Vec<Option<Vec<u8>>> {
+        S::raw_keys(value)
+            .into_iter()
+            .enumerate()
+            .map(|(i, secondary_key)| {
+                secondary_key.map(|secondary_key| {
+                    let mut entry_key = prefixed_key(i as u8 + 1, &secondary_key);
+                    entry_key.extend_from_slice(raw_key);
+                    entry_key
+                })
+            })
+            .collect()
+    }
+
+    /// Inserts a key-value pair into the map, updating the secondary indexes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, indexed_map::SecondaryIndex, Database, TemporaryDB};
+    /// # struct ByLength;
+    /// # impl SecondaryIndex<String> for ByLength {
+    /// #     type Key = u64;
+    /// #     const NAME: &'static str = "by_length";
+    /// #     fn key(value: &String) -> Option<u64> { Some(value.len() as u64) }
+    /// # }
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_indexed_map::<_, u8, String, (ByLength,)>("name");
+    /// index.put(&1, "foo".to_owned());
+    /// assert!(index.keys_by::<ByLength>(&3).eq(vec![1]));
+    ///
+    /// index.put(&1, "fizz".to_owned());
+    /// assert_eq!(index.keys_by::<ByLength>(&3).count(), 0);
+    /// assert!(index.keys_by::<ByLength>(&4).eq(vec![1]));
+    /// ```
+    pub fn put(&mut self, key: &K, value: V) {
+        self.record_index_names();
+        let raw_key = key_bytes(key);
+        let new_entries = Self::index_entry_keys(&value, &raw_key);
+        let old_entries = match self.get(key) {
+            Some(old_value) => Self::index_entry_keys(&old_value, &raw_key),
+            None => vec![None; new_entries.len()],
+        };
+
+        for (old_entry, new_entry) in old_entries.into_iter().zip(new_entries) {
+            if old_entry == new_entry {
+                continue;
+            }
+            if let Some(old_entry) = old_entry {
+                self.base.remove(&old_entry);
+            }
+            if let Some(new_entry) = new_entry {
+                self.base.put(&new_entry, ());
+            }
+        }
+        self.base.put(&prefixed_key(VALUES_PREFIX, key), value);
+    }
+
+    /// Removes a key from the map, updating the secondary indexes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, indexed_map::SecondaryIndex, Database, TemporaryDB};
+    /// # struct ByLength;
+    /// # impl SecondaryIndex<String> for ByLength {
+    /// #     type Key = u64;
+    /// #     const NAME: &'static str = "by_length";
+    /// #     fn key(value: &String) -> Option<u64> { Some(value.len() as u64) }
+    /// # }
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_indexed_map::<_, u8, String, (ByLength,)>("name");
+    /// index.put(&1, "foo".to_owned());
+    ///
+    /// index.remove(&1);
+    /// assert!(!index.contains(&1));
+    /// assert_eq!(index.keys_by::<ByLength>(&3).count(), 0);
+    /// ```
+    pub fn remove(&mut self, key: &K) {
+        let old_value = match self.get(key) {
+            Some(value) => value,
+            None => return,
+        };
+        let raw_key = key_bytes(key);
+        for old_entry in Self::index_entry_keys(&old_value, &raw_key)
+            .into_iter()
+            .flatten()
+        {
+            self.base.remove(&old_entry);
+        }
+        self.base.remove(&prefixed_key(VALUES_PREFIX, key));
+    }
+
+    /// Clears the map, removing all entries and the contents of the secondary indexes.
+    /// The names of the secondary indexes remain recorded in the index metadata.
+    ///
+    /// # Notes
+    /// Currently, this method is not optimized to delete a large set of data. During the execution of
+    /// this method, the amount of allocated memory is linearly dependent on the number of elements
+    /// in the index.
+    pub fn clear(&mut self) {
+        self.base.clear();
+    }
+}
+
+impl<'a, T, K, V, S> IntoIterator for &'a IndexedMap<T, K, V, S>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+    S: SecondaryIndexes<V>,
+{
+    type Item = (K::Owned, V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+where
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+{
+    type Item = (K::Owned, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base_iter.next().map(|(k, v)| (K::read(&k[1..]), v))
+    }
+}
+
+impl<'a, K> Iterator for Keys<'a, K>
+where
+    K: BinaryKey + ?Sized,
+{
+    type Item = K::Owned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key_offset = self.key_offset;
+        self.base_iter
+            .next()
+            .map(|(k, _)| K::read(&k[key_offset..]))
+    }
+}
+
+impl<'a, V> Iterator for Values<'a, V>
+where
+    V: BinaryValue,
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base_iter.next().map(|(.., v)| v)
+    }
+}
+
+impl<'a, T, K, V> Iterator for IterBy<'a, T, K, V>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+{
+    type Item = (K::Owned, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entry_key, _) = self.base_iter.next()?;
+        let raw_key = &entry_key[self.key_offset..];
+        let value = self
+            .base
+            .get(&prefixed_key(VALUES_PREFIX, raw_key))
+            .expect("Secondary index entry refers to a non-existing value");
+        Some((K::read(raw_key), value))
+    }
+}
+
+impl<'a, Q, K> Iterator for IndexIter<'a, Q, K>
+where
+    Q: FixedSizeKey,
+    K: BinaryKey + ?Sized,
+{
+    type Item = (Q::Owned, K::Owned);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base_iter.next().map(|(k, _)| {
+            let (secondary_key, key) = k[1..].split_at(Q::SIZE);
+            (Q::read(secondary_key), K::read(key))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::{access::AccessExt, Database};
+
+    const IDX_NAME: &str = "idx_name";
+
+    struct ByLength;
+
+    impl SecondaryIndex<String> for ByLength {
+        type Key = u32;
+        const NAME: &'static str = "by_length";
+
+        fn key(value: &String) -> Option<u32> {
+            Some(value.len() as u32)
+        }
+    }
+
+    struct ByFirstChar;
+
+    impl SecondaryIndex<String> for ByFirstChar {
+        type Key = u8;
+        const NAME: &'static str = "by_first_char";
+
+        fn key(value: &String) -> Option<u8> {
+            value.bytes().next()
+        }
+    }
+
+    type Indexes = (ByLength, ByFirstChar);
+
+    fn secondary_indexes_are_maintained(db: &dyn Database) {
+        let fork = db.fork();
+        let mut map = fork.get_indexed_map::<_, u64, String, Indexes>(IDX_NAME);
+        map.put(&1, "foo".to_owned());
+        map.put(&2, "bar".to_owned());
+        map.put(&3, "fizz".to_owned());
+        map.put(&4, String::new());
+
+        assert_eq!(map.keys_by::<ByLength>(&3).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            map.keys_by::<ByFirstChar>(&b'f').collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(map.keys_by::<ByLength>(&0).collect::<Vec<_>>(), vec![4]);
+        // Empty strings are not included into `ByFirstChar`.
+        assert_eq!(map.index_iter::<ByFirstChar>().count(), 3);
+
+        // Update a value so that only one of its secondary keys changes.
+        map.put(&1, "fuzz".to_owned());
+        assert_eq!(map.keys_by::<ByLength>(&3).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(
+            map.iter_by::<ByLength>(&4).collect::<Vec<_>>(),
+            vec![(1, "fuzz".to_owned()), (3, "fizz".to_owned())]
+        );
+        assert_eq!(
+            map.keys_by::<ByFirstChar>(&b'f').collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+
+        map.remove(&3);
+        map.remove(&5);
+        assert_eq!(
+            map.keys_by::<ByFirstChar>(&b'f').collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(
+            map.index_iter::<ByLength>().collect::<Vec<_>>(),
+            vec![(0, 4), (3, 2), (4, 1)]
+        );
+        assert_eq!(
+            map.index_iter_from::<ByLength>(&1).collect::<Vec<_>>(),
+            vec![(3, 2), (4, 1)]
+        );
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![1, 2, 4]);
+        assert_eq!(map.iter_from(&2).count(), 2);
+
+        map.clear();
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(map.index_iter::<ByLength>().count(), 0);
+    }
+
+    fn secondary_index_names_are_recorded(db: &dyn Database) {
+        let fork = db.fork();
+        fork.get_indexed_map::<_, u64, String, Indexes>(IDX_NAME)
+            .put(&1, "foo".to_owned());
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map = snapshot.get_indexed_map::<_, u64, String, Indexes>(IDX_NAME);
+        assert_eq!(map.get(&1), Some("foo".to_owned()));
+        assert_eq!(
+            map.state.get_ref().unwrap(),
+            &vec!["by_length".to_owned(), "by_first_char".to_owned()]
+        );
+
+        let err =
+            IndexedMap::<_, u64, String, (ByLength,)>::from_access(&snapshot, IDX_NAME.into())
+                .unwrap_err();
+        assert_matches!(
+            err.kind,
+            AccessErrorKind::SecondaryIndexesMismatch { ref expected, ref actual }
+                if *expected == ["by_length"] && actual.len() == 2
+        );
+        let err = IndexedMap::<_, u64, String, (ByFirstChar, ByLength)>::from_access(
+            &snapshot,
+            IDX_NAME.into(),
+        )
+        .unwrap_err();
+        assert_matches!(err.kind, AccessErrorKind::SecondaryIndexesMismatch { .. });
+
+        let metadata = snapshot.get_index_metadata(IDX_NAME.into()).unwrap();
+        assert_eq!(metadata.unwrap().index_type(), IndexType::IndexedMap);
+    }
+
+    fn invalid_secondary_indexes(db: &dyn Database) {
+        let fork = db.fork();
+        let err =
+            IndexedMap::<_, u64, String, (ByLength, ByLength)>::from_access(&fork, IDX_NAME.into())
+                .unwrap_err();
+        assert_matches!(err.kind, AccessErrorKind::Custom(_));
+
+        fork.get_map::<_, u64, String>("map");
+        let err =
+            IndexedMap::<_, u64, String, Indexes>::from_access(&fork, "map".into()).unwrap_err();
+        assert_matches!(err.kind, AccessErrorKind::WrongIndexType { .. });
+    }
+
+    backend_tests!(
+        secondary_indexes_are_maintained,
+        secondary_index_names_are_recorded,
+        invalid_secondary_indexes,
+    );
+}
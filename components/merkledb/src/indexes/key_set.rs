@@ -18,7 +18,7 @@
 //! The given section contains information on the methods related to `KeySetIndex`
 //! and the iterator over the items of this set.
 
-use std::{borrow::Borrow, marker::PhantomData};
+use std::{borrow::Borrow, marker::PhantomData, ops::RangeBounds};
 
 use crate::{
     access::{Access, AccessError, FromAccess},
@@ -42,11 +42,14 @@ pub struct KeySetIndex<T: RawAccess, K> {
 
 /// Returns an iterator over the items of a `KeySetIndex`.
 ///
-/// This struct is created by the [`iter`] or
-/// [`iter_from`] method on [`KeySetIndex`]. See its documentation for details.
+/// This struct is created by the [`iter`], [`iter_from`], [`iter_rev`], [`iter_rev_from`]
+/// or [`range`] method on [`KeySetIndex`]. See its documentation for details.
 ///
 /// [`iter`]: struct.KeySetIndex.html#method.iter
 /// [`iter_from`]: struct.KeySetIndex.html#method.iter_from
+/// [`iter_rev`]: struct.KeySetIndex.html#method.iter_rev
+/// [`iter_rev_from`]: struct.KeySetIndex.html#method.iter_rev_from
+/// [`range`]: struct.KeySetIndex.html#method.range
 /// [`KeySetIndex`]: struct.KeySetIndex.html
 #[derive(Debug)]
 pub struct Iter<'a, K> {
@@ -69,7 +72,7 @@ where
     T: RawAccess,
     K: BinaryKey,
 {
-    fn new(view: ViewWithMetadata<T>) -> Self {
+    pub(crate) fn new(view: ViewWithMetadata<T>) -> Self {
         let base = view.into();
         Self {
             base,
@@ -142,6 +145,80 @@ where
             base_iter: self.base.iter_from(&(), from),
         }
     }
+
+    /// Returns an iterator visiting all elements in descending order. The iterator element
+    /// type is K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, KeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_key_set::<_, u8>("name");
+    /// for i in 1..=3 {
+    ///     index.insert(i);
+    /// }
+    ///
+    /// assert_eq!(index.iter_rev().collect::<Vec<_>>(), vec![3, 2, 1]);
+    /// ```
+    pub fn iter_rev(&self) -> Iter<'_, K> {
+        Iter {
+            base_iter: self.base.iter_rev(&()),
+        }
+    }
+
+    /// Returns an iterator visiting all elements in descending order starting from
+    /// the specified value (inclusive). The iterator element type is K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, KeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_key_set::<_, u8>("name");
+    /// for i in 1..=3 {
+    ///     index.insert(i);
+    /// }
+    ///
+    /// assert_eq!(index.iter_rev_from(&2).collect::<Vec<_>>(), vec![2, 1]);
+    /// ```
+    pub fn iter_rev_from(&self, from: &K) -> Iter<'_, K> {
+        Iter {
+            base_iter: self.base.iter_rev_from(&(), from),
+        }
+    }
+
+    /// Returns an iterator visiting elements within the specified range in ascending order.
+    /// The iterator element type is K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, KeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_key_set::<_, u8>("name");
+    /// for i in 0..10 {
+    ///     index.insert(i);
+    /// }
+    ///
+    /// assert_eq!(index.range(2..5).collect::<Vec<_>>(), vec![2, 3, 4]);
+    /// ```
+    pub fn range<R>(&self, range: R) -> Iter<'_, K>
+    where
+        R: RangeBounds<K>,
+    {
+        Iter {
+            base_iter: self
+                .base
+                .iter_range(&(), range.start_bound(), range.end_bound()),
+        }
+    }
 }
 
 impl<T, K> KeySetIndex<T, K>
@@ -247,14 +324,12 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{access::AccessExt, Database, TemporaryDB};
+    use crate::{access::AccessExt, Database};
 
     const INDEX_NAME: &str = "test_index_name";
 
-    #[test]
-    fn str_key() {
+    fn str_key(db: &dyn Database) {
         const KEY: &str = "key_1";
-        let db = TemporaryDB::new();
         let fork = db.fork();
 
         let mut index: KeySetIndex<_, String> = fork.get_key_set(INDEX_NAME);
@@ -265,10 +340,8 @@ mod tests {
         assert_eq!(false, index.contains(KEY));
     }
 
-    #[test]
-    fn u8_slice_key() {
+    fn u8_slice_key(db: &dyn Database) {
         const KEY: &[u8] = &[1, 2, 3];
-        let db = TemporaryDB::new();
         let fork = db.fork();
 
         let mut index: KeySetIndex<_, Vec<u8>> = fork.get_key_set(INDEX_NAME);
@@ -279,9 +352,7 @@ mod tests {
         assert_eq!(false, index.contains(KEY));
     }
 
-    #[test]
-    fn key_set_methods() {
-        let db = TemporaryDB::default();
+    fn key_set_methods(db: &dyn Database) {
         let fork = db.fork();
 
         let mut index = fork.get_key_set(INDEX_NAME);
@@ -296,9 +367,7 @@ mod tests {
         assert!(!index.contains(&2_u8));
     }
 
-    #[test]
-    fn no_infinite_iteration_in_flushed_fork() {
-        let db = TemporaryDB::new();
+    fn no_infinite_iteration_in_flushed_fork(db: &dyn Database) {
         let mut fork = db.fork();
         {
             let mut set = fork.get_key_set::<_, u8>(INDEX_NAME);
@@ -316,4 +385,11 @@ mod tests {
         let items: Vec<_> = set.iter().collect();
         assert!(items.is_empty());
     }
+
+    backend_tests!(
+        str_key,
+        u8_slice_key,
+        key_set_methods,
+        no_infinite_iteration_in_flushed_fork,
+    );
 }
@@ -17,7 +17,7 @@
 //! The given section contains methods related to `ListIndex` and the iterator
 //! over the items of this list.
 
-use std::marker::PhantomData;
+use std::{marker::PhantomData, ops::Bound};
 
 use crate::{
     access::{Access, AccessError, FromAccess},
@@ -46,11 +46,13 @@ pub struct ListIndex<T: RawAccess, V> {
 
 /// Returns an iterator over the items of a `ListIndex`.
 ///
-/// This struct is created by the [`iter`] or
-/// [`iter_from`] method on [`ListIndex`]. See its documentation for details.
+/// This struct is created by the [`iter`], [`iter_from`], [`iter_rev`] or
+/// [`iter_rev_from`] method on [`ListIndex`]. See its documentation for details.
 ///
 /// [`iter`]: struct.ListIndex.html#method.iter
 /// [`iter_from`]: struct.ListIndex.html#method.iter_from
+/// [`iter_rev`]: struct.ListIndex.html#method.iter_rev
+/// [`iter_rev_from`]: struct.ListIndex.html#method.iter_rev_from
 /// [`ListIndex`]: struct.ListIndex.html
 #[derive(Debug)]
 pub struct Iter<'a, V> {
@@ -211,6 +213,49 @@ where
             base_iter: self.base.iter_from(&(), &from),
         }
     }
+
+    /// Returns an iterator over the list in reverse order. The iterator element type is V.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ListIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_list("name");
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    ///
+    /// assert_eq!(index.iter_rev().collect::<Vec<i32>>(), vec![5, 4, 3, 2, 1]);
+    /// ```
+    pub fn iter_rev(&self) -> Iter<'_, V> {
+        Iter {
+            base_iter: self.base.iter_rev(&()),
+        }
+    }
+
+    /// Returns an iterator over the list in reverse order starting from the specified
+    /// position (inclusive). The iterator element type is V.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ListIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_list("name");
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    ///
+    /// assert_eq!(index.iter_rev_from(2).collect::<Vec<i32>>(), vec![3, 2, 1]);
+    /// ```
+    pub fn iter_rev_from(&self, from: u64) -> Iter<'_, V> {
+        Iter {
+            base_iter: self.base.iter_rev_from(&(), &from),
+        }
+    }
 }
 
 impl<T, V> ListIndex<T, V>
@@ -314,9 +359,10 @@ where
     /// assert_eq!(3, index.len());
     /// ```
     pub fn truncate(&mut self, len: u64) {
-        // TODO: Optimize this. (ECR-175)
-        while self.len() > len {
-            self.pop();
+        if self.len() > len {
+            self.base
+                .remove_range(Bound::Included(&len), Bound::Unbounded);
+            self.set_len(len);
         }
     }
 
@@ -536,33 +582,25 @@ mod tests {
 
     const IDX_NAME: &str = "idx_name";
 
-    #[test]
-    fn test_list_index_methods() {
-        let db = TemporaryDB::new();
+    fn test_list_index_methods(db: &dyn Database) {
         let fork = db.fork();
         let mut list_index = fork.get_list(IDX_NAME);
         list_index_methods(&mut list_index);
     }
 
-    #[test]
-    fn test_list_index_in_family_methods() {
-        let db = TemporaryDB::new();
+    fn test_list_index_in_family_methods(db: &dyn Database) {
         let fork = db.fork();
         let mut list_index = fork.get_list((IDX_NAME, &vec![1]));
         list_index_methods(&mut list_index);
     }
 
-    #[test]
-    fn test_list_index_iter() {
-        let db = TemporaryDB::new();
+    fn test_list_index_iter(db: &dyn Database) {
         let fork = db.fork();
         let mut list_index = fork.get_list(IDX_NAME);
         list_index_iter(&mut list_index);
     }
 
-    #[test]
-    fn test_list_index_in_family_iter() {
-        let db = TemporaryDB::new();
+    fn test_list_index_in_family_iter(db: &dyn Database) {
         let fork = db.fork();
         let mut list_index = fork.get_list((IDX_NAME, &vec![1]));
         list_index_iter(&mut list_index);
@@ -576,18 +614,14 @@ mod tests {
         }
     }
 
-    #[test]
-    fn restore_after_no_op_initialization() {
-        let db = TemporaryDB::new();
+    fn restore_after_no_op_initialization(db: &dyn Database) {
         let fork = db.fork();
         fork.get_list::<_, u32>(IDX_NAME);
         let list: ListIndex<_, u32> = fork.readonly().get_list(IDX_NAME);
         assert!(list.is_empty());
     }
 
-    #[test]
-    fn after_clearing_and_flushing() {
-        let db = TemporaryDB::new();
+    fn after_clearing_and_flushing(db: &dyn Database) {
         let fork = db.fork();
         {
             let mut list = fork.get_list::<_, u32>(IDX_NAME);
@@ -609,4 +643,36 @@ mod tests {
         assert_eq!(list.get(1), None);
         assert_eq!(list.iter().collect::<Vec<_>>(), vec![3]);
     }
+
+    fn reverse_iteration(db: &dyn Database) {
+        let fork = db.fork();
+        fork.get_list(IDX_NAME).extend(vec![1_u32, 2, 3]);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        let mut list = fork.get_list::<_, u32>(IDX_NAME);
+        list.push(4);
+        list.set(1, 20);
+        assert_eq!(list.iter_rev().collect::<Vec<_>>(), vec![4, 3, 20, 1]);
+        assert_eq!(list.iter_rev_from(1).collect::<Vec<_>>(), vec![20, 1]);
+        assert_eq!(
+            list.iter_rev_from(10).collect::<Vec<_>>(),
+            vec![4, 3, 20, 1]
+        );
+
+        list.truncate(2);
+        assert_eq!(list.iter_rev().collect::<Vec<_>>(), vec![20, 1]);
+        list.clear();
+        assert_eq!(list.iter_rev().count(), 0);
+    }
+
+    backend_tests!(
+        test_list_index_methods,
+        test_list_index_in_family_methods,
+        test_list_index_iter,
+        test_list_index_in_family_iter,
+        restore_after_no_op_initialization,
+        after_clearing_and_flushing,
+        reverse_iteration,
+    );
 }
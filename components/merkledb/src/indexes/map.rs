@@ -18,7 +18,7 @@
 //! the [`BinaryValue`] trait. The given section contains methods related to
 //! `MapIndex` and iterators over the items of this map.
 
-use std::{borrow::Borrow, marker::PhantomData};
+use std::{borrow::Borrow, marker::PhantomData, ops::RangeBounds};
 
 use crate::{
     access::{Access, AccessError, FromAccess},
@@ -44,11 +44,14 @@ pub struct MapIndex<T: RawAccess, K: ?Sized, V> {
 
 /// Returns an iterator over the entries of a `MapIndex`.
 ///
-/// This struct is created by the [`iter`] or
-/// [`iter_from`] method on [`MapIndex`]. See its documentation for additional details.
+/// This struct is created by the [`iter`], [`iter_from`], [`iter_rev`], [`iter_rev_from`]
+/// or [`range`] method on [`MapIndex`]. See its documentation for additional details.
 ///
 /// [`iter`]: struct.MapIndex.html#method.iter
 /// [`iter_from`]: struct.MapIndex.html#method.iter_from
+/// [`iter_rev`]: struct.MapIndex.html#method.iter_rev
+/// [`iter_rev_from`]: struct.MapIndex.html#method.iter_rev_from
+/// [`range`]: struct.MapIndex.html#method.range
 /// [`MapIndex`]: struct.MapIndex.html
 #[derive(Debug)]
 pub struct Iter<'a, K: ?Sized, V> {
@@ -276,6 +279,84 @@ where
             base_iter: self.base.iter_from(&(), from),
         }
     }
+
+    /// Returns an iterator over the entries of a map in descending order. The iterator element
+    /// type is (K, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, MapIndex};
+    ///
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index: MapIndex<_, u8, u8> = fork.get_map("name");
+    /// index.put(&1, 10);
+    /// index.put(&2, 20);
+    ///
+    /// let entries: Vec<_> = index.iter_rev().collect();
+    /// assert_eq!(entries, vec![(2, 20), (1, 10)]);
+    /// ```
+    pub fn iter_rev(&self) -> Iter<'_, K, V> {
+        Iter {
+            base_iter: self.base.iter_rev(&()),
+        }
+    }
+
+    /// Returns an iterator over the entries of a map in descending order starting from the
+    /// specified key (inclusive). The iterator element type is (K, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, MapIndex};
+    ///
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index: MapIndex<_, u8, u8> = fork.get_map("name");
+    /// index.put(&1, 10);
+    /// index.put(&2, 20);
+    /// index.put(&3, 30);
+    ///
+    /// let keys: Vec<_> = index.iter_rev_from(&2).map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![2, 1]);
+    /// ```
+    pub fn iter_rev_from(&self, from: &K) -> Iter<'_, K, V> {
+        Iter {
+            base_iter: self.base.iter_rev_from(&(), from),
+        }
+    }
+
+    /// Returns an iterator over the entries of a map in ascending order with keys
+    /// within the specified range. The iterator element type is (K, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, MapIndex};
+    ///
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index: MapIndex<_, u8, u8> = fork.get_map("name");
+    /// for i in 0..10 {
+    ///     index.put(&i, i * 10);
+    /// }
+    ///
+    /// let keys: Vec<_> = index.range(3..6).map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![3, 4, 5]);
+    /// let keys: Vec<_> = index.range(..=1).map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![0, 1]);
+    /// ```
+    pub fn range<R>(&self, range: R) -> Iter<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        Iter {
+            base_iter: self
+                .base
+                .iter_range(&(), range.start_bound(), range.end_bound()),
+        }
+    }
 }
 
 impl<T, K, V> MapIndex<T, K, V>
@@ -327,6 +408,35 @@ where
         self.base.remove(key);
     }
 
+    /// Removes all entries with keys within the specified range.
+    ///
+    /// The removed entries are not loaded into memory; the range is removed
+    /// as a whole when the changes are merged into the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, MapIndex};
+    ///
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index: MapIndex<_, u8, u8> = fork.get_map("name");
+    /// for i in 0..10 {
+    ///     index.put(&i, i * 10);
+    /// }
+    ///
+    /// index.remove_range(3..8);
+    /// let keys: Vec<_> = index.keys().collect();
+    /// assert_eq!(keys, vec![0, 1, 2, 8, 9]);
+    /// ```
+    pub fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<K>,
+    {
+        self.base
+            .remove_range(range.start_bound(), range.end_bound());
+    }
+
     /// Clears a map, removing all entries.
     ///
     /// # Notes
@@ -404,14 +514,14 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{access::AccessExt, Database, TemporaryDB};
+    use std::ops::Bound;
+
+    use crate::{access::AccessExt, Database};
 
     const IDX_NAME: &str = "idx_name";
 
-    #[test]
-    fn test_str_key() {
+    fn test_str_key(db: &dyn Database) {
         const KEY: &str = "key_1";
-        let db = TemporaryDB::default();
         let fork = db.fork();
 
         let mut index = fork.get_map(IDX_NAME);
@@ -422,10 +532,8 @@ mod tests {
         assert_eq!(false, index.contains(KEY));
     }
 
-    #[test]
-    fn test_u8_slice_key() {
+    fn test_u8_slice_key(db: &dyn Database) {
         const KEY: &[u8] = &[1, 2, 3];
-        let db = TemporaryDB::default();
         let fork = db.fork();
 
         let mut index = fork.get_map(IDX_NAME);
@@ -438,9 +546,7 @@ mod tests {
         assert_eq!(false, index.contains(KEY));
     }
 
-    #[test]
-    fn test_methods() {
-        let db = TemporaryDB::default();
+    fn test_methods(db: &dyn Database) {
         let fork = db.fork();
 
         let mut map_index = fork.get_map(IDX_NAME);
@@ -463,9 +569,7 @@ mod tests {
         assert!(!map_index.contains(&3_u8));
     }
 
-    #[test]
-    fn test_iter() {
-        let db = TemporaryDB::default();
+    fn test_iter(db: &dyn Database) {
         let fork = db.fork();
         let mut map_index = fork.get_map(IDX_NAME);
 
@@ -531,9 +635,7 @@ mod tests {
         );
     }
 
-    #[test]
-    fn index_as_iterator() {
-        let db = TemporaryDB::default();
+    fn index_as_iterator(db: &dyn Database) {
         let fork = db.fork();
         let mut map_index = fork.get_map(IDX_NAME);
 
@@ -569,4 +671,109 @@ mod tests {
             ]
         );
     }
+
+    fn reverse_and_bounded_iteration(db: &dyn Database) {
+        let fork = db.fork();
+        {
+            let mut map_index = fork.get_map(IDX_NAME);
+            for i in 0_u8..5 {
+                map_index.put(&i, i);
+            }
+            // Index in the same family to check that iteration does not escape the index.
+            let mut other_index = fork.get_map((IDX_NAME, &1_u8));
+            other_index.put(&10_u8, 10_u8);
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        let mut map_index = fork.get_map::<_, u8, u8>(IDX_NAME);
+        map_index.remove(&1);
+        map_index.put(&5, 5);
+
+        assert_eq!(
+            map_index.iter_rev().collect::<Vec<_>>(),
+            vec![(5, 5), (4, 4), (3, 3), (2, 2), (0, 0)]
+        );
+        assert_eq!(
+            map_index.iter_rev_from(&3).collect::<Vec<_>>(),
+            vec![(3, 3), (2, 2), (0, 0)]
+        );
+        assert_eq!(
+            map_index.iter_rev_from(&1).collect::<Vec<_>>(),
+            vec![(0, 0)]
+        );
+        assert_eq!(map_index.iter_rev_from(&10).count(), 5);
+
+        fn keys(iter: super::Iter<'_, u8, u8>) -> Vec<u8> {
+            iter.map(|(k, _)| k).collect()
+        }
+
+        assert_eq!(keys(map_index.range(..)), vec![0, 2, 3, 4, 5]);
+        assert_eq!(keys(map_index.range(1..4)), vec![2, 3]);
+        assert_eq!(keys(map_index.range(2..=4)), vec![2, 3, 4]);
+        assert_eq!(keys(map_index.range(..3)), vec![0, 2]);
+        assert_eq!(keys(map_index.range(4..)), vec![4, 5]);
+        assert_eq!(keys(map_index.range(6..)), Vec::<u8>::new());
+
+        let excluded_start = (Bound::Excluded(2), Bound::Unbounded);
+        assert_eq!(keys(map_index.range(excluded_start)), vec![3, 4, 5]);
+    }
+
+    fn remove_range(db: &dyn Database) {
+        let fork = db.fork();
+        {
+            let mut map_index = fork.get_map(IDX_NAME);
+            for i in 0_u8..10 {
+                map_index.put(&i, i);
+            }
+            let mut other_index = fork.get_map((IDX_NAME, &1_u8));
+            other_index.put(&0_u8, 0_u8);
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        {
+            let mut map_index = fork.get_map::<_, u8, u8>(IDX_NAME);
+            map_index.remove_range(2..5);
+            assert_eq!(map_index.get(&2), None);
+            assert!(!map_index.contains(&4));
+            map_index.put(&3, 33);
+            map_index.remove_range(7..);
+            assert_eq!(
+                map_index.iter().collect::<Vec<_>>(),
+                vec![(0, 0), (1, 1), (3, 33), (5, 5), (6, 6)]
+            );
+            assert_eq!(
+                map_index.iter_rev().map(|(k, _)| k).collect::<Vec<_>>(),
+                vec![6, 5, 3, 1, 0]
+            );
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map_index = snapshot.get_map::<_, u8, u8>(IDX_NAME);
+        assert_eq!(map_index.keys().collect::<Vec<_>>(), vec![0, 1, 3, 5, 6]);
+        let other_index = snapshot.get_map::<_, u8, u8>((IDX_NAME, &1_u8));
+        assert_eq!(other_index.get(&0), Some(0));
+
+        let fork = db.fork();
+        fork.get_map::<_, u8, u8>(IDX_NAME).remove_range(..);
+        db.merge(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get_map::<_, u8, u8>(IDX_NAME).iter().count(), 0);
+        assert_eq!(
+            snapshot.get_map::<_, u8, u8>((IDX_NAME, &1_u8)).get(&0),
+            Some(0)
+        );
+    }
+
+    backend_tests!(
+        test_str_key,
+        test_u8_slice_key,
+        test_methods,
+        test_iter,
+        index_as_iterator,
+        reverse_and_bounded_iteration,
+        remove_range,
+    );
 }
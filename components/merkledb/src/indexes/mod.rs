@@ -17,12 +17,14 @@
 pub use self::{entry::Entry, group::Group, proof_entry::ProofEntry};
 
 mod entry;
-mod group;
 mod proof_entry;
 
+pub mod group;
+pub mod indexed_map;
 pub mod key_set;
 pub mod list;
 pub mod map;
+pub mod proof_key_set;
 pub mod proof_list;
 pub mod proof_map;
 pub mod sparse_list;
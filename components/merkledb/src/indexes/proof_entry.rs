@@ -232,12 +232,10 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{access::AccessExt, Database, TemporaryDB};
+    use crate::{access::AccessExt, Database};
     use std::borrow::Cow;
 
-    #[test]
-    fn basics() {
-        let db = TemporaryDB::new();
+    fn basics(db: &dyn Database) {
         let fork = db.fork();
         {
             let mut entry = fork.get_proof_entry("test");
@@ -255,8 +253,7 @@ mod tests {
         assert_eq!(entry.object_hash(), 42_u64.object_hash());
     }
 
-    #[test]
-    fn entry_with_custom_hashing() {
+    fn entry_with_custom_hashing(db: &dyn Database) {
         #[derive(Debug, PartialEq)]
         struct CustomHash(u8);
 
@@ -276,7 +273,6 @@ mod tests {
             }
         }
 
-        let db = TemporaryDB::new();
         let fork = db.fork();
         {
             let mut entry = fork.get_proof_entry("test");
@@ -293,4 +289,6 @@ mod tests {
             Hash::new([11; exonum_crypto::HASH_SIZE])
         );
     }
+
+    backend_tests!(basics, entry_with_custom_hashing,);
 }
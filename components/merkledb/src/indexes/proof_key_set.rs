@@ -0,0 +1,431 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a Merkelized set of keys.
+//!
+//! `ProofKeySetIndex` implements a set backed by a Merkle Patricia tree. The set
+//! can provide proofs of membership and non-membership for its elements.
+
+use exonum_crypto::Hash;
+
+use std::fmt;
+
+use super::proof_map::{Hashed, Keys, MapProof, ProofMapIndex, ToProofPath};
+use crate::{
+    access::{Access, AccessError, FromAccess},
+    views::{IndexAddress, IndexType, RawAccess, RawAccessMut},
+    BinaryKey, ObjectHash,
+};
+
+/// A Merkelized set of keys that provides proofs of membership and non-membership
+/// for the set elements.
+///
+/// `ProofKeySetIndex` requires that elements implement the [`BinaryKey`] trait.
+/// Internally, the set is stored as a Merkle Patricia tree with empty values, so its
+/// object hash coincides with the hash of a [`ProofMapIndex`] mapping the set elements
+/// to `()`. Correspondingly, proofs for the set are expressed as [`MapProof`]s
+/// with `()` values: elements present in the set are returned as proof entries,
+/// and absent elements are returned as missing keys.
+///
+/// [`BinaryKey`]: ../../trait.BinaryKey.html
+/// [`ProofMapIndex`]: ../proof_map/struct.ProofMapIndex.html
+/// [`MapProof`]: ../proof_map/struct.MapProof.html
+pub struct ProofKeySetIndex<T: RawAccess, K: ?Sized, KeyMode: ToProofPath<K> = Hashed> {
+    inner: ProofMapIndex<T, K, (), KeyMode>,
+}
+
+/// An iterator over the elements of a `ProofKeySetIndex`.
+///
+/// This struct is created by the [`iter`] or
+/// [`iter_from`] method on [`ProofKeySetIndex`]. See its documentation for details.
+///
+/// [`iter`]: struct.ProofKeySetIndex.html#method.iter
+/// [`iter_from`]: struct.ProofKeySetIndex.html#method.iter_from
+/// [`ProofKeySetIndex`]: struct.ProofKeySetIndex.html
+#[derive(Debug)]
+pub struct Iter<'a, K: ?Sized> {
+    base_iter: Keys<'a, K>,
+}
+
+impl<T, K, KeyMode> FromAccess<T> for ProofKeySetIndex<T::Base, K, KeyMode>
+where
+    T: Access,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    fn from_access(access: T, addr: IndexAddress) -> Result<Self, AccessError> {
+        let view = access.get_or_create_view(addr, IndexType::ProofKeySet)?;
+        Ok(Self {
+            inner: ProofMapIndex::new(view),
+        })
+    }
+}
+
+impl<T, K, KeyMode> ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    /// Returns `true` if the set contains the indicated element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofKeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    /// assert!(!index.contains(&1_u64));
+    ///
+    /// index.insert(&1_u64);
+    /// assert!(index.contains(&1_u64));
+    /// ```
+    pub fn contains(&self, item: &K) -> bool {
+        self.inner.contains(item)
+    }
+
+    /// Returns the proof of membership or non-membership for the specified element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{
+    ///     access::AccessExt, ObjectHash, TemporaryDB, Database, ProofKeySetIndex,
+    /// };
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set::<_, str>("name");
+    /// index.insert("foo");
+    ///
+    /// let proof = index.get_proof("foo".to_owned());
+    /// let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+    /// assert_eq!(checked_proof.entries().count(), 1);
+    ///
+    /// let proof = index.get_proof("bar".to_owned());
+    /// let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+    /// assert_eq!(checked_proof.missing_keys().collect::<Vec<_>>(), vec!["bar"]);
+    /// ```
+    pub fn get_proof(&self, item: K::Owned) -> MapProof<K::Owned, (), KeyMode> {
+        self.inner.get_proof(item)
+    }
+
+    /// Returns the combined proof of membership or non-membership for the multiple
+    /// specified elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{
+    ///     access::AccessExt, ObjectHash, TemporaryDB, Database, ProofKeySetIndex,
+    /// };
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set::<_, u64>("name");
+    /// index.insert(&1);
+    /// index.insert(&2);
+    ///
+    /// let proof = index.get_multiproof(vec![1, 3]);
+    /// let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+    /// assert_eq!(checked_proof.entries().map(|(k, _)| *k).collect::<Vec<_>>(), vec![1]);
+    /// assert_eq!(checked_proof.missing_keys().cloned().collect::<Vec<_>>(), vec![3]);
+    /// ```
+    pub fn get_multiproof<KI>(&self, items: KI) -> MapProof<K::Owned, (), KeyMode>
+    where
+        KI: IntoIterator<Item = K::Owned>,
+    {
+        self.inner.get_multiproof(items)
+    }
+
+    /// Returns an iterator visiting all elements in ascending order of their binary
+    /// representation. The iterator element type is `K::Owned`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofKeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let index = fork.get_proof_key_set::<_, u64>("name");
+    ///
+    /// for val in index.iter() {
+    ///     println!("{}", val);
+    /// }
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter {
+            base_iter: self.inner.keys(),
+        }
+    }
+
+    /// Returns an iterator visiting all elements in ascending order starting from
+    /// the specified value. The iterator element type is `K::Owned`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofKeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let index = fork.get_proof_key_set::<_, u64>("name");
+    ///
+    /// for val in index.iter_from(&2) {
+    ///     println!("{}", val);
+    /// }
+    /// ```
+    pub fn iter_from(&self, from: &K) -> Iter<'_, K> {
+        Iter {
+            base_iter: self.inner.keys_from(from),
+        }
+    }
+}
+
+impl<T, K, KeyMode> ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccessMut,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    /// Adds an element to the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofKeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    ///
+    /// index.insert(&1_u64);
+    /// assert!(index.contains(&1_u64));
+    /// ```
+    pub fn insert(&mut self, item: &K) {
+        self.inner.put(item, ())
+    }
+
+    /// Removes an element from the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofKeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    ///
+    /// index.insert(&1_u64);
+    /// index.remove(&1_u64);
+    /// assert!(!index.contains(&1_u64));
+    /// ```
+    pub fn remove(&mut self, item: &K) {
+        self.inner.remove(item)
+    }
+
+    /// Clears the set, removing all elements.
+    ///
+    /// # Notes
+    ///
+    /// Currently, this method is not optimized to delete a large set of data. During the execution of
+    /// this method, the amount of allocated memory is linearly dependent on the number of elements
+    /// in the index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofKeySetIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    ///
+    /// index.insert(&1_u64);
+    /// index.clear();
+    /// assert!(!index.contains(&1_u64));
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+}
+
+/// `object_hash` is computed as the root hash of the underlying Merkle Patricia tree
+/// with the `map_node` hash tag, exactly as for a `ProofMapIndex` with `()` values.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{
+///     access::AccessExt, HashTag, ObjectHash, TemporaryDB, Database, ProofKeySetIndex,
+/// };
+///
+/// let db = TemporaryDB::new();
+/// let fork = db.fork();
+/// let mut index = fork.get_proof_key_set::<_, u64>("name");
+/// assert_eq!(index.object_hash(), HashTag::empty_map_hash());
+///
+/// index.insert(&1);
+/// assert_ne!(index.object_hash(), HashTag::empty_map_hash());
+/// ```
+impl<T, K, KeyMode> ObjectHash for ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    fn object_hash(&self) -> Hash {
+        self.inner.object_hash()
+    }
+}
+
+impl<'a, T, K, KeyMode> IntoIterator for &'a ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    type Item = K::Owned;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K> Iterator for Iter<'a, K>
+where
+    K: BinaryKey + ?Sized,
+{
+    type Item = K::Owned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base_iter.next()
+    }
+}
+
+impl<T, K, KeyMode> fmt::Debug for ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    K::Owned: fmt::Debug,
+    KeyMode: ToProofPath<K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProofKeySetIndex")
+            .field("object_hash", &self.object_hash())
+            .field("items", &self.iter().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_crypto::Hash;
+
+    use super::*;
+    use crate::{
+        access::AccessExt, indexes::proof_map::Raw, Database, HashTag, ObjectHash, SystemSchema,
+    };
+
+    const INDEX_NAME: &str = "test_index_name";
+
+    fn basic_operations(db: &dyn Database) {
+        let fork = db.fork();
+        let mut set = fork.get_proof_key_set::<_, u64>(INDEX_NAME);
+        assert_eq!(set.object_hash(), HashTag::empty_map_hash());
+
+        set.insert(&3);
+        set.insert(&1);
+        set.insert(&2);
+        set.insert(&1);
+        assert!(set.contains(&1));
+        assert!(!set.contains(&4));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(set.iter_from(&2).collect::<Vec<_>>(), vec![2, 3]);
+
+        set.remove(&2);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![1, 3]);
+
+        // The set hash coincides with the hash of a map with empty values.
+        let mut map = fork.get_proof_map::<_, u64, ()>("map");
+        map.put(&1, ());
+        map.put(&3, ());
+        assert_eq!(set.object_hash(), map.object_hash());
+
+        set.clear();
+        assert_eq!(set.iter().count(), 0);
+        assert_eq!(set.object_hash(), HashTag::empty_map_hash());
+    }
+
+    fn proofs(db: &dyn Database) {
+        let fork = db.fork();
+        let mut set = fork.get_proof_key_set::<_, str>(INDEX_NAME);
+        for item in &["foo", "bar", "baz"] {
+            set.insert(item);
+        }
+        let set_hash = set.object_hash();
+
+        let proof = set.get_proof("bar".to_owned());
+        let checked_proof = proof.check_against_hash(set_hash).unwrap();
+        assert_eq!(
+            checked_proof.entries().collect::<Vec<_>>(),
+            vec![(&"bar".to_owned(), &())]
+        );
+        assert_eq!(checked_proof.missing_keys().count(), 0);
+
+        let proof = set.get_multiproof(vec!["foo".to_owned(), "qux".to_owned()]);
+        let checked_proof = proof.check_against_hash(set_hash).unwrap();
+        assert_eq!(
+            checked_proof.entries().map(|(k, _)| k).collect::<Vec<_>>(),
+            vec!["foo"]
+        );
+        assert_eq!(
+            checked_proof.missing_keys().collect::<Vec<_>>(),
+            vec!["qux"]
+        );
+
+        // Proofs are invalid against a different hash.
+        let proof = set.get_proof("qux".to_owned());
+        assert!(proof.check_against_hash(Hash::zero()).is_err());
+    }
+
+    fn raw_keys(db: &dyn Database) {
+        let fork = db.fork();
+        let mut set: ProofKeySetIndex<_, Hash, Raw> = fork.get_generic_proof_key_set(INDEX_NAME);
+        let item = Hash::zero();
+        set.insert(&item);
+
+        let proof = set.get_proof(item);
+        let checked_proof = proof.check_against_hash(set.object_hash()).unwrap();
+        assert_eq!(checked_proof.entries().count(), 1);
+    }
+
+    fn state_aggregation(db: &dyn Database) {
+        let fork = db.fork();
+        fork.get_proof_key_set::<_, u64>(INDEX_NAME).insert(&42);
+        let patch = fork.into_patch();
+
+        let set = patch.get_proof_key_set::<_, u64>(INDEX_NAME);
+        let aggregator = SystemSchema::new(&patch).state_aggregator();
+        assert_eq!(aggregator.get(INDEX_NAME), Some(set.object_hash()));
+        assert_eq!(patch.index_type(INDEX_NAME), Some(IndexType::ProofKeySet));
+    }
+
+    backend_tests!(basic_operations, proofs, raw_keys, state_aggregation,);
+}
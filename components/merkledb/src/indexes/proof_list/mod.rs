@@ -14,11 +14,20 @@
 
 //! An implementation of a Merkelized version of an array list (Merkle tree).
 
-pub use self::proof::{CheckedListProof, ListProof, ListProofError, ValidationError};
+pub use self::proof::{
+    CheckedListConsistencyProof, CheckedListProof, ListConsistencyProof, ListProof, ListProofError,
+    ValidationError,
+};
 
 use exonum_crypto::Hash;
 
-use std::{cmp, iter, marker::PhantomData, ops::RangeBounds};
+use std::{
+    cmp,
+    convert::TryInto,
+    iter,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
 use self::{
     key::{ProofListKey, MAX_INDEX},
@@ -28,6 +37,7 @@ use self::{
 use crate::{
     access::{Access, AccessError, FromAccess},
     hash::HashTag,
+    integrity::InconsistencyKind,
     views::{
         IndexState, IndexType, Iter as ViewIter, RawAccess, RawAccessMut, View, ViewWithMetadata,
     },
@@ -63,11 +73,13 @@ pub struct ProofListIndex<T: RawAccess, V> {
 
 /// An iterator over the items of a `ProofListIndex`.
 ///
-/// This struct is created by the [`iter`] or
-/// [`iter_from`] method on [`ProofListIndex`]. See its documentation for details.
+/// This struct is created by the [`iter`], [`iter_from`], [`iter_rev`] or
+/// [`iter_rev_from`] method on [`ProofListIndex`]. See its documentation for details.
 ///
 /// [`iter`]: struct.ProofListIndex.html#method.iter
 /// [`iter_from`]: struct.ProofListIndex.html#method.iter_from
+/// [`iter_rev`]: struct.ProofListIndex.html#method.iter_rev
+/// [`iter_rev_from`]: struct.ProofListIndex.html#method.iter_rev_from
 /// [`ProofListIndex`]: struct.ProofListIndex.html
 #[derive(Debug)]
 pub struct Iter<'a, V> {
@@ -142,6 +154,156 @@ where
         ProofListKey::new(self.height(), 0)
     }
 
+    /// Returns positions of the elements differing between this list and `other`
+    /// in the ascending order. Positions present in only one of the lists are considered
+    /// differing as well.
+    ///
+    /// Subtrees with equal hashes in both lists are skipped, so the method is logarithmic
+    /// w.r.t. list lengths for each differing element.
+    pub(crate) fn changed_positions<U, W>(&self, other: &ProofListIndex<U, W>) -> Vec<u64>
+    where
+        U: RawAccess,
+        W: BinaryValue,
+    {
+        fn branch<T: RawAccess, V: BinaryValue>(
+            list: &ProofListIndex<T, V>,
+            key: ProofListKey,
+        ) -> Option<Hash> {
+            if key.height() <= list.height() {
+                list.get_branch(key)
+            } else {
+                None
+            }
+        }
+
+        let height = cmp::max(self.height(), other.height());
+        let mut positions = vec![];
+        if height == 0 {
+            return positions;
+        }
+
+        let mut stack = vec![ProofListKey::new(height, 0)];
+        while let Some(key) = stack.pop() {
+            if branch(self, key) == branch(other, key) {
+                continue;
+            }
+            if key.height() == 1 {
+                positions.push(key.index());
+            } else {
+                let left_child = ProofListKey::new(key.height() - 1, key.index() << 1);
+                // The right child is pushed first, so that the left child is processed first.
+                stack.push(left_child.as_right());
+                stack.push(left_child);
+            }
+        }
+        positions
+    }
+
+    /// Checks that the Merkle tree of the list is consistent with the list values, pushing
+    /// found inconsistencies into `report`.
+    ///
+    /// Each tree node is compared with the hash computed from its stored children, so
+    /// a corrupted node is reported exactly once. Returns the object hash of the list,
+    /// or `None` if the root of the tree cannot be read.
+    pub(crate) fn check_integrity(&self, report: &mut Vec<InconsistencyKind>) -> Option<Hash> {
+        let len = self.len();
+        let height = self.height();
+
+        // The first level of the tree consists of the value hashes.
+        for index in 0..len {
+            let value_key = ProofListKey::leaf(index);
+            match self.base.get::<_, Vec<u8>>(&value_key) {
+                Some(value) => {
+                    let key = ProofListKey::new(1, index);
+                    self.check_node(key, HashTag::hash_leaf(&value), report);
+                }
+                None => report.push(InconsistencyKind::MissingEntry {
+                    key: value_key.as_db_key().to_be_bytes().to_vec(),
+                }),
+            }
+        }
+
+        let mut children_count = len;
+        for height in 2..=height {
+            let nodes_count = (children_count + 1) / 2;
+            for index in 0..nodes_count {
+                let left_key = ProofListKey::new(height - 1, index << 1);
+                let left_hash = self.stored_node(left_key);
+                let hash = if left_key.index() + 1 < children_count {
+                    let right_hash = self.stored_node(left_key.as_right());
+                    left_hash
+                        .and_then(|left| right_hash.map(|right| HashTag::hash_node(&left, &right)))
+                } else {
+                    left_hash.as_ref().map(HashTag::hash_single_node)
+                };
+                // If a child is missing or malformed, it is already reported.
+                if let Some(hash) = hash {
+                    self.check_node(ProofListKey::new(height, index), hash, report);
+                }
+            }
+            children_count = nodes_count;
+        }
+
+        let unexpected_entries = self
+            .base
+            .iter::<_, [u8], ()>(&())
+            .filter(|(key, ())| {
+                let key = match key.as_slice().try_into() {
+                    Ok(key) => u64::from_be_bytes(key),
+                    Err(_) => return true,
+                };
+                // `ProofListKey` is not constructed directly since it asserts that
+                // the height is valid.
+                let (key_height, index) = (key >> 56, key & MAX_INDEX);
+                let level_len = match key_height {
+                    0 | 1 => len,
+                    h if h <= u64::from(height) => {
+                        let shift = h - 1;
+                        (len + (1 << shift) - 1) >> shift
+                    }
+                    _ => return true,
+                };
+                index >= level_len
+            })
+            .count();
+        if unexpected_entries > 0 {
+            report.push(InconsistencyKind::UnexpectedEntries {
+                count: unexpected_entries as u64,
+            });
+        }
+
+        let merkle_root = if len == 0 {
+            Hash::zero()
+        } else {
+            self.stored_node(self.root_key())?
+        };
+        Some(HashTag::hash_list_node(len, merkle_root))
+    }
+
+    /// Reads a tree node without panicking if the node is malformed.
+    fn stored_node(&self, key: ProofListKey) -> Option<Hash> {
+        self.base
+            .get::<_, Vec<u8>>(&key)
+            .and_then(|bytes| Hash::from_slice(&bytes))
+    }
+
+    fn check_node(&self, key: ProofListKey, hash: Hash, report: &mut Vec<InconsistencyKind>) {
+        let raw_key = key.as_db_key().to_be_bytes().to_vec();
+        let kind = match self.base.get::<_, Vec<u8>>(&key) {
+            None => InconsistencyKind::MissingEntry { key: raw_key },
+            Some(bytes) => match Hash::from_slice(&bytes) {
+                None => InconsistencyKind::InvalidEntry { key: raw_key },
+                Some(stored) if stored != hash => InconsistencyKind::HashMismatch {
+                    key: raw_key,
+                    stored,
+                    computed: hash,
+                },
+                Some(_) => return,
+            },
+        };
+        report.push(kind);
+    }
+
     /// Returns the element at the indicated position or `None` if the indicated position
     /// is out of bounds.
     ///
@@ -298,6 +460,75 @@ where
         self.create_range_proof(range)
     }
 
+    /// Returns the proof of existence for the list elements at the specified indexes.
+    /// Unlike separate proofs for each element, the returned proof shares the hashes
+    /// necessary to restore the Merkle tree root among the elements, which makes it
+    /// more compact.
+    ///
+    /// Indexes may be specified in any order and may repeat. Indexes exceeding the list
+    /// length are ignored; if there are no indexes within the list, the proof does not
+    /// contain any elements (i.e., it is a proof of absence).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ObjectHash};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_list("name");
+    /// index.extend(0_u64..1_000);
+    ///
+    /// let proof = index.get_multiproof(vec![500, 3, 100, 3]);
+    /// let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+    /// assert_eq!(
+    ///     checked_proof.entries(),
+    ///     &[(3, 3), (100, 100), (500, 500)][..]
+    /// );
+    /// // This proof does not contain any elements at all.
+    /// let empty_proof = index.get_multiproof(vec![1_000, 5_000]);
+    /// assert!(empty_proof.entries_unchecked().is_empty());
+    /// ```
+    pub fn get_multiproof<I>(&self, indexes: I) -> ListProof<V>
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        self.create_multiproof(indexes)
+    }
+
+    /// Returns the proof that the list with the specified `old_length` is a prefix
+    /// of the current list, i.e., that the current list was obtained from the older one
+    /// by appending elements.
+    ///
+    /// The proof only makes sense if the list has not been modified otherwise
+    /// (e.g., with `set` or `truncate`) since it had `old_length`; if it has been,
+    /// the proof will not match the older list hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `old_length` exceeds the current list length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ObjectHash};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_list("name");
+    /// index.extend(vec![1, 2, 3]);
+    /// let old_hash = index.object_hash();
+    /// index.extend(vec![4, 5, 6, 7]);
+    ///
+    /// let proof = index.get_consistency_proof(3);
+    /// let checked_proof = proof.check().unwrap();
+    /// assert_eq!(checked_proof.old_hash(), old_hash);
+    /// assert_eq!(checked_proof.new_hash(), index.object_hash());
+    /// ```
+    pub fn get_consistency_proof(&self, old_length: u64) -> ListConsistencyProof {
+        self.create_consistency_proof(old_length)
+    }
+
     /// Returns an iterator over the list. The iterator element type is V.
     ///
     /// # Examples
@@ -340,6 +571,47 @@ where
             base_iter: self.base.iter_from(&0_u8, &ProofListKey::leaf(from)),
         }
     }
+
+    /// Returns an iterator over the list in reverse order. The iterator element type is V.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofListIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_list("name");
+    /// index.extend(vec![1_u8, 2, 3]);
+    ///
+    /// assert_eq!(index.iter_rev().collect::<Vec<_>>(), vec![3, 2, 1]);
+    /// ```
+    pub fn iter_rev(&self) -> Iter<'_, V> {
+        Iter {
+            base_iter: self.base.iter_rev(&0_u8),
+        }
+    }
+
+    /// Returns an iterator over the list in reverse order starting from the specified
+    /// position (inclusive). The iterator element type is V.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofListIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_list("name");
+    /// index.extend(vec![1_u8, 2, 3]);
+    ///
+    /// assert_eq!(index.iter_rev_from(1).collect::<Vec<_>>(), vec![2, 1]);
+    /// ```
+    pub fn iter_rev_from(&self, from: u64) -> Iter<'_, V> {
+        Iter {
+            base_iter: self.base.iter_rev_from(&0_u8, &ProofListKey::leaf(from)),
+        }
+    }
 }
 
 impl<T, V> ProofListIndex<T, V>
@@ -435,8 +707,12 @@ where
 
         for height in 1..old_height {
             // Remove excessive branches on the level.
-            for index in last_index.map_or(0, |i| i + 1)..=old_last_index {
-                self.base.remove(&ProofListKey::new(height, index));
+            let first_removed_index = last_index.map_or(0, |i| i + 1);
+            if first_removed_index <= old_last_index {
+                self.base.remove_range(
+                    Bound::Included(&ProofListKey::new(height, first_removed_index)),
+                    Bound::Included(&ProofListKey::new(height, old_last_index)),
+                );
             }
 
             // Recalculate the hash of the last element on the next level if it has changed.
@@ -593,9 +869,10 @@ where
         self.set_len(new_length);
 
         // Remove values.
-        for index in new_length..=old_last_index {
-            self.base.remove(&ProofListKey::leaf(index));
-        }
+        self.base.remove_range(
+            Bound::Included(&ProofListKey::leaf(new_length)),
+            Bound::Included(&ProofListKey::leaf(old_last_index)),
+        );
 
         self.remove_range(old_last_index, old_height);
     }
@@ -755,7 +1032,7 @@ mod proto {
 
     use std::borrow::Cow;
 
-    use super::{HashedEntry, ListProof, ProofListKey};
+    use super::{HashedEntry, ListConsistencyProof, ListProof, ProofListKey};
     pub use crate::proto::{self, *};
     use crate::{indexes::proof_list::MAX_INDEX, BinaryValue};
 
@@ -835,4 +1112,48 @@ mod proto {
             Ok(ListProof::from_raw_parts(proof, entries, pb.get_length()))
         }
     }
+
+    impl ProtobufConvert for ListConsistencyProof {
+        type ProtoStruct = proto::ListConsistencyProof;
+
+        fn to_pb(&self) -> Self::ProtoStruct {
+            let mut proof = proto::ListConsistencyProof::new();
+            proof.set_old_length(self.old_length_unchecked());
+            proof.set_new_length(self.new_length_unchecked());
+
+            let old_hashes = self
+                .old_hashes_unchecked()
+                .iter()
+                .map(HashedEntry::to_pb)
+                .collect();
+            let new_hashes = self
+                .new_hashes_unchecked()
+                .iter()
+                .map(HashedEntry::to_pb)
+                .collect();
+            proof.set_old_hashes(RepeatedField::from_vec(old_hashes));
+            proof.set_new_hashes(RepeatedField::from_vec(new_hashes));
+            proof
+        }
+
+        fn from_pb(mut pb: Self::ProtoStruct) -> Result<Self, Error> {
+            let old_hashes = pb
+                .take_old_hashes()
+                .into_iter()
+                .map(HashedEntry::from_pb)
+                .collect::<Result<_, Error>>()?;
+            let new_hashes = pb
+                .take_new_hashes()
+                .into_iter()
+                .map(HashedEntry::from_pb)
+                .collect::<Result<_, Error>>()?;
+
+            Ok(ListConsistencyProof::new(
+                pb.get_old_length(),
+                pb.get_new_length(),
+                old_hashes,
+                new_hashes,
+            ))
+        }
+    }
 }
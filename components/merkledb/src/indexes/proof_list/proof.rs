@@ -13,15 +13,17 @@
 // limitations under the License.
 
 pub use crate::ValidationError; // TODO Change for a type alias after EJB switching to rust > 1.36
+pub use exonum_proofs::ListProofError;
 
 use exonum_crypto::Hash;
-use failure::Fail;
+use exonum_proofs::internal::{restore_consistent_roots, restore_list_root};
 use serde_derive::*;
 
-use std::cmp::Ordering;
-
 use super::{key::ProofListKey, tree_height_by_length};
-use crate::{BinaryValue, HashTag};
+use crate::{
+    hash::{from_proofs_hash, to_proofs_hash},
+    BinaryValue, HashTag,
+};
 
 #[cfg(feature = "with-protobuf")]
 use crate::{proto, ProtobufConvert};
@@ -42,6 +44,11 @@ impl HashedEntry {
     pub fn new(key: ProofListKey, hash: Hash) -> Self {
         Self { key, hash }
     }
+
+    /// Converts the entry into the format used by `exonum-proofs`.
+    fn to_proofs_entry(&self) -> (ProofListKey, exonum_proofs::Hash) {
+        (self.key, to_proofs_hash(&self.hash))
+    }
 }
 
 /// View of a `ProofListIndex`, i.e., a subset of its elements coupled with a *proof*,
@@ -51,10 +58,11 @@ impl HashedEntry {
 ///
 /// # Workflow
 ///
-/// You can create `ListProof`s with [`get_proof()`] and [`get_range_proof()`] methods of
-/// `ProofListIndex`. Proofs can be verified on the server side with the help of
-/// [`check()`]. Prior to the `check` conversion, you may use `*unchecked` methods
-/// to obtain information about the proof.
+/// You can create `ListProof`s with [`get_proof()`], [`get_range_proof()`]
+/// and [`get_multiproof()`] methods of `ProofListIndex`. The latter method proves
+/// several (not necessarily contiguous) elements with a single proof. Proofs can be verified
+/// on the server side with the help of [`check()`]. Prior to the `check` conversion,
+/// you may use `*unchecked` methods to obtain information about the proof.
 ///
 /// ```
 /// # use exonum_merkledb::{
@@ -128,6 +136,7 @@ impl HashedEntry {
 ///
 /// [`get_proof()`]: struct.ProofListIndex.html#method.get_proof
 /// [`get_range_proof()`]: struct.ProofListIndex.html#method.get_range_proof
+/// [`get_multiproof()`]: struct.ProofListIndex.html#method.get_multiproof
 /// [`check()`]: #method.check
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ListProof<V> {
@@ -136,123 +145,6 @@ pub struct ListProof<V> {
     length: u64,
 }
 
-/// Merges two iterators with `HashedEntry`s so that the elements in the resulting iterator
-/// are ordered by increasing `HashedEntry.key`.
-///
-/// # Arguments
-///
-/// Both inputs need to be ordered by `HashedEntry.key`.
-///
-/// # Return value
-///
-/// Iterator will yield an error if there is an equal `HashedEntry.key` present in both
-/// input iterators.
-fn merge(
-    first: impl Iterator<Item = HashedEntry>,
-    second: impl Iterator<Item = HashedEntry>,
-) -> impl Iterator<Item = Result<HashedEntry, ()>> {
-    struct Merge<T, U> {
-        first: T,
-        second: U,
-        first_item: Option<HashedEntry>,
-        second_item: Option<HashedEntry>,
-    }
-
-    impl<T, U> Merge<T, U>
-    where
-        T: Iterator<Item = HashedEntry>,
-        U: Iterator<Item = HashedEntry>,
-    {
-        fn new(mut first: T, mut second: U) -> Self {
-            let (first_item, second_item) = (first.next(), second.next());
-            Self {
-                first,
-                second,
-                first_item,
-                second_item,
-            }
-        }
-    }
-
-    impl<T, U> Iterator for Merge<T, U>
-    where
-        T: Iterator<Item = HashedEntry>,
-        U: Iterator<Item = HashedEntry>,
-    {
-        type Item = Result<HashedEntry, ()>;
-
-        fn next(&mut self) -> Option<Self::Item> {
-            match (self.first_item, self.second_item) {
-                (Some(x), Some(y)) => match x.key.cmp(&y.key) {
-                    Ordering::Less => {
-                        self.first_item = self.first.next();
-                        Some(Ok(x))
-                    }
-                    Ordering::Greater => {
-                        self.second_item = self.second.next();
-                        Some(Ok(y))
-                    }
-                    Ordering::Equal => Some(Err(())),
-                },
-
-                (Some(x), None) => {
-                    self.first_item = self.first.next();
-                    Some(Ok(x))
-                }
-
-                (None, Some(y)) => {
-                    self.second_item = self.second.next();
-                    Some(Ok(y))
-                }
-
-                (None, None) => None,
-            }
-        }
-    }
-
-    Merge::new(first, second)
-}
-
-/// Takes a subset of hashes at a particular height in the Merkle tree and
-/// computes all known hashes on the next height.
-///
-/// # Arguments
-///
-/// - `last_index` is the index of the last element in the Merkle tree on the given height.
-///
-/// # Return value
-///
-/// The `layer` is modified in place. An error is returned if the layer is malformed (e.g.,
-/// there is insufficient data to hash it).
-///
-/// # Examples
-///
-/// See unit tests at the end of this file.
-fn hash_layer(layer: &mut Vec<HashedEntry>, last_index: u64) -> Result<(), ListProofError> {
-    let new_len = (layer.len() + 1) / 2;
-    for i in 0..new_len {
-        let x = &layer[2 * i];
-        layer[i] = if let Some(y) = layer.get(2 * i + 1) {
-            // To be able to zip two hashes on the layer, they need to be adjacent to each other,
-            // and the first of them needs to have an even index.
-            if !x.key.is_left() || y.key.index() != x.key.index() + 1 {
-                return Err(ListProofError::MissingHash);
-            }
-            HashedEntry::new(x.key.parent(), HashTag::hash_node(&x.hash, &y.hash))
-        } else {
-            // If there is an odd number of hashes on the layer, the solitary hash must have
-            // the greatest possible index.
-            if last_index % 2 == 1 || x.key.index() != last_index {
-                return Err(ListProofError::MissingHash);
-            }
-            HashedEntry::new(x.key.parent(), HashTag::hash_single_node(&x.hash))
-        };
-    }
-
-    layer.truncate(new_len);
-    Ok(())
-}
-
 impl<V: BinaryValue> ListProof<V> {
     pub(super) fn new<I>(values: I, length: u64) -> Self
     where
@@ -302,120 +194,17 @@ impl<V: BinaryValue> ListProof<V> {
 
     /// Restores the root hash of the Merkle tree.
     ///
-    /// The root hash is computed by iterating over each height of the Merkle tree
-    /// and computing hashes on this height based on the information in the proof.
-    /// We don't need to restore *all* hashes on *all* heights; we just need sufficient information
-    /// to restore the single hash at the last height (which is the Merkle tree root).
-    ///
-    /// For proofs of a single element or a contiguous range of elements,
-    /// the total number of restored hashes is `O(log_2(N))`, where `N` is the list length.
+    /// The verification algorithm is shared with light clients and is implemented
+    /// in `exonum-proofs`.
     fn collect(&self) -> Result<Hash, ListProofError> {
-        let tree_height = tree_height_by_length(self.length);
-
-        // First, check an edge case when the list contains no elements.
-        if tree_height == 0 {
-            return if self.proof.is_empty() && self.entries.is_empty() {
-                Ok(Hash::zero())
-            } else {
-                Err(ListProofError::NonEmptyProof)
-            };
-        }
-
-        // Fast path in case there are no values: in this case, the proof can contain
-        // only a single root hash.
-        if self.entries.is_empty() {
-            return match self.proof[..] {
-                [] => Err(ListProofError::MissingHash),
-                [HashedEntry { key, hash }] if key == ProofListKey::new(tree_height, 0) => Ok(hash),
-                _ => Err(ListProofError::UnexpectedBranch),
-            };
-        }
-
-        // Check ordering of `self.values` and `self.hashes`, which is relied upon
-        // in the following steps.
-        let values_ordered = self
-            .entries
-            .windows(2)
-            .all(|window| window[0].0 < window[1].0);
-        if !values_ordered {
-            return Err(ListProofError::Unordered);
-        }
-
-        let hashes_ordered = self
-            .proof
-            .windows(2)
-            .all(|window| window[0].key < window[1].key);
-        if !hashes_ordered {
-            return Err(ListProofError::Unordered);
-        }
-
-        // Check that hashes on each height have indexes in the allowed range.
-        for &HashedEntry { key, .. } in &self.proof {
-            let height = key.height();
-            if height == 0 {
-                return Err(ListProofError::UnexpectedLeaf);
-            }
-
-            // `self.length - 1` is the index of the last element at `height = 1`. This index
-            // is divided by 2 with each new height.
-            if height >= tree_height || key.index() > (self.length - 1) >> u64::from(height - 1) {
-                return Err(ListProofError::UnexpectedBranch);
-            }
-        }
-
-        let mut layer: Vec<_> = self
-            .entries
-            .iter()
-            .map(|(i, value)| {
-                HashedEntry::new(
-                    ProofListKey::new(1, *i),
-                    HashTag::hash_leaf(&value.to_bytes()),
-                )
-            })
-            .collect();
-
-        let mut hashes = self.proof.clone();
-        // We track `last_index` instead of layer length in order to be able to more efficiently
-        // update it when transitioning to the next height. It suffices to divide `last_index` by 2,
-        // while if we used length, it would need to be modified as `l = (l + 1) / 2`.
-        let mut last_index = self.length - 1;
-        // We have covered `self.length == 0` case before, so the subtraction above is safe.
-
-        for height in 1..tree_height {
-            // We split `hashes` into those at `height` and those having greater height
-            // (by construction, there may be no hashes with the lesser height).
-            let split_key = ProofListKey::new(height + 1, 0);
-            let split_index = hashes
-                .binary_search_by(|entry| entry.key.cmp(&split_key))
-                .unwrap_or_else(|i| i);
-            let remaining_hashes = hashes.split_off(split_index);
-            debug_assert!(
-                hashes.iter().all(|entry| entry.key.height() == height),
-                "Unexpected `hashes`: {:?}",
-                hashes
-            );
-            debug_assert!(
-                remaining_hashes
-                    .first()
-                    .map_or(true, |first| first.key.height() > height),
-                "Unexpected `remaining_hashes`: {:?}",
-                remaining_hashes
-            );
-
-            // Merge `hashes` with those obtained by zipping the previous layer.
-            layer = merge(layer.into_iter(), hashes.into_iter())
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|_| ListProofError::RedundantHash)?;
-
-            // Zip the current layer.
-            hash_layer(&mut layer, last_index)?;
-            last_index /= 2;
-            hashes = remaining_hashes;
-        }
-
-        debug_assert_eq!(layer.len(), 1);
-        debug_assert_eq!(layer[0].key, ProofListKey::new(tree_height, 0));
-        Ok(layer[0].hash)
+        let entries = self.entries.iter().map(|(index, value)| {
+            (
+                *index,
+                to_proofs_hash(&HashTag::hash_leaf(&value.to_bytes())),
+            )
+        });
+        let proof = self.proof.iter().map(HashedEntry::to_proofs_entry);
+        restore_list_root(entries, proof, self.length).map(from_proofs_hash)
     }
 
     /// Returns the length of the underlying `ProofListIndex`.
@@ -444,6 +233,11 @@ impl<V: BinaryValue> ListProof<V> {
     /// An error will be returned if the proof fails basic integrity checks. Not returning an error
     /// does not guarantee that the proof is valid, however; the estimation skips most
     /// of the checks for speed.
+    ///
+    /// The estimate is exact for valid proofs, including proofs of non-contiguous elements.
+    /// Indeed, in a valid proof, each known hash on a certain height (except for the last hash
+    /// on the height) has a known sibling; thus, `n` known hashes on a height always produce
+    /// `ceil(n / 2)` hashes on the next height.
     pub fn hash_ops(&self) -> Result<usize, ListProofError> {
         // First, we need to hash all values in the proof.
         let mut hash_ops = self.entries.len();
@@ -587,126 +381,174 @@ impl<'a, V> CheckedListProof<'a, V> {
     }
 }
 
-/// An error that is returned when the list proof is invalid.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Fail)]
-pub enum ListProofError {
-    /// Proof contains a hash in a place where a value was expected.
-    #[fail(display = "proof contains a hash in a place where a value was expected")]
-    UnexpectedLeaf,
-
-    /// Proof contains a hash in the position which is impossible according to the list length.
-    #[fail(
-        display = "proof contains a hash in the position which is impossible according to the list length"
-    )]
-    UnexpectedBranch,
-
-    /// Values or hashes in the proof are not ordered by their keys.
-    #[fail(display = "values or hashes in the proof are not ordered by their keys")]
-    Unordered,
-
-    /// There are redundant hashes in the proof: the hash of the underlying list can be calculated
-    /// without some of them.
-    #[fail(display = "redundant hash in the proof")]
-    RedundantHash,
-
-    /// Proof does not contain necessary information to compute the hash of the underlying list.
-    #[fail(display = "missing hash")]
-    MissingHash,
-
-    /// Non-empty proof for an empty list.
-    ///
-    /// Empty lists should always have empty proofs, since there is no data to get values
-    /// or hashes from.
-    #[fail(display = "non-empty proof for an empty list")]
-    NonEmptyProof,
+/// Proof that a `ProofListIndex` with a certain length is a prefix of the same index
+/// with a greater length, i.e., that the newer state of the list was obtained
+/// from the older one by only appending elements.
+///
+/// The proof consists of hashes of the perfect subtrees covering the older list
+/// (these hashes are shared by the older and the newer Merkle trees) and hashes
+/// from the newer tree necessary to restore its root. Both sets of hashes are
+/// ordered by increasing `(height, index)` tuple.
+///
+/// # Workflow
+///
+/// Consistency proofs are created with the [`get_consistency_proof()`] method of
+/// `ProofListIndex` and can be verified with [`check()`] or [`check_against_hashes()`].
+///
+/// ```
+/// # use exonum_merkledb::{access::AccessExt, Database, TemporaryDB, ObjectHash};
+/// let fork = { let db = TemporaryDB::new(); db.fork() };
+/// let mut list = fork.get_proof_list("index");
+/// list.extend(vec![1_u32, 2, 3]);
+/// let old_hash = list.object_hash();
+/// list.extend(vec![4, 5]);
+/// let new_hash = list.object_hash();
+///
+/// let proof = list.get_consistency_proof(3);
+/// let checked_proof = proof.check_against_hashes(old_hash, new_hash).unwrap();
+/// assert_eq!(checked_proof.old_length(), 3);
+/// assert_eq!(checked_proof.new_length(), 5);
+/// ```
+///
+/// [`get_consistency_proof()`]: struct.ProofListIndex.html#method.get_consistency_proof
+/// [`check()`]: #method.check
+/// [`check_against_hashes()`]: #method.check_against_hashes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListConsistencyProof {
+    old_length: u64,
+    new_length: u64,
+    old_hashes: Vec<HashedEntry>,
+    new_hashes: Vec<HashedEntry>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{access::AccessExt, Database, TemporaryDB};
+impl ListConsistencyProof {
+    pub(super) fn new(
+        old_length: u64,
+        new_length: u64,
+        old_hashes: Vec<HashedEntry>,
+        new_hashes: Vec<HashedEntry>,
+    ) -> Self {
+        Self {
+            old_length,
+            new_length,
+            old_hashes,
+            new_hashes,
+        }
+    }
 
-    fn entry(height: u8, index: u64) -> HashedEntry {
-        HashedEntry::new(
-            ProofListKey::new(height, index),
-            HashTag::hash_leaf(&index.to_bytes()),
-        )
+    /// Returns the length of the older list without verifying the proof.
+    pub fn old_length_unchecked(&self) -> u64 {
+        self.old_length
     }
 
-    #[test]
-    fn merge_example() {
-        let first = vec![entry(1, 0), entry(1, 5), entry(2, 5)].into_iter();
-        let second = vec![
-            entry(1, 1),
-            entry(2, 2),
-            entry(2, 3),
-            entry(3, 0),
-            entry(4, 1),
-        ]
-        .into_iter();
-        let merged = merge(first, second).collect::<Result<Vec<_>, _>>().unwrap();
-
-        assert_eq!(
-            merged,
-            vec![
-                entry(1, 0),
-                entry(1, 1),
-                entry(1, 5),
-                entry(2, 2),
-                entry(2, 3),
-                entry(2, 5),
-                entry(3, 0),
-                entry(4, 1),
-            ]
-        );
+    /// Returns the length of the newer list without verifying the proof.
+    pub fn new_length_unchecked(&self) -> u64 {
+        self.new_length
     }
 
-    #[test]
-    fn hash_layer_example() {
-        let mut layer = vec![
-            entry(1, 0),
-            entry(1, 1),
-            entry(1, 6),
-            entry(1, 7),
-            entry(1, 8),
-        ];
-        hash_layer(&mut layer, 8).unwrap();
-        assert!(layer.iter().map(|entry| entry.key).eq(vec![
-            ProofListKey::new(2, 0),
-            ProofListKey::new(2, 3),
-            ProofListKey::new(2, 4),
-        ]));
-
-        assert_eq!(
-            layer[0].hash,
-            HashTag::hash_node(
-                &HashTag::hash_leaf(&0_u64.to_bytes()),
-                &HashTag::hash_leaf(&1_u64.to_bytes()),
-            )
-        );
-        assert_eq!(
-            layer[2].hash,
-            HashTag::hash_single_node(&HashTag::hash_leaf(&8_u64.to_bytes()))
-        );
+    /// Provides access to the hashes of the older list. Used in serialization.
+    pub(crate) fn old_hashes_unchecked(&self) -> &[HashedEntry] {
+        &self.old_hashes
+    }
 
-        // layer[0] has odd index
-        let mut layer = vec![entry(1, 1), entry(1, 2)];
-        assert!(hash_layer(&mut layer, 2).is_err());
+    /// Provides access to the hashes of the newer list. Used in serialization.
+    pub(crate) fn new_hashes_unchecked(&self) -> &[HashedEntry] {
+        &self.new_hashes
+    }
 
-        // layer[1] is not adjacent to layer[0]
-        let mut layer = vec![entry(1, 0), entry(1, 2)];
-        assert!(hash_layer(&mut layer, 3).is_err());
-        let mut layer = vec![entry(1, 0), entry(1, 3)];
-        assert!(hash_layer(&mut layer, 3).is_err());
+    /// Verifies the correctness of the proof.
+    ///
+    /// If the proof is valid, the hashes of the older and the newer lists are returned
+    /// within a checked proof. The caller should compare them to the trusted hashes;
+    /// alternatively, [`check_against_hashes()`] can be used.
+    ///
+    /// ## Errors
+    ///
+    /// An error is returned if proof is malformed. The following checks are performed:
+    ///
+    /// - The older length does not exceed the newer one.
+    /// - `old_hashes` correspond exactly to the perfect subtrees covering the older list.
+    /// - `new_hashes` are ordered by increasing `(height, index)` tuple, have feasible positions
+    ///   and do not duplicate `old_hashes`.
+    /// - There is sufficient information to restore both Merkle tree roots, and there
+    ///   are no redundant hashes.
+    ///
+    /// [`check_against_hashes()`]: #method.check_against_hashes
+    pub fn check(&self) -> Result<CheckedListConsistencyProof, ListProofError> {
+        let (old_root, new_root) = restore_consistent_roots(
+            self.old_length,
+            self.new_length,
+            self.old_hashes.iter().map(HashedEntry::to_proofs_entry),
+            self.new_hashes.iter().map(HashedEntry::to_proofs_entry),
+        )?;
+        let (old_root, new_root) = (from_proofs_hash(old_root), from_proofs_hash(new_root));
+
+        Ok(CheckedListConsistencyProof {
+            old_length: self.old_length,
+            new_length: self.new_length,
+            old_hash: HashTag::hash_list_node(self.old_length, old_root),
+            new_hash: HashTag::hash_list_node(self.new_length, new_root),
+        })
+    }
+
+    /// Verifies the correctness of the proof according to the trusted hashes
+    /// of the older and the newer lists.
+    ///
+    /// The method is essentially a convenience wrapper around `check()`.
+    pub fn check_against_hashes(
+        &self,
+        expected_old_hash: Hash,
+        expected_new_hash: Hash,
+    ) -> Result<CheckedListConsistencyProof, ValidationError<ListProofError>> {
+        self.check()
+            .map_err(ValidationError::Malformed)
+            .and_then(|checked_proof| {
+                if checked_proof.old_hash == expected_old_hash
+                    && checked_proof.new_hash == expected_new_hash
+                {
+                    Ok(checked_proof)
+                } else {
+                    Err(ValidationError::UnmatchedRootHash)
+                }
+            })
+    }
+}
+
+/// Version of `ListConsistencyProof` obtained after verification.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CheckedListConsistencyProof {
+    old_length: u64,
+    new_length: u64,
+    old_hash: Hash,
+    new_hash: Hash,
+}
+
+impl CheckedListConsistencyProof {
+    /// Returns the length of the older list.
+    pub fn old_length(&self) -> u64 {
+        self.old_length
+    }
+
+    /// Returns the length of the newer list.
+    pub fn new_length(&self) -> u64 {
+        self.new_length
+    }
 
-        // layer[-1] has odd index, while there is even number of elements in the layer
-        let mut layer = vec![entry(1, 0), entry(1, 1), entry(1, 7)];
-        assert!(hash_layer(&mut layer, 7).is_err());
+    /// Returns the `object_hash()` of the older list.
+    pub fn old_hash(&self) -> Hash {
+        self.old_hash
+    }
 
-        // layer[-1] has index lesser that the layer length
-        let mut layer = vec![entry(1, 0), entry(1, 1), entry(1, 4)];
-        assert!(hash_layer(&mut layer, 6).is_err());
+    /// Returns the `object_hash()` of the newer list.
+    pub fn new_hash(&self) -> Hash {
+        self.new_hash
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{access::AccessExt, Database};
 
     #[test]
     fn hash_ops_examples() {
@@ -764,8 +606,7 @@ mod tests {
         //   x   x   Values
     }
 
-    #[test]
-    fn hash_ops_in_full_tree() {
+    fn hash_ops_in_full_tree(db: &dyn Database) {
         // Consider a graph for computing Merkle root of the tree, such as one depicted above.
         // Denote `l` the number of leaves in this tree (i.e., nodes with degree 1),
         // `v2` number of nodes with degree 2, and `v3` the number of nodes with degree 3.
@@ -793,7 +634,6 @@ mod tests {
         // For a full Merkle tree, this becomes an equality, as there can be no other degree-2
         // nodes in the proof.
 
-        let db = TemporaryDB::new();
         let fork = db.fork();
         let mut list = fork.get_proof_list("test");
         list.extend(0_u32..8);
@@ -810,4 +650,6 @@ mod tests {
             }
         }
     }
+
+    backend_tests!(hash_ops_in_full_tree,);
 }
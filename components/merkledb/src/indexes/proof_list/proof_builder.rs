@@ -1,10 +1,15 @@
 //! Building `ListProof`s.
 
 use exonum_crypto::Hash;
+use exonum_proofs::internal::prefix_subtrees;
 
 use std::ops::{Bound, RangeBounds};
 
-use super::{key::ProofListKey, tree_height_by_length, ListProof};
+use super::{
+    key::ProofListKey,
+    proof::{HashedEntry, ListConsistencyProof},
+    tree_height_by_length, ListProof,
+};
 use crate::BinaryValue;
 
 /// Encapsulation of a binary Merkle tree allowing to access its terminal and intermediate
@@ -32,6 +37,8 @@ pub trait MerkleTree<V> {
 pub trait BuildProof<V> {
     fn create_proof(&self, index: u64) -> ListProof<V>;
     fn create_range_proof(&self, indexes: impl RangeBounds<u64>) -> ListProof<V>;
+    fn create_multiproof(&self, indexes: impl IntoIterator<Item = u64>) -> ListProof<V>;
+    fn create_consistency_proof(&self, old_length: u64) -> ListConsistencyProof;
 }
 
 impl<V, T> BuildProof<V> for T
@@ -71,6 +78,98 @@ where
         );
         create_proof(self, from, to)
     }
+
+    fn create_multiproof(&self, indexes: impl IntoIterator<Item = u64>) -> ListProof<V> {
+        let tree_len = self.len();
+        let mut indexes: Vec<_> = indexes
+            .into_iter()
+            .filter(|&index| index < tree_len)
+            .collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        if indexes.is_empty() {
+            return ListProof::empty(self.merkle_root(), tree_len);
+        }
+
+        let items = indexes.iter().map(|&index| {
+            let value = self.values(index).next();
+            // `.unwrap()` is safe: we've checked that the index is within the list bounds.
+            (index, value.unwrap())
+        });
+        let mut proof = ListProof::new(items, tree_len);
+
+        // `indexes` track the known nodes on the particular `height` of the tree, in the
+        // increasing order. On each height, we add to the proof the hashes of the siblings
+        // of known nodes, unless these siblings are known themselves (or do not exist).
+        // Since hashes are added in the order of increasing height, and on each height
+        // in the order of increasing index, the hashes in the proof are properly ordered.
+        let tree_height = tree_height_by_length(tree_len);
+        let mut last_index_on_level = tree_len - 1;
+        for height in 1..tree_height {
+            for (i, &index) in indexes.iter().enumerate() {
+                if index % 2 == 1 {
+                    let is_sibling_known = i > 0 && indexes[i - 1] == index - 1;
+                    if !is_sibling_known {
+                        let hash = self.node(ProofListKey::new(height, index - 1));
+                        proof.push_hash(height, index - 1, hash);
+                    }
+                } else {
+                    let is_sibling_known = indexes.get(i + 1) == Some(&(index + 1));
+                    if !is_sibling_known && index < last_index_on_level {
+                        let hash = self.node(ProofListKey::new(height, index + 1));
+                        proof.push_hash(height, index + 1, hash);
+                    }
+                }
+            }
+
+            for index in &mut indexes {
+                *index /= 2;
+            }
+            indexes.dedup();
+            last_index_on_level /= 2;
+        }
+        proof
+    }
+
+    fn create_consistency_proof(&self, old_length: u64) -> ListConsistencyProof {
+        let new_length = self.len();
+        assert!(
+            old_length <= new_length,
+            "Illegal old length: the list length is {}, but the old length is {}",
+            new_length,
+            old_length
+        );
+
+        let old_hashes = prefix_subtrees(old_length)
+            .into_iter()
+            .map(|key| HashedEntry::new(key, self.node(key)))
+            .collect();
+
+        let tree_height = tree_height_by_length(new_length);
+        let mut new_hashes = vec![];
+        if old_length == 0 {
+            if new_length > 0 {
+                let key = ProofListKey::new(tree_height, 0);
+                new_hashes.push(HashedEntry::new(key, self.node(key)));
+            }
+        } else {
+            // The older list is restored from `old_hashes`; similar to `create_proof()`,
+            // we need to add the hashes to the right of its last element on each height,
+            // provided that these hashes exist in the newer tree.
+            let mut right = old_length - 1;
+            let mut last_index_on_level = new_length - 1;
+            for height in 1..tree_height {
+                if right % 2 == 0 && right < last_index_on_level {
+                    let key = ProofListKey::new(height, right + 1);
+                    new_hashes.push(HashedEntry::new(key, self.node(key)));
+                }
+                right /= 2;
+                last_index_on_level /= 2;
+            }
+        }
+
+        ListConsistencyProof::new(old_length, new_length, old_hashes, new_hashes)
+    }
 }
 
 /// Creates a `ListProof` for a contiguous half-open range of indexes `[from, to)`.
@@ -22,8 +22,13 @@ use serde_json::{self, json};
 
 use std::cmp;
 
-use super::{key::ProofListKey, tree_height_by_length, ListProof, ListProofError, ProofListIndex};
-use crate::{access::AccessExt, BinaryValue, Database, HashTag, ObjectHash, TemporaryDB};
+use super::{
+    key::ProofListKey, tree_height_by_length, ListConsistencyProof, ListProof, ListProofError,
+    ProofListIndex,
+};
+use crate::{
+    access::AccessExt, BinaryValue, Database, HashTag, ObjectHash, TemporaryDB, ValidationError,
+};
 
 const IDX_NAME: &str = "idx_name";
 
@@ -45,9 +50,7 @@ fn random_values<R: Rng>(rng: &mut R, len: usize) -> Vec<Vec<u8>> {
     (0..len).map(generator).collect::<Vec<_>>()
 }
 
-#[test]
-fn list_methods() {
-    let db = TemporaryDB::new();
+fn list_methods(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
 
@@ -68,9 +71,7 @@ fn list_methods() {
     assert_eq!(index.get(2), Some(vec![3]));
 }
 
-#[test]
-fn extend_is_equivalent_to_sequential_pushes() {
-    let db = TemporaryDB::new();
+fn extend_is_equivalent_to_sequential_pushes(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
 
@@ -127,9 +128,7 @@ fn extend_is_equivalent_to_sequential_pushes() {
     }
 }
 
-#[test]
-fn tree_height() {
-    let db = TemporaryDB::new();
+fn tree_height(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
 
@@ -144,9 +143,7 @@ fn tree_height() {
     assert_eq!(index.height(), 3);
 }
 
-#[test]
-fn iter() {
-    let db = TemporaryDB::new();
+fn iter(db: &dyn Database) {
     let fork = db.fork();
     let mut list_index = fork.get_proof_list(IDX_NAME);
 
@@ -161,9 +158,7 @@ fn iter() {
     );
 }
 
-#[test]
-fn simple_proof() {
-    let db = TemporaryDB::new();
+fn simple_proof(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
 
@@ -269,9 +264,7 @@ fn simple_proof() {
     );
 }
 
-#[test]
-fn proofs_in_empty_list() {
-    let db = TemporaryDB::new();
+fn proofs_in_empty_list(db: &dyn Database) {
     let fork = db.fork();
     let index: ProofListIndex<_, u32> = fork.get_proof_list(IDX_NAME);
     let proof = index.get_range_proof(..);
@@ -281,9 +274,7 @@ fn proofs_in_empty_list() {
     assert!(proof.check_against_hash(index.object_hash()).is_ok());
 }
 
-#[test]
-fn empty_proof_ranges() {
-    let db = TemporaryDB::new();
+fn empty_proof_ranges(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
     index.extend(vec![1_u32, 2, 3]);
@@ -301,12 +292,10 @@ fn empty_proof_ranges() {
         .is_empty());
 }
 
-#[test]
-fn random_proofs() {
+fn random_proofs(db: &dyn Database) {
     const LIST_SIZE: usize = 1 << 10;
     const MAX_RANGE_SIZE: u64 = 128;
 
-    let db = TemporaryDB::new();
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
 
@@ -334,9 +323,84 @@ fn random_proofs() {
     }
 }
 
-#[test]
-fn index_and_proof_roots() {
-    let db = TemporaryDB::new();
+fn multiproofs(db: &dyn Database) {
+    let fork = db.fork();
+    let mut index = fork.get_proof_list(IDX_NAME);
+
+    for len in 1_u64..20 {
+        index.clear();
+        index.extend(0..len);
+        let index_hash = index.object_hash();
+
+        // Check all subsets of the first 10 indexes.
+        for mask in 0_u32..(1 << 10) {
+            let indexes: Vec<u64> = (0..10).filter(|&i| mask & (1 << i) != 0).collect();
+            let proof = index.get_multiproof(indexes.clone());
+            let checked_proof = proof.check_against_hash(index_hash).unwrap();
+            let expected_indexes = indexes.into_iter().filter(|&i| i < len);
+            assert!(checked_proof.indexes().eq(expected_indexes));
+            assert!(checked_proof.entries().iter().all(|(i, value)| i == value));
+        }
+    }
+}
+
+fn multiproofs_are_equivalent_to_other_proofs(db: &dyn Database) {
+    let fork = db.fork();
+    let mut index = fork.get_proof_list(IDX_NAME);
+    index.extend(0_u64..100);
+
+    assert_eq!(index.get_multiproof(vec![5]), index.get_proof(5));
+    assert_eq!(index.get_multiproof(10..20), index.get_range_proof(10..20));
+    assert_eq!(
+        index.get_multiproof(vec![19, 10, 15, 12, 11, 18, 17, 16, 14, 13, 10]),
+        index.get_range_proof(10..20)
+    );
+    assert_eq!(index.get_multiproof(vec![]), index.get_range_proof(100..));
+    assert_eq!(index.get_multiproof(vec![100, 200]), index.get_proof(100));
+}
+
+fn random_multiproofs(db: &dyn Database) {
+    const LIST_SIZE: usize = 1 << 10;
+
+    let fork = db.fork();
+    let mut index = fork.get_proof_list(IDX_NAME);
+
+    let mut rng = thread_rng();
+    let values = random_values(&mut rng, LIST_SIZE);
+    index.extend(values.iter().cloned());
+
+    let index_hash = index.object_hash();
+    for _ in 0..10 {
+        let count = rng.gen_range(1, 20);
+        let mut indexes: Vec<u64> = (0..count)
+            .map(|_| rng.gen_range(0, LIST_SIZE as u64))
+            .collect();
+
+        let proof = index.get_multiproof(indexes.clone());
+        let checked_proof = proof.check_against_hash(index_hash).unwrap();
+        indexes.sort();
+        indexes.dedup();
+        let expected_entries = indexes.iter().map(|&i| (i, &values[i as usize]));
+        assert!(checked_proof
+            .entries()
+            .iter()
+            .map(|(i, value)| (*i, value))
+            .eq(expected_entries));
+
+        // The multiproof is not larger than separate proofs for each element.
+        let separate_proofs_len: usize = indexes
+            .iter()
+            .map(|&i| index.get_proof(i).proof_unchecked().len())
+            .sum();
+        assert!(proof.proof_unchecked().len() <= separate_proofs_len);
+        assert_eq!(
+            proof.hash_ops().unwrap(),
+            2 * proof.entries_unchecked().len() + proof.proof_unchecked().len() - 1
+        );
+    }
+}
+
+fn index_and_proof_roots(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
     assert_eq!(index.object_hash(), HashTag::empty_list_hash());
@@ -469,10 +533,7 @@ fn index_and_proof_roots() {
     assert_eq!(index.get(0), Some(vec![1, 2]));
 }
 
-#[test]
-#[should_panic(expected = "the range start is 2, but the range end is 1")]
-fn proof_illegal_range() {
-    let db = TemporaryDB::new();
+fn proof_illegal_range(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
     for i in 0_u8..4 {
@@ -481,10 +542,7 @@ fn proof_illegal_range() {
     index.get_range_proof(2..1);
 }
 
-#[test]
-#[should_panic(expected = "the range start is 2, but the range end is 1")]
-fn proof_illegal_inclusive_range() {
-    let db = TemporaryDB::new();
+fn proof_illegal_inclusive_range(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
     for i in 0_u8..4 {
@@ -568,9 +626,7 @@ fn ranges_work_similar_to_vec_slicing() {
     }
 }
 
-#[test]
-fn proof_with_range_start_exceeding_list_size() {
-    let db = TemporaryDB::new();
+fn proof_with_range_start_exceeding_list_size(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
     for i in 0_u8..4 {
@@ -592,9 +648,7 @@ fn proof_with_range_start_exceeding_list_size() {
         .is_empty());
 }
 
-#[test]
-fn proof_with_range_end_exceeding_list_size() {
-    let db = TemporaryDB::new();
+fn proof_with_range_end_exceeding_list_size(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_list(IDX_NAME);
     for i in 0_u8..4 {
@@ -612,9 +666,7 @@ fn proof_with_range_end_exceeding_list_size() {
     );
 }
 
-#[test]
-fn setting_elements_leads_to_correct_list_hash() {
-    let db = TemporaryDB::new();
+fn setting_elements_leads_to_correct_list_hash(db: &dyn Database) {
     let hash1 = {
         let fork = db.fork();
         let mut list = fork.get_proof_list(IDX_NAME);
@@ -641,12 +693,10 @@ fn setting_elements_leads_to_correct_list_hash() {
     assert_eq!(hash1, hash2);
 }
 
-#[test]
-fn setting_elements_leads_to_correct_list_hash_randomized() {
+fn setting_elements_leads_to_correct_list_hash_randomized(db: &dyn Database) {
     const LIST_LEN: usize = 32;
 
     let mut rng = thread_rng();
-    let db = TemporaryDB::new();
     let fork = db.fork();
     let mut list = fork.get_proof_list(IDX_NAME);
 
@@ -694,9 +744,7 @@ fn setting_elements_leads_to_correct_list_hash_randomized() {
     }
 }
 
-#[test]
-fn truncating_list() {
-    let db = TemporaryDB::new();
+fn truncating_list(db: &dyn Database) {
     let fork = db.fork();
     let mut list = fork.get_proof_list(IDX_NAME);
     list.extend(0_u32..30);
@@ -730,10 +778,8 @@ fn truncating_list() {
     }
 }
 
-#[test]
-fn truncating_list_leads_to_expected_hash() {
+fn truncating_list_leads_to_expected_hash(db: &dyn Database) {
     let mut rng = thread_rng();
-    let db = TemporaryDB::new();
     let fork = db.fork();
     let mut list = fork.get_proof_list(IDX_NAME);
 
@@ -784,9 +830,7 @@ fn truncating_list_leads_to_expected_hash() {
     }
 }
 
-#[test]
-fn popping_element_from_list() {
-    let db = TemporaryDB::new();
+fn popping_element_from_list(db: &dyn Database) {
     let fork = db.fork();
     let mut list = fork.get_proof_list(IDX_NAME);
     list.extend(0_i32..10);
@@ -911,6 +955,11 @@ fn proofs_with_unexpected_branches() {
 
     let mut proof = ListProof::new(vec![(1, "foo".to_owned()), (2, "bar".to_owned())], 5);
     proof.push_hash(1, 6, Hash::zero());
+
+    // Entry index is greater than the index of the last list element.
+    let mut proof = ListProof::new(vec![(3, "foo".to_owned())], 3);
+    proof.push_hash(2, 0, Hash::zero());
+    assert_eq!(proof.check().unwrap_err(), ListProofError::UnexpectedBranch);
 }
 
 #[test]
@@ -992,6 +1041,188 @@ fn invalid_proofs_with_no_values() {
     );
 }
 
+fn consistency_proofs(db: &dyn Database) {
+    let fork = db.fork();
+    let mut list = fork.get_proof_list(IDX_NAME);
+
+    let mut hashes = vec![list.object_hash()];
+    for i in 0..70_u64 {
+        list.push(i);
+        hashes.push(list.object_hash());
+    }
+
+    for new_length in 0..=list.len() {
+        list.truncate(new_length);
+        for old_length in 0..=new_length {
+            let proof = list.get_consistency_proof(old_length);
+            let checked_proof = proof
+                .check_against_hashes(hashes[old_length as usize], hashes[new_length as usize])
+                .unwrap_or_else(|e| {
+                    panic!(
+                        "Invalid consistency proof for {} -> {}: {}",
+                        old_length, new_length, e
+                    )
+                });
+            assert_eq!(checked_proof.old_length(), old_length);
+            assert_eq!(checked_proof.new_length(), new_length);
+        }
+        list.extend(new_length..70);
+    }
+}
+
+fn random_consistency_proofs(db: &dyn Database) {
+    const LIST_SIZE: usize = 1 << 10;
+
+    let fork = db.fork();
+    let mut list = fork.get_proof_list(IDX_NAME);
+
+    let mut rng = thread_rng();
+    let values = random_values(&mut rng, LIST_SIZE);
+    let mut hashes = vec![list.object_hash()];
+    for value in &values {
+        list.push(value.clone());
+        hashes.push(list.object_hash());
+    }
+
+    for _ in 0..100 {
+        let old_length = rng.gen_range(0, LIST_SIZE + 1);
+        let proof = list.get_consistency_proof(old_length as u64);
+        let checked_proof = proof.check().unwrap();
+        assert_eq!(checked_proof.old_hash(), hashes[old_length]);
+        assert_eq!(checked_proof.new_hash(), hashes[LIST_SIZE]);
+    }
+}
+
+fn consistency_proofs_do_not_match_modified_lists(db: &dyn Database) {
+    let fork = db.fork();
+    let mut list = fork.get_proof_list(IDX_NAME);
+    list.extend(0_u64..10);
+    let old_hash = list.object_hash();
+
+    list.set(3, 100);
+    list.extend(10..15);
+    let proof = list.get_consistency_proof(10);
+    assert_eq!(
+        proof
+            .check_against_hashes(old_hash, list.object_hash())
+            .unwrap_err(),
+        ValidationError::UnmatchedRootHash
+    );
+}
+
+fn consistency_proof_with_illegal_old_length(db: &dyn Database) {
+    let fork = db.fork();
+    let mut list = fork.get_proof_list(IDX_NAME);
+    list.extend(vec![1_u8, 2, 3]);
+    list.get_consistency_proof(4);
+}
+
+fn consistency_proof_json_serialization(db: &dyn Database) {
+    let fork = db.fork();
+    let mut list = fork.get_proof_list(IDX_NAME);
+    list.extend(0_u64..5);
+
+    let proof = list.get_consistency_proof(3);
+    let node = |height, index| list.get_branch_unchecked(ProofListKey::new(height, index));
+    let json = serde_json::to_value(&proof).unwrap();
+    assert_eq!(
+        json,
+        json!({
+            "old_length": 3,
+            "new_length": 5,
+            "old_hashes": [
+                { "height": 1, "index": 2, "hash": node(1, 2) },
+                { "height": 2, "index": 0, "hash": node(2, 0) },
+            ],
+            "new_hashes": [
+                { "height": 1, "index": 3, "hash": node(1, 3) },
+                { "height": 3, "index": 1, "hash": node(3, 1) },
+            ],
+        })
+    );
+
+    let proof_from_json: ListConsistencyProof = serde_json::from_value(json).unwrap();
+    assert_eq!(proof_from_json, proof);
+}
+
+fn invalid_consistency_proofs(db: &dyn Database) {
+    let fork = db.fork();
+    let mut list = fork.get_proof_list(IDX_NAME);
+    list.extend(0_u64..5);
+    let proof = list.get_consistency_proof(3);
+    let mut json = serde_json::to_value(&proof).unwrap();
+
+    // Old length exceeding the new one.
+    json["old_length"] = json!(6);
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::InvalidLength
+    );
+
+    // Old length not corresponding to `old_hashes`.
+    json["old_length"] = json!(4);
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::UnexpectedBranch
+    );
+    json["old_length"] = json!(3);
+
+    // Missing hash of the older list.
+    let mut bogus_json = json.clone();
+    bogus_json["old_hashes"].as_array_mut().unwrap().pop();
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(bogus_json).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::MissingHash
+    );
+
+    // Missing hash of the newer list.
+    let mut bogus_json = json.clone();
+    bogus_json["new_hashes"].as_array_mut().unwrap().pop();
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(bogus_json).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::MissingHash
+    );
+
+    // Hash of the newer list duplicating a hash of the older list.
+    let mut bogus_json = json.clone();
+    let old_hash = bogus_json["old_hashes"][0].clone();
+    bogus_json["new_hashes"]
+        .as_array_mut()
+        .unwrap()
+        .insert(0, old_hash);
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(bogus_json).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::RedundantHash
+    );
+
+    // Hash of the newer list at an impossible position.
+    let mut bogus_json = json.clone();
+    bogus_json["new_hashes"][1]["index"] = json!(2);
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(bogus_json).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::UnexpectedBranch
+    );
+
+    // Non-empty proof for empty lists.
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(json!({
+        "old_length": 0,
+        "new_length": 0,
+        "old_hashes": [],
+        "new_hashes": [{ "height": 1, "index": 0, "hash": Hash::zero() }],
+    }))
+    .unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::NonEmptyProof
+    );
+}
+
 mod root_hash {
     use crate::{access::AccessExt, hash::HashTag, BinaryValue, Database, ObjectHash, TemporaryDB};
     use exonum_crypto::{self, Hash};
@@ -1076,3 +1307,36 @@ mod root_hash {
         assert_object_hash_correct(&to_list_of_hashes(&[]));
     }
 }
+
+backend_tests!(
+    list_methods,
+    extend_is_equivalent_to_sequential_pushes,
+    tree_height,
+    iter,
+    simple_proof,
+    proofs_in_empty_list,
+    empty_proof_ranges,
+    random_proofs,
+    multiproofs,
+    multiproofs_are_equivalent_to_other_proofs,
+    random_multiproofs,
+    index_and_proof_roots,
+    #[should_panic(expected = "the range start is 2, but the range end is 1")]
+    proof_illegal_range,
+    #[should_panic(expected = "the range start is 2, but the range end is 1")]
+    proof_illegal_inclusive_range,
+    proof_with_range_start_exceeding_list_size,
+    proof_with_range_end_exceeding_list_size,
+    setting_elements_leads_to_correct_list_hash,
+    setting_elements_leads_to_correct_list_hash_randomized,
+    truncating_list,
+    truncating_list_leads_to_expected_hash,
+    popping_element_from_list,
+    consistency_proofs,
+    random_consistency_proofs,
+    consistency_proofs_do_not_match_modified_lists,
+    #[should_panic(expected = "Illegal old length")]
+    consistency_proof_with_illegal_old_length,
+    consistency_proof_json_serialization,
+    invalid_consistency_proofs,
+);
@@ -14,23 +14,31 @@
 
 //! An implementation of a Merkelized version of a map (Merkle Patricia tree).
 
-pub(crate) use self::key::ProofPath;
+pub(crate) use self::key::{ProofPath, ProofPathExt, VALUE_KEY_PREFIX};
 pub use self::{
     key::{Hashed, Raw, ToProofPath, KEY_SIZE as PROOF_MAP_KEY_SIZE, PROOF_PATH_SIZE},
-    proof::{CheckedMapProof, MapProof, MapProofError, ValidationError},
+    proof::{CheckedMapProof, MapProof, MapProofError, MapRangeProof, ValidationError},
 };
 
-use std::{fmt, io, marker::PhantomData};
+use std::{
+    borrow::Borrow,
+    cmp,
+    collections::HashMap,
+    fmt, io,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
 use exonum_crypto::Hash;
 
 use self::{
-    key::{BitsRange, ChildKind, VALUE_KEY_PREFIX},
+    key::{BitsRange, ChildKind, BRANCH_KEY_PREFIX, LEAF_KEY_PREFIX},
     node::{BranchNode, Node},
-    proof_builder::{BuildProof, MerklePatriciaTree},
+    proof_builder::{BuildProof, BuildRangeProof, MerklePatriciaTree},
 };
 use crate::{
     access::{Access, AccessError, FromAccess},
+    integrity::InconsistencyKind,
     views::{
         BinaryAttribute, IndexAddress, IndexState, IndexType, Iter as ViewIter, RawAccess,
         RawAccessMut, View, ViewWithMetadata,
@@ -83,13 +91,26 @@ pub struct ProofMapIndex<T: RawAccess, K: ?Sized, V, KeyMode: ToProofPath<K> = H
     _key_mode: PhantomData<KeyMode>,
 }
 
+/// Statistics collected while checking integrity of a `ProofMapIndex`.
+#[derive(Debug, Default)]
+struct TreeStats {
+    branches_count: u64,
+    leaves_count: u64,
+    /// Numbers of the tree leaves with a certain hash minus numbers of the map values
+    /// with the same hash.
+    leaf_hashes: HashMap<Hash, i64>,
+}
+
 /// An iterator over the entries of a `ProofMapIndex`.
 ///
-/// This struct is created by the [`iter`] or
-/// [`iter_from`] method on [`ProofMapIndex`]. See its documentation for details.
+/// This struct is created by the [`iter`], [`iter_from`], [`iter_rev`], [`iter_rev_from`]
+/// or [`range`] method on [`ProofMapIndex`]. See its documentation for details.
 ///
 /// [`iter`]: struct.ProofMapIndex.html#method.iter
 /// [`iter_from`]: struct.ProofMapIndex.html#method.iter_from
+/// [`iter_rev`]: struct.ProofMapIndex.html#method.iter_rev
+/// [`iter_rev_from`]: struct.ProofMapIndex.html#method.iter_rev_from
+/// [`range`]: struct.ProofMapIndex.html#method.range
 /// [`ProofMapIndex`]: struct.ProofMapIndex.html
 #[derive(Debug)]
 pub struct Iter<'a, K: ?Sized, V> {
@@ -248,6 +269,131 @@ where
         }
     }
 
+    /// Checks that the Merkle Patricia tree of the map is consistent with the map values,
+    /// pushing found inconsistencies into `report`.
+    ///
+    /// Returns the object hash of the map, or `None` if the root of the tree cannot be read.
+    pub(crate) fn check_integrity(&self, report: &mut Vec<InconsistencyKind>) -> Option<Hash> {
+        let mut stats = TreeStats::default();
+        let merkle_root = match self.get_root_path() {
+            None => Some(Hash::zero()),
+            Some(path) => self.check_subtree(&path, &mut stats, report).map(|hash| {
+                if path.is_leaf() {
+                    HashTag::hash_single_entry_map(&path, &hash)
+                } else {
+                    hash
+                }
+            }),
+        };
+
+        // Leaf nodes are not linked to the values directly (the key transform is unknown
+        // without the map type), so we check that the multisets of leaf hashes
+        // and value hashes coincide.
+        let (mut branches_count, mut leaves_count, mut unexpected_entries) = (0, 0, 0);
+        for (key, value) in self.base.iter::<_, [u8], Vec<u8>>(&()) {
+            match key.first() {
+                Some(&BRANCH_KEY_PREFIX) => branches_count += 1,
+                Some(&LEAF_KEY_PREFIX) => leaves_count += 1,
+                Some(&VALUE_KEY_PREFIX) => {
+                    *stats
+                        .leaf_hashes
+                        .entry(HashTag::hash_leaf(&value))
+                        .or_default() -= 1;
+                }
+                _ => unexpected_entries += 1,
+            }
+        }
+        unexpected_entries += branches_count.saturating_sub(stats.branches_count);
+        unexpected_entries += leaves_count.saturating_sub(stats.leaves_count);
+        if unexpected_entries > 0 {
+            report.push(InconsistencyKind::UnexpectedEntries {
+                count: unexpected_entries,
+            });
+        }
+
+        let (mut unmatched_leaves, mut unmatched_values) = (0, 0);
+        for &count in stats.leaf_hashes.values() {
+            if count > 0 {
+                unmatched_leaves += count as u64;
+            } else {
+                unmatched_values += (-count) as u64;
+            }
+        }
+        let count = cmp::max(unmatched_leaves, unmatched_values);
+        if count > 0 {
+            report.push(InconsistencyKind::ValuesMismatch { count });
+        }
+
+        merkle_root.map(HashTag::hash_map_node)
+    }
+
+    /// Recursively checks the subtree with the root at `path`, returning the hash of the root
+    /// as referenced by its parent node.
+    fn check_subtree(
+        &self,
+        path: &ProofPath,
+        stats: &mut TreeStats,
+        report: &mut Vec<InconsistencyKind>,
+    ) -> Option<Hash> {
+        let raw_key = path.as_bytes().to_vec();
+        let bytes = match self.base.get::<_, Vec<u8>>(path) {
+            Some(bytes) => bytes,
+            None => {
+                report.push(InconsistencyKind::MissingEntry { key: raw_key });
+                return None;
+            }
+        };
+
+        if path.is_leaf() {
+            let hash = Hash::from_slice(&bytes);
+            if let Some(hash) = hash {
+                stats.leaves_count += 1;
+                *stats.leaf_hashes.entry(hash).or_default() += 1;
+            } else {
+                report.push(InconsistencyKind::InvalidEntry { key: raw_key });
+            }
+            return hash;
+        }
+
+        let branch = match BranchNode::from_bytes(bytes.into()) {
+            Ok(branch) => branch,
+            Err(_) => {
+                report.push(InconsistencyKind::InvalidEntry { key: raw_key });
+                return None;
+            }
+        };
+        stats.branches_count += 1;
+
+        for &kind in &[ChildKind::Left, ChildKind::Right] {
+            let child_path = branch.checked_child_path(kind).filter(|child| {
+                // Checking that the child path extends the parent path guarantees
+                // that the traversal terminates.
+                child.len() > path.len() && child.starts_with(path) && child.bit(path.len()) == kind
+            });
+            let child_path = match child_path {
+                Some(child_path) => child_path,
+                None => {
+                    report.push(InconsistencyKind::InvalidEntry {
+                        key: raw_key.clone(),
+                    });
+                    continue;
+                }
+            };
+
+            if let Some(computed) = self.check_subtree(&child_path, stats, report) {
+                let stored = branch.child_hash(kind);
+                if stored != computed {
+                    report.push(InconsistencyKind::HashMismatch {
+                        key: child_path.as_bytes().to_vec(),
+                        stored,
+                        computed,
+                    });
+                }
+            }
+        }
+        Some(branch.object_hash())
+    }
+
     /// Returns a value corresponding to the key.
     ///
     /// # Examples
@@ -480,6 +626,152 @@ where
                 .iter_from(&VALUE_KEY_PREFIX, &from.to_value_path()),
         }
     }
+
+    /// Returns an iterator over the entries of the map in descending order. The iterator element
+    /// type is `(K::Output, V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofMapIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index: ProofMapIndex<_, u64, u8> = fork.get_proof_map("name");
+    /// index.put(&1, 10);
+    /// index.put(&2, 20);
+    ///
+    /// assert_eq!(index.iter_rev().collect::<Vec<_>>(), vec![(2, 20), (1, 10)]);
+    /// ```
+    pub fn iter_rev(&self) -> Iter<'_, K, V> {
+        Iter {
+            base_iter: self.base.iter_rev(&VALUE_KEY_PREFIX),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the map in descending order starting from the
+    /// specified key (inclusive). The iterator element type is `(K::Output, V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofMapIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index: ProofMapIndex<_, u64, u8> = fork.get_proof_map("name");
+    /// index.put(&1, 10);
+    /// index.put(&2, 20);
+    /// index.put(&3, 30);
+    ///
+    /// let keys: Vec<_> = index.iter_rev_from(&2).map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![2, 1]);
+    /// ```
+    pub fn iter_rev_from(&self, from: &K) -> Iter<'_, K, V> {
+        Iter {
+            base_iter: self
+                .base
+                .iter_rev_from(&VALUE_KEY_PREFIX, &from.to_value_path()),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the map in ascending order with keys within
+    /// the specified range. The iterator element type is `(K::Output, V)`.
+    ///
+    /// The keys are ordered by their binary representation, regardless of the `KeyMode`
+    /// of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofMapIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index: ProofMapIndex<_, u64, u8> = fork.get_proof_map("name");
+    /// for i in 0..10 {
+    ///     index.put(&i, i as u8);
+    /// }
+    ///
+    /// let keys: Vec<_> = index.range(3..=5).map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![3, 4, 5]);
+    /// ```
+    pub fn range<R>(&self, range: R) -> Iter<'_, K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        let start = value_path_bound(range.start_bound());
+        let end = value_path_bound(range.end_bound());
+        Iter {
+            base_iter: self.base.iter_range(
+                &VALUE_KEY_PREFIX,
+                bound_as_slice(&start),
+                bound_as_slice(&end),
+            ),
+            _k: PhantomData,
+        }
+    }
+}
+
+/// Converts a bound on the map keys into a bound on the corresponding value paths.
+fn value_path_bound<K: BinaryKey + ?Sized>(bound: Bound<&K>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.to_value_path()),
+        Bound::Excluded(key) => Bound::Excluded(key.to_value_path()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn bound_as_slice(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key),
+        Bound::Excluded(key) => Bound::Excluded(key),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl<T, K, V> ProofMapIndex<T, K, V, Raw>
+where
+    T: RawAccess,
+    K: BinaryKey<Owned = K> + Clone,
+    V: BinaryValue,
+    Raw: ToProofPath<K>,
+{
+    /// Returns the proof of all entries with keys within the specified range.
+    ///
+    /// Unlike [`get_multiproof`], the returned proof asserts that the map contains
+    /// no other entries within the range. Note that the range is defined with respect
+    /// to the ordering of `ProofPath`s, which differs from the lexicographic ordering
+    /// of keys; see [`MapRangeProof`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ObjectHash};
+    /// use exonum_crypto::Hash;
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_raw_proof_map("name");
+    /// let (first, second) = (Hash::new([1; 32]), Hash::new([2; 32]));
+    /// index.put(&first, 1_u8);
+    /// index.put(&second, 2_u8);
+    ///
+    /// let proof = index.get_range_proof(..);
+    /// let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+    /// assert_eq!(checked_proof.entries().count(), 2);
+    /// ```
+    ///
+    /// [`get_multiproof`]: #method.get_multiproof
+    /// [`MapRangeProof`]: struct.MapRangeProof.html
+    pub fn get_range_proof<R>(&self, range: R) -> MapRangeProof<K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        self.create_range_proof(range.start_bound(), range.end_bound())
+    }
 }
 
 impl<T, K, V, KeyMode> ProofMapIndex<T, K, V, KeyMode>
@@ -621,6 +913,72 @@ where
         RemoveAction::KeyNotFound
     }
 
+    /// Removes leaves with the specified `paths` from the subtree with the root at `path`.
+    /// `hash` is the hash of the subtree root. All `paths` must start with the `path`
+    /// of the parent node.
+    ///
+    /// Each affected node is read and updated at most once, regardless of the number
+    /// of removed leaves in its subtree.
+    ///
+    /// Returns the path and the hash of the new subtree root, or `None` if the subtree
+    /// becomes empty.
+    fn remove_leaves(
+        &mut self,
+        path: &ProofPath,
+        hash: Hash,
+        paths: Vec<ProofPath>,
+    ) -> Option<(ProofPath, Hash)> {
+        let paths: Vec<_> = paths
+            .into_iter()
+            .filter(|removed_path| removed_path.starts_with(path))
+            .collect();
+        if paths.is_empty() {
+            return Some((*path, hash));
+        }
+        if path.is_leaf() {
+            // The only removed path starting with the leaf path is the leaf path itself.
+            self.base.remove(path);
+            return None;
+        }
+
+        let mut branch: BranchNode = match self.get_node_unchecked(path) {
+            Node::Branch(branch) => branch,
+            Node::Leaf(_) => unreachable!("Node at the branch path is a leaf"),
+        };
+        let (left_paths, right_paths): (Vec<_>, Vec<_>) = paths
+            .into_iter()
+            .partition(|removed_path| removed_path.bit(path.len()) == ChildKind::Left);
+        let left = self.remove_leaves(
+            &branch.child_path(ChildKind::Left),
+            branch.child_hash(ChildKind::Left),
+            left_paths,
+        );
+        let right = self.remove_leaves(
+            &branch.child_path(ChildKind::Right),
+            branch.child_hash(ChildKind::Right),
+            right_paths,
+        );
+
+        match (left, right) {
+            (Some((left_path, left_hash)), Some((right_path, right_hash))) => {
+                branch.set_child(ChildKind::Left, &left_path, &left_hash);
+                branch.set_child(ChildKind::Right, &right_path, &right_hash);
+                let hash = branch.object_hash();
+                self.base.put(path, branch);
+                Some((*path, hash))
+            }
+            // The remaining child replaces the branch.
+            (Some(child), None) | (None, Some(child)) => {
+                self.base.remove(path);
+                Some(child)
+            }
+            (None, None) => {
+                self.base.remove(path);
+                None
+            }
+        }
+    }
+
     /// Inserts the key-value pair into the proof map.
     ///
     /// # Examples
@@ -694,6 +1052,112 @@ where
         self.update_root_path(root_path);
     }
 
+    /// Builds the subtree with the specified leaves and returns the path and the hash
+    /// of its root. `leaves` must be non-empty, sorted by their paths and must not contain
+    /// duplicate paths.
+    ///
+    /// Each branch node of the subtree is written exactly once.
+    fn build_subtree(&mut self, leaves: &[(ProofPath, Hash)]) -> (ProofPath, Hash) {
+        debug_assert!(!leaves.is_empty());
+        let (first_path, first_hash) = leaves[0];
+        if leaves.len() == 1 {
+            return (first_path, first_hash);
+        }
+
+        // Since the leaves are sorted, the common prefix of all leaves coincides
+        // with the common prefix of the first and the last leaf.
+        let last_path = leaves[leaves.len() - 1].0;
+        let prefix_len = first_path.common_prefix_len(&last_path);
+        let split_pos = leaves
+            .iter()
+            .position(|(path, _)| path.bit(prefix_len) == ChildKind::Right)
+            .expect("Sorted leaves with distinct paths diverge at the common prefix end");
+
+        let (left_path, left_hash) = self.build_subtree(&leaves[..split_pos]);
+        let (right_path, right_hash) = self.build_subtree(&leaves[split_pos..]);
+        let mut branch = BranchNode::empty();
+        branch.set_child(ChildKind::Left, &left_path, &left_hash);
+        branch.set_child(ChildKind::Right, &right_path, &right_hash);
+        let hash = branch.object_hash();
+        let branch_path = first_path.prefix(prefix_len);
+        self.base.put(&branch_path, branch);
+        (branch_path, hash)
+    }
+
+    /// Inserts multiple key-value pairs into the proof map. The entries may be provided
+    /// in any order; if the same key occurs several times, the last value wins,
+    /// just like with consecutive [`put`]s.
+    ///
+    /// If the map is empty, the Merkle Patricia tree is built bottom-up in a single pass,
+    /// so that each branch node is written exactly once. This is much faster than inserting
+    /// the entries one by one, since `put` rewrites all branches on the path to the inserted
+    /// leaf. The resulting map (including its `object_hash`) is the same as if the entries
+    /// were inserted with `put`. If the map is not empty, the entries are inserted one by one.
+    ///
+    /// Entries are sorted internally by their position in the tree, which requires keeping
+    /// a path and a hash per entry in memory; the values themselves are written
+    /// to the fork immediately.
+    ///
+    /// [`put`]: #method.put
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, ObjectHash, TemporaryDB, Database, ProofMapIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index: ProofMapIndex<_, u64, String> = fork.get_proof_map("name");
+    /// index.bulk_load((0..100).map(|i| (i, i.to_string())));
+    /// assert_eq!(index.get(&42), Some("42".to_owned()));
+    ///
+    /// let mut other_index = fork.get_proof_map("other");
+    /// for i in (0..100).rev() {
+    ///     other_index.put(&i, i.to_string());
+    /// }
+    /// assert_eq!(index.object_hash(), other_index.object_hash());
+    /// ```
+    pub fn bulk_load<I, Q>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (Q, V)>,
+        Q: Borrow<K>,
+    {
+        if self.get_root_path().is_some() {
+            for (key, value) in entries {
+                self.put(key.borrow(), value);
+            }
+            return;
+        }
+
+        let mut leaves: Vec<_> = entries
+            .into_iter()
+            .map(|(key, value)| {
+                let key = key.borrow();
+                let proof_path = KeyMode::transform_key(key);
+                let hash = self.insert_leaf(&proof_path, key, value);
+                (proof_path, hash)
+            })
+            .collect();
+        if leaves.is_empty() {
+            return;
+        }
+
+        // The sort is stable, so the last entry for each key is the last one
+        // among entries with the same path.
+        leaves.sort_by(|(x, _), (y, _)| x.partial_cmp(y).expect("Misaligned proof paths"));
+        leaves.dedup_by(|(path, hash), (prev_path, prev_hash)| {
+            if path == prev_path {
+                *prev_hash = *hash;
+                true
+            } else {
+                false
+            }
+        });
+
+        let (root_path, _) = self.build_subtree(&leaves);
+        self.update_root_path(root_path);
+    }
+
     /// Removes a key from the proof map.
     ///
     /// # Examples
@@ -753,6 +1217,67 @@ where
         }
     }
 
+    /// Removes all entries with keys within the specified range.
+    ///
+    /// Values are removed as a single range, so that they are not loaded into the fork.
+    /// The Merkle Patricia tree is restructured in a single pass: each affected node
+    /// is updated once, rather than once per removed key. Note that the removed keys
+    /// still need to be read in order to locate the corresponding tree leaves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, ProofMapIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index: ProofMapIndex<_, u64, u8> = fork.get_proof_map("name");
+    /// for i in 0..10 {
+    ///     index.put(&i, i as u8);
+    /// }
+    ///
+    /// index.remove_range(2..8);
+    /// let keys: Vec<_> = index.keys().collect();
+    /// assert_eq!(keys, vec![0, 1, 8, 9]);
+    /// ```
+    pub fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<K>,
+    {
+        let start = match value_path_bound(range.start_bound()) {
+            Bound::Unbounded => Bound::Included(vec![VALUE_KEY_PREFIX]),
+            bound => bound,
+        };
+        let end = match value_path_bound(range.end_bound()) {
+            Bound::Unbounded => Bound::Excluded(vec![VALUE_KEY_PREFIX + 1]),
+            bound => bound,
+        };
+
+        let removed_paths: Vec<_> = self
+            .base
+            .iter_range::<_, _, [u8], ()>(
+                &VALUE_KEY_PREFIX,
+                bound_as_slice(&start),
+                bound_as_slice(&end),
+            )
+            .map(|(value_path, ())| {
+                let key = K::from_value_path(&value_path);
+                KeyMode::transform_key(key.borrow())
+            })
+            .collect();
+        if removed_paths.is_empty() {
+            return;
+        }
+
+        self.base
+            .remove_range::<[u8]>(bound_as_slice(&start), bound_as_slice(&end));
+        let root_path = self.get_root_path().expect("Map with entries has no root");
+        match self.remove_leaves(&root_path, Hash::zero(), removed_paths) {
+            Some((root_path, _)) => self.update_root_path(root_path),
+            None => self.state.unset(),
+        }
+    }
+
     /// Clears the proof map, removing all entries.
     ///
     /// # Notes
@@ -21,7 +21,7 @@ use smallvec::{smallvec, SmallVec};
 
 use exonum_crypto::{self, Hash, HASH_SIZE};
 
-use super::key::{ChildKind, ProofPath, PROOF_PATH_SIZE};
+use super::key::{ChildKind, ProofPath, ProofPathExt, PROOF_PATH_SIZE};
 use crate::{BinaryKey, BinaryValue, HashTag, ObjectHash};
 
 const BRANCH_NODE_SIZE: usize = 2 * (HASH_SIZE + PROOF_PATH_SIZE);
@@ -65,6 +65,15 @@ impl BranchNode {
         ProofPath::read(&self.raw[from..from + PROOF_PATH_SIZE])
     }
 
+    /// Same as `child_path`, but returns `None` if the stored path is malformed.
+    pub(crate) fn checked_child_path(&self, kind: ChildKind) -> Option<ProofPath> {
+        let from = match kind {
+            ChildKind::Right => 2 * HASH_SIZE + PROOF_PATH_SIZE,
+            ChildKind::Left => 2 * HASH_SIZE,
+        };
+        ProofPath::read_checked(&self.raw[from..from + PROOF_PATH_SIZE])
+    }
+
     pub(crate) fn set_child_path(&mut self, kind: ChildKind, prefix: &ProofPath) {
         let from = match kind {
             ChildKind::Right => 2 * HASH_SIZE + PROOF_PATH_SIZE,
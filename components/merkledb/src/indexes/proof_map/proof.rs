@@ -14,117 +14,19 @@
 
 pub use crate::ValidationError; // TODO Change for a type alias after EJB switching to rust > 1.36 (ECR-3827)
 
+pub use exonum_proofs::MapProofError;
+
 use exonum_crypto::Hash;
-use failure::Fail;
-use serde::{Deserializer, Serializer};
+use exonum_proofs::internal::{restore_map_root, PathRange};
 use serde_derive::{Deserialize, Serialize};
 
-use std::{borrow::Cow, marker::PhantomData};
+use std::{marker::PhantomData, ops::Bound};
 
-use super::{
-    key::{BitsRange, ChildKind, ProofPath, KEY_SIZE},
-    node::BranchNode,
+use super::key::{Hashed, ProofPath, Raw, ToProofPath};
+use crate::{
+    hash::{from_proofs_hash, to_proofs_hash},
+    BinaryValue, HashTag,
 };
-use crate::{BinaryValue, HashTag, ObjectHash};
-
-use crate::indexes::proof_map::key::{Hashed, ToProofPath};
-
-impl serde::Serialize for ProofPath {
-    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut repr = String::with_capacity(KEY_SIZE * 8);
-        for index in 0..self.len() {
-            match self.bit(index) {
-                ChildKind::Left => {
-                    repr.push('0');
-                }
-                ChildKind::Right => {
-                    repr.push('1');
-                }
-            }
-        }
-        ser.serialize_str(&repr)
-    }
-}
-
-#[allow(clippy::use_self)]
-impl<'de> serde::Deserialize<'de> for ProofPath {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        use serde::de::{self, Unexpected, Visitor};
-        use std::fmt;
-
-        struct ProofPathVisitor;
-
-        impl<'de> Visitor<'de> for ProofPathVisitor {
-            type Value = ProofPath;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-                write!(
-                    formatter,
-                    "binary string with length between 1 and {}",
-                    KEY_SIZE * 8
-                )
-            }
-
-            fn visit_str<E>(self, value: &str) -> Result<ProofPath, E>
-            where
-                E: de::Error,
-            {
-                let len = value.len();
-                if len == 0 || len > 8 * KEY_SIZE {
-                    return Err(de::Error::invalid_value(Unexpected::Str(value), &self));
-                }
-
-                let mut bytes = [0_u8; KEY_SIZE];
-                for (i, ch) in value.chars().enumerate() {
-                    match ch {
-                        '0' => {}
-                        '1' => bytes[i / 8] += 1 << (i % 8),
-                        _ => return Err(de::Error::invalid_value(Unexpected::Str(value), &self)),
-                    }
-                }
-
-                Ok(if len == 8 * KEY_SIZE {
-                    ProofPath::from_bytes(&bytes)
-                } else {
-                    ProofPath::from_bytes(&bytes).prefix(len as u16)
-                })
-            }
-        }
-
-        deserializer.deserialize_str(ProofPathVisitor)
-    }
-}
-
-/// An error returned when a map proof is invalid.
-#[derive(Debug, Fail)]
-pub enum MapProofError {
-    /// Non-terminal node for a map consisting of a single node.
-    #[fail(display = "non-terminal node as a single key in proof")]
-    NonTerminalNode(ProofPath),
-
-    /// One path in the proof is a prefix of another path.
-    #[fail(display = "embedded paths in proof")]
-    EmbeddedPaths {
-        /// Prefix key.
-        prefix: ProofPath,
-        /// Key containing the prefix.
-        path: ProofPath,
-    },
-
-    /// One path is mentioned several times in the proof.
-    #[fail(display = "duplicate path in proof")]
-    DuplicatePath(ProofPath),
-
-    /// Entries in the proof are not ordered by increasing path.
-    #[fail(display = "invalid path ordering")]
-    InvalidOrdering(ProofPath, ProofPath),
-}
 
 // Used instead of `(ProofPath, Hash)` only for the purpose of clearer (de)serialization.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -287,95 +189,6 @@ pub struct CheckedMapProof<'a, K, V> {
     hash: Hash,
 }
 
-/// Computes the root hash of the Merkle Patricia tree backing the specified entries
-/// in the map view.
-///
-/// The tree is not restored in full; instead, we add the paths to
-/// the tree in their lexicographic order (i.e., according to the `PartialOrd` implementation
-/// of `ProofPath`) and keep track of the rightmost nodes (the right contour) of the tree.
-/// It is easy to see that adding paths in the lexicographic order means that only
-/// the nodes in the right contour may be updated on each step. Further, on each step
-/// zero or more nodes are evicted from the contour, and a single new node is
-/// added to it.
-///
-/// `entries` are assumed to be sorted by the path in increasing order.
-fn collect(entries: &[Cow<'_, MapProofEntry>]) -> Result<Hash, MapProofError> {
-    fn common_prefix(x: &ProofPath, y: &ProofPath) -> ProofPath {
-        x.prefix(x.common_prefix_len(y))
-    }
-
-    fn hash_branch(left_child: &MapProofEntry, right_child: &MapProofEntry) -> Hash {
-        let mut branch = BranchNode::empty();
-        branch.set_child(ChildKind::Left, &left_child.path, &left_child.hash);
-        branch.set_child(ChildKind::Right, &right_child.path, &right_child.hash);
-        branch.object_hash()
-    }
-
-    /// Folds two last entries in a contour and replaces them with the folded entry.
-    ///
-    /// Returns an updated common prefix between two last entries in the contour.
-    fn fold(contour: &mut Vec<MapProofEntry>, last_prefix: ProofPath) -> Option<ProofPath> {
-        let last_entry = contour.pop().unwrap();
-        let penultimate_entry = contour.pop().unwrap();
-
-        contour.push(MapProofEntry {
-            path: last_prefix,
-            hash: hash_branch(&penultimate_entry, &last_entry),
-        });
-
-        if contour.len() > 1 {
-            let penultimate_entry = contour[contour.len() - 2];
-            Some(common_prefix(&penultimate_entry.path, &last_prefix))
-        } else {
-            None
-        }
-    }
-
-    match entries.len() {
-        0 => Ok(Hash::default()),
-
-        1 => {
-            if entries[0].path.is_leaf() {
-                Ok(HashTag::hash_single_entry_map(
-                    &entries[0].path,
-                    &entries[0].hash,
-                ))
-            } else {
-                Err(MapProofError::NonTerminalNode(entries[0].path))
-            }
-        }
-
-        _ => {
-            let (first_entry, second_entry) = (&entries[0], &entries[1]);
-            let mut contour: Vec<MapProofEntry> = vec![**first_entry, **second_entry];
-            // invariant: equal to the common prefix of the 2 last nodes in the contour
-            let mut last_prefix = common_prefix(&first_entry.path, &second_entry.path);
-
-            for entry in entries.iter().skip(2) {
-                let new_prefix = common_prefix(&contour.last().unwrap().path, &entry.path);
-                let new_prefix_len = new_prefix.len();
-
-                while contour.len() > 1 && new_prefix_len < last_prefix.len() {
-                    if let Some(prefix) = fold(&mut contour, last_prefix) {
-                        last_prefix = prefix;
-                    }
-                }
-
-                contour.push(**entry);
-                last_prefix = new_prefix;
-            }
-
-            while contour.len() > 1 {
-                if let Some(prefix) = fold(&mut contour, last_prefix) {
-                    last_prefix = prefix;
-                }
-            }
-
-            Ok(contour[0].hash)
-        }
-    }
-}
-
 impl<K, V, KeyMode> MapProof<K, V, KeyMode> {
     /// Provides access to the proof part of the view. Useful mainly for debug purposes.
     pub fn proof_unchecked(&self) -> Vec<(ProofPath, Hash)> {
@@ -453,64 +266,6 @@ where
     V: BinaryValue,
     KeyMode: ToProofPath<K>,
 {
-    fn precheck(&self) -> Result<(), MapProofError> {
-        use self::MapProofError::*;
-        use std::cmp::Ordering;
-
-        // Check that entries in `proof` are in increasing order.
-        for w in self.proof.windows(2) {
-            let (prev_path, path) = (&w[0].path, &w[1].path);
-            match prev_path.partial_cmp(path) {
-                Some(Ordering::Less) => {
-                    if path.starts_with(prev_path) {
-                        return Err(EmbeddedPaths {
-                            prefix: *prev_path,
-                            path: *path,
-                        });
-                    }
-                }
-                Some(Ordering::Equal) => {
-                    return Err(DuplicatePath(*path));
-                }
-                Some(Ordering::Greater) => {
-                    return Err(InvalidOrdering(*prev_path, *path));
-                }
-                None => unreachable!("Incomparable keys in proof"),
-            }
-        }
-
-        // Check that no entry has a prefix among the paths in the proof entries.
-        // In order to do this, it suffices to locate the closest smaller path in the proof entries
-        // and check only it.
-        for e in &self.entries {
-            let path = KeyMode::transform_key(e.key());
-
-            match self.proof.binary_search_by(|pe| {
-                pe.path
-                    .partial_cmp(&path)
-                    .expect("Incomparable paths in proof")
-            }) {
-                Ok(_) => {
-                    return Err(DuplicatePath(path));
-                }
-
-                Err(index) if index > 0 => {
-                    let prev_path = &self.proof[index - 1].path;
-                    if path.starts_with(prev_path) {
-                        return Err(EmbeddedPaths {
-                            prefix: *prev_path,
-                            path,
-                        });
-                    }
-                }
-
-                _ => {}
-            }
-        }
-
-        Ok(())
-    }
-
     /// Checks this proof.
     ///
     /// ## Errors
@@ -541,40 +296,25 @@ where
     ///
     /// [`ProofMapIndex`]: struct.ProofMapIndex.html
     pub fn check(&self) -> Result<CheckedMapProof<'_, K, V>, MapProofError> {
-        self.precheck()?;
-
-        let mut proof: Vec<_> = self.proof.iter().map(Cow::Borrowed).collect();
-        proof.extend(self.entries.iter().filter_map(|e| {
-            e.as_kv().map(|(k, v)| {
-                Cow::Owned(MapProofEntry {
-                    path: KeyMode::transform_key(&k),
-                    hash: HashTag::hash_leaf(&v.to_bytes()),
-                })
+        let proof: Vec<_> = self
+            .proof
+            .iter()
+            .map(|entry| (entry.path, to_proofs_hash(&entry.hash)))
+            .collect();
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry.as_tuple();
+                let hash =
+                    value.map(|value| to_proofs_hash(&HashTag::hash_leaf(&value.to_bytes())));
+                (KeyMode::transform_key(key), hash)
             })
-        }));
-
-        // Rust docs state that in the case `self.proof` and `self.entries` are sorted
-        // (which is the case for `MapProof`s returned by `ProofMapIndex.get_proof()`),
-        // the sort is performed very quickly.
-        proof.sort_unstable_by(|x, y| {
-            x.path.partial_cmp(&y.path).expect(
-                "Incorrectly formed paths supplied to MapProof; \
-                 paths should have `start` field set to 0",
-            )
-        });
-
-        // This check is required as duplicate paths can be introduced by entries
-        // (further, it's generally possible that two different entry keys lead to the same
-        // `ProofPath`).
-        for window in proof.windows(2) {
-            if window[0].path == window[1].path {
-                return Err(MapProofError::DuplicatePath(window[0].path));
-            }
-        }
+            .collect();
 
-        collect(&proof).map(|root_hash| CheckedMapProof {
+        restore_map_root(&proof, &entries).map(|root_hash| CheckedMapProof {
             entries: &self.entries,
-            hash: HashTag::hash_map_node(root_hash),
+            hash: HashTag::hash_map_node(from_proofs_hash(root_hash)),
         })
     }
 
@@ -618,3 +358,194 @@ impl<'a, K, V> CheckedMapProof<'a, K, V> {
         self.hash
     }
 }
+
+/// Bound of the key range in a `MapRangeProof`.
+// Used instead of `Bound<K>` only for the purpose of clearer (de)serialization.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum KeyBound<K> {
+    Included(K),
+    Excluded(K),
+    Unbounded,
+}
+
+impl<K: Clone> KeyBound<K> {
+    fn from_bound(bound: Bound<&K>) -> Self {
+        match bound {
+            Bound::Included(key) => KeyBound::Included(key.clone()),
+            Bound::Excluded(key) => KeyBound::Excluded(key.clone()),
+            Bound::Unbounded => KeyBound::Unbounded,
+        }
+    }
+}
+
+impl<K> KeyBound<K> {
+    fn as_bound(&self) -> Bound<&K> {
+        match self {
+            KeyBound::Included(key) => Bound::Included(key),
+            KeyBound::Excluded(key) => Bound::Excluded(key),
+            KeyBound::Unbounded => Bound::Unbounded,
+        }
+    }
+}
+
+/// Creates a range of leaf `ProofPath`s corresponding to the specified range of `Raw` keys.
+pub(super) fn path_range<K>(start: Bound<&K>, end: Bound<&K>) -> PathRange
+where
+    Raw: ToProofPath<K>,
+{
+    fn to_path<K>(bound: Bound<&K>) -> Bound<ProofPath>
+    where
+        Raw: ToProofPath<K>,
+    {
+        match bound {
+            Bound::Included(key) => Bound::Included(Raw::transform_key(key)),
+            Bound::Excluded(key) => Bound::Excluded(Raw::transform_key(key)),
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+
+    PathRange::new(to_path(start), to_path(end))
+}
+
+/// Proof of *all* entries of a `RawProofMapIndex` with keys within a certain range.
+///
+/// Unlike [`MapProof`], which proves presence or absence of specific keys, `MapRangeProof`
+/// asserts that the index contains no entries in the range other than the ones
+/// listed in the proof. This allows to use the proof for paginated, verifiable reads
+/// of the index.
+///
+/// # Key ordering
+///
+/// Ranges are defined with respect to the ordering of leaves in the Merkle Patricia tree
+/// backing the index. Keys are compared as bit strings, with bits within each byte
+/// read starting from the least significant one. This ordering **differs** from
+/// the lexicographic ordering of key bytes used by index iterators. For example,
+/// a key starting with byte `0x01` is greater than a key starting with `0x02`.
+///
+/// # Workflow
+///
+/// Range proofs are created with the [`get_range_proof()`] method of `RawProofMapIndex`
+/// and are verified with [`check()`], which returns a [`CheckedMapProof`].
+///
+/// ```
+/// # use exonum_merkledb::{access::AccessExt, Database, TemporaryDB, ObjectHash};
+/// # use exonum_crypto::Hash;
+/// # use failure::Error;
+/// # fn main() -> Result<(), Error> {
+/// let fork = { let db = TemporaryDB::new(); db.fork() };
+/// let mut map = fork.get_raw_proof_map("index");
+/// let keys: Vec<_> = (0_u8..8).map(|i| Hash::new([i; 32])).collect();
+/// for (i, key) in keys.iter().enumerate() {
+///     map.put(key, i as u64);
+/// }
+///
+/// // Prove all entries with keys from `keys[0]` up to `keys[1]` (exclusive).
+/// // Per the ordering described above, these are keys with an even first byte.
+/// let proof = map.get_range_proof(keys[0]..keys[1]);
+/// let checked_proof = proof.check_against_hash(map.object_hash())?;
+/// assert!(checked_proof
+///     .entries()
+///     .eq(vec![(&keys[0], &0), (&keys[4], &4), (&keys[2], &2), (&keys[6], &6)]));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # JSON serialization
+///
+/// `MapRangeProof` is serialized to JSON in the same way as [`MapProof`] with
+/// 2 additional fields:
+///
+/// - `start` is the lower bound of the range, which can be `{ included: K }`,
+///   `{ excluded: K }` or `"unbounded"`.
+/// - `end` is the upper bound of the range, with the same possible values.
+///
+/// [`MapProof`]: struct.MapProof.html
+/// [`CheckedMapProof`]: struct.CheckedMapProof.html
+/// [`get_range_proof()`]: struct.ProofMapIndex.html#method.get_range_proof
+/// [`check()`]: #method.check
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MapRangeProof<K, V> {
+    start: KeyBound<K>,
+    end: KeyBound<K>,
+    #[serde(flatten)]
+    inner: MapProof<K, V, Raw>,
+}
+
+impl<K, V> MapRangeProof<K, V> {
+    /// Creates a new range proof from the range bounds and the underlying proof.
+    pub(crate) fn new(start: Bound<&K>, end: Bound<&K>, inner: MapProof<K, V, Raw>) -> Self
+    where
+        K: Clone,
+    {
+        Self {
+            start: KeyBound::from_bound(start),
+            end: KeyBound::from_bound(end),
+            inner,
+        }
+    }
+
+    /// Returns the bounds of the range asserted by the proof. This method does not perform
+    /// any integrity checks of the proof.
+    pub fn bounds_unchecked(&self) -> (Bound<&K>, Bound<&K>) {
+        (self.start.as_bound(), self.end.as_bound())
+    }
+
+    /// Returns the underlying `MapProof`. The returned proof does not assert completeness
+    /// of entries in the range.
+    pub fn as_map_proof(&self) -> &MapProof<K, V, Raw> {
+        &self.inner
+    }
+}
+
+impl<K, V> MapRangeProof<K, V>
+where
+    V: BinaryValue,
+    Raw: ToProofPath<K>,
+{
+    /// Checks this proof.
+    ///
+    /// ## Errors
+    ///
+    /// Apart from the checks performed by [`MapProof::check()`], the following conditions
+    /// are verified:
+    ///
+    /// - All entries in the proof are present in the map and lie within the range.
+    /// - No hashed subtree in `proof` may contain keys within the range.
+    ///
+    /// [`MapProof::check()`]: struct.MapProof.html#method.check
+    pub fn check(&self) -> Result<CheckedMapProof<'_, K, V>, MapProofError> {
+        let range = path_range(self.start.as_bound(), self.end.as_bound());
+
+        for entry in &self.inner.entries {
+            let path = Raw::transform_key(entry.key());
+            if entry.as_missing().is_some() || !range.contains(&path) {
+                return Err(MapProofError::InvalidRangeEntry(path));
+            }
+        }
+        for entry in &self.inner.proof {
+            if !range.is_disjoint(&entry.path) {
+                return Err(MapProofError::IncompleteRange(entry.path));
+            }
+        }
+
+        self.inner.check()
+    }
+
+    /// Checks this proof against a trusted map hash. Fails if the proof is malformed or the
+    /// hash does not match the one computed from the proof.
+    pub fn check_against_hash(
+        &self,
+        expected_map_hash: Hash,
+    ) -> Result<CheckedMapProof<'_, K, V>, ValidationError<MapProofError>> {
+        self.check()
+            .map_err(ValidationError::Malformed)
+            .and_then(|checked| {
+                if checked.index_hash() == expected_map_hash {
+                    Ok(checked)
+                } else {
+                    Err(ValidationError::UnmatchedRootHash)
+                }
+            })
+    }
+}
@@ -1,14 +1,16 @@
 //! Building `MapProof`s. See README.md in the module directory for high-level explanation
 //! how the proofs are built.
 
-use std::borrow::Borrow;
+use std::{borrow::Borrow, ops::Bound};
 
 use exonum_crypto::Hash;
+use exonum_proofs::internal::PathRange;
 
 use super::{
     key::{BitsRange, ChildKind, ProofPath},
     node::{BranchNode, Node},
-    MapProof, ToProofPath,
+    proof::path_range,
+    MapProof, MapRangeProof, Raw, ToProofPath,
 };
 use crate::BinaryKey;
 
@@ -81,6 +83,57 @@ impl<K, V, KeyMode> MapProof<K, V, KeyMode> {
     }
 }
 
+impl<K, V> MapProof<K, V, Raw>
+where
+    K: BinaryKey<Owned = K>,
+{
+    /// Includes a child of a branch node into a range proof. Subtrees disjoint with
+    /// the range are added as hashes, leaves within the range as entries, and other
+    /// subtrees are processed recursively.
+    fn process_range_child(
+        self,
+        tree: &impl MerklePatriciaTree<K, V>,
+        range: &PathRange,
+        path: ProofPath,
+        hash: Hash,
+    ) -> Self {
+        if range.is_disjoint(&path) {
+            self.add_proof_entry(path, hash)
+        } else if path.is_leaf() {
+            // The `Raw` key transform is the identity, so the key can be restored
+            // from the path.
+            let key = K::read(path.raw_key());
+            let value = tree.value(&key);
+            self.add_entry(key, value)
+        } else {
+            match tree.node(&path) {
+                Node::Branch(branch) => self.process_range_branch(tree, range, &branch),
+                Node::Leaf(_) => unreachable!("Leaf node with a branch path"),
+            }
+        }
+    }
+
+    fn process_range_branch(
+        self,
+        tree: &impl MerklePatriciaTree<K, V>,
+        range: &PathRange,
+        branch: &BranchNode,
+    ) -> Self {
+        self.process_range_child(
+            tree,
+            range,
+            branch.child_path(ChildKind::Left),
+            branch.child_hash(ChildKind::Left),
+        )
+        .process_range_child(
+            tree,
+            range,
+            branch.child_path(ChildKind::Right),
+            branch.child_hash(ChildKind::Right),
+        )
+    }
+}
+
 /// Encapsulation of a Merkle Patricia tree allowing to access its terminal and intermediate
 /// nodes.
 pub trait MerklePatriciaTree<K: ?Sized, V> {
@@ -321,3 +374,37 @@ where
         }
     }
 }
+
+/// Builds proofs for contiguous ranges of keys in a Merkelized map with `Raw` keys.
+///
+/// This is an extension trait to [`MerklePatriciaTree`] similar to [`BuildProof`].
+///
+/// [`MerklePatriciaTree`]: trait.MerklePatriciaTree.html
+/// [`BuildProof`]: trait.BuildProof.html
+pub trait BuildRangeProof<K, V> {
+    /// Creates a proof of all entries with keys within the specified bounds.
+    fn create_range_proof(&self, start: Bound<&K>, end: Bound<&K>) -> MapRangeProof<K, V>;
+}
+
+impl<K, V, T> BuildRangeProof<K, V> for T
+where
+    K: BinaryKey<Owned = K> + Clone,
+    T: MerklePatriciaTree<K, V>,
+    Raw: ToProofPath<K>,
+{
+    fn create_range_proof(&self, start: Bound<&K>, end: Bound<&K>) -> MapRangeProof<K, V> {
+        let range = path_range(start, end);
+        let proof = match self.root_node() {
+            // Children of the root node are always visited; otherwise, the proof
+            // would consist of a single non-terminal node.
+            Some((_, Node::Branch(root_branch))) => {
+                MapProof::new().process_range_branch(self, &range, &root_branch)
+            }
+            Some((root_path, Node::Leaf(root_hash))) => {
+                MapProof::new().process_range_child(self, &range, root_path, root_hash)
+            }
+            None => MapProof::new(),
+        };
+        MapRangeProof::new(start, end, proof)
+    }
+}
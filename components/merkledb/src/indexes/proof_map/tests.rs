@@ -24,12 +24,14 @@ use rand::{
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{self, json};
 
-use std::{cmp, collections::HashSet, fmt::Debug, hash::Hash as StdHash, marker::PhantomData};
+use std::{
+    cmp, collections::HashSet, fmt::Debug, hash::Hash as StdHash, marker::PhantomData, ops::Bound,
+};
 
 use super::{
     key::{BitsRange, ChildKind, KEY_SIZE, LEAF_KEY_PREFIX},
     node::BranchNode,
-    MapProof, MapProofError, ProofPath,
+    MapProof, MapProofError, MapRangeProof, ProofPath,
 };
 use crate::{
     access::AccessExt,
@@ -993,6 +995,104 @@ where
             Vec::<u8>::new()
         );
     }
+
+    fn test_bulk_load() {
+        let db = TemporaryDB::default();
+        let mut data = generate_random_data(300);
+        // Add entries with duplicate keys; the last value for a key should win.
+        let duplicates: Vec<_> = data[..10]
+            .iter()
+            .map(|(key, _)| (*key, vec![1, 2, 3]))
+            .collect();
+        data.extend(duplicates);
+
+        let fork = db.fork();
+        let mut index = fork.get_generic_proof_map::<_, [u8; 32], Vec<u8>, S>(IDX_NAME);
+        index.bulk_load(data.clone());
+        let mut reference = fork.get_generic_proof_map::<_, _, _, S>("reference");
+        for (key, value) in &data {
+            reference.put(key, value.clone());
+        }
+
+        assert_eq!(index.object_hash(), reference.object_hash());
+        assert_eq!(
+            index.iter().collect::<Vec<_>>(),
+            reference.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(index.get(&data[0].0), Some(vec![1, 2, 3]));
+        let mut report = vec![];
+        assert_eq!(
+            index.check_integrity(&mut report),
+            Some(index.merkle_root())
+        );
+        assert!(report.is_empty());
+
+        let key = data[42].0;
+        check_map_proof(&index.get_proof(key), Some(key), &index);
+
+        // Loading entries into a non-empty map falls back to sequential insertion.
+        let extra_data = generate_random_data(50);
+        index.bulk_load(extra_data.iter().map(|(key, value)| (key, value.clone())));
+        for (key, value) in &extra_data {
+            reference.put(key, value.clone());
+        }
+        assert_eq!(index.object_hash(), reference.object_hash());
+
+        // Single-entry and empty maps.
+        let mut index = fork.get_generic_proof_map::<_, [u8; 32], Vec<u8>, S>("single");
+        index.bulk_load(Vec::<([u8; 32], Vec<u8>)>::new());
+        assert_eq!(index.object_hash(), HashTag::empty_map_hash());
+        index.bulk_load(data[..1].to_vec());
+        let mut reference = fork.get_generic_proof_map::<_, _, _, S>("single_reference");
+        reference.put(&data[0].0, data[0].1.clone());
+        assert_eq!(index.object_hash(), reference.object_hash());
+    }
+
+    fn test_remove_range() {
+        let db = TemporaryDB::default();
+        let mut data = generate_random_data(200);
+        data.sort_by(|x, y| x.0.cmp(&y.0));
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..10 {
+            let start = rng.gen_range(0, data.len());
+            let end = rng.gen_range(start, data.len());
+            let fork = db.fork();
+            let mut index = fork.get_generic_proof_map::<_, _, _, S>(IDX_NAME);
+            for item in &data {
+                index.put(&item.0, item.1.clone());
+            }
+            index.remove_range(data[start].0..data[end].0);
+
+            let reference_fork = db.fork();
+            let mut reference = reference_fork.get_generic_proof_map::<_, _, _, S>("reference");
+            for item in data[..start].iter().chain(&data[end..]) {
+                reference.put(&item.0, item.1.clone());
+            }
+            assert_eq!(index.object_hash(), reference.object_hash());
+            assert_eq!(
+                index.keys().collect::<Vec<_>>(),
+                reference.keys().collect::<Vec<_>>()
+            );
+
+            let removed_key = data[start].0;
+            let proof = index.get_proof(removed_key);
+            if start == end {
+                check_map_proof(&proof, Some(removed_key), &index);
+            } else {
+                check_map_proof(&proof, None, &index);
+            }
+        }
+
+        let fork = db.fork();
+        let mut index = fork.get_generic_proof_map::<_, _, _, S>(IDX_NAME);
+        for item in &data {
+            index.put(&item.0, item.1.clone());
+        }
+        index.remove_range(..);
+        assert_eq!(index.merkle_root(), Hash::zero());
+        assert_eq!(index.keys().count(), 0);
+    }
 }
 
 #[test]
@@ -1140,6 +1240,26 @@ fn test_fuzz_delete_hashed() {
     ProofMapTester::<Hashed>::test_fuzz_delete()
 }
 
+#[test]
+fn test_bulk_load_raw() {
+    ProofMapTester::<Raw>::test_bulk_load()
+}
+
+#[test]
+fn test_bulk_load_hashed() {
+    ProofMapTester::<Hashed>::test_bulk_load()
+}
+
+#[test]
+fn test_remove_range_raw() {
+    ProofMapTester::<Raw>::test_remove_range()
+}
+
+#[test]
+fn test_remove_range_hashed() {
+    ProofMapTester::<Hashed>::test_remove_range()
+}
+
 #[test]
 fn test_fuzz_insert_after_delete_raw() {
     ProofMapTester::<Raw>::test_fuzz_insert_after_delete()
@@ -1160,9 +1280,7 @@ fn test_iter_hashed() {
     ProofMapTester::<Hashed>::test_iter()
 }
 
-#[test]
-fn index_as_iterator() {
-    let db = TemporaryDB::default();
+fn index_as_iterator(db: &dyn Database) {
     let fork = db.fork();
     let mut map = fork.get_proof_map(IDX_NAME);
     map.put(&1_u8, 1_u8);
@@ -1221,9 +1339,7 @@ fn test_build_proof_in_single_node_tree_hashed() {
     ProofMapTester::<Hashed>::test_build_proof_in_single_node_tree()
 }
 
-#[test]
-fn test_insert_same_key() {
-    let db = TemporaryDB::default();
+fn test_insert_same_key(db: &dyn Database) {
     let fork = db.fork();
     let mut table = fork.get_raw_proof_map(IDX_NAME);
     assert_eq!(table.object_hash(), HashTag::empty_map_hash());
@@ -1240,9 +1356,7 @@ fn test_insert_same_key() {
     assert_eq!(table.object_hash(), HashTag::hash_map_node(hash));
 }
 
-#[test]
-fn test_merkle_root_leaf() {
-    let db = TemporaryDB::default();
+fn test_merkle_root_leaf(db: &dyn Database) {
     let fork = db.fork();
     let mut index = fork.get_proof_map(IDX_NAME);
 
@@ -1510,9 +1624,147 @@ fn test_invalid_map_proofs() {
     }
 }
 
-#[test]
-fn test_fuzz_insert_build_proofs_in_table_filled_with_hashes() {
-    let db = TemporaryDB::default();
+fn path_in_range(path: &ProofPath, start: Bound<&[u8; 32]>, end: Bound<&[u8; 32]>) -> bool {
+    use std::cmp::Ordering;
+
+    let cmp = |key: &[u8; 32]| path.partial_cmp(&Raw::transform_key(key)).unwrap();
+    let after_start = match start {
+        Bound::Included(key) => cmp(key) != Ordering::Less,
+        Bound::Excluded(key) => cmp(key) == Ordering::Greater,
+        Bound::Unbounded => true,
+    };
+    let before_end = match end {
+        Bound::Included(key) => cmp(key) != Ordering::Greater,
+        Bound::Excluded(key) => cmp(key) == Ordering::Less,
+        Bound::Unbounded => true,
+    };
+    after_start && before_end
+}
+
+fn check_map_range_proof(
+    table: &ProofMapIndex<&Fork, [u8; 32], Vec<u8>, Raw>,
+    start: Bound<&[u8; 32]>,
+    end: Bound<&[u8; 32]>,
+) {
+    let mut expected_entries: Vec<_> = table
+        .iter()
+        .filter(|(key, _)| path_in_range(&Raw::transform_key(key), start, end))
+        .collect();
+    expected_entries.sort_unstable_by(|(x, _), (y, _)| {
+        Raw::transform_key(x)
+            .partial_cmp(&Raw::transform_key(y))
+            .unwrap()
+    });
+
+    let proof = table.get_range_proof((start, end));
+    let checked_proof = proof.check_against_hash(table.object_hash()).unwrap();
+    assert!(checked_proof.missing_keys().next().is_none());
+    assert_eq!(
+        checked_proof.entries().collect::<Vec<_>>(),
+        expected_entries
+            .iter()
+            .map(|(k, v)| (k, v))
+            .collect::<Vec<_>>()
+    );
+
+    let serialized_proof = serde_json::to_value(&proof).unwrap();
+    let deserialized_proof: MapRangeProof<[u8; 32], Vec<u8>> =
+        serde_json::from_value(serialized_proof).unwrap();
+    assert_eq!(deserialized_proof, proof);
+}
+
+fn test_range_proofs_in_empty_and_single_node_tree(db: &dyn Database) {
+    let fork = db.fork();
+    let mut table = fork.get_raw_proof_map(IDX_NAME);
+
+    check_map_range_proof(&table, Bound::Unbounded, Bound::Unbounded);
+    check_map_range_proof(&table, Bound::Included(&[1; 32]), Bound::Excluded(&[2; 32]));
+
+    table.put(&[1; 32], vec![1]);
+    check_map_range_proof(&table, Bound::Unbounded, Bound::Unbounded);
+    check_map_range_proof(&table, Bound::Included(&[1; 32]), Bound::Included(&[1; 32]));
+    check_map_range_proof(&table, Bound::Excluded(&[1; 32]), Bound::Unbounded);
+    check_map_range_proof(&table, Bound::Unbounded, Bound::Excluded(&[1; 32]));
+}
+
+fn test_fuzz_range_proofs(db: &dyn Database) {
+    const SAMPLE_SIZE: usize = 200;
+
+    let fork = db.fork();
+    let mut table = fork.get_raw_proof_map(IDX_NAME);
+    let data = generate_random_data(SAMPLE_SIZE);
+    for (key, value) in &data {
+        table.put(key, value.clone());
+    }
+
+    let mut rng = thread_rng();
+    let random_key = |rng: &mut rand::rngs::ThreadRng| {
+        if rng.gen::<bool>() {
+            data.choose(rng).unwrap().0
+        } else {
+            let mut key = [0; 32];
+            rng.fill_bytes(&mut key);
+            key
+        }
+    };
+
+    for _ in 0..100 {
+        let (start_key, end_key) = (random_key(&mut rng), random_key(&mut rng));
+        let random_bound = |key, rng: &mut rand::rngs::ThreadRng| match rng.gen_range(0, 3) {
+            0 => Bound::Included(key),
+            1 => Bound::Excluded(key),
+            _ => Bound::Unbounded,
+        };
+        let start = random_bound(&start_key, &mut rng);
+        let end = random_bound(&end_key, &mut rng);
+        check_map_range_proof(&table, start, end);
+    }
+}
+
+fn test_invalid_map_range_proofs(db: &dyn Database) {
+    use self::MapProofError::*;
+
+    let fork = db.fork();
+    let mut table = fork.get_raw_proof_map(IDX_NAME);
+    for i in 0_u8..16 {
+        table.put(&[i; 32], vec![i]);
+    }
+
+    // Narrow the range of a proof covering the entire map.
+    let proof = table.get_range_proof(..);
+    let mut json = serde_json::to_value(&proof).unwrap();
+    let start_key = [3_u8; 32];
+    json["start"] = json!({ "included": start_key });
+    let proof: MapRangeProof<[u8; 32], Vec<u8>> = serde_json::from_value(json).unwrap();
+    match proof.check().unwrap_err() {
+        InvalidRangeEntry(..) => {}
+        e => panic!("expected invalid range entry error, got {}", e),
+    }
+
+    // Extend the range of a proof covering a single key.
+    let proof = table.get_range_proof([3; 32]..=[3; 32]);
+    assert_eq!(proof.check().unwrap().entries().count(), 1);
+    let mut json = serde_json::to_value(&proof).unwrap();
+    json["end"] = json!("unbounded");
+    let proof: MapRangeProof<[u8; 32], Vec<u8>> = serde_json::from_value(json).unwrap();
+    match proof.check().unwrap_err() {
+        IncompleteRange(..) => {}
+        e => panic!("expected incomplete range error, got {}", e),
+    }
+
+    // Missing keys are not allowed in range proofs.
+    let proof: MapRangeProof<[u8; 32], Vec<u8>> = MapRangeProof::new(
+        Bound::Unbounded,
+        Bound::Unbounded,
+        MapProof::new().add_missing([3; 32]),
+    );
+    match proof.check().unwrap_err() {
+        InvalidRangeEntry(..) => {}
+        e => panic!("expected invalid range entry error, got {}", e),
+    }
+}
+
+fn test_fuzz_insert_build_proofs_in_table_filled_with_hashes(db: &dyn Database) {
     let mut rng = thread_rng();
     let batch_sizes = (7..9).map(|x| 1 << x);
 
@@ -1531,13 +1783,11 @@ fn test_fuzz_insert_build_proofs_in_table_filled_with_hashes() {
                 .flat_map(|(key, val)| vec![hash(&key), hash(&val)])
                 .collect();
 
-        check_proofs_for_data(&db, &data, &nonexisting_keys);
+        check_proofs_for_data(db, &data, &nonexisting_keys);
     }
 }
 
-#[test]
-fn test_fuzz_insert_build_proofs() {
-    let db = TemporaryDB::default();
+fn test_fuzz_insert_build_proofs(db: &dyn Database) {
     let mut rng = thread_rng();
     let batch_sizes = (7..9).map(|x| (1 << x) - 1);
 
@@ -1552,13 +1802,11 @@ fn test_fuzz_insert_build_proofs() {
                 .map(|(key, _)| key)
                 .collect();
 
-        check_proofs_for_data(&db, &data, &nonexisting_keys);
+        check_proofs_for_data(db, &data, &nonexisting_keys);
     }
 }
 
-#[test]
-fn test_fuzz_insert_build_multiproofs() {
-    let db = TemporaryDB::default();
+fn test_fuzz_insert_build_multiproofs(db: &dyn Database) {
     let mut rng = thread_rng();
     let batch_sizes = (7..9).map(|x| 1 << x);
 
@@ -1573,14 +1821,12 @@ fn test_fuzz_insert_build_multiproofs() {
                 .map(|(key, _)| key)
                 .collect();
 
-        check_multiproofs_for_data(&db, &data, &nonexisting_keys);
+        check_multiproofs_for_data(db, &data, &nonexisting_keys);
     }
 }
 
-#[test]
-fn test_fuzz_delete_build_proofs() {
+fn test_fuzz_delete_build_proofs(db: &dyn Database) {
     const SAMPLE_SIZE: usize = 200;
-    let db = TemporaryDB::default();
 
     let mut rng = thread_rng();
     let mut exists_keys = HashSet::default();
@@ -1619,17 +1865,14 @@ fn test_fuzz_delete_build_proofs() {
     }
 }
 
-#[test]
-fn restore_after_no_op_initialization() {
-    let db = TemporaryDB::new();
+fn restore_after_no_op_initialization(db: &dyn Database) {
     let fork = db.fork();
     fork.get_proof_map::<_, Hash, u32>(IDX_NAME);
     let map = fork.readonly().get_proof_map::<_, Hash, u32>(IDX_NAME);
     assert_eq!(map.iter().count(), 0);
 }
 
-#[test]
-fn test_tree_with_hashed_key() {
+fn test_tree_with_hashed_key(db: &dyn Database) {
     use byteorder::{ByteOrder, LittleEndian};
     use exonum_crypto::Hash;
     use failure::{self, ensure};
@@ -1694,7 +1937,6 @@ fn test_tree_with_hashed_key() {
         HashTag::hash_map_node(HashTag::hash_single_entry_map(&key, &h))
     }
 
-    let db = TemporaryDB::default();
     let fork = db.fork();
     let mut table = fork.get_proof_map(IDX_NAME);
 
@@ -1750,9 +1992,7 @@ fn test_tree_with_hashed_key() {
     );
 }
 
-#[test]
-fn unsized_key() {
-    let db = TemporaryDB::default();
+fn unsized_key(db: &dyn Database) {
     let fork = db.fork();
     let mut table = fork.get_proof_map::<_, str, _>(IDX_NAME);
     table.put("key1", vec![1]);
@@ -1770,3 +2010,19 @@ fn unsized_key() {
 
     assert!(proof.check().is_ok());
 }
+
+backend_tests!(
+    index_as_iterator,
+    test_insert_same_key,
+    test_merkle_root_leaf,
+    test_range_proofs_in_empty_and_single_node_tree,
+    test_fuzz_range_proofs,
+    test_invalid_map_range_proofs,
+    test_fuzz_insert_build_proofs_in_table_filled_with_hashes,
+    test_fuzz_insert_build_proofs,
+    test_fuzz_insert_build_multiproofs,
+    test_fuzz_delete_build_proofs,
+    restore_after_no_op_initialization,
+    test_tree_with_hashed_key,
+    unsized_key,
+);
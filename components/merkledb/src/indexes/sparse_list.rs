@@ -83,10 +83,13 @@ pub struct SparseListIndex<T: RawAccess, V> {
 
 /// Returns an iterator over the items of a `SparseListIndex`.
 ///
-/// This struct is created by the [`iter`] method on [`SparseListIndex`].
-/// See its documentation for details.
+/// This struct is created by the [`iter`], [`iter_from`], [`iter_rev`] or [`iter_rev_from`]
+/// method on [`SparseListIndex`]. See its documentation for details.
 ///
 /// [`iter`]: struct.SparseListIndex.html#method.iter
+/// [`iter_from`]: struct.SparseListIndex.html#method.iter_from
+/// [`iter_rev`]: struct.SparseListIndex.html#method.iter_rev
+/// [`iter_rev_from`]: struct.SparseListIndex.html#method.iter_rev_from
 /// [`SparseListIndex`]: struct.SparseListIndex.html
 #[derive(Debug)]
 pub struct Iter<'a, V> {
@@ -333,6 +336,53 @@ where
             base_iter: self.base.iter_from(&(), &from),
         }
     }
+
+    /// Returns an iterator over the list elements in reverse order. The iterator element type
+    /// is (u64, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, SparseListIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_sparse_list("name");
+    ///
+    /// index.extend([1, 2, 3].iter().cloned());
+    /// index.remove(1);
+    ///
+    /// assert_eq!(index.iter_rev().collect::<Vec<(u64, i32)>>(), vec![(2, 3), (0, 1)]);
+    /// ```
+    pub fn iter_rev(&self) -> Iter<'_, V> {
+        Iter {
+            base_iter: self.base.iter_rev(&()),
+        }
+    }
+
+    /// Returns an iterator over the list elements in reverse order starting from the specified
+    /// position (inclusive). The iterator element type is (u64, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::AccessExt, TemporaryDB, Database, SparseListIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_sparse_list("name");
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    /// index.remove(2);
+    ///
+    /// let indexes: Vec<u64> = index.iter_rev_from(3).map(|(i, _)| i).collect();
+    /// assert_eq!(indexes, vec![3, 1, 0]);
+    /// ```
+    pub fn iter_rev_from(&self, from: u64) -> Iter<'_, V> {
+        Iter {
+            base_iter: self.base.iter_rev_from(&(), &from),
+        }
+    }
 }
 
 impl<T, V> SparseListIndex<T, V>
@@ -572,14 +622,11 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{access::AccessExt, db::Database, TemporaryDB};
+    use crate::{access::AccessExt, db::Database};
 
     const IDX_NAME: &str = "idx_name";
 
-    #[test]
-    #[allow(clippy::cognitive_complexity)]
-    fn test_list_index_methods() {
-        let db = TemporaryDB::default();
+    fn test_list_index_methods(db: &dyn Database) {
         let fork = db.fork();
         let mut list_index = fork.get_sparse_list(IDX_NAME);
 
@@ -647,9 +694,7 @@ mod tests {
         assert_eq!(0, list_index.len());
     }
 
-    #[test]
-    fn test_list_index_iter() {
-        let db = TemporaryDB::default();
+    fn test_list_index_iter(db: &dyn Database) {
         let fork = db.fork();
         let mut list_index = fork.get_sparse_list(IDX_NAME);
 
@@ -691,12 +736,17 @@ mod tests {
         assert_eq!(list_index.values().collect::<Vec<u8>>(), vec![1_u8, 2, 3]);
     }
 
-    #[test]
-    fn restore_after_no_op_initialization() {
-        let db = TemporaryDB::new();
+    fn restore_after_no_op_initialization(db: &dyn Database) {
         let fork = db.fork();
         fork.get_sparse_list::<_, u32>(IDX_NAME);
         let list = fork.readonly().get_sparse_list::<_, u32>(IDX_NAME);
         assert!(list.is_empty());
     }
+
+    backend_tests!(
+        #[allow(clippy::cognitive_complexity)]
+        test_list_index_methods,
+        test_list_index_iter,
+        restore_after_no_op_initialization,
+    );
 }
@@ -357,11 +357,9 @@ impl<'a> Iterator for Hashes<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{access::AccessExt, Database, ObjectHash, TemporaryDB};
+    use crate::{access::AccessExt, Database, ObjectHash};
 
-    #[test]
-    fn value_set_methods() {
-        let db = TemporaryDB::default();
+    fn value_set_methods(db: &dyn Database) {
         let fork = db.fork();
         let mut index = fork.get_value_set("index");
 
@@ -380,4 +378,6 @@ mod tests {
         index.clear();
         assert!(!index.contains(&2_u8));
     }
+
+    backend_tests!(value_set_methods,);
 }
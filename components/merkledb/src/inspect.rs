@@ -0,0 +1,250 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Low-level database inspection.
+//!
+//! [`indexes`] lists all indexes in the database, including system ones, based on the index
+//! metadata. The contents of an index can then be read without knowing the types of its
+//! keys and values: [`IndexContents`] yields raw keys and values of the index entries
+//! as they are encoded by the index, skipping auxiliary data such as Merkle tree nodes
+//! or secondary index entries.
+//!
+//! The module is intended for debugging and maintenance tools; e.g., it powers
+//! the `exonum-inspect` binary from the `exonum-cli` crate.
+//!
+//! [`indexes`]: fn.indexes.html
+//! [`IndexContents`]: struct.IndexContents.html
+//!
+//! # Examples
+//!
+//! ```
+//! # use exonum_merkledb::{access::AccessExt, inspect, Database, IndexType, TemporaryDB};
+//! let db = TemporaryDB::new();
+//! let fork = db.fork();
+//! fork.get_proof_list("list").extend(vec![1_u32, 2, 3]);
+//! fork.get_map(("map", &1_u8)).put(&1_u8, "foo".to_owned());
+//! db.merge(fork.into_patch()).unwrap();
+//!
+//! let snapshot = db.snapshot();
+//! let indexes = inspect::indexes(&*snapshot);
+//! let list = indexes.iter().find(|info| info.to_string() == "list").unwrap();
+//! assert_eq!(list.index_type(), IndexType::ProofList);
+//! let contents = list.contents(&*snapshot);
+//! // The list contains 3 values and 6 Merkle tree nodes.
+//! let stats = contents.stats();
+//! assert_eq!(stats.len, 3);
+//! assert_eq!(stats.raw_entries, 9);
+//!
+//! let map = indexes.iter().find(|info| info.to_string() == "map[01]").unwrap();
+//! let entries: Vec<_> = map.contents(&*snapshot).entries().collect();
+//! assert_eq!(entries, vec![(vec![1], b"foo".to_vec())]);
+//! ```
+
+use std::fmt;
+
+use crate::{
+    indexes::{indexed_map::VALUES_PREFIX, proof_map::VALUE_KEY_PREFIX},
+    views::{IndexMetadata, IndexType, IndexesPool, View, ViewWithMetadata},
+    IndexAddress, Snapshot, ValueCompression,
+};
+
+/// Information about an index stored in the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexInfo {
+    address: IndexAddress,
+    metadata: IndexMetadata,
+}
+
+impl IndexInfo {
+    /// Returns the address of the index.
+    pub fn address(&self) -> &IndexAddress {
+        &self.address
+    }
+
+    /// Returns the type of the index.
+    pub fn index_type(&self) -> IndexType {
+        self.metadata.index_type()
+    }
+
+    /// Returns the compression applied to the index values.
+    pub fn compression(&self) -> ValueCompression {
+        self.metadata.compression()
+    }
+
+    /// Checks if the index is a system index maintained by the database itself
+    /// (e.g., the state aggregator).
+    pub fn is_system(&self) -> bool {
+        self.address.is_system()
+    }
+
+    /// Checks if the index belongs to a migration.
+    pub fn is_in_migration(&self) -> bool {
+        self.address.in_migration()
+    }
+
+    /// Returns the contents of the index in the database state represented by `snapshot`.
+    /// The snapshot must be the one the index info was obtained from.
+    pub fn contents<'a>(&self, snapshot: &'a dyn Snapshot) -> IndexContents<'a> {
+        let view: View<_> =
+            ViewWithMetadata::from_metadata(snapshot, &self.address, self.metadata.clone()).into();
+        let (value_prefix, key_offset) = match self.index_type() {
+            // List values are stored at the zero height of the Merkle tree.
+            IndexType::ProofList => (vec![0], 0),
+            IndexType::ProofMap | IndexType::ProofKeySet => (vec![VALUE_KEY_PREFIX], 1),
+            // Secondary index entries are auxiliary data.
+            IndexType::IndexedMap => (vec![VALUES_PREFIX], 1),
+            _ => (vec![], 0),
+        };
+        IndexContents {
+            view,
+            value_prefix,
+            key_offset,
+        }
+    }
+}
+
+/// Displays the index address in the form `name[id_in_group]`, where the key within
+/// the group is hex-encoded. Addresses of indexes in migrations are prefixed with `^`.
+impl fmt::Display for IndexInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.address.in_migration() {
+            f.write_str("^")?;
+        }
+        f.write_str(self.address.name())?;
+        if let Some(key) = self.address.id_in_group() {
+            write!(f, "[{}]", hex::encode(key))?;
+        }
+        Ok(())
+    }
+}
+
+/// Size statistics of an index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IndexStats {
+    /// Number of values in the index, e.g., the length of a list or the number of entries
+    /// in a map.
+    pub len: u64,
+    /// Number of entries in the underlying key-value storage, including auxiliary data
+    /// such as Merkle tree nodes.
+    pub raw_entries: u64,
+    /// Total size of keys and values of the raw entries in bytes. Values are measured
+    /// after decompression.
+    pub size: u64,
+}
+
+/// Contents of an index.
+#[derive(Debug)]
+pub struct IndexContents<'a> {
+    view: View<&'a dyn Snapshot>,
+    value_prefix: Vec<u8>,
+    key_offset: usize,
+}
+
+impl IndexContents<'_> {
+    /// Iterates over the index values in the order of their keys. Keys are encoded the same way
+    /// as by the index; e.g., list indexes have 8-byte big-endian indexes of values as keys,
+    /// and `Entry` / `ProofEntry` have a single value with an empty key. Auxiliary data
+    /// of the index (such as Merkle tree nodes) is skipped.
+    pub fn entries(&self) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> + '_ {
+        let key_offset = self.key_offset;
+        self.view
+            .iter::<_, [u8], Vec<u8>>(&self.value_prefix[..])
+            .map(move |(mut key, value)| (key.split_off(key_offset), value))
+    }
+
+    /// Computes size statistics of the index. This requires a full scan of the index.
+    pub fn stats(&self) -> IndexStats {
+        let mut stats = IndexStats::default();
+        for (key, value) in self.view.iter::<_, [u8], Vec<u8>>(&()) {
+            stats.raw_entries += 1;
+            stats.size += (key.len() + value.len()) as u64;
+            if key.starts_with(&self.value_prefix) {
+                stats.len += 1;
+            }
+        }
+        stats
+    }
+}
+
+/// Returns information about all indexes in the database state represented by `snapshot`,
+/// in the order of their fully qualified names.
+pub fn indexes(snapshot: &dyn Snapshot) -> Vec<IndexInfo> {
+    IndexesPool::new(snapshot)
+        .iter()
+        .map(|(address, metadata)| IndexInfo { address, metadata })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{access::AccessExt, migration::Migration, Database, TemporaryDB};
+
+    fn find<'a>(indexes: &'a [IndexInfo], name: &str) -> &'a IndexInfo {
+        indexes
+            .iter()
+            .find(|info| info.to_string() == name)
+            .unwrap_or_else(|| panic!("index `{}` not found", name))
+    }
+
+    #[test]
+    fn inspecting_indexes() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_list("list").extend(0_u32..10);
+        let mut map = fork.get_proof_map("map");
+        for i in 0_u8..5 {
+            map.put(&i, u64::from(i));
+        }
+        fork.get_proof_entry("entry").set(42_u64);
+        fork.get_key_set(("set", &1_u8)).insert(&3_u16);
+        Migration::new("test", &fork).get_list("list").push(1_u8);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let indexes = indexes(&*snapshot);
+        assert!(indexes.iter().any(IndexInfo::is_system));
+
+        let list = find(&indexes, "list");
+        assert_eq!(list.index_type(), IndexType::ProofList);
+        let contents = list.contents(&*snapshot);
+        let stats = contents.stats();
+        assert_eq!(stats.len, 10);
+        // 10 values + 10 + 5 + 3 + 2 + 1 Merkle tree nodes.
+        assert_eq!(stats.raw_entries, 31);
+        let entries: Vec<_> = contents.entries().collect();
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[3], (3_u64.to_be_bytes().to_vec(), vec![3, 0, 0, 0]));
+
+        let map = find(&indexes, "map");
+        let contents = map.contents(&*snapshot);
+        assert_eq!(contents.stats().len, 5);
+        let keys: Vec<_> = contents.entries().map(|(key, _)| key).collect();
+        assert_eq!(keys, (0_u8..5).map(|i| vec![i]).collect::<Vec<_>>());
+
+        let entry = find(&indexes, "entry");
+        let entries: Vec<_> = entry.contents(&*snapshot).entries().collect();
+        assert_eq!(entries, vec![(vec![], 42_u64.to_le_bytes().to_vec())]);
+
+        let set = find(&indexes, "set[01]");
+        assert_eq!(set.index_type(), IndexType::KeySet);
+        let stats = set.contents(&*snapshot).stats();
+        assert_eq!(stats.len, 1);
+        assert_eq!(stats.size, 2);
+
+        let migrated_list = find(&indexes, "^test.list");
+        assert!(migrated_list.is_in_migration());
+        assert_eq!(migrated_list.contents(&*snapshot).stats().len, 1);
+    }
+}
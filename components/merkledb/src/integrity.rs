@@ -0,0 +1,568 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Database integrity checks.
+//!
+//! [`check`] walks all indexes in the database and verifies Merkelized ones:
+//!
+//! - Merkle tree nodes of `ProofListIndex`es and `ProofMapIndex`es are recomputed
+//!   from the index values and compared with the stored nodes
+//! - Hashes of aggregated indexes are compared with the ones recorded in the state aggregator
+//!
+//! The check is intended to detect storage corruption (e.g., after a disk failure);
+//! it reads the entire database and may take a long time for large databases.
+//!
+//! [`check`]: fn.check.html
+//!
+//! # Examples
+//!
+//! ```
+//! # use exonum_merkledb::{access::AccessExt, integrity, Database, TemporaryDB};
+//! let db = TemporaryDB::new();
+//! let fork = db.fork();
+//! fork.get_proof_list("list").extend(vec![1_u32, 2, 3]);
+//! fork.get_proof_map("map").put(&1_u64, "foo".to_owned());
+//! db.merge(fork.into_patch()).unwrap();
+//!
+//! let inconsistencies = integrity::check(&*db.snapshot());
+//! assert!(inconsistencies.is_empty());
+//! ```
+
+use exonum_crypto::Hash;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use crate::{
+    indexes::proof_map::{ProofPath, ProofPathExt, VALUE_KEY_PREFIX},
+    views::{
+        aggregation_prefix, get_aggregated_prefixes, IndexMetadata, IndexType, IndexesPool, View,
+        ViewWithMetadata, PREFIX_AGGREGATOR, STATE_AGGREGATOR,
+    },
+    IndexAddress, ProofListIndex, ProofMapIndex, Snapshot,
+};
+
+/// Kind of an inconsistency found by the integrity check. Keys mentioned in the variants
+/// are raw keys of the index entries in the underlying key-value storage.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InconsistencyKind {
+    /// Index metadata is malformed.
+    InvalidMetadata(String),
+
+    /// An entry required by the index structure is missing.
+    MissingEntry {
+        /// Key of the missing entry.
+        key: Vec<u8>,
+    },
+
+    /// An entry cannot be decoded.
+    InvalidEntry {
+        /// Key of the malformed entry.
+        key: Vec<u8>,
+    },
+
+    /// The stored hash of a Merkle tree node differs from the hash computed from
+    /// the node children or the underlying value.
+    HashMismatch {
+        /// Key of the node.
+        key: Vec<u8>,
+        /// Stored hash of the node.
+        stored: Hash,
+        /// Computed hash of the node.
+        computed: Hash,
+    },
+
+    /// The index contains entries not belonging to its structure (e.g., Merkle tree nodes
+    /// unreachable from the tree root).
+    UnexpectedEntries {
+        /// Number of unexpected entries.
+        count: u64,
+    },
+
+    /// Values of a `ProofMapIndex` do not correspond to the leaves of its Merkle tree.
+    ValuesMismatch {
+        /// Number of values without a matching tree leaf, or vice versa.
+        count: u64,
+    },
+
+    /// The hash of an aggregated index recorded in the state aggregator differs
+    /// from the actual index hash.
+    AggregatedHashMismatch {
+        /// Hash recorded in the state aggregator, or `None` if the hash is absent
+        /// or malformed.
+        stored: Option<Hash>,
+        /// Actual hash of the index.
+        computed: Hash,
+    },
+
+    /// The state aggregator contains a hash of an index that is not aggregated.
+    UnknownAggregatedIndex {
+        /// Name of the index.
+        name: String,
+    },
+}
+
+impl fmt::Display for InconsistencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InconsistencyKind::InvalidMetadata(message) => {
+                write!(f, "invalid metadata: {}", message)
+            }
+            InconsistencyKind::MissingEntry { key } => {
+                write!(f, "missing entry with key {}", hex::encode(key))
+            }
+            InconsistencyKind::InvalidEntry { key } => {
+                write!(f, "malformed entry with key {}", hex::encode(key))
+            }
+            InconsistencyKind::HashMismatch {
+                key,
+                stored,
+                computed,
+            } => write!(
+                f,
+                "hash mismatch for node with key {}: stored {}, computed {}",
+                hex::encode(key),
+                hex::encode(stored),
+                hex::encode(computed)
+            ),
+            InconsistencyKind::UnexpectedEntries { count } => {
+                write!(f, "{} unexpected entries", count)
+            }
+            InconsistencyKind::ValuesMismatch { count } => {
+                write!(f, "{} values do not match tree leaves", count)
+            }
+            InconsistencyKind::AggregatedHashMismatch { stored, computed } => {
+                let stored = stored.map_or_else(|| "none".to_owned(), hex::encode);
+                write!(
+                    f,
+                    "aggregated hash mismatch: stored {}, computed {}",
+                    stored,
+                    hex::encode(computed)
+                )
+            }
+            InconsistencyKind::UnknownAggregatedIndex { name } => {
+                write!(f, "hash of unknown index `{}` is aggregated", name)
+            }
+        }
+    }
+}
+
+/// Inconsistency in the database found by the integrity check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inconsistency {
+    address: IndexAddress,
+    kind: InconsistencyKind,
+}
+
+impl Inconsistency {
+    /// Returns the address of the index containing the inconsistency.
+    pub fn address(&self) -> &IndexAddress {
+        &self.address
+    }
+
+    /// Returns the kind of the inconsistency.
+    pub fn kind(&self) -> &InconsistencyKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.address.in_migration() {
+            f.write_str("^")?;
+        }
+        f.write_str(self.address.name())?;
+        if let Some(key) = self.address.id_in_group() {
+            write!(f, "[{}]", hex::encode(key))?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+/// State aggregator checked by the integrity check.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Aggregator {
+    /// Aggregator for a namespace. The default namespace is denoted by an empty string.
+    Namespace(String),
+    /// Aggregator for a hierarchically aggregated prefix.
+    Prefix(String),
+}
+
+/// Checks integrity of the database state represented by `snapshot`. Returns the list
+/// of found inconsistencies, which is empty if the database is consistent.
+pub fn check(snapshot: &dyn Snapshot) -> Vec<Inconsistency> {
+    let mut inconsistencies = vec![];
+    // Addresses and computed hashes of aggregated indexes keyed by the aggregator
+    // and the index name. `None` hash means that the hash cannot be computed because
+    // the index is corrupted.
+    let mut aggregated_hashes = BTreeMap::new();
+    // State aggregators.
+    let mut aggregators = BTreeMap::new();
+    let aggregated_prefixes: BTreeSet<_> = get_aggregated_prefixes(snapshot).iter().collect();
+
+    for (address, metadata) in IndexesPool::new(snapshot).iter_checked() {
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                inconsistencies.push(Inconsistency {
+                    address,
+                    kind: InconsistencyKind::InvalidMetadata(e.to_string()),
+                });
+                continue;
+            }
+        };
+
+        let index_type = metadata.index_type();
+        let mut report = vec![];
+        let hash = check_index(snapshot, &address, metadata.clone(), &mut report);
+        inconsistencies.extend(report.into_iter().map(|kind| Inconsistency {
+            address: address.clone(),
+            kind,
+        }));
+
+        if index_type == IndexType::ProofMap {
+            let group_key = address.id_in_group().unwrap_or_default();
+            let group_key = String::from_utf8_lossy(group_key).into_owned();
+            if address.name() == STATE_AGGREGATOR {
+                let aggregator = Aggregator::Namespace(group_key);
+                aggregators.insert(aggregator, (address.clone(), metadata));
+            } else if address.name() == PREFIX_AGGREGATOR {
+                // The hash of the prefix aggregator is aggregated in the default aggregator.
+                let key = (Aggregator::Namespace(String::new()), group_key.clone());
+                aggregated_hashes.insert(key, (address.clone(), hash));
+                let aggregator = Aggregator::Prefix(group_key);
+                aggregators.insert(aggregator, (address.clone(), metadata));
+            }
+        }
+
+        let is_aggregated =
+            index_type.is_merkelized() && address.id_in_group().is_none() && !address.is_system();
+        if is_aggregated {
+            let namespace = address.namespace();
+            let prefix = aggregation_prefix(address.name());
+            let aggregator = if namespace.is_empty() && aggregated_prefixes.contains(prefix) {
+                Aggregator::Prefix(prefix.to_owned())
+            } else {
+                Aggregator::Namespace(namespace.to_owned())
+            };
+            let key = (aggregator, address.name().to_owned());
+            aggregated_hashes.insert(key, (address, hash));
+        }
+    }
+
+    for (aggregator, (aggregator_address, metadata)) in aggregators {
+        if metadata.resolve(&aggregator_address).id.is_none() {
+            // The metadata is already reported as invalid.
+            continue;
+        }
+        let view: View<_> =
+            ViewWithMetadata::from_metadata(snapshot, &aggregator_address, metadata).into();
+
+        for (key, value) in view.iter::<_, [u8], Vec<u8>>(&VALUE_KEY_PREFIX) {
+            let name = String::from_utf8_lossy(&key[1..]).into_owned();
+            let stored = Hash::from_slice(&value);
+            let (address, kind) = match aggregated_hashes.remove(&(aggregator.clone(), name)) {
+                Some((address, Some(computed))) => {
+                    if stored == Some(computed) {
+                        continue;
+                    }
+                    let kind = InconsistencyKind::AggregatedHashMismatch { stored, computed };
+                    (address, kind)
+                }
+                Some((_, None)) => continue,
+                None => {
+                    let name = String::from_utf8_lossy(&key[1..]).into_owned();
+                    let kind = InconsistencyKind::UnknownAggregatedIndex { name };
+                    (aggregator_address.clone(), kind)
+                }
+            };
+            inconsistencies.push(Inconsistency { address, kind });
+        }
+    }
+
+    // Remaining indexes are missing from the state aggregators.
+    for (_, (address, hash)) in aggregated_hashes {
+        if let Some(computed) = hash {
+            let kind = InconsistencyKind::AggregatedHashMismatch {
+                stored: None,
+                computed,
+            };
+            inconsistencies.push(Inconsistency { address, kind });
+        }
+    }
+    inconsistencies
+}
+
+/// Checks a single index, returning its object hash if the index is Merkelized and its hash
+/// can be computed.
+fn check_index(
+    snapshot: &dyn Snapshot,
+    address: &IndexAddress,
+    metadata: IndexMetadata,
+    report: &mut Vec<InconsistencyKind>,
+) -> Option<Hash> {
+    if metadata.resolve(address).id.is_none() {
+        let message = "zero index identifier".to_owned();
+        report.push(InconsistencyKind::InvalidMetadata(message));
+        return None;
+    }
+
+    // Index states are checked before instantiating indexes, since indexes panic
+    // on malformed states.
+    let state = metadata.raw_state();
+    let is_state_valid = match metadata.index_type() {
+        IndexType::ProofList => state.map_or(true, |state| state.len() == 8),
+        IndexType::ProofMap | IndexType::ProofKeySet => {
+            state.map_or(true, |state| ProofPath::read_checked(state).is_some())
+        }
+        IndexType::ProofEntry => state.map_or(true, |state| Hash::from_slice(state).is_some()),
+        _ => return None,
+    };
+    if !is_state_valid {
+        let message = "malformed index state".to_owned();
+        report.push(InconsistencyKind::InvalidMetadata(message));
+        return None;
+    }
+
+    let index_type = metadata.index_type();
+    let entry_hash = state.and_then(Hash::from_slice);
+    let view = ViewWithMetadata::from_metadata(snapshot, address, metadata);
+    match index_type {
+        // Element types do not matter, since the checks only use raw values.
+        IndexType::ProofList => ProofListIndex::<_, ()>::new(view).check_integrity(report),
+        // Key sets are stored as maps with `()` values.
+        IndexType::ProofMap | IndexType::ProofKeySet => {
+            ProofMapIndex::<_, (), ()>::new(view).check_integrity(report)
+        }
+        IndexType::ProofEntry => {
+            // The entry hash is stored directly in the metadata.
+            let view: View<_> = view.into();
+            if view.contains(&()) != entry_hash.is_some() {
+                let message = "entry hash is inconsistent with the entry value".to_owned();
+                report.push(InconsistencyKind::InvalidMetadata(message));
+            }
+            Some(entry_hash.unwrap_or_default())
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::{
+        access::AccessExt, migration::Migration, views::get_state_aggregator, Database, Fork,
+        ObjectHash, SystemSchema, TemporaryDB,
+    };
+
+    fn create_db() -> TemporaryDB {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_list("list").extend(0_u32..10);
+        let mut map = fork.get_proof_map("map");
+        for i in 0_u64..10 {
+            map.put(&i, i.to_string());
+        }
+        fork.get_proof_entry("entry").set(42_u64);
+        fork.get_proof_list(("group", &1_u8)).push(1_u8);
+        fork.get_map("non_hashed").put(&1_u8, 2_u8);
+        Migration::new("test", &fork)
+            .get_proof_map("map")
+            .put(&1_u8, 1_u8);
+        db.merge(fork.into_patch()).unwrap();
+        db
+    }
+
+    fn raw_view<'a>(fork: &'a Fork, name: &str) -> View<&'a Fork> {
+        let address = IndexAddress::from_root(name);
+        let (_, metadata) = IndexesPool::new(fork)
+            .iter()
+            .find(|(addr, _)| *addr == address)
+            .unwrap();
+        View::new(fork, metadata.resolve(&address))
+    }
+
+    fn list_key(height: u8, index: u64) -> Vec<u8> {
+        ((u64::from(height) << 56) + index).to_be_bytes().to_vec()
+    }
+
+    #[test]
+    fn consistent_database() {
+        let db = create_db();
+        assert_eq!(check(&*db.snapshot()), vec![]);
+
+        let fork = db.fork();
+        fork.get_proof_list::<_, u32>("list").truncate(3);
+        fork.get_proof_map::<_, u64, String>("map").remove(&5);
+        fork.get_proof_entry::<_, u64>("entry").remove();
+        let patch = fork.into_patch();
+        assert_eq!(check(&patch), vec![]);
+        db.merge(patch).unwrap();
+        assert_eq!(check(&*db.snapshot()), vec![]);
+    }
+
+    #[test]
+    fn corrupted_list() {
+        let db = create_db();
+        let fork = db.fork();
+        let mut view = raw_view(&fork, "list");
+        view.put(&list_key(0, 3)[..], 100_u32);
+        view.remove(&list_key(2, 4)[..]);
+        view.put(&list_key(1, 10)[..], Hash::zero());
+        db.merge(fork.into_patch()).unwrap();
+
+        let inconsistencies = check(&*db.snapshot());
+        assert_eq!(inconsistencies.len(), 3);
+        assert!(inconsistencies
+            .iter()
+            .all(|inconsistency| inconsistency.address().name() == "list"));
+        assert_matches!(
+            inconsistencies[0].kind(),
+            InconsistencyKind::HashMismatch { key, .. } if *key == list_key(1, 3)
+        );
+        assert_eq!(
+            *inconsistencies[1].kind(),
+            InconsistencyKind::MissingEntry {
+                key: list_key(2, 4)
+            }
+        );
+        assert_eq!(
+            *inconsistencies[2].kind(),
+            InconsistencyKind::UnexpectedEntries { count: 1 }
+        );
+    }
+
+    #[test]
+    fn corrupted_map() {
+        let db = create_db();
+        let fork = db.fork();
+        let mut view = raw_view(&fork, "map");
+        let leaf_key = ProofPath::from_bytes(1_u64.object_hash())
+            .as_bytes()
+            .to_vec();
+        view.put(&leaf_key[..], Hash::zero());
+        db.merge(fork.into_patch()).unwrap();
+
+        let inconsistencies = check(&*db.snapshot());
+        assert_eq!(inconsistencies.len(), 2);
+        assert_matches!(
+            inconsistencies[0].kind(),
+            InconsistencyKind::HashMismatch { key, stored, computed }
+                if *key == leaf_key && *computed == Hash::zero() && *stored != Hash::zero()
+        );
+        assert_eq!(
+            *inconsistencies[1].kind(),
+            InconsistencyKind::ValuesMismatch { count: 1 }
+        );
+
+        let fork = db.fork();
+        let mut view = raw_view(&fork, "map");
+        let mut value_key = vec![VALUE_KEY_PREFIX];
+        value_key.extend_from_slice(&3_u64.to_be_bytes());
+        view.put(&value_key[..], "?".to_owned());
+        view.put(&[3_u8][..], vec![0_u8; 3]);
+        db.merge(fork.into_patch()).unwrap();
+
+        let inconsistencies = check(&*db.snapshot());
+        assert_eq!(inconsistencies.len(), 3);
+        assert_eq!(
+            *inconsistencies[1].kind(),
+            InconsistencyKind::UnexpectedEntries { count: 1 }
+        );
+        assert_eq!(
+            *inconsistencies[2].kind(),
+            InconsistencyKind::ValuesMismatch { count: 2 }
+        );
+    }
+
+    #[test]
+    fn corrupted_aggregator() {
+        let db = create_db();
+        let fork = db.fork();
+        let mut aggregator = get_state_aggregator(&fork, "");
+        aggregator.put("list", Hash::zero());
+        aggregator.put("unknown", Hash::zero());
+        aggregator.remove("entry");
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let list_hash = snapshot.get_proof_list::<_, u32>("list").object_hash();
+        let entry_hash = snapshot.get_proof_entry::<_, u64>("entry").object_hash();
+        let inconsistencies = check(&*snapshot);
+        let inconsistencies: Vec<_> = inconsistencies
+            .iter()
+            .map(|inconsistency| (inconsistency.address().name(), inconsistency.kind()))
+            .collect();
+        assert_eq!(
+            inconsistencies,
+            vec![
+                (
+                    "list",
+                    &InconsistencyKind::AggregatedHashMismatch {
+                        stored: Some(Hash::zero()),
+                        computed: list_hash,
+                    }
+                ),
+                (
+                    STATE_AGGREGATOR,
+                    &InconsistencyKind::UnknownAggregatedIndex {
+                        name: "unknown".to_owned(),
+                    }
+                ),
+                (
+                    "entry",
+                    &InconsistencyKind::AggregatedHashMismatch {
+                        stored: None,
+                        computed: entry_hash,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_with_prefix_aggregation() {
+        let db = create_db();
+        let mut fork = db.fork();
+        fork.get_proof_list("service.list").extend(0_u32..5);
+        fork.get_proof_entry("service").set(1_u8);
+        fork.enable_prefix_aggregation("service");
+        fork.enable_prefix_aggregation("list");
+        fork.get_proof_entry("service.entry").set(2_u8);
+        db.merge(fork.into_patch()).unwrap();
+        assert!(check(&*db.snapshot()).is_empty());
+
+        let fork = db.fork();
+        let mut aggregator = get_state_aggregator(&fork, "");
+        aggregator.put("service", Hash::zero());
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let service_hash = SystemSchema::new(&snapshot).prefix_state_hash("service");
+        let inconsistencies = check(&*snapshot);
+        assert_eq!(inconsistencies.len(), 1);
+        assert_eq!(inconsistencies[0].address().name(), PREFIX_AGGREGATOR);
+        assert_eq!(
+            *inconsistencies[0].kind(),
+            InconsistencyKind::AggregatedHashMismatch {
+                stored: Some(Hash::zero()),
+                computed: service_hash,
+            }
+        );
+    }
+}
@@ -86,6 +86,18 @@ pub trait BinaryKey: ToOwned {
     fn read(buffer: &[u8]) -> Self::Owned;
 }
 
+/// A key with the serialized size not depending on the key value.
+///
+/// Fixed-size keys can be safely concatenated: the boundaries between concatenated keys
+/// are known in advance, and the order of composite keys follows the order of their components.
+/// The trait is used by `#[derive(BinaryKey)]` from the `exonum-derive` crate to check
+/// that composite keys are unambiguous.
+pub trait FixedSizeKey: BinaryKey {
+    /// Size of the serialized key in bytes. Must be equal to the value returned
+    /// by `BinaryKey::size()` for any key.
+    const SIZE: usize;
+}
+
 /// No-op implementation.
 impl BinaryKey for () {
     fn size(&self) -> usize {
@@ -100,6 +112,10 @@ impl BinaryKey for () {
     fn read(_buffer: &[u8]) -> Self::Owned {}
 }
 
+impl FixedSizeKey for () {
+    const SIZE: usize = 0;
+}
+
 impl BinaryKey for u8 {
     fn size(&self) -> usize {
         1
@@ -115,6 +131,10 @@ impl BinaryKey for u8 {
     }
 }
 
+impl FixedSizeKey for u8 {
+    const SIZE: usize = 1;
+}
+
 /// Uses encoding with the values mapped to `u8`
 /// by adding the corresponding constant (`128`) to the value.
 impl BinaryKey for i8 {
@@ -132,6 +152,10 @@ impl BinaryKey for i8 {
     }
 }
 
+impl FixedSizeKey for i8 {
+    const SIZE: usize = 1;
+}
+
 // spell-checker:ignore utype, itype, vals, ints
 
 macro_rules! storage_key_for_ints {
@@ -168,6 +192,14 @@ macro_rules! storage_key_for_ints {
                 BigEndian::$read_method(buffer).wrapping_sub(Self::min_value() as $utype) as Self
             }
         }
+
+        impl FixedSizeKey for $utype {
+            const SIZE: usize = $size;
+        }
+
+        impl FixedSizeKey for $itype {
+            const SIZE: usize = $size;
+        }
     };
 }
 
@@ -192,6 +224,10 @@ macro_rules! storage_key_for_crypto_types {
                 Self::from_slice(buffer).unwrap()
             }
         }
+
+        impl FixedSizeKey for $type {
+            const SIZE: usize = $size;
+        }
     };
 }
 
@@ -246,6 +282,10 @@ impl BinaryKey for [u8; 32] {
     }
 }
 
+impl FixedSizeKey for [u8; 32] {
+    const SIZE: usize = 32;
+}
+
 /// Uses UTF-8 string serialization.
 impl BinaryKey for String {
     fn size(&self) -> usize {
@@ -305,6 +345,10 @@ impl BinaryKey for DateTime<Utc> {
     }
 }
 
+impl FixedSizeKey for DateTime<Utc> {
+    const SIZE: usize = 12;
+}
+
 impl BinaryKey for Uuid {
     fn size(&self) -> usize {
         16
@@ -320,6 +364,10 @@ impl BinaryKey for Uuid {
     }
 }
 
+impl FixedSizeKey for Uuid {
+    const SIZE: usize = 16;
+}
+
 impl BinaryKey for Decimal {
     fn size(&self) -> usize {
         16
@@ -337,6 +385,10 @@ impl BinaryKey for Decimal {
     }
 }
 
+impl FixedSizeKey for Decimal {
+    const SIZE: usize = 16;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -26,7 +26,17 @@
 //! that is, the Exonum process has exclusive access to the DB during blockchain operation.
 //! You can interact with the `Database` from multiple threads by cloning its instance.
 //!
-//! This crate provides two database types: [`RocksDB`] and [`TemporaryDB`].
+//! This crate provides two database types: [`RocksDB`] and [`TemporaryDB`]. With the `sled`
+//! crate feature enabled, a persistent database written in pure Rust, `SledDB`,
+//! is available as well.
+//!
+//! `RocksDB` and `TemporaryDB` are gated behind the `rocksdb` crate feature, which is enabled
+//! by default. Since RocksDB is written in C++, building it requires a C++ compiler; the crate
+//! can be built without one by disabling default features and enabling `sled`.
+//! Likewise, [`ValueCompression::Zstd`] requires the default `zstd` feature.
+//!
+//! Any database can be wrapped in [`CachedDB`], which caches point reads in memory
+//! and can be used to reduce storage load for nodes serving heavy read traffic.
 //!
 //! # Snapshot and Fork
 //!
@@ -48,7 +58,10 @@
 //!
 //! If you need to use your own data types as keys or values in the storage, you need to implement
 //! the [`BinaryKey`] or [`BinaryValue`] traits respectively. These traits have already been
-//! implemented for most standard types.
+//! implemented for most standard types. Composite keys consisting of several fields
+//! can be implemented with `#[derive(BinaryKey)]` from the `exonum-derive` crate; the derived
+//! implementation relies on the [`FixedSizeKey`] trait to check that the fields can be
+//! concatenated unambiguously.
 //!
 //! # Indexes
 //!
@@ -74,11 +87,15 @@
 //! - [`SparseListIndex`] is a list of items stored in a sequential order. Similar to `ListIndex`,
 //!   but may contain indexes without elements.
 //! - [`MapIndex`] is a map of keys and values. Similar to [`BTreeMap`].
+//! - [`IndexedMap`] is a version of `MapIndex` with automatically maintained secondary
+//!   indexes over its values.
 //! - [`ProofEntry`] is a Merkelized version of `Entry`.
 //! - [`ProofListIndex`] is a Merkelized version of `ListIndex` that supports cryptographic
 //!   proofs of existence and is implemented as a Merkle tree.
 //! - [`ProofMapIndex`] is a Merkelized version of `MapIndex` that supports cryptographic
 //!   proofs of existence and is implemented as a binary Merkle Patricia tree.
+//! - [`ProofKeySetIndex`] is a Merkelized version of `KeySetIndex` that supports cryptographic
+//!   proofs of membership and absence of its elements.
 //! - [`KeySetIndex`] and [`ValueSetIndex`] are sets of items, similar to [`BTreeSet`] and
 //!   [`HashSet`] accordingly.
 //!
@@ -93,7 +110,8 @@
 //! as per [`ObjectHash`] implementation. An index is aggregated if and only if it satisfies
 //! the following constraints:
 //!
-//! - Index has a matching type (`ProofListIndex`, `ProofMapIndex`, or `ProofEntry`)
+//! - Index has a matching type (`ProofListIndex`, `ProofMapIndex`, `ProofKeySetIndex`,
+//!   or `ProofEntry`)
 //! - Index is not a part of a group, i.e., its address does not contain the `bytes` part
 //!
 //! The aggregation is automatically updated when a `Fork` is converted into a `Patch`.
@@ -101,6 +119,11 @@
 //! to the aggregated state; the index hashes in the `state_aggregator` match their actual values.
 //! This is **not** the case for `Fork`s, in which `state_aggregator` may be stale.
 //!
+//! Aggregation can be made hierarchical for indexes with a specific prefix
+//! (e.g., `service` for `service.list` and `service.map`). In this case, such indexes
+//! are aggregated in a separate aggregator, and the `state_aggregator` contains a single
+//! entry with the hash of this aggregator. See [`SystemSchema`] for details.
+//!
 //! # Migrations
 //!
 //! The database [provides tooling](migration/index.html) for data migrations. With the help
@@ -110,6 +133,8 @@
 //! [`Database`]: trait.Database.html
 //! [`RocksDB`]: struct.RocksDB.html
 //! [`TemporaryDB`]: struct.TemporaryDB.html
+//! [`CachedDB`]: struct.CachedDB.html
+//! [`ValueCompression::Zstd`]: enum.ValueCompression.html#variant.Zstd
 //! [`Snapshot`]: trait.Snapshot.html
 //! [`Fork`]: struct.Fork.html
 //! [`Patch`]: struct.Patch.html
@@ -117,14 +142,17 @@
 //! [2]: trait.Database.html#method.fork
 //! [`merge`]: trait.Database.html#tymethod.merge
 //! [`BinaryKey`]: trait.BinaryKey.html
+//! [`FixedSizeKey`]: trait.FixedSizeKey.html
 //! [`BinaryValue`]: trait.BinaryValue.html
 //! [`Entry`]: indexes/entry/struct.Entry.html
 //! [`ProofEntry`]: indexes/proof_entry/struct.ProofEntry.html
 //! [`ListIndex`]: indexes/list/struct.ListIndex.html
 //! [`SparseListIndex`]: indexes/sparse_list/struct.SparseListIndex.html
 //! [`MapIndex`]: indexes/map/struct.MapIndex.html
+//! [`IndexedMap`]: indexes/indexed_map/struct.IndexedMap.html
 //! [`ProofListIndex`]: indexes/proof_list/struct.ProofListIndex.html
 //! [`ProofMapIndex`]: indexes/proof_map/struct.ProofMapIndex.html
+//! [`ProofKeySetIndex`]: indexes/proof_key_set/struct.ProofKeySetIndex.html
 //! [`KeySetIndex`]: indexes/key_set/struct.KeySetIndex.html
 //! [`ValueSetIndex`]: indexes/value_set/struct.ValueSetIndex.html
 //! [`ObjectHash`]: trait.ObjectHash.html
@@ -136,6 +164,7 @@
 //! [`BTreeSet`]: https://doc.rust-lang.org/std/collections/struct.BTreeSet.html
 //! [`HashSet`]: https://doc.rust-lang.org/std/collections/struct.HashSet.html
 //! [`state_aggregator`]: struct.SystemSchema.html#method.state_aggregator
+//! [`SystemSchema`]: struct.SystemSchema.html#hierarchical-aggregation
 //! [`Group`]: indexes/group/struct.Group.html
 //! [`IndexAddress`]: struct.IndexAddress.html
 //! [Exonum]: https://exonum.com/
@@ -162,33 +191,47 @@
     clippy::default_trait_access,
 )]
 
+#[cfg(feature = "sled")]
+pub use self::backends::sled::SledDB;
+#[cfg(feature = "rocksdb")]
+pub use self::backends::{rocksdb::RocksDB, temporarydb::TemporaryDB};
 pub use self::{
-    backends::{rocksdb::RocksDB, temporarydb::TemporaryDB},
+    backends::cached::{CacheStats, CachedDB},
     db::{Database, DatabaseExt, Fork, Iter, Iterator, Patch, ReadonlyFork, Snapshot},
-    error::Error,
+    error::{Error, SizeLimitExceeded},
     hash::{root_hash, HashTag, ObjectHash, ValidationError},
-    keys::BinaryKey,
+    keys::{BinaryKey, FixedSizeKey},
     lazy::Lazy,
-    options::DbOptions,
+    options::{ColumnFamilyOverride, CompressionType, DbOptions, HistoryOptions},
     values::BinaryValue,
-    views::{AsReadonly, IndexAddress, IndexType, ResolvedAddress, SystemSchema},
+    views::{AsReadonly, IndexAddress, IndexType, ResolvedAddress, SystemSchema, ValueCompression},
 };
 // Workaround for 'Linked file at path {exonum_merkledb_path}/struct.ProofMapIndex.html
 // does not exist!'
 #[doc(no_inline)]
 pub use self::indexes::{
+    group::{self, Group},
+    indexed_map::{self, IndexedMap},
     key_set::{self, KeySetIndex},
     list::{self, ListIndex},
     map::{self, MapIndex},
-    proof_list::{self, ListProof, ProofListIndex},
-    proof_map::{self, MapProof, ProofMapIndex, RawProofMapIndex},
+    proof_key_set::{self, ProofKeySetIndex},
+    proof_list::{self, ListConsistencyProof, ListProof, ProofListIndex},
+    proof_map::{self, MapProof, MapRangeProof, ProofMapIndex, RawProofMapIndex},
     sparse_list::{self, SparseListIndex},
     value_set::{self, ValueSetIndex},
-    Entry, Group, ProofEntry,
+    Entry, ProofEntry,
 };
 
+#[macro_use]
+mod macros;
+
 pub mod access;
+pub mod diff;
+pub mod dump;
 pub mod indexes;
+pub mod inspect;
+pub mod integrity;
 pub mod migration;
 pub mod validation;
 
@@ -201,12 +244,11 @@ use exonum_proto::ProtobufConvert;
 /// A specialized `Result` type for I/O operations with storage.
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[macro_use]
-mod macros;
 mod backends;
 mod db;
 mod error;
 mod hash;
+mod history;
 mod keys;
 mod lazy;
 mod options;
@@ -48,6 +48,51 @@ macro_rules! concat_keys {
     });
 }
 
+/// Generates tests running the specified test functions against all database backends.
+/// Each function must accept a single `&dyn Database` argument; attributes (e.g.,
+/// `#[should_panic]`) are applied to the generated tests.
+#[cfg(test)]
+macro_rules! backend_tests {
+    ($($(#[$attr:meta])* $test:ident,)+) => {
+        #[cfg(feature = "rocksdb")]
+        mod temporary_db {
+            $(
+                #[test]
+                $(#[$attr])*
+                fn $test() {
+                    super::$test(&crate::TemporaryDB::new());
+                }
+            )+
+        }
+
+        #[cfg(feature = "rocksdb")]
+        mod rocksdb {
+            $(
+                #[test]
+                $(#[$attr])*
+                fn $test() {
+                    let dir = tempfile::TempDir::new().unwrap();
+                    let db = crate::RocksDB::open(&dir, &crate::DbOptions::default()).unwrap();
+                    super::$test(&db);
+                }
+            )+
+        }
+
+        #[cfg(feature = "sled")]
+        mod sled {
+            $(
+                #[test]
+                $(#[$attr])*
+                fn $test() {
+                    let dir = tempfile::TempDir::new().unwrap();
+                    let db = crate::SledDB::open(&dir, &crate::DbOptions::default()).unwrap();
+                    super::$test(&db);
+                }
+            )+
+        }
+    };
+}
+
 /// Implement `ObjectHash` trait for any type that implements `BinaryValue`.
 #[macro_export]
 macro_rules! impl_object_hash_for_binary_value {
@@ -30,7 +30,9 @@
 //! It is possible to periodically persist migrated data to the database
 //! (indeed, this is a best practice to avoid out-of-memory errors). It is even possible
 //! to restart the process handling the migration, provided it can recover from such a restart
-//! on the application level.
+//! on the application level. [`MigrationHelper::run_chunked`] provides such recovery
+//! out of the box: it persists a cursor into the migrated data together with each processed
+//! chunk, and resumes from the cursor if the process was restarted.
 //!
 //! # Finalizing Migration
 //!
@@ -45,6 +47,7 @@
 //! [aggregated]: ../index.html#state-aggregation
 //! [`Fork::flush_migration`]: ../struct.Fork.html#method.flush_migration
 //! [`Fork::rollback_migration`]: ../struct.Fork.html#method.rollback_migration
+//! [`MigrationHelper::run_chunked`]: struct.MigrationHelper.html#method.run_chunked
 //!
 //! # Examples
 //!
@@ -112,20 +115,128 @@
 //! # }
 //! ```
 
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use exonum_crypto::Hash;
+use failure::ensure;
 
-use std::{fmt, mem, sync::Arc};
+use std::{borrow::Cow, fmt, mem, sync::Arc};
 
 use crate::views::IndexMetadata;
 use crate::{
-    access::{Access, AccessError, Prefixed, RawAccess},
+    access::{
+        sealed, Access, AccessError, AccessExt, AccessGroups, GroupKeys, Prefixed, RawAccess,
+    },
     validation::assert_valid_name_component,
     views::{
-        get_state_aggregator, AsReadonly, IndexAddress, IndexType, RawAccessMut, ViewWithMetadata,
+        get_state_aggregator, AsReadonly, IndexAddress, IndexType, RawAccessMut, ValueCompression,
+        ViewWithMetadata,
     },
-    Database, Fork, ObjectHash, ProofMapIndex, ReadonlyFork,
+    BinaryValue, Database, Entry, Fork, ObjectHash, ProofMapIndex, ReadonlyFork,
 };
 
+/// Name of the index within a migration namespace, which stores the progress of the migration
+/// script run with [`MigrationHelper::run_chunked`]. The index is removed when the migration
+/// is flushed or rolled back.
+///
+/// [`MigrationHelper::run_chunked`]: struct.MigrationHelper.html#method.run_chunked
+pub(crate) const PROGRESS_INDEX_NAME: &str = "__progress__";
+
+/// Progress of a migration script run with [`MigrationHelper::run_chunked`].
+///
+/// [`MigrationHelper::run_chunked`]: struct.MigrationHelper.html#method.run_chunked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    /// Number of items processed by the script so far.
+    pub processed: u64,
+    /// Total number of items to process, as specified when running the script.
+    pub total: u64,
+    /// Whether the script has finished processing data.
+    pub is_finished: bool,
+}
+
+/// Outcome of processing a single chunk of data by a migration script.
+/// See [`MigrationHelper::run_chunked`] for details.
+///
+/// [`MigrationHelper::run_chunked`]: struct.MigrationHelper.html#method.run_chunked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunk<K> {
+    /// A chunk of data has been processed.
+    Processed {
+        /// Cursor pointing to the last processed item (e.g., a key in a map or an index
+        /// in a list). The next chunk should be processed starting after this item.
+        cursor: K,
+        /// Number of items in the processed chunk.
+        items: u64,
+    },
+    /// All data has been processed.
+    Finished,
+}
+
+/// Progress of a migration script together with the serialized cursor.
+#[derive(Debug, Clone, Default)]
+struct PersistedProgress {
+    progress: Progress,
+    cursor: Option<Vec<u8>>,
+}
+
+impl PersistedProgress {
+    const FINISHED_FLAG: u8 = 1;
+    const CURSOR_FLAG: u8 = 2;
+}
+
+impl BinaryValue for PersistedProgress {
+    fn to_bytes(&self) -> Vec<u8> {
+        let cursor_len = self.cursor.as_ref().map_or(0, Vec::len);
+        let mut buf = Vec::with_capacity(17 + cursor_len);
+        buf.write_u64::<LittleEndian>(self.progress.processed)
+            .unwrap();
+        buf.write_u64::<LittleEndian>(self.progress.total).unwrap();
+
+        let mut flags = 0;
+        if self.progress.is_finished {
+            flags |= Self::FINISHED_FLAG;
+        }
+        if self.cursor.is_some() {
+            flags |= Self::CURSOR_FLAG;
+        }
+        buf.push(flags);
+        if let Some(ref cursor) = self.cursor {
+            buf.extend_from_slice(cursor);
+        }
+        buf
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Result<Self, failure::Error> {
+        let mut bytes = bytes.as_ref();
+        let processed = bytes.read_u64::<LittleEndian>()?;
+        let total = bytes.read_u64::<LittleEndian>()?;
+        let flags = bytes.read_u8()?;
+        ensure!(
+            flags & !(Self::FINISHED_FLAG | Self::CURSOR_FLAG) == 0,
+            "Unknown migration progress flags: {}",
+            flags
+        );
+
+        let cursor = if flags & Self::CURSOR_FLAG == 0 {
+            ensure!(
+                bytes.is_empty(),
+                "Unexpected bytes after migration progress"
+            );
+            None
+        } else {
+            Some(bytes.to_vec())
+        };
+        Ok(Self {
+            progress: Progress {
+                processed,
+                total,
+                is_finished: flags & Self::FINISHED_FLAG != 0,
+            },
+            cursor,
+        })
+    }
+}
+
 /// Access to migrated indexes.
 ///
 /// `Migration` is conceptually similar to a [`Prefixed`] access. For example, an index with
@@ -152,6 +263,18 @@ impl<'a, T: RawAccess> Migration<'a, T> {
     pub fn state_hash(&self) -> Hash {
         get_state_aggregator(self.access.clone(), self.namespace).object_hash()
     }
+
+    /// Returns the progress of the migration script run with [`MigrationHelper::run_chunked`]
+    /// in this namespace, or `None` if no script was run.
+    ///
+    /// [`MigrationHelper::run_chunked`]: struct.MigrationHelper.html#method.run_chunked
+    pub fn progress(&self) -> Option<Progress> {
+        self.progress_entry().get().map(|state| state.progress)
+    }
+
+    fn progress_entry(&self) -> Entry<T, PersistedProgress> {
+        Migration::new(self.namespace, self.access.clone()).get_entry(PROGRESS_INDEX_NAME)
+    }
 }
 
 impl<T: RawAccess + AsReadonly> Migration<'_, T> {
@@ -216,6 +339,28 @@ impl<T: RawAccess> Access for Migration<'_, T> {
         prefixed_addr.set_in_migration();
         self.access.get_or_create_view(prefixed_addr, index_type)
     }
+
+    fn get_or_create_compressed_view(
+        self,
+        addr: IndexAddress,
+        index_type: IndexType,
+        compression: ValueCompression,
+    ) -> Result<ViewWithMetadata<Self::Base>, AccessError> {
+        let mut prefixed_addr = addr.prepend_name(self.namespace.as_ref());
+        prefixed_addr.set_in_migration();
+        self.access
+            .get_or_create_compressed_view(prefixed_addr, index_type, compression)
+    }
+}
+
+impl<T: RawAccess> sealed::Sealed for Migration<'_, T> {}
+
+impl<T: RawAccess> AccessGroups for Migration<'_, T> {
+    fn group_keys(self, group_addr: IndexAddress, from: &[u8]) -> GroupKeys<Self::Base> {
+        let mut prefixed_addr = group_addr.prepend_name(self.namespace.as_ref());
+        prefixed_addr.set_in_migration();
+        self.access.group_keys(prefixed_addr, from)
+    }
 }
 
 /// Migration helper.
@@ -286,15 +431,117 @@ impl MigrationHelper {
         let hash = Migration::new(&self.namespace, &patch).state_hash();
         self.db.merge(patch).map(|()| hash)
     }
+
+    /// Returns the progress of the migration script run with [`run_chunked`], including
+    /// changes not merged to the database yet.
+    ///
+    /// [`run_chunked`]: #method.run_chunked
+    pub fn progress(&self) -> Option<Progress> {
+        self.new_data().progress()
+    }
+
+    /// Runs a resumable migration script, which processes data in chunks.
+    ///
+    /// `process_chunk` is called repeatedly with the cursor returned by the previous call
+    /// (`None` on the first call). After each call, the changes to the migrated data are merged
+    /// to the database together with the updated cursor and the number of processed items.
+    /// Thus, if the process is restarted, calling `run_chunked` again resumes the script
+    /// from the last merged chunk. The progress can be monitored via [`Migration::progress`],
+    /// e.g., from a database snapshot.
+    ///
+    /// The method returns after `process_chunk` returns [`Chunk::Finished`]; if the script
+    /// has already finished before the call, `process_chunk` is not called at all.
+    /// `total` is the total number of items to process; it is only used to report progress.
+    ///
+    /// The progress is stored in the migration namespace under the reserved `__progress__` name.
+    /// It is not aggregated and does not influence the migration state hash. The progress
+    /// is removed when the migration is flushed or rolled back.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if merging changes to the database fails, or if the persisted cursor
+    /// cannot be deserialized.
+    ///
+    /// [`Migration::progress`]: struct.Migration.html#method.progress
+    /// [`Chunk::Finished`]: enum.Chunk.html#variant.Finished
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::AccessExt, Database, TemporaryDB};
+    /// # use exonum_merkledb::migration::{Chunk, Migration, MigrationHelper};
+    /// # use std::sync::Arc;
+    /// # fn main() -> exonum_merkledb::Result<()> {
+    /// let db = Arc::new(TemporaryDB::new());
+    /// let fork = db.fork();
+    /// fork.get_list("test.list").extend(0_u64..10);
+    /// db.merge(fork.into_patch())?;
+    ///
+    /// let mut helper = MigrationHelper::new(Arc::clone(&db) as Arc<dyn Database>, "test");
+    /// let progress = helper.run_chunked(10, |helper, cursor: Option<u64>| {
+    ///     let old_list = helper.old_data().get_list::<_, u64>("list");
+    ///     let mut new_list = helper.new_data().get_proof_list::<_, u64>("list");
+    ///     let start = cursor.map_or(0, |index| index + 1);
+    ///     if start == old_list.len() {
+    ///         return Chunk::Finished;
+    ///     }
+    ///     let end = (start + 3).min(old_list.len());
+    ///     new_list.extend(old_list.iter_from(start).take((end - start) as usize));
+    ///     Chunk::Processed { cursor: end - 1, items: end - start }
+    /// })?;
+    /// assert_eq!((progress.processed, progress.total), (10, 10));
+    /// assert!(progress.is_finished);
+    ///
+    /// let snapshot = db.snapshot();
+    /// let migration = Migration::new("test", &snapshot);
+    /// assert_eq!(migration.progress(), Some(progress));
+    /// assert_eq!(migration.get_proof_list::<_, u64>("list").len(), 10);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_chunked<K, F>(&mut self, total: u64, mut process_chunk: F) -> crate::Result<Progress>
+    where
+        K: BinaryValue,
+        F: FnMut(&Self, Option<K>) -> Chunk<K>,
+    {
+        let mut state = self.new_data().progress_entry().get().unwrap_or_default();
+        state.progress.total = total;
+
+        while !state.progress.is_finished {
+            let cursor = match state.cursor {
+                Some(ref bytes) => {
+                    let cursor = K::from_bytes(bytes.as_slice().into()).map_err(|e| {
+                        crate::Error::new(format!("Cannot deserialize migration cursor: {}", e))
+                    })?;
+                    Some(cursor)
+                }
+                None => None,
+            };
+
+            match process_chunk(self, cursor) {
+                Chunk::Processed { cursor, items } => {
+                    state.progress.processed += items;
+                    state.cursor = Some(cursor.into_bytes());
+                }
+                Chunk::Finished => {
+                    state.progress.is_finished = true;
+                    state.cursor = None;
+                }
+            }
+            self.new_data().progress_entry().set(state.clone());
+            // The cursor is merged atomically with the data in the processed chunk.
+            self.merge()?;
+        }
+        Ok(state.progress)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        access::{AccessExt, RawAccess},
-        HashTag, ObjectHash, SystemSchema, TemporaryDB,
-    };
+    use crate::{access::RawAccess, HashTag, ObjectHash, SystemSchema, TemporaryDB};
+
+    use std::panic;
 
     #[test]
     fn in_memory_migration() {
@@ -575,6 +822,111 @@ mod tests {
         test_migration_rollback(true);
     }
 
+    fn migrate_list_chunk(helper: &MigrationHelper, cursor: Option<u64>) -> Chunk<u64> {
+        let old_list = helper.old_data().get_list::<_, u64>("list");
+        let mut new_list = helper.new_data().get_proof_list::<_, u64>("list");
+        let start = cursor.map_or(0, |index| index + 1);
+        if start == old_list.len() {
+            return Chunk::Finished;
+        }
+        let end = (start + 3).min(old_list.len());
+        new_list.extend(old_list.iter_from(start).take(3).map(|x| x * 2));
+        Chunk::Processed {
+            cursor: end - 1,
+            items: end - start,
+        }
+    }
+
+    #[test]
+    fn chunked_migration_is_resumed() {
+        let db = Arc::new(TemporaryDB::new());
+        let fork = db.fork();
+        fork.get_list("test.list").extend(0_u64..10);
+        db.merge(fork.into_patch()).unwrap();
+
+        // Emulate a crash after two chunks are processed.
+        let mut helper = MigrationHelper::new(Arc::clone(&db) as Arc<dyn Database>, "test");
+        let mut chunks = 0;
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            helper.run_chunked(10, |helper, cursor| {
+                if chunks == 2 {
+                    panic!("Crash");
+                }
+                chunks += 1;
+                migrate_list_chunk(helper, cursor)
+            })
+        }));
+        assert!(res.is_err());
+
+        let snapshot = db.snapshot();
+        let migration = Migration::new("test", &snapshot);
+        let expected_progress = Progress {
+            processed: 6,
+            total: 10,
+            is_finished: false,
+        };
+        assert_eq!(migration.progress(), Some(expected_progress));
+        assert_eq!(migration.get_proof_list::<_, u64>("list").len(), 6);
+
+        let mut helper = MigrationHelper::new(Arc::clone(&db) as Arc<dyn Database>, "test");
+        assert_eq!(helper.progress(), Some(expected_progress));
+        let progress = helper.run_chunked(10, migrate_list_chunk).unwrap();
+        let expected_progress = Progress {
+            processed: 10,
+            total: 10,
+            is_finished: true,
+        };
+        assert_eq!(progress, expected_progress);
+
+        // A finished script is not run again.
+        let progress = helper
+            .run_chunked(10, |_, _: Option<u64>| -> Chunk<u64> { unreachable!() })
+            .unwrap();
+        assert_eq!(progress, expected_progress);
+        let migration_hash = helper.finish().unwrap();
+
+        let snapshot = db.snapshot();
+        let migration = Migration::new("test", &snapshot);
+        assert_eq!(migration.progress(), Some(expected_progress));
+        let list = migration.get_proof_list::<_, u64>("list");
+        assert_eq!(
+            list.iter().collect::<Vec<_>>(),
+            (0..10).map(|x| x * 2).collect::<Vec<_>>()
+        );
+        // The progress does not influence the migration state hash.
+        let aggregator = migration.state_aggregator();
+        assert_eq!(aggregator.keys().collect::<Vec<_>>(), vec!["test.list"]);
+        assert_eq!(migration_hash, aggregator.object_hash());
+
+        let mut fork = db.fork();
+        fork.flush_migration("test");
+        let patch = fork.into_patch();
+        assert_eq!(Migration::new("test", &patch).progress(), None);
+        assert!(patch.index_type("test.__progress__").is_none());
+        assert_eq!(patch.get_proof_list::<_, u64>("test.list").len(), 10);
+    }
+
+    #[test]
+    fn chunked_migration_progress_is_rolled_back() {
+        let db = Arc::new(TemporaryDB::new());
+        let fork = db.fork();
+        fork.get_list("test.list").extend(0_u64..5);
+        db.merge(fork.into_patch()).unwrap();
+
+        let mut helper = MigrationHelper::new(Arc::clone(&db) as Arc<dyn Database>, "test");
+        helper.run_chunked(5, migrate_list_chunk).unwrap();
+        let mut fork = db.fork();
+        fork.rollback_migration("test");
+        db.merge(fork.into_patch()).unwrap();
+
+        let mut helper = MigrationHelper::new(Arc::clone(&db) as Arc<dyn Database>, "test");
+        assert_eq!(helper.progress(), None);
+        let progress = helper.run_chunked(5, migrate_list_chunk).unwrap();
+        assert_eq!(progress.processed, 5);
+        let list = helper.new_data().get_proof_list::<_, u64>("list");
+        assert_eq!(list.len(), 5);
+    }
+
     #[test]
     fn concurrent_borrow_of_original_and_migrated_index() {
         let db = TemporaryDB::new();
@@ -14,13 +14,23 @@
 
 //! Abstract settings for databases.
 
+#[cfg(feature = "rocksdb")]
 use rocksdb::DBCompressionType;
 use serde_derive::{Deserialize, Serialize};
 
+use std::path::PathBuf;
+
+use crate::IndexType;
+
 /// Options for the database.
 ///
 /// These parameters apply to the underlying database of Exonum, currently `RocksDB`.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+///
+/// Options related to the storage of individual column families (compression, block cache,
+/// write buffers and bloom filters) may be overridden for a subset of indexes
+/// with the help of [`column_family_overrides`](#structfield.column_family_overrides).
+/// All other options apply to the database as a whole.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DbOptions {
     /// Number of open files that can be used by the database.
     ///
@@ -45,6 +55,193 @@ pub struct DbOptions {
     ///
     /// Defaults to `CompressionType::None`, meaning there is no compression.
     pub compression_type: CompressionType,
+    /// Size of the LRU cache for uncompressed data blocks in bytes. The cache is allocated
+    /// separately for each column family.
+    ///
+    /// Defaults to `None`, meaning that the `RocksDB` default is used.
+    pub block_cache_size: Option<usize>,
+    /// Size of a single in-memory write buffer (memtable) of a column family in bytes.
+    /// Larger buffers improve write performance at the cost of memory usage
+    /// and recovery time.
+    ///
+    /// Defaults to `None`, meaning that the `RocksDB` default is used.
+    pub write_buffer_size: Option<usize>,
+    /// Maximum number of write buffers of a column family that are kept in memory.
+    ///
+    /// Defaults to `None`, meaning that the `RocksDB` default is used.
+    pub max_write_buffer_number: Option<i32>,
+    /// Number of bits per key in bloom filters for data blocks. Bloom filters speed up
+    /// point reads of absent keys; 10 bits per key yield a false positive rate of about 1%.
+    ///
+    /// Defaults to `None`, meaning that bloom filters are not used.
+    pub bloom_filter_bits_per_key: Option<i32>,
+    /// Maximum number of concurrent background compaction jobs.
+    ///
+    /// Defaults to `None`, meaning that the `RocksDB` default is used.
+    pub max_background_compactions: Option<i32>,
+    /// Maximum number of concurrent background memtable flush jobs.
+    ///
+    /// Defaults to `None`, meaning that the `RocksDB` default is used.
+    pub max_background_flushes: Option<i32>,
+    /// Maximum total size of write-ahead log (WAL) files in bytes. Once the limit is exceeded,
+    /// column families backed by the oldest WAL file are flushed.
+    ///
+    /// Defaults to `None`, meaning that the limit is determined by `RocksDB` based
+    /// on write buffer sizes.
+    pub max_total_wal_size: Option<u64>,
+    /// Directory to store write-ahead log (WAL) files in, e.g., on a separate drive.
+    ///
+    /// Defaults to `None`, meaning that WAL files are stored together with the database.
+    pub wal_dir: Option<PathBuf>,
+    /// Overrides of column family options for a subset of indexes. Overrides are applied
+    /// in order: if several overrides match an index, options specified in the later
+    /// overrides take precedence.
+    ///
+    /// Overrides are applied when a column family is created and when the database
+    /// is opened.
+    ///
+    /// Defaults to an empty list.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_family_overrides: Vec<ColumnFamilyOverride>,
+}
+
+#[cfg(feature = "rocksdb")]
+impl DbOptions {
+    /// Returns the column family options for indexes with the specified name and type.
+    /// The type is `None` for system views and for indexes, the type of which is not known.
+    pub(crate) fn column_family_options(
+        &self,
+        name: &str,
+        index_type: Option<IndexType>,
+    ) -> ColumnFamilyOptions {
+        let mut options = ColumnFamilyOptions {
+            compression_type: self.compression_type,
+            block_cache_size: self.block_cache_size,
+            write_buffer_size: self.write_buffer_size,
+            max_write_buffer_number: self.max_write_buffer_number,
+            bloom_filter_bits_per_key: self.bloom_filter_bits_per_key,
+        };
+        let matching_overrides = self
+            .column_family_overrides
+            .iter()
+            .filter(|cf_override| cf_override.matches(name, index_type));
+        for cf_override in matching_overrides {
+            cf_override.apply(&mut options);
+        }
+        options
+    }
+
+    /// Checks whether column family options depend on index types.
+    pub(crate) fn has_index_type_overrides(&self) -> bool {
+        self.column_family_overrides
+            .iter()
+            .any(|cf_override| cf_override.index_type.is_some())
+    }
+}
+
+/// Override of column family options for indexes matching certain criteria.
+///
+/// Since indexes with the same name (e.g., members of a [`Group`]) share a column family,
+/// overrides are matched against the index name and type rather than the entire
+/// index address.
+///
+/// # Examples
+///
+/// Increasing write buffers for append-only lists, and enabling bloom filters for maps
+/// within the `wallets` namespace:
+///
+/// ```
+/// # use exonum_merkledb::{ColumnFamilyOverride, DbOptions, IndexType};
+/// let options = DbOptions {
+///     column_family_overrides: vec![
+///         ColumnFamilyOverride {
+///             index_type: Some(IndexType::ProofList),
+///             write_buffer_size: Some(256 << 20),
+///             ..ColumnFamilyOverride::default()
+///         },
+///         ColumnFamilyOverride {
+///             name_prefix: Some("wallets.".to_owned()),
+///             index_type: Some(IndexType::ProofMap),
+///             bloom_filter_bits_per_key: Some(10),
+///             ..ColumnFamilyOverride::default()
+///         },
+///     ],
+///     ..DbOptions::default()
+/// };
+/// ```
+///
+/// [`Group`]: indexes/group/struct.Group.html
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ColumnFamilyOverride {
+    /// Prefix of the names of indexes the override applies to. `None` matches any name.
+    pub name_prefix: Option<String>,
+    /// Type of indexes the override applies to. `None` matches any type, including
+    /// system views, which do not have a type.
+    pub index_type: Option<IndexType>,
+    /// Overridden compression algorithm.
+    pub compression_type: Option<CompressionType>,
+    /// Overridden size of the block cache in bytes.
+    pub block_cache_size: Option<usize>,
+    /// Overridden size of a single write buffer in bytes.
+    pub write_buffer_size: Option<usize>,
+    /// Overridden maximum number of write buffers.
+    pub max_write_buffer_number: Option<i32>,
+    /// Overridden number of bits per key in bloom filters.
+    pub bloom_filter_bits_per_key: Option<i32>,
+}
+
+#[cfg(feature = "rocksdb")]
+impl ColumnFamilyOverride {
+    fn matches(&self, name: &str, index_type: Option<IndexType>) -> bool {
+        let name_matches = self
+            .name_prefix
+            .as_ref()
+            .map_or(true, |prefix| name.starts_with(prefix.as_str()));
+        let type_matches = self.index_type.map_or(true, |ty| index_type == Some(ty));
+        name_matches && type_matches
+    }
+
+    fn apply(&self, options: &mut ColumnFamilyOptions) {
+        if let Some(compression_type) = self.compression_type {
+            options.compression_type = compression_type;
+        }
+        if self.block_cache_size.is_some() {
+            options.block_cache_size = self.block_cache_size;
+        }
+        if self.write_buffer_size.is_some() {
+            options.write_buffer_size = self.write_buffer_size;
+        }
+        if self.max_write_buffer_number.is_some() {
+            options.max_write_buffer_number = self.max_write_buffer_number;
+        }
+        if self.bloom_filter_bits_per_key.is_some() {
+            options.bloom_filter_bits_per_key = self.bloom_filter_bits_per_key;
+        }
+    }
+}
+
+/// Resolved options for a single column family.
+#[cfg(feature = "rocksdb")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ColumnFamilyOptions {
+    pub compression_type: CompressionType,
+    pub block_cache_size: Option<usize>,
+    pub write_buffer_size: Option<usize>,
+    pub max_write_buffer_number: Option<i32>,
+    pub bloom_filter_bits_per_key: Option<i32>,
+}
+
+/// Options for recording historical database states with [`merge_with_history`].
+///
+/// [`merge_with_history`]: trait.DatabaseExt.html#method.merge_with_history
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct HistoryOptions {
+    /// Number of past versions, for which historical states are retained. If the latest
+    /// merged version is `v`, states for versions `v - retained_versions ..= v` can be
+    /// restored.
+    ///
+    /// Defaults to `None`, meaning that all historical states are retained.
+    pub retained_versions: Option<u64>,
 }
 
 /// Algorithms of compression for the database.
@@ -56,15 +253,23 @@ pub struct DbOptions {
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum CompressionType {
+    /// Bzip2 compression.
     Bz2,
+    /// LZ4 compression.
     Lz4,
+    /// LZ4 compression in the high compression mode.
     Lz4hc,
+    /// Snappy compression.
     Snappy,
+    /// Zlib compression.
     Zlib,
+    /// Zstandard compression.
     Zstd,
+    /// No compression.
     None,
 }
 
+#[cfg(feature = "rocksdb")]
 impl From<CompressionType> for DBCompressionType {
     fn from(compression_type: CompressionType) -> Self {
         match compression_type {
@@ -85,6 +290,80 @@ impl Default for DbOptions {
             max_open_files: None,
             create_if_missing: true,
             compression_type: CompressionType::None,
+            block_cache_size: None,
+            write_buffer_size: None,
+            max_write_buffer_number: None,
+            bloom_filter_bits_per_key: None,
+            max_background_compactions: None,
+            max_background_flushes: None,
+            max_total_wal_size: None,
+            wal_dir: None,
+            column_family_overrides: vec![],
         }
     }
 }
+
+#[cfg(all(test, feature = "rocksdb"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_family_overrides() {
+        let options = DbOptions {
+            write_buffer_size: Some(1_024),
+            column_family_overrides: vec![
+                ColumnFamilyOverride {
+                    index_type: Some(IndexType::ProofList),
+                    write_buffer_size: Some(2_048),
+                    ..ColumnFamilyOverride::default()
+                },
+                ColumnFamilyOverride {
+                    name_prefix: Some("foo.".to_owned()),
+                    compression_type: Some(CompressionType::Lz4),
+                    bloom_filter_bits_per_key: Some(10),
+                    ..ColumnFamilyOverride::default()
+                },
+            ],
+            ..DbOptions::default()
+        };
+        assert!(options.has_index_type_overrides());
+
+        let cf_options = options.column_family_options("bar", Some(IndexType::ProofList));
+        assert_eq!(cf_options.write_buffer_size, Some(2_048));
+        assert_eq!(cf_options.compression_type, CompressionType::None);
+        let cf_options = options.column_family_options("bar", Some(IndexType::Map));
+        assert_eq!(cf_options.write_buffer_size, Some(1_024));
+        let cf_options = options.column_family_options("bar", None);
+        assert_eq!(cf_options.write_buffer_size, Some(1_024));
+
+        let cf_options = options.column_family_options("foo.list", Some(IndexType::ProofList));
+        assert_eq!(cf_options.write_buffer_size, Some(2_048));
+        assert_eq!(cf_options.compression_type, CompressionType::Lz4);
+        assert_eq!(cf_options.bloom_filter_bits_per_key, Some(10));
+        let cf_options = options.column_family_options("foo", None);
+        assert_eq!(cf_options.bloom_filter_bits_per_key, None);
+    }
+
+    #[test]
+    fn options_serialization() {
+        let options = DbOptions {
+            max_total_wal_size: Some(1 << 30),
+            column_family_overrides: vec![ColumnFamilyOverride {
+                name_prefix: Some("foo".to_owned()),
+                index_type: Some(IndexType::Map),
+                block_cache_size: Some(1 << 20),
+                ..ColumnFamilyOverride::default()
+            }],
+            ..DbOptions::default()
+        };
+        let json = serde_json::to_string(&options).unwrap();
+        let restored: DbOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, options);
+
+        // Options without the new fields can be deserialized.
+        let json =
+            r#"{ "max_open_files": null, "create_if_missing": true, "compression_type": "none" }"#;
+        let restored: DbOptions = serde_json::from_str(json).unwrap();
+        assert_eq!(restored, DbOptions::default());
+    }
+}
@@ -24,9 +24,12 @@ use exonum_proto::ProtobufConvert;
 use failure::{ensure, Error};
 use protobuf::{well_known_types::Empty, RepeatedField};
 
-use std::borrow::Cow;
+use std::{borrow::Cow, ops::Bound};
 
-use crate::{proof_map::ProofPath, BinaryKey, BinaryValue};
+use crate::{
+    proof_map::{ProofPath, Raw, ToProofPath},
+    BinaryKey, BinaryValue,
+};
 
 include!(concat!(env!("OUT_DIR"), "/protobuf_mod.rs"));
 
@@ -119,6 +122,70 @@ where
     }
 }
 
+fn key_bound_to_pb<K: BinaryKey + ?Sized>(bound: Bound<&K>) -> KeyBound {
+    let key_to_pb = |key: &K| {
+        let mut buf = vec![0_u8; key.size()];
+        key.write(&mut buf);
+        buf
+    };
+
+    let mut key_bound = KeyBound::new();
+    match bound {
+        Bound::Included(key) => key_bound.set_included(key_to_pb(key)),
+        Bound::Excluded(key) => key_bound.set_excluded(key_to_pb(key)),
+        Bound::Unbounded => key_bound.set_unbounded(Empty::new()),
+    }
+    key_bound
+}
+
+fn key_bound_from_pb<K: BinaryKey<Owned = K>>(pb: &KeyBound) -> Result<Bound<K>, Error> {
+    Ok(if pb.has_included() {
+        Bound::Included(K::read(pb.get_included()))
+    } else if pb.has_excluded() {
+        Bound::Excluded(K::read(pb.get_excluded()))
+    } else {
+        ensure!(pb.has_unbounded(), "malformed message, key bound is absent");
+        Bound::Unbounded
+    })
+}
+
+fn bound_as_ref<K>(bound: &Bound<K>) -> Bound<&K> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key),
+        Bound::Excluded(key) => Bound::Excluded(key),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl<K, V> ProtobufConvert for crate::MapRangeProof<K, V>
+where
+    K: BinaryKey + ToOwned<Owned = K> + Clone,
+    V: BinaryValue,
+    Raw: ToProofPath<K>,
+{
+    type ProtoStruct = MapRangeProof;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        let (start, end) = self.bounds_unchecked();
+        let mut range_proof = MapRangeProof::new();
+        range_proof.set_start(key_bound_to_pb(start));
+        range_proof.set_end(key_bound_to_pb(end));
+        range_proof.set_proof(self.as_map_proof().to_pb());
+        range_proof
+    }
+
+    fn from_pb(mut pb: Self::ProtoStruct) -> Result<Self, Error> {
+        let start = key_bound_from_pb::<K>(pb.get_start())?;
+        let end = key_bound_from_pb::<K>(pb.get_end())?;
+        let proof = crate::MapProof::from_pb(pb.take_proof())?;
+        Ok(crate::MapRangeProof::new(
+            bound_as_ref(&start),
+            bound_as_ref(&end),
+            proof,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use exonum_crypto::{proto::types, PublicKey};
@@ -129,7 +196,8 @@ mod tests {
 
     use crate::{
         access::AccessExt, indexes::proof_map::ToProofPath, proto, BinaryKey, BinaryValue,
-        Database, ListProof, MapProof, ObjectHash, TemporaryDB,
+        Database, ListConsistencyProof, ListProof, MapProof, MapRangeProof, ObjectHash,
+        TemporaryDB,
     };
 
     #[test]
@@ -171,6 +239,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn serialize_map_range_proof() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        let mut table = fork.get_raw_proof_map("index");
+        let keys: Vec<_> = (0_u8..16).map(|i| PublicKey::new([i; 32])).collect();
+
+        let proof = table.get_range_proof(keys[0]..keys[8]);
+        assert_range_proof_roundtrip(&proof);
+
+        for (i, key) in keys.iter().enumerate() {
+            table.put(key, i as u64);
+        }
+
+        let proof = table.get_range_proof(keys[3]..=keys[7]);
+        assert_range_proof_roundtrip(&proof);
+        let proof = table.get_range_proof(..keys[5]);
+        assert_range_proof_roundtrip(&proof);
+        let proof = table.get_range_proof(..);
+        assert_range_proof_roundtrip(&proof);
+    }
+
+    fn assert_range_proof_roundtrip(proof: &MapRangeProof<PublicKey, u64>) {
+        let pb = proof.to_pb();
+        let deserialized = MapRangeProof::from_pb(pb).unwrap();
+        let checked_proof = deserialized
+            .check()
+            .expect("deserialized proof is not valid");
+
+        assert_eq!(proof, &deserialized);
+        assert_eq!(
+            checked_proof.index_hash(),
+            proof.check().unwrap().index_hash()
+        );
+    }
+
     #[test]
     fn map_proof_malformed_serialize() {
         let mut proof = proto::MapProof::new();
@@ -243,6 +347,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn serialize_list_consistency_proof() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        let mut table = fork.get_proof_list("index");
+
+        let proof = table.get_consistency_proof(0);
+        assert_list_consistency_proof_roundtrip(&proof);
+
+        table.extend(0_u32..100);
+        for &old_length in &[0, 1, 31, 64, 99, 100] {
+            let proof = table.get_consistency_proof(old_length);
+            assert_list_consistency_proof_roundtrip(&proof);
+        }
+    }
+
+    fn assert_list_consistency_proof_roundtrip(proof: &ListConsistencyProof) {
+        let pb = proof.to_pb();
+        let deserialized = ListConsistencyProof::from_pb(pb).unwrap();
+        let checked_proof = deserialized
+            .check()
+            .expect("deserialized proof is not valid");
+
+        assert_eq!(proof, &deserialized);
+        assert_eq!(checked_proof, proof.check().unwrap());
+    }
+
     #[test]
     fn invalid_list_proof_key() {
         let mut proof = proto::ListProof::new();
@@ -78,8 +78,13 @@ pub(crate) fn check_index_valid_full_name(name: &str) -> Result<(), AccessErrorK
     check_valid_name(name, is_valid_identifier, "a-zA-Z0-9 and _-.")
 }
 
+/// Checks that provided name is a valid component of an index name.
+pub(crate) fn check_valid_name_component(name: &str) -> Result<(), AccessErrorKind> {
+    check_valid_name(name, is_valid_index_name_component, "a-zA-Z0-9 and _-")
+}
+
 pub(crate) fn assert_valid_name_component(name: &str) {
-    check_valid_name(name, is_valid_index_name_component, "a-zA-Z0-9 and _-").unwrap();
+    check_valid_name_component(name).unwrap();
 }
 
 #[cfg(test)]
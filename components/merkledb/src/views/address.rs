@@ -1,4 +1,4 @@
-use std::{borrow::Cow, num::NonZeroU64};
+use std::{borrow::Cow, num::NonZeroU64, ops::Bound};
 
 use crate::BinaryKey;
 
@@ -6,6 +6,16 @@ pub fn key_bytes<K: BinaryKey + ?Sized>(key: &K) -> Vec<u8> {
     concat_keys!(key)
 }
 
+/// Returns the lexicographically smallest byte string that is greater than all strings
+/// starting with `prefix`, or `None` if there is no such string (i.e., `prefix` is empty
+/// or consists solely of `0xff` bytes).
+pub fn next_prefix(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last_incrementable = prefix.iter().rposition(|&byte| byte != u8::max_value())?;
+    let mut next = prefix[..=last_incrementable].to_vec();
+    next[last_incrementable] += 1;
+    Some(next)
+}
+
 const SEPARATOR_CHAR: u8 = 0;
 const MIGRATION_CHAR: u8 = b'^';
 
@@ -69,7 +79,7 @@ impl IndexAddress {
     /// is defined as the component of the address name up (but not including) the first dot `'.'`
     /// char in the name (e.g., `foo` for address `^foo.bar`). For non-migrated indexes,
     /// the namespace is the empty string.
-    pub(super) fn namespace(&self) -> &str {
+    pub(crate) fn namespace(&self) -> &str {
         if self.in_migration {
             let dot_position = self.name.find('.');
             if let Some(pos) = dot_position {
@@ -149,6 +159,16 @@ impl IndexAddress {
         self.in_migration = true;
     }
 
+    pub(crate) fn in_migration(&self) -> bool {
+        self.in_migration
+    }
+
+    /// Checks if the address corresponds to a system index (e.g., the state aggregator),
+    /// which is maintained by the database itself.
+    pub(crate) fn is_system(&self) -> bool {
+        self.name.starts_with("__") && !self.name.contains('.')
+    }
+
     /// Full address with a separator between `name` and `bytes` represented as byte array.
     pub(super) fn fully_qualified_name(&self) -> Vec<u8> {
         /// Separator between the name and the additional bytes in family indexes.
@@ -165,6 +185,16 @@ impl IndexAddress {
         }
     }
 
+    /// Common prefix of fully qualified names of indexes in a group with this address.
+    /// The group keys are appended to this prefix.
+    pub(super) fn qualified_group_prefix(&self) -> Vec<u8> {
+        let mut prefix = self.fully_qualified_name();
+        if self.id_in_group.is_none() {
+            prefix.push(SEPARATOR_CHAR);
+        }
+        prefix
+    }
+
     /// Infers the name part of the fully qualified name that was obtained with
     /// `fully_qualified_name`. This is the part corresponding to `ResolvedAddress.name`.
     /// `min_name_len` specifies the minimum known length of the name part.
@@ -210,6 +240,23 @@ impl IndexAddress {
         (name, is_in_group)
     }
 
+    /// Restores an address from its fully qualified name obtained with `fully_qualified_name`.
+    pub(super) fn from_fully_qualified_name(qualified_name: &[u8]) -> Self {
+        let (name, is_in_group) = Self::parse_fully_qualified_name(qualified_name, 0);
+        let in_migration = qualified_name[0] == MIGRATION_CHAR;
+        let id_in_group = if is_in_group {
+            let separator_pos = name.len() + usize::from(in_migration);
+            Some(qualified_name[separator_pos + 1..].to_vec())
+        } else {
+            None
+        };
+        Self {
+            name,
+            id_in_group,
+            in_migration,
+        }
+    }
+
     /// Converts a migration namespace into the form that all indexes in the namespace
     /// begin with.
     #[inline]
@@ -293,6 +340,20 @@ impl ResolvedAddress {
             }
         }
     }
+
+    /// Converts an upper bound on the keys of the view into an upper bound on the keys
+    /// in the column family. An unbounded upper bound is restricted to the keys
+    /// prefixed by the `id`.
+    pub(crate) fn keyed_upper_bound(&self, bound: Bound<&[u8]>) -> Bound<Vec<u8>> {
+        match bound {
+            Bound::Included(key) => Bound::Included(self.keyed(key).into_owned()),
+            Bound::Excluded(key) => Bound::Excluded(self.keyed(key).into_owned()),
+            Bound::Unbounded => self
+                .id_to_bytes()
+                .and_then(|prefix| next_prefix(&prefix))
+                .map_or(Bound::Unbounded, Bound::Excluded),
+        }
+    }
 }
 
 // This conversion is only useful for tests, since all user-created indexes should have an ID set.
@@ -0,0 +1,184 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transparent compression of index values.
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::borrow::Cow;
+
+/// Tag of a value stored without compression.
+const RAW_VALUE_TAG: u8 = 0;
+/// Tag of a value compressed with zstd.
+const ZSTD_VALUE_TAG: u8 = 1;
+/// Minimum length of a value to be compressed. Shorter values (e.g., hashes in Merkle trees)
+/// are stored without compression, since compression overhead outweighs the gains for them.
+const MIN_COMPRESSED_LEN: usize = 64;
+/// Compression level used by zstd; zero means the default level.
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 0;
+
+/// Algorithm used to compress values stored in an index.
+///
+/// Compression is transparent for the index users: values are compressed when they are
+/// written to the index and decompressed when they are read. Compression does not influence
+/// hashes of Merkelized indexes, since the hashes are computed from the uncompressed values.
+///
+/// The compression algorithm is recorded in the index metadata when the index is created,
+/// and cannot be changed afterwards. Use [`Compressed`] access to create indexes
+/// with compressed values.
+///
+/// Zstd compression requires the `zstd` crate feature, which is enabled by default.
+/// If the feature is disabled, accessing indexes with zstd-compressed values results
+/// in an [`UnsupportedCompression`] error.
+///
+/// [`Compressed`]: access/struct.Compressed.html
+/// [`UnsupportedCompression`]: access/enum.AccessErrorKind.html#variant.UnsupportedCompression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ValueCompression {
+    /// Values are stored without compression.
+    None,
+    /// Values are compressed with [zstd](https://facebook.github.io/zstd/).
+    Zstd,
+}
+
+impl Default for ValueCompression {
+    fn default() -> Self {
+        ValueCompression::None
+    }
+}
+
+impl ValueCompression {
+    /// Converts the compression into a byte recorded in the index metadata.
+    pub(super) fn to_u8(self) -> u8 {
+        match self {
+            ValueCompression::None => 0,
+            ValueCompression::Zstd => 1,
+        }
+    }
+
+    /// Restores the compression from a byte recorded in the index metadata.
+    pub(super) fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ValueCompression::None),
+            1 => Some(ValueCompression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the compression is supported with the enabled crate features.
+    pub(crate) fn is_supported(self) -> bool {
+        match self {
+            ValueCompression::None => true,
+            ValueCompression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Converts a value into the form stored in the database.
+    pub(super) fn encode(self, value: Vec<u8>) -> Vec<u8> {
+        match self {
+            ValueCompression::None => value,
+            ValueCompression::Zstd => {
+                if value.len() >= MIN_COMPRESSED_LEN {
+                    let mut compressed = vec![ZSTD_VALUE_TAG];
+                    zstd_encode(&value, &mut compressed);
+                    // The value is stored uncompressed if the compression is not effective.
+                    if compressed.len() <= value.len() {
+                        return compressed;
+                    }
+                }
+
+                let mut stored = Vec::with_capacity(value.len() + 1);
+                stored.push(RAW_VALUE_TAG);
+                stored.extend_from_slice(&value);
+                stored
+            }
+        }
+    }
+
+    /// Restores a value from the form stored in the database.
+    pub(super) fn decode(self, stored: Cow<'_, [u8]>) -> Cow<'_, [u8]> {
+        if self == ValueCompression::None {
+            return stored;
+        }
+
+        let tag = stored.first().copied();
+        match tag {
+            Some(RAW_VALUE_TAG) => match stored {
+                Cow::Borrowed(stored) => Cow::Borrowed(&stored[1..]),
+                Cow::Owned(mut stored) => {
+                    stored.remove(0);
+                    Cow::Owned(stored)
+                }
+            },
+            Some(ZSTD_VALUE_TAG) => {
+                let mut value = vec![];
+                zstd_decode(&stored[1..], &mut value);
+                Cow::Owned(value)
+            }
+            _ => panic!("Invalid tag of a compressed value; the database may be corrupted"),
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_encode(value: &[u8], output: &mut Vec<u8>) {
+    zstd::stream::copy_encode(value, output, ZSTD_LEVEL).expect("Error while compressing value");
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decode(stored: &[u8], output: &mut Vec<u8>) {
+    zstd::stream::copy_decode(stored, output).expect("Error while decompressing value");
+}
+
+// Accessing indexes with unsupported compression via the public API results in an error,
+// so these functions are only reached by crate-wide operations, such as database dumps.
+#[cfg(not(feature = "zstd"))]
+fn zstd_encode(_value: &[u8], _output: &mut Vec<u8>) {
+    panic!("Zstd compression requires the `zstd` crate feature");
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decode(_stored: &[u8], _output: &mut Vec<u8>) {
+    panic!("Zstd compression requires the `zstd` crate feature");
+}
+
+#[cfg(all(test, feature = "zstd"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_roundtrip() {
+        let values = vec![
+            vec![],
+            vec![1, 2, 3],
+            vec![0; 1_000],
+            (0..=255).collect::<Vec<u8>>(),
+        ];
+        for value in values {
+            let stored = ValueCompression::Zstd.encode(value.clone());
+            assert!(stored.len() <= value.len() + 1);
+            let decoded = ValueCompression::Zstd.decode(Cow::Borrowed(&stored));
+            assert_eq!(decoded.as_ref(), value.as_slice());
+            let decoded = ValueCompression::Zstd.decode(Cow::Owned(stored));
+            assert_eq!(decoded.as_ref(), value.as_slice());
+        }
+
+        let stored = ValueCompression::Zstd.encode(vec![0; 1_000]);
+        assert_eq!(stored[0], ZSTD_VALUE_TAG);
+        assert!(stored.len() < 100);
+        let stored = ValueCompression::None.encode(vec![0; 1_000]);
+        assert_eq!(stored, vec![0; 1_000]);
+    }
+}
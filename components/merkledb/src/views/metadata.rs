@@ -15,15 +15,17 @@
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use enum_primitive_derive::Primitive;
 use exonum_crypto::Hash;
-use failure::{self, ensure, format_err};
+use failure::{self, bail, ensure, format_err};
 use num_traits::FromPrimitive;
 use serde_derive::{Deserialize, Serialize};
 
-use std::{borrow::Cow, io::Error, mem, num::NonZeroU64};
+use std::{borrow::Cow, collections::VecDeque, fmt, io::Error, mem, num::NonZeroU64};
 
-use super::{IndexAddress, RawAccess, RawAccessMut, ResolvedAddress, View};
+use super::{IndexAddress, RawAccess, RawAccessMut, ResolvedAddress, ValueCompression, View};
 use crate::{
     access::{AccessError, AccessErrorKind},
+    db::AggregationKey,
+    migration::PROGRESS_INDEX_NAME,
     validation::check_index_valid_full_name,
     BinaryValue,
 };
@@ -55,6 +57,10 @@ pub enum IndexType {
     ProofMap = 8,
     /// Merkelized entry.
     ProofEntry = 9,
+    /// Merkelized set index with elements stored as keys of a Merkle Patricia tree.
+    ProofKeySet = 10,
+    /// Non-merkelized map with automatically maintained secondary indexes.
+    IndexedMap = 11,
 
     /// Tombstone indicating necessity to remove an index after migration is completed.
     Tombstone = 254,
@@ -67,7 +73,10 @@ impl IndexType {
     /// Checks if the index of this type is Merkelized.
     pub fn is_merkelized(self) -> bool {
         match self {
-            IndexType::ProofList | IndexType::ProofMap | IndexType::ProofEntry => true,
+            IndexType::ProofList
+            | IndexType::ProofMap
+            | IndexType::ProofEntry
+            | IndexType::ProofKeySet => true,
             _ => false,
         }
     }
@@ -75,6 +84,10 @@ impl IndexType {
 
 /// Index state attribute tag.
 const INDEX_STATE_TAG: u32 = 0;
+/// Column family attribute tag.
+const COLUMN_FAMILY_TAG: u32 = 1;
+/// Value compression attribute tag.
+const COMPRESSION_TAG: u32 = 2;
 
 /// A type that can be (de)serialized as a metadata value.
 pub trait BinaryAttribute: Sized {
@@ -151,6 +164,42 @@ impl BinaryAttribute for Vec<u8> {
     }
 }
 
+/// Used to store names of secondary indexes of an `IndexedMap`. Each name is prefixed
+/// with its length as a `u32` in the little-endian encoding.
+impl BinaryAttribute for Vec<String> {
+    fn size(&self) -> usize {
+        self.iter()
+            .map(|name| mem::size_of::<u32>() + name.len())
+            .sum()
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        for name in self {
+            buffer.write_u32::<LittleEndian>(name.len() as u32).unwrap();
+            buffer.extend_from_slice(name.as_bytes());
+        }
+    }
+
+    fn read(mut buffer: &[u8]) -> Result<Self, Error> {
+        let mut names = vec![];
+        while !buffer.is_empty() {
+            let len = buffer.read_u32::<LittleEndian>()? as usize;
+            if buffer.len() < len {
+                return Err(Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Secondary index name is too short",
+                ));
+            }
+            let (name, rest) = buffer.split_at(len);
+            let name = String::from_utf8(name.to_vec())
+                .map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+            names.push(name);
+            buffer = rest;
+        }
+        Ok(names)
+    }
+}
+
 impl Default for IndexType {
     fn default() -> Self {
         IndexType::Unknown
@@ -161,7 +210,7 @@ impl Default for IndexType {
 /// In metadata one can store any arbitrary data serialized as byte array.
 ///
 /// See also `BinaryAttribute`.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IndexMetadata<V = Vec<u8>> {
     identifier: u64,
     index_type: IndexType,
@@ -170,6 +219,13 @@ pub struct IndexMetadata<V = Vec<u8>> {
     // calls `IndexState::unset()`. `None` option does not occupy space in the metadata
     // and can therefore be preferable to explicit "default" option.
     state: Option<V>,
+    // Name of the column family storing the index data. `None` means that the name
+    // coincides with the name of the index; this is always the case unless the index
+    // was moved with `Fork::move_index()`.
+    column_family: Option<String>,
+    // Compression of the index values. The attribute is not stored if the values
+    // are not compressed.
+    compression: ValueCompression,
 }
 
 impl<V> BinaryValue for IndexMetadata<V>
@@ -181,17 +237,35 @@ where
         if let Some(ref state) = self.state {
             capacity += mem::size_of_val(&INDEX_STATE_TAG) + mem::size_of::<u32>() + state.size();
         }
+        if let Some(ref column_family) = self.column_family {
+            capacity +=
+                mem::size_of_val(&COLUMN_FAMILY_TAG) + mem::size_of::<u32>() + column_family.len();
+        }
+        if self.compression != ValueCompression::None {
+            capacity += mem::size_of_val(&COMPRESSION_TAG) + mem::size_of::<u32>() + 1;
+        }
         let mut buf = Vec::with_capacity(capacity);
 
         buf.write_u64::<LittleEndian>(self.identifier).unwrap();
         buf.write_u32::<LittleEndian>(self.index_type as u32)
             .unwrap();
+        // Attributes are written in TLV (tag, length, value) form.
         if let Some(ref state) = self.state {
-            // Writes index state in TLV (tag, length, value) form.
             buf.write_u32::<LittleEndian>(INDEX_STATE_TAG).unwrap();
             buf.write_u32::<LittleEndian>(state.size() as u32).unwrap();
             state.write(&mut buf);
         }
+        if let Some(ref column_family) = self.column_family {
+            buf.write_u32::<LittleEndian>(COLUMN_FAMILY_TAG).unwrap();
+            buf.write_u32::<LittleEndian>(column_family.len() as u32)
+                .unwrap();
+            buf.extend_from_slice(column_family.as_bytes());
+        }
+        if self.compression != ValueCompression::None {
+            buf.write_u32::<LittleEndian>(COMPRESSION_TAG).unwrap();
+            buf.write_u32::<LittleEndian>(1).unwrap();
+            buf.push(self.compression.to_u8());
+        }
         buf
     }
 
@@ -203,31 +277,35 @@ where
         let index_type = IndexType::from_u32(index_type)
             .ok_or_else(|| format_err!("Unknown index type: {}", index_type))?;
 
-        if bytes.is_empty() {
-            // There are no tags in the metadata, correspondingly, no index state.
-            return Ok(Self {
-                identifier,
-                index_type,
-                state: None,
-            });
+        let mut state = None;
+        let mut column_family = None;
+        let mut compression = ValueCompression::None;
+        // Reads attributes in TLV (tag, length, value) form.
+        while !bytes.is_empty() {
+            let tag = bytes.read_u32::<LittleEndian>()?;
+            let len = bytes.read_u32::<LittleEndian>()? as usize;
+            ensure!(bytes.len() >= len, "Index attribute is too short");
+
+            let (value, rest) = bytes.split_at(len);
+            match tag {
+                INDEX_STATE_TAG => state = Some(V::read(value)?),
+                COLUMN_FAMILY_TAG => column_family = Some(String::from_utf8(value.to_vec())?),
+                COMPRESSION_TAG => {
+                    ensure!(value.len() == 1, "Invalid length of compression attribute");
+                    compression = ValueCompression::from_u8(value[0])
+                        .ok_or_else(|| format_err!("Unknown value compression: {}", value[0]))?;
+                }
+                _ => bail!("Attribute with unknown tag: {}", tag),
+            }
+            bytes = rest;
         }
 
-        // Reads index state in TLV (tag, length, value) form.
-        let state_tag = bytes.read_u32::<LittleEndian>()?;
-        let state_len = bytes.read_u32::<LittleEndian>()? as usize;
-
-        ensure!(
-            state_tag == INDEX_STATE_TAG,
-            "Attribute with unknown tag: {}",
-            state_tag
-        );
-        ensure!(bytes.len() >= state_len, "Index state is too short");
-
-        let state_bytes = &bytes[0..state_len];
         Ok(Self {
             identifier,
             index_type,
-            state: Some(V::read(state_bytes)?),
+            state,
+            column_family,
+            compression,
         })
     }
 }
@@ -237,14 +315,44 @@ impl<V> IndexMetadata<V> {
     pub fn index_type(&self) -> IndexType {
         self.index_type
     }
+
+    /// Returns the compression of the index values.
+    pub fn compression(&self) -> ValueCompression {
+        self.compression
+    }
 }
 
 impl IndexMetadata {
+    /// Returns the resolved address of the index with this metadata.
+    pub(crate) fn resolve(&self, index_address: &IndexAddress) -> ResolvedAddress {
+        self.resolve_name(index_address.name())
+    }
+
+    /// Returns the resolved address of the index with this metadata and the specified
+    /// name part of the address.
+    fn resolve_name(&self, index_name: &str) -> ResolvedAddress {
+        let name = self
+            .column_family
+            .clone()
+            .unwrap_or_else(|| index_name.to_owned());
+        ResolvedAddress {
+            name,
+            id: NonZeroU64::new(self.identifier),
+        }
+    }
+
+    /// Returns the serialized index state.
+    pub(crate) fn raw_state(&self) -> Option<&[u8]> {
+        self.state.as_ref().map(Vec::as_slice)
+    }
+
     fn convert<V: BinaryAttribute>(self) -> IndexMetadata<V> {
         let index_type = self.index_type;
         IndexMetadata {
             identifier: self.identifier,
             index_type,
+            column_family: self.column_family,
+            compression: self.compression,
             state: self.state.map(|state| {
                 V::read(&state).unwrap_or_else(|e| {
                     panic!(
@@ -277,6 +385,16 @@ where
     }
 }
 
+impl<T, V> IndexState<T, V>
+where
+    T: RawAccess,
+    V: BinaryAttribute,
+{
+    pub fn get_ref(&self) -> Option<&V> {
+        self.metadata.state.as_ref()
+    }
+}
+
 impl<T, V> IndexState<T, V>
 where
     T: RawAccessMut,
@@ -318,6 +436,42 @@ impl<T: RawAccess> IndexesPool<T> {
         self.0.get(index_name)
     }
 
+    /// Iterates over all indexes in the pool in the order of their fully qualified names.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (IndexAddress, IndexMetadata)> + '_ {
+        // The empty key is occupied by the pool length, so we skip it.
+        self.0
+            .iter_from::<_, _, [u8], IndexMetadata>(&(), &[0_u8][..])
+            .map(|(full_name, metadata)| {
+                let address = IndexAddress::from_fully_qualified_name(&full_name);
+                (address, metadata)
+            })
+    }
+
+    /// Same as `iter`, but returns an error for metadata that cannot be decoded instead
+    /// of panicking.
+    pub(crate) fn iter_checked(
+        &self,
+    ) -> impl Iterator<Item = (IndexAddress, Result<IndexMetadata, failure::Error>)> + '_ {
+        self.0
+            .iter_from::<_, _, [u8], Vec<u8>>(&(), &[0_u8][..])
+            .map(|(full_name, bytes)| {
+                let address = IndexAddress::from_fully_qualified_name(&full_name);
+                (address, IndexMetadata::from_bytes(bytes.into()))
+            })
+    }
+
+    /// Returns up to `limit` fully qualified names of indexes starting with `prefix`,
+    /// beginning from `from` (inclusive). Tombstones are skipped.
+    fn names_with_prefix(&self, prefix: &[u8], from: &[u8], limit: usize) -> Vec<Vec<u8>> {
+        self.0
+            .iter_from::<_, _, Vec<u8>, IndexMetadata>(&(), from)
+            .take_while(|(full_name, _)| full_name.starts_with(prefix))
+            .filter(|(_, metadata)| metadata.index_type != IndexType::Tombstone)
+            .map(|(full_name, _)| full_name)
+            .take(limit)
+            .collect()
+    }
+
     fn set_len(&mut self, len: u64) {
         self.0.put_or_forget(&(), len);
     }
@@ -330,6 +484,7 @@ impl<T: RawAccess> IndexesPool<T> {
         &mut self,
         index_name: &[u8],
         index_type: IndexType,
+        compression: ValueCompression,
     ) -> (IndexMetadata<V>, bool)
     where
         V: BinaryAttribute,
@@ -340,6 +495,8 @@ impl<T: RawAccess> IndexesPool<T> {
             // ^-- Identifier should be non-zero to translate to a correct id in `ResolvedAddress`
             index_type,
             state: None,
+            column_family: None,
+            compression,
         };
         let is_phantom = !self.0.put_or_forget(index_name, metadata.to_bytes());
         self.set_len(len + 1);
@@ -351,7 +508,8 @@ impl<T: RawAccessMut> IndexesPool<T> {
     /// Moves indexes with the specified prefix from the next version (i.e., `^prefix.*` form)
     /// to the current version (`prefix.*` form). The existing old indexes are replaced, or
     /// removed if the new index is a `Tombstone`. If there is no overriding index, an old
-    /// index is left in place.
+    /// index is left in place. The progress of the migration script stored in the migration
+    /// is removed.
     ///
     /// # Return value
     ///
@@ -364,10 +522,19 @@ impl<T: RawAccessMut> IndexesPool<T> {
         // the name part of the address doesn't start with '^'. Since the '^' char is removed from
         // the name, this length is one lesser than the length of the `prefix`.
         let min_name_len = prefix.len() - 1;
+        let progress_key = [prefix.as_slice(), PROGRESS_INDEX_NAME.as_bytes()].concat();
 
         let moved_indexes: Vec<_> = self.0.iter::<_, Vec<u8>, IndexMetadata>(&prefix).collect();
         let mut removed_addrs = Vec::new();
         for (key, metadata) in moved_indexes {
+            if key == progress_key {
+                // The progress of the migration script is not moved; it is removed instead.
+                let (name, _) = IndexAddress::parse_fully_qualified_name(&key, prefix.len());
+                removed_addrs.push((metadata.resolve_name(&name), false));
+                self.0.remove(&key);
+                continue;
+            }
+
             let migrated_key = IndexAddress::migrate_qualified_name(&key);
             debug_assert!({
                 let migrated_prefix = IndexAddress::migrate_qualified_name(&prefix);
@@ -377,10 +544,7 @@ impl<T: RawAccessMut> IndexesPool<T> {
             if let Some(old_metadata) = self.0.get::<_, IndexMetadata>(migrated_key) {
                 let (name, is_in_group) =
                     IndexAddress::parse_fully_qualified_name(migrated_key, min_name_len);
-                let resolved = ResolvedAddress {
-                    name,
-                    id: NonZeroU64::new(old_metadata.identifier),
-                };
+                let resolved = old_metadata.resolve_name(&name);
                 let is_removed_from_aggregation = !is_in_group
                     && old_metadata.index_type.is_merkelized()
                     && !metadata.index_type.is_merkelized();
@@ -398,6 +562,69 @@ impl<T: RawAccessMut> IndexesPool<T> {
         removed_addrs
     }
 
+    /// Moves the index at `from` to the address `to`. If `from` does not contain a key part,
+    /// the indexes in the group with the address `from` are moved as well, with their keys
+    /// appended to `to`. Index data is not moved; instead, the metadata of the moved indexes
+    /// records the column family where the data is stored.
+    ///
+    /// # Return value
+    ///
+    /// Returns the old and new addresses of the moved indexes together with their metadata.
+    pub(crate) fn move_index(
+        &mut self,
+        from: &IndexAddress,
+        to: &IndexAddress,
+    ) -> Result<Vec<(IndexAddress, IndexAddress, IndexMetadata)>, AccessError> {
+        let mut moved_indexes = vec![];
+        if let Some(metadata) = self.index_metadata(&from.fully_qualified_name()) {
+            moved_indexes.push((from.to_owned(), to.to_owned(), metadata));
+        }
+        if from.id_in_group.is_none() {
+            let prefix = from.qualified_group_prefix();
+            let group_indexes = self
+                .0
+                .iter_from::<_, _, Vec<u8>, IndexMetadata>(&(), &prefix)
+                .take_while(|(full_name, _)| full_name.starts_with(&prefix));
+            for (full_name, metadata) in group_indexes {
+                let key = &full_name[prefix.len()..];
+                let old_address = IndexAddress::from_fully_qualified_name(&full_name);
+                let new_address = to.to_owned().append_key(key);
+                moved_indexes.push((old_address, new_address, metadata));
+            }
+        }
+
+        if moved_indexes.is_empty() {
+            return Err(AccessError {
+                addr: from.to_owned(),
+                kind: AccessErrorKind::DoesNotExist,
+            });
+        }
+        for (_, new_address, _) in &moved_indexes {
+            if self
+                .index_metadata(&new_address.fully_qualified_name())
+                .is_some()
+            {
+                return Err(AccessError {
+                    addr: new_address.to_owned(),
+                    kind: AccessErrorKind::AlreadyExists,
+                });
+            }
+        }
+
+        for (old_address, new_address, metadata) in &mut moved_indexes {
+            let column_family = metadata.resolve(old_address).name;
+            metadata.column_family = if column_family == new_address.name() {
+                None
+            } else {
+                Some(column_family)
+            };
+            self.0.remove(&old_address.fully_qualified_name());
+            self.0
+                .put(&new_address.fully_qualified_name(), metadata.clone());
+        }
+        Ok(moved_indexes)
+    }
+
     pub(crate) fn rollback_migration(&mut self, prefix: &str) -> Vec<ResolvedAddress> {
         let prefix = IndexAddress::qualify_migration_namespace(prefix);
         let (removed_names, removed_addrs): (Vec<_>, Vec<_>) = self
@@ -405,11 +632,7 @@ impl<T: RawAccessMut> IndexesPool<T> {
             .iter::<_, Vec<u8>, IndexMetadata>(&prefix)
             .map(|(key, metadata)| {
                 let (name, _) = IndexAddress::parse_fully_qualified_name(&key, prefix.len());
-                let resolved = ResolvedAddress {
-                    name,
-                    id: NonZeroU64::new(metadata.identifier),
-                };
-                (key, resolved)
+                (key, metadata.resolve_name(&name))
             })
             .unzip();
         for full_name in &removed_names {
@@ -419,15 +642,89 @@ impl<T: RawAccessMut> IndexesPool<T> {
     }
 }
 
-/// Obtains `object_hash` for an aggregated index.
-pub fn get_object_hash<T: RawAccess>(
+/// Iterator over keys of indexes in a group, returned by [`AccessGroups::group_keys`].
+///
+/// The keys are returned in the serialized form in the order of their fully qualified
+/// index names, i.e., in the lexicographic order of serialized keys.
+///
+/// The iterator reads metadata from the indexes pool in batches and does not hold
+/// the pool between batches. Thus, it is possible to access (and create) indexes
+/// in the group while iterating over it; the indexes created during iteration may or may not
+/// be returned by the iterator.
+///
+/// [`AccessGroups::group_keys`]: trait.AccessGroups.html#tymethod.group_keys
+pub struct GroupKeys<T: RawAccess> {
     access: T,
-    addr: ResolvedAddress,
-    is_in_migration: bool,
-) -> Hash {
+    prefix: Vec<u8>,
+    next_name: Option<Vec<u8>>,
+    buffer: VecDeque<Vec<u8>>,
+}
+
+impl<T: RawAccess> fmt::Debug for GroupKeys<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("GroupKeys")
+            .field("prefix", &self.prefix)
+            .field("next_name", &self.next_name)
+            .finish()
+    }
+}
+
+impl<T: RawAccess> GroupKeys<T> {
+    /// Number of index names loaded from the pool at once.
+    const BATCH_SIZE: usize = 256;
+
+    pub(crate) fn new(access: T, group_addr: &IndexAddress, from: &[u8]) -> Self {
+        let prefix = group_addr.qualified_group_prefix();
+        let mut next_name = prefix.clone();
+        next_name.extend_from_slice(from);
+        Self {
+            access,
+            prefix,
+            next_name: Some(next_name),
+            buffer: VecDeque::new(),
+        }
+    }
+
+    fn load_batch(&mut self) {
+        let from = match self.next_name.take() {
+            Some(name) => name,
+            None => return,
+        };
+
+        let pool = IndexesPool::new(self.access.clone());
+        let names = pool.names_with_prefix(&self.prefix, &from, Self::BATCH_SIZE);
+        if names.len() == Self::BATCH_SIZE {
+            // The smallest name greater than the last loaded one.
+            let mut next_name = names[names.len() - 1].clone();
+            next_name.push(0);
+            self.next_name = Some(next_name);
+        }
+
+        let prefix_len = self.prefix.len();
+        self.buffer = names
+            .into_iter()
+            .map(|mut name| name.split_off(prefix_len))
+            .collect();
+    }
+}
+
+impl<T: RawAccess> Iterator for GroupKeys<T> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() {
+            self.load_batch();
+        }
+        self.buffer.pop_front()
+    }
+}
+
+/// Obtains `object_hash` for an aggregated index.
+pub fn get_object_hash<T: RawAccess>(access: T, index_name: &str, is_in_migration: bool) -> Hash {
     use crate::{ObjectHash, ProofListIndex, ProofMapIndex};
 
-    let mut original_addr = IndexAddress::from_root(&addr.name);
+    let mut original_addr = IndexAddress::from_root(index_name);
     if is_in_migration {
         original_addr.in_migration = true;
     }
@@ -436,7 +733,7 @@ pub fn get_object_hash<T: RawAccess>(
     let metadata = IndexesPool::new(access.clone())
         .index_metadata(&index_full_name)
         .unwrap_or_else(|| {
-            panic!("Metadata absent for aggregated index {:?}", addr);
+            panic!("Metadata absent for aggregated index {:?}", original_addr);
         });
     let index_type = metadata.index_type;
 
@@ -445,13 +742,9 @@ pub fn get_object_hash<T: RawAccess>(
             // Hash is stored directly in the metadata.
             metadata.convert::<Hash>().state.unwrap_or_default()
         }
-        IndexType::ProofList | IndexType::ProofMap => {
-            let view_with_metadata = ViewWithMetadata {
-                view: View::new(access, addr),
-                metadata,
-                index_full_name,
-                is_phantom: false,
-            };
+        IndexType::ProofList | IndexType::ProofMap | IndexType::ProofKeySet => {
+            let view_with_metadata =
+                ViewWithMetadata::from_metadata(access, &original_addr, metadata);
 
             if index_type == IndexType::ProofList {
                 // We don't access list elements, so the element type doesn't matter.
@@ -459,6 +752,7 @@ pub fn get_object_hash<T: RawAccess>(
                 list.object_hash()
             } else {
                 // We don't access map elements, so the key / value types don't matter.
+                // Key sets are stored as maps with `()` values and have the same hash.
                 let map = ProofMapIndex::<_, (), ()>::new(view_with_metadata);
                 map.object_hash()
             }
@@ -486,12 +780,46 @@ where
         index_access: T,
         index_address: &IndexAddress,
         index_type: IndexType,
+    ) -> Result<Self, AccessError> {
+        Self::get_or_create_compressed(
+            index_access,
+            index_address,
+            index_type,
+            ValueCompression::None,
+        )
+    }
+
+    /// Same as `get_or_create`, but the values of a created index are compressed
+    /// with the specified algorithm. Existing indexes use the compression recorded
+    /// in their metadata.
+    pub(crate) fn get_or_create_compressed(
+        index_access: T,
+        index_address: &IndexAddress,
+        index_type: IndexType,
+        compression: ValueCompression,
     ) -> Result<Self, AccessError> {
         check_index_valid_full_name(&index_address.name).map_err(|kind| AccessError {
             addr: index_address.to_owned(),
             kind,
         })?;
-        Self::get_or_create_unchecked(index_access, index_address, index_type)
+        Self::get_or_create_inner(index_access, index_address, index_type, compression)
+    }
+
+    /// Creates a view for an existing index with the specified metadata.
+    pub(crate) fn from_metadata(
+        index_access: T,
+        index_address: &IndexAddress,
+        metadata: IndexMetadata,
+    ) -> Self {
+        let addr = metadata.resolve(index_address);
+        let mut view = View::new(index_access, addr);
+        view.set_compression(metadata.compression);
+        Self {
+            view,
+            metadata,
+            index_full_name: index_address.fully_qualified_name(),
+            is_phantom: false,
+        }
     }
 
     /// Gets index metadata. Unlike `get_or_create`, this method will not create an index
@@ -519,6 +847,34 @@ where
         index_access: T,
         index_address: &IndexAddress,
         index_type: IndexType,
+    ) -> Result<Self, AccessError> {
+        Self::get_or_create_inner(
+            index_access,
+            index_address,
+            index_type,
+            ValueCompression::None,
+        )
+    }
+
+    fn check_compression(
+        index_address: &IndexAddress,
+        compression: ValueCompression,
+    ) -> Result<(), AccessError> {
+        if compression.is_supported() {
+            Ok(())
+        } else {
+            Err(AccessError {
+                kind: AccessErrorKind::UnsupportedCompression(compression),
+                addr: index_address.to_owned(),
+            })
+        }
+    }
+
+    fn get_or_create_inner(
+        index_access: T,
+        index_address: &IndexAddress,
+        index_type: IndexType,
+        compression: ValueCompression,
     ) -> Result<Self, AccessError> {
         if index_type == IndexType::Tombstone && !index_address.in_migration {
             return Err(AccessError {
@@ -527,28 +883,34 @@ where
             });
         }
 
-        // Actual name.
-        let index_name = index_address.name().to_owned();
         // Full name for internal usage.
         let index_full_name = index_address.fully_qualified_name();
 
         let mut pool = IndexesPool::new(index_access.clone());
         let mut is_phantom = false;
-        let metadata = pool.index_metadata(&index_full_name).unwrap_or_else(|| {
-            let (metadata, phantom_flag) = pool.create_index_metadata(&index_full_name, index_type);
-            is_phantom = phantom_flag;
-            metadata
-        });
-        let real_index_type = metadata.index_type;
-        let addr = ResolvedAddress {
-            name: index_name,
-            id: NonZeroU64::new(metadata.identifier),
+        let metadata = match pool.index_metadata(&index_full_name) {
+            Some(metadata) => {
+                Self::check_compression(index_address, metadata.compression)?;
+                metadata
+            }
+            None => {
+                Self::check_compression(index_address, compression)?;
+                let (metadata, phantom_flag) =
+                    pool.create_index_metadata(&index_full_name, index_type, compression);
+                is_phantom = phantom_flag;
+                metadata
+            }
         };
+        let real_index_type = metadata.index_type;
+        let addr = metadata.resolve(index_address);
 
         let is_aggregated =
             !is_phantom && real_index_type.is_merkelized() && index_address.id_in_group.is_none();
-        let namespace = if is_aggregated {
-            Some(index_address.namespace().to_owned())
+        let aggregation = if is_aggregated {
+            Some(AggregationKey {
+                namespace: index_address.namespace().to_owned(),
+                name: index_address.name().to_owned(),
+            })
         } else {
             None
         };
@@ -558,7 +920,9 @@ where
         } else {
             View::new(index_access, addr)
         };
-        view.set_or_forget_aggregation(namespace);
+        view.set_compression(metadata.compression);
+        view.set_or_forget_aggregation(aggregation);
+        view.set_or_forget_index_type(real_index_type);
         let this = Self {
             view,
             metadata,
@@ -617,6 +981,19 @@ mod tests {
             identifier: 12,
             index_type: IndexType::ProofList,
             state: Some(16_u64),
+            column_family: None,
+            compression: ValueCompression::None,
+        };
+
+        let bytes = metadata.to_bytes();
+        assert_eq!(IndexMetadata::from_bytes(bytes.into()).unwrap(), metadata);
+
+        let metadata = IndexMetadata {
+            identifier: 12,
+            index_type: IndexType::ProofList,
+            state: None::<u64>,
+            column_family: None,
+            compression: ValueCompression::None,
         };
 
         let bytes = metadata.to_bytes();
@@ -625,7 +1002,20 @@ mod tests {
         let metadata = IndexMetadata {
             identifier: 12,
             index_type: IndexType::ProofList,
+            state: Some(16_u64),
+            column_family: Some("moved".to_owned()),
+            compression: ValueCompression::None,
+        };
+
+        let bytes = metadata.to_bytes();
+        assert_eq!(IndexMetadata::from_bytes(bytes.into()).unwrap(), metadata);
+
+        let metadata = IndexMetadata {
+            identifier: 12,
+            index_type: IndexType::Map,
             state: None::<u64>,
+            column_family: Some("moved".to_owned()),
+            compression: ValueCompression::Zstd,
         };
 
         let bytes = metadata.to_bytes();
@@ -639,6 +1029,8 @@ mod tests {
             identifier: 12,
             index_type: IndexType::ProofList,
             state: Some(16_u64),
+            column_family: None,
+            compression: ValueCompression::None,
         };
 
         let mut bytes = metadata.to_bytes();
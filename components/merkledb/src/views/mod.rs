@@ -14,22 +14,30 @@
 
 pub use self::{
     address::{IndexAddress, ResolvedAddress},
+    compression::ValueCompression,
     metadata::{
-        get_object_hash, BinaryAttribute, IndexMetadata, IndexState, IndexType, IndexesPool,
-        ViewWithMetadata,
+        get_object_hash, BinaryAttribute, GroupKeys, IndexMetadata, IndexState, IndexType,
+        IndexesPool, ViewWithMetadata,
+    },
+    system_schema::{
+        aggregation_prefix, get_aggregated_prefixes, get_state_aggregator, SystemSchema,
+        PREFIX_AGGREGATOR, STATE_AGGREGATOR,
     },
-    system_schema::{get_state_aggregator, SystemSchema},
 };
 
-use std::{borrow::Cow, fmt, iter::Peekable, marker::PhantomData};
+pub(crate) use self::address::next_prefix;
+
+use std::{borrow::Cow, fmt, iter::Peekable, marker::PhantomData, ops::Bound};
 
 use self::address::key_bytes;
 use super::{
-    db::{Change, ChangesMut, ChangesRef, ForkIter, ViewChanges},
-    BinaryKey, BinaryValue, Iter as BytesIter, Iterator as BytesIterator, Snapshot,
+    db::{AggregationKey, Change, ChangesMut, ChangesRef, ForkIter, ViewChanges},
+    BinaryKey, BinaryValue, Iter as BytesIter, Iterator as BytesIterator, SizeLimitExceeded,
+    Snapshot,
 };
 
 mod address;
+mod compression;
 mod metadata;
 mod system_schema;
 #[cfg(test)]
@@ -48,6 +56,7 @@ pub struct ViewInner<T: RawAccess> {
     address: ResolvedAddress,
     index_access: T,
     changes: T::Changes,
+    compression: ValueCompression,
 }
 
 impl<T: RawAccess> fmt::Debug for ViewInner<T> {
@@ -55,6 +64,7 @@ impl<T: RawAccess> fmt::Debug for ViewInner<T> {
         formatter
             .debug_struct("ViewInner")
             .field("address", &self.address)
+            .field("compression", &self.compression)
             .finish()
     }
 }
@@ -65,6 +75,13 @@ pub trait ChangeSet {
     /// Provides mutable reference to changes. The implementation for a `RawAccessMut` type
     /// should always return `Some(_)`.
     fn as_mut(&mut self) -> Option<&mut ViewChanges>;
+    /// Checks whether the changes may be modified so that their size becomes `new_size`.
+    /// Returns an error if the size limit for the changes would be exceeded.
+    fn check_size(&self, _new_size: usize) -> Result<(), SizeLimitExceeded> {
+        Ok(())
+    }
+    /// Accounts the size of the changes after they were modified.
+    fn account_size(&mut self) {}
 }
 
 /// No-op implementation used in `Snapshot`.
@@ -93,6 +110,12 @@ impl ChangeSet for ChangesMut<'_> {
     fn as_mut(&mut self) -> Option<&mut ViewChanges> {
         Some(&mut *self)
     }
+    fn check_size(&self, new_size: usize) -> Result<(), SizeLimitExceeded> {
+        ChangesMut::check_size(self, new_size)
+    }
+    fn account_size(&mut self) {
+        ChangesMut::account_size(self);
+    }
 }
 
 /// Allows to read data from the database. The data consists of a snapshot and
@@ -214,10 +237,38 @@ impl<T: RawAccess> ViewInner<T> {
             // Ignore all changes from the snapshot.
             Box::new(ChangesIter::new(changes_iter.unwrap()))
         } else {
-            Box::new(ForkIter::new(
-                self.snapshot().iter(&self.address, from),
-                changes_iter,
-            ))
+            let snapshot_iter = self.snapshot().iter(&self.address, from);
+            let snapshot_iter = match self.changes.as_ref() {
+                Some(changes) => {
+                    changes.skip_removed(self.snapshot(), &self.address, snapshot_iter, false)
+                }
+                None => snapshot_iter,
+            };
+            Box::new(ForkIter::new(snapshot_iter, changes_iter))
+        }
+    }
+
+    fn iter_bytes_rev(&self, upper_bound: Bound<&[u8]>) -> BytesIter<'_> {
+        let changes_iter = self.changes.as_ref().map(|changes| {
+            changes
+                .data
+                .range::<[u8], _>((Bound::Unbounded, upper_bound))
+                .rev()
+        });
+
+        let is_cleared = self.changes.as_ref().map_or(false, ViewChanges::is_cleared);
+        if is_cleared {
+            // Ignore all changes from the snapshot.
+            Box::new(ChangesIter::new(changes_iter.unwrap()))
+        } else {
+            let snapshot_iter = self.snapshot().iter_rev(&self.address, upper_bound);
+            let snapshot_iter = match self.changes.as_ref() {
+                Some(changes) => {
+                    changes.skip_removed(self.snapshot(), &self.address, snapshot_iter, true)
+                }
+                None => snapshot_iter,
+            };
+            Box::new(ForkIter::new_rev(snapshot_iter, changes_iter))
         }
     }
 }
@@ -231,6 +282,7 @@ impl<T: RawAccess> View<T> {
             index_access,
             changes,
             address,
+            compression: ValueCompression::None,
         })
     }
 
@@ -248,6 +300,20 @@ impl<T: RawAccess> View<T> {
         }
     }
 
+    /// Sets the compression of values in the view.
+    pub(crate) fn set_compression(&mut self, compression: ValueCompression) {
+        if let View::Real(inner) = self {
+            inner.compression = compression;
+        }
+    }
+
+    fn compression(&self) -> ValueCompression {
+        match self {
+            View::Real(inner) => inner.compression,
+            View::Phantom => ValueCompression::None,
+        }
+    }
+
     fn get_bytes(&self, key: &[u8]) -> Option<Vec<u8>> {
         match self {
             View::Real(inner) => inner.get_bytes(key),
@@ -269,6 +335,13 @@ impl<T: RawAccess> View<T> {
         }
     }
 
+    fn iter_bytes_rev(&self, upper_bound: Bound<&[u8]>) -> BytesIter<'_> {
+        match self {
+            View::Real(inner) => inner.iter_bytes_rev(upper_bound),
+            View::Phantom => Box::new(EmptyIterator),
+        }
+    }
+
     /// Returns a value of *any* type corresponding to the key of *any* type.
     pub fn get<K, V>(&self, key: &K) -> Option<V>
     where
@@ -276,7 +349,8 @@ impl<T: RawAccess> View<T> {
         V: BinaryValue,
     {
         self.get_bytes(&key_bytes(key)).map(|v| {
-            BinaryValue::from_bytes(Cow::Owned(v)).expect("Error while deserializing value")
+            let v = self.compression().decode(Cow::Owned(v));
+            BinaryValue::from_bytes(v).expect("Error while deserializing value")
         })
     }
 
@@ -302,7 +376,9 @@ impl<T: RawAccess> View<T> {
         Iter {
             base_iter: self.iter_bytes(&iter_prefix),
             prefix: iter_prefix,
+            upper_bound: Bound::Unbounded,
             ended: false,
+            compression: self.compression(),
             _k: PhantomData,
             _v: PhantomData,
         }
@@ -323,7 +399,100 @@ impl<T: RawAccess> View<T> {
         Iter {
             base_iter: self.iter_bytes(&iter_from),
             prefix: iter_prefix,
+            upper_bound: Bound::Unbounded,
+            ended: false,
+            compression: self.compression(),
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the index in ascending order within
+    /// the specified key range. The iterator element type is *any* key-value pair.
+    /// An argument `subprefix` allows specifying a subset of iteration.
+    pub fn iter_range<P, F, K, V>(
+        &self,
+        subprefix: &P,
+        start: Bound<&F>,
+        end: Bound<&F>,
+    ) -> Iter<'_, K, V>
+    where
+        P: BinaryKey + ?Sized,
+        F: BinaryKey + ?Sized,
+        K: BinaryKey + ?Sized,
+        V: BinaryValue,
+    {
+        let iter_prefix = key_bytes(subprefix);
+        let iter_from = match start {
+            Bound::Included(from) => key_bytes(from),
+            Bound::Excluded(from) => {
+                // The smallest key greater than `from`.
+                let mut from = key_bytes(from);
+                from.push(0);
+                from
+            }
+            Bound::Unbounded => iter_prefix.clone(),
+        };
+        let upper_bound = match end {
+            Bound::Included(to) => Bound::Included(key_bytes(to)),
+            Bound::Excluded(to) => Bound::Excluded(key_bytes(to)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        Iter {
+            base_iter: self.iter_bytes(&iter_from),
+            prefix: iter_prefix,
+            upper_bound,
+            ended: false,
+            compression: self.compression(),
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the index in descending order. The iterator
+    /// element type is *any* key-value pair. An argument `subprefix` allows specifying a subset
+    /// of keys for iteration.
+    pub fn iter_rev<P, K, V>(&self, subprefix: &P) -> Iter<'_, K, V>
+    where
+        P: BinaryKey + ?Sized,
+        K: BinaryKey + ?Sized,
+        V: BinaryValue,
+    {
+        let iter_prefix = key_bytes(subprefix);
+        let base_iter = match next_prefix(&iter_prefix) {
+            Some(end) => self.iter_bytes_rev(Bound::Excluded(&end[..])),
+            None => self.iter_bytes_rev(Bound::Unbounded),
+        };
+        Iter {
+            base_iter,
+            prefix: iter_prefix,
+            upper_bound: Bound::Unbounded,
             ended: false,
+            compression: self.compression(),
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the index in descending order starting from the
+    /// specified key (inclusive). The iterator element type is *any* key-value pair.
+    /// An argument `subprefix` allows specifying a subset of iteration.
+    pub fn iter_rev_from<P, F, K, V>(&self, subprefix: &P, from: &F) -> Iter<'_, K, V>
+    where
+        P: BinaryKey + ?Sized,
+        F: BinaryKey + ?Sized,
+        K: BinaryKey + ?Sized,
+        V: BinaryValue,
+    {
+        let iter_prefix = key_bytes(subprefix);
+        let iter_from = key_bytes(from);
+        Iter {
+            base_iter: self.iter_bytes_rev(Bound::Included(&iter_from[..])),
+            prefix: iter_prefix,
+            upper_bound: Bound::Unbounded,
+            ended: false,
+            compression: self.compression(),
             _k: PhantomData,
             _v: PhantomData,
         }
@@ -341,10 +510,12 @@ impl<T: RawAccess> View<T> {
         V: BinaryValue,
     {
         if let View::Real(inner) = self {
+            let value = inner.compression.encode(value.into_bytes());
             if let Some(changes) = inner.changes.as_mut() {
-                changes
-                    .data
-                    .insert(concat_keys!(key), Change::Put(value.into_bytes()));
+                changes.put(concat_keys!(key), Change::Put(value));
+                // Metadata writes are system writes, so they are not subject to the size limit;
+                // the size of the changes is still accounted.
+                inner.changes.account_size();
                 return true;
             }
         }
@@ -355,34 +526,75 @@ impl<T: RawAccess> View<T> {
     /// (in which case, the flag is forgotten).
     ///
     /// The aggregation flag is used by `Fork::into_patch()` to update the state aggregator.
-    pub(crate) fn set_or_forget_aggregation(&mut self, namespace: Option<String>) {
+    pub(crate) fn set_or_forget_aggregation(&mut self, aggregation: Option<AggregationKey>) {
+        if let View::Real(ViewInner { changes, .. }) = self {
+            if let Some(changes) = changes.as_mut() {
+                changes.set_aggregation(aggregation);
+            }
+        }
+    }
+
+    /// Records the type of the index backed by the view, unless the view is backed
+    /// by a readonly access (in which case, the type is forgotten).
+    ///
+    /// The type is used by database backends when creating storage for new indexes.
+    pub(crate) fn set_or_forget_index_type(&mut self, index_type: IndexType) {
         if let View::Real(ViewInner { changes, .. }) = self {
             if let Some(changes) = changes.as_mut() {
-                changes.set_aggregation(namespace);
+                changes.set_index_type(index_type);
             }
         }
     }
 }
 
 impl<T: RawAccessMut> View<T> {
-    fn changes_mut(&mut self) -> &mut ViewChanges {
+    fn changes_mut(&mut self) -> &mut T::Changes {
         match self {
-            View::Real(ViewInner { changes, .. }) => changes.as_mut().unwrap(),
+            View::Real(ViewInner { changes, .. }) => changes,
             View::Phantom => unreachable!(
                 "Mutable accesses should create views on demand rather than return phantom views"
             ),
         }
     }
 
+    /// Modifies the view changes and accounts their size. `new_size` computes the size
+    /// of the changes after the modification; the size limit is checked before
+    /// the changes are modified, so a failed write leaves the changes intact.
+    ///
+    /// # Panics
+    ///
+    /// Since index writes are infallible, the [`SizeLimitExceeded`] error returned
+    /// by the changes is raised as a panic payload. This is the only place where
+    /// the size limit is enforced via a panic.
+    ///
+    /// [`SizeLimitExceeded`]: ../struct.SizeLimitExceeded.html
+    fn modify_changes<A>(
+        &mut self,
+        args: A,
+        new_size: impl FnOnce(&ViewChanges, &A) -> usize,
+        modify: impl FnOnce(&mut ViewChanges, A),
+    ) {
+        let changes = self.changes_mut();
+        let new_size = new_size(changes.as_ref().unwrap(), &args);
+        if let Err(err) = changes.check_size(new_size) {
+            panic!(err);
+        }
+        modify(changes.as_mut().unwrap(), args);
+        changes.account_size();
+    }
+
     /// Inserts a key-value pair into the fork.
     pub fn put<K, V>(&mut self, key: &K, value: V)
     where
         K: BinaryKey + ?Sized,
         V: BinaryValue,
     {
-        self.changes_mut()
-            .data
-            .insert(concat_keys!(key), Change::Put(value.into_bytes()));
+        let value = self.compression().encode(value.into_bytes());
+        self.modify_changes(
+            (concat_keys!(key), Change::Put(value)),
+            |changes, (key, change)| changes.size_after_put(key, change),
+            |changes, (key, change)| changes.put(key, change),
+        );
     }
 
     /// Removes a key from the view.
@@ -390,14 +602,53 @@ impl<T: RawAccessMut> View<T> {
     where
         K: BinaryKey + ?Sized,
     {
-        self.changes_mut()
-            .data
-            .insert(concat_keys!(key), Change::Delete);
+        self.modify_changes(
+            concat_keys!(key),
+            |changes, key| changes.size_after_put(key, &Change::Delete),
+            |changes, key| changes.put(key, Change::Delete),
+        );
+    }
+
+    /// Removes all keys within the specified range from the view.
+    ///
+    /// Unlike removing keys one by one, this method does not read the removed keys
+    /// from the database; the removed range is recorded as a whole and is later
+    /// applied to the database in a single operation if the backend supports it.
+    pub fn remove_range<K>(&mut self, start: Bound<&K>, end: Bound<&K>)
+    where
+        K: BinaryKey + ?Sized,
+    {
+        let start = match start {
+            Bound::Included(start) => key_bytes(start),
+            Bound::Excluded(start) => {
+                // The smallest key greater than `start`.
+                let mut start = key_bytes(start);
+                start.push(0);
+                start
+            }
+            Bound::Unbounded => vec![],
+        };
+        let end = match end {
+            Bound::Included(end) => {
+                let mut end = key_bytes(end);
+                end.push(0);
+                Some(end)
+            }
+            Bound::Excluded(end) => Some(key_bytes(end)),
+            Bound::Unbounded => None,
+        };
+        self.modify_changes(
+            (start, end),
+            |changes, (start, end)| {
+                changes.size_after_remove_range(start, end.as_ref().map(Vec::as_slice))
+            },
+            |changes, (start, end)| changes.remove_range(start, end),
+        );
     }
 
     /// Clears the view removing all its elements.
     pub fn clear(&mut self) {
-        self.changes_mut().clear();
+        self.modify_changes((), |_, ()| 0, |changes, ()| changes.clear());
     }
 }
 
@@ -471,16 +722,23 @@ where
 
 /// An iterator over the entries of a `View`.
 ///
-/// This struct is created by the [`iter`] or
-/// [`iter_from`] method on [`View`]. See its documentation for details.
+/// This struct is created by the [`iter`], [`iter_from`], [`iter_range`], [`iter_rev`]
+/// or [`iter_rev_from`] method on [`View`]. See its documentation for details.
 ///
-/// [`iter`]: struct.BaseIndex.html#method.iter
-/// [`iter_from`]: struct.BaseIndex.html#method.iter_from
-/// [`BaseIndex`]: struct.BaseIndex.html
+/// [`iter`]: enum.View.html#method.iter
+/// [`iter_from`]: enum.View.html#method.iter_from
+/// [`iter_range`]: enum.View.html#method.iter_range
+/// [`iter_rev`]: enum.View.html#method.iter_rev
+/// [`iter_rev_from`]: enum.View.html#method.iter_rev_from
+/// [`View`]: enum.View.html
 pub struct Iter<'a, K: ?Sized, V> {
     base_iter: BytesIter<'a>,
     prefix: Vec<u8>,
+    /// Upper bound on the keys yielded by an ascending iterator.
+    upper_bound: Bound<Vec<u8>>,
     ended: bool,
+    /// Compression of values in the iterated view.
+    compression: ValueCompression,
     _k: PhantomData<K>,
     _v: PhantomData<V>,
 }
@@ -504,11 +762,16 @@ where
         }
 
         if let Some((k, v)) = self.base_iter.next() {
-            if k.starts_with(&self.prefix) {
+            let is_within_bound = match self.upper_bound {
+                Bound::Included(ref end) => k <= &end[..],
+                Bound::Excluded(ref end) => k < &end[..],
+                Bound::Unbounded => true,
+            };
+            if k.starts_with(&self.prefix) && is_within_bound {
+                let v = self.compression.decode(Cow::Borrowed(v));
                 return Some((
                     K::read(k),
-                    V::from_bytes(Cow::Borrowed(v))
-                        .expect("Unable to decode value from bytes, an error occurred"),
+                    V::from_bytes(v).expect("Unable to decode value from bytes, an error occurred"),
                 ));
             }
         }
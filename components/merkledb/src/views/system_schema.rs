@@ -1,10 +1,16 @@
 use exonum_crypto::Hash;
 
+use std::collections::BTreeSet;
+
 use super::{AsReadonly, IndexType, RawAccess, ViewWithMetadata};
-use crate::{Fork, ObjectHash, ProofMapIndex};
+use crate::{Fork, KeySetIndex, ObjectHash, ProofMapIndex, ReadonlyFork};
 
 /// Name of the state aggregator proof map.
-const STATE_AGGREGATOR: &str = "__STATE_AGGREGATOR__";
+pub(crate) const STATE_AGGREGATOR: &str = "__STATE_AGGREGATOR__";
+/// Name of the group of state aggregators for hierarchically aggregated prefixes.
+pub(crate) const PREFIX_AGGREGATOR: &str = "__PREFIX_AGGREGATOR__";
+/// Name of the set of hierarchically aggregated prefixes.
+pub(crate) const AGGREGATED_PREFIXES: &str = "__AGGREGATED_PREFIXES__";
 
 pub fn get_state_aggregator<T: RawAccess>(
     access: T,
@@ -19,6 +25,35 @@ pub fn get_state_aggregator<T: RawAccess>(
     ProofMapIndex::new(view)
 }
 
+pub(crate) fn get_prefix_aggregator<T: RawAccess>(
+    access: T,
+    prefix: &str,
+) -> ProofMapIndex<T, str, Hash> {
+    let view = ViewWithMetadata::get_or_create_unchecked(
+        access,
+        &(PREFIX_AGGREGATOR, prefix).into(),
+        IndexType::ProofMap,
+    )
+    .expect("Internal MerkleDB failure while aggregating state");
+    ProofMapIndex::new(view)
+}
+
+pub(crate) fn get_aggregated_prefixes<T: RawAccess>(access: T) -> KeySetIndex<T, String> {
+    let view = ViewWithMetadata::get_or_create_unchecked(
+        access,
+        &AGGREGATED_PREFIXES.into(),
+        IndexType::KeySet,
+    )
+    .expect("Internal MerkleDB failure while aggregating state");
+    KeySetIndex::new(view)
+}
+
+/// Returns the prefix of an index name used for hierarchical aggregation, i.e.,
+/// the first component of the name.
+pub(crate) fn aggregation_prefix(index_name: &str) -> &str {
+    index_name.split('.').next().unwrap_or_default()
+}
+
 /// System-wide information about the database.
 ///
 /// # Examples
@@ -52,6 +87,46 @@ pub fn get_state_aggregator<T: RawAccess>(
 /// let proof = aggregator.get_proof("list".to_owned());
 /// proof.check_against_hash(state_hash).unwrap();
 /// ```
+///
+/// # Hierarchical aggregation
+///
+/// By default, all aggregated indexes are placed into a single flat aggregator. Alternatively,
+/// hierarchical aggregation can be enabled for indexes with a specific prefix (e.g., all indexes
+/// of a service) via [`Fork::enable_prefix_aggregation`]. In this case, indexes with the prefix
+/// are aggregated in a separate aggregator, and the hash of this aggregator is placed
+/// into the default aggregator under the prefix name. Thus, the state of all indexes
+/// with the prefix can be proven with a single proof from the default aggregator.
+///
+/// ```
+/// # use exonum_merkledb::{access::AccessExt, Database, ObjectHash, TemporaryDB, SystemSchema};
+/// let db = TemporaryDB::new();
+/// let mut fork = db.fork();
+/// fork.enable_prefix_aggregation("service");
+/// fork.get_proof_list("service.list").extend(vec![1_u32, 2, 3]);
+/// fork.get_proof_entry("service.entry").set("!".to_owned());
+/// fork.get_proof_entry("other").set("?".to_owned());
+///
+/// let patch = fork.into_patch();
+/// let schema = SystemSchema::new(&patch);
+/// let aggregator = schema.state_aggregator();
+/// assert_eq!(
+///     aggregator.keys().collect::<Vec<_>>(),
+///     vec!["other".to_owned(), "service".to_owned()]
+/// );
+/// let service_hash = schema.prefix_state_hash("service");
+/// assert_eq!(aggregator.get("service"), Some(service_hash));
+///
+/// let service_aggregator = schema.prefix_state_aggregator("service");
+/// assert_eq!(
+///     service_aggregator.keys().collect::<Vec<_>>(),
+///     vec!["service.entry".to_owned(), "service.list".to_owned()]
+/// );
+/// // The state of the service can be proven with a single proof.
+/// let proof = aggregator.get_proof("service".to_owned());
+/// proof.check_against_hash(schema.state_hash()).unwrap();
+/// ```
+///
+/// [`Fork::enable_prefix_aggregation`]: struct.Fork.html#method.enable_prefix_aggregation
 #[derive(Debug, Clone, Copy)]
 pub struct SystemSchema<T>(T);
 
@@ -70,6 +145,23 @@ impl<T: RawAccess> SystemSchema<T> {
     pub fn state_hash(&self) -> Hash {
         get_state_aggregator(self.0.clone(), "").object_hash()
     }
+
+    /// Checks whether hierarchical aggregation is enabled for the specified prefix.
+    pub fn is_prefix_aggregated(&self, prefix: &str) -> bool {
+        get_aggregated_prefixes(self.0.clone()).contains(prefix)
+    }
+
+    /// Returns the state hash of indexes with the specified prefix. The hash is recorded
+    /// in the default state aggregator under the prefix name. The state hash is up to date
+    /// for `Snapshot`s (including `Patch`es), but is generally stale for `Fork`s.
+    ///
+    /// If hierarchical aggregation is not enabled for the prefix, returns the hash
+    /// of an empty aggregator.
+    ///
+    /// See [hierarchical aggregation](#hierarchical-aggregation) for details.
+    pub fn prefix_state_hash(&self, prefix: &str) -> Hash {
+        get_prefix_aggregator(self.0.clone(), prefix).object_hash()
+    }
 }
 
 impl<T: RawAccess + AsReadonly> SystemSchema<T> {
@@ -82,6 +174,17 @@ impl<T: RawAccess + AsReadonly> SystemSchema<T> {
     pub fn state_aggregator(&self) -> ProofMapIndex<T::Readonly, str, Hash> {
         get_state_aggregator(self.0.as_readonly(), "")
     }
+
+    /// Returns the state aggregator for indexes with the specified prefix. The aggregator
+    /// is up to date for `Snapshot`s (including `Patch`es), but is generally stale for `Fork`s.
+    ///
+    /// Keys in the aggregator are full index names, which include the prefix.
+    /// If hierarchical aggregation is not enabled for the prefix, the aggregator is empty.
+    ///
+    /// See [hierarchical aggregation](#hierarchical-aggregation) for details.
+    pub fn prefix_state_aggregator(&self, prefix: &str) -> ProofMapIndex<T::Readonly, str, Hash> {
+        get_prefix_aggregator(self.0.as_readonly(), prefix)
+    }
 }
 
 impl SystemSchema<&Fork> {
@@ -90,18 +193,25 @@ impl SystemSchema<&Fork> {
         &mut self,
         entries: impl IntoIterator<Item = (String, String, Hash)>,
     ) {
+        let mut default_aggregator = DefaultAggregator::new(self.0);
         for (ns, index_name, hash) in entries {
-            get_state_aggregator(self.0, &ns).put(&index_name, hash);
+            if ns.is_empty() {
+                default_aggregator.put(&index_name, hash);
+            } else {
+                get_state_aggregator(self.0, &ns).put(&index_name, hash);
+            }
         }
+        default_aggregator.finish();
     }
 
     /// Removes indexes with the specified names from the aggregated indexes
     /// in the default namespace.
     pub(crate) fn remove_aggregated_indexes(&mut self, names: impl IntoIterator<Item = String>) {
-        let mut aggregator = get_state_aggregator(self.0, "");
+        let mut aggregator = DefaultAggregator::new(self.0);
         for name in names {
             aggregator.remove(&name);
         }
+        aggregator.finish();
     }
 
     /// Removes an aggregation namespace, moving all aggregated indexes in the namespace into
@@ -110,10 +220,11 @@ impl SystemSchema<&Fork> {
         debug_assert!(!namespace.is_empty(), "Cannot remove default namespace");
 
         let mut ns_aggregator = get_state_aggregator(self.0, namespace);
-        let mut default_aggregator = get_state_aggregator(self.0, "");
+        let mut default_aggregator = DefaultAggregator::new(self.0);
         for (index_name, hash) in &ns_aggregator {
             default_aggregator.put(&index_name, hash);
         }
+        default_aggregator.finish();
         ns_aggregator.clear();
     }
 
@@ -121,12 +232,97 @@ impl SystemSchema<&Fork> {
     pub(crate) fn remove_namespace(&mut self, namespace: &str) {
         get_state_aggregator(self.0, namespace).clear();
     }
+
+    /// Enables hierarchical aggregation for indexes with the specified prefix. Indexes
+    /// with the prefix are moved from the default aggregator into the prefix aggregator.
+    pub(crate) fn enable_prefix_aggregation(&mut self, prefix: &str) {
+        let mut prefixes = get_aggregated_prefixes(self.0);
+        if prefixes.contains(prefix) {
+            return;
+        }
+        prefixes.insert(prefix.to_owned());
+
+        let mut default_aggregator = get_state_aggregator(self.0, "");
+        let mut prefix_aggregator = get_prefix_aggregator(self.0, prefix);
+        // Keys in the aggregator are ordered by their hashes, so we need a full scan.
+        let moved_indexes: Vec<_> = default_aggregator
+            .iter()
+            .filter(|(index_name, _)| aggregation_prefix(index_name) == prefix)
+            .collect();
+        for (index_name, hash) in moved_indexes {
+            default_aggregator.remove(&index_name);
+            prefix_aggregator.put(&index_name, hash);
+        }
+        default_aggregator.put(prefix, prefix_aggregator.object_hash());
+    }
+}
+
+/// Helper for updating the default state aggregator, which routes indexes with hierarchically
+/// aggregated prefixes to the corresponding prefix aggregators.
+struct DefaultAggregator<'a> {
+    fork: &'a Fork,
+    aggregator: ProofMapIndex<&'a Fork, str, Hash>,
+    /// Set of aggregated prefixes. The set is accessed in the read-only mode, so that
+    /// its metadata is not created in each patch.
+    prefixes: KeySetIndex<ReadonlyFork<'a>, String>,
+    /// Prefixes with updated aggregators.
+    updated_prefixes: BTreeSet<String>,
+}
+
+impl<'a> DefaultAggregator<'a> {
+    fn new(fork: &'a Fork) -> Self {
+        Self {
+            fork,
+            aggregator: get_state_aggregator(fork, ""),
+            prefixes: get_aggregated_prefixes(fork.readonly()),
+            updated_prefixes: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the aggregated prefix for the index, or `None` if the index is aggregated
+    /// in the default aggregator directly.
+    fn prefix<'n>(&self, index_name: &'n str) -> Option<&'n str> {
+        let prefix = aggregation_prefix(index_name);
+        if self.prefixes.contains(prefix) {
+            Some(prefix)
+        } else {
+            None
+        }
+    }
+
+    fn put(&mut self, index_name: &str, hash: Hash) {
+        if let Some(prefix) = self.prefix(index_name) {
+            get_prefix_aggregator(self.fork, prefix).put(index_name, hash);
+            self.updated_prefixes.insert(prefix.to_owned());
+        } else {
+            self.aggregator.put(index_name, hash);
+        }
+    }
+
+    fn remove(&mut self, index_name: &str) {
+        if let Some(prefix) = self.prefix(index_name) {
+            get_prefix_aggregator(self.fork, prefix).remove(index_name);
+            self.updated_prefixes.insert(prefix.to_owned());
+        } else {
+            self.aggregator.remove(index_name);
+        }
+    }
+
+    /// Updates hashes of the prefix aggregators in the default aggregator.
+    fn finish(mut self) {
+        for prefix in &self.updated_prefixes {
+            let hash = get_prefix_aggregator(self.fork, prefix).object_hash();
+            self.aggregator.put(prefix, hash);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{access::AccessExt, migration::Migration, Database, HashTag, TemporaryDB};
+    use crate::{
+        access::AccessExt, migration::Migration, views::IndexesPool, Database, HashTag, TemporaryDB,
+    };
 
     fn initial_changes(fork: &Fork) {
         fork.get_proof_list("list").extend(vec![1_u32, 2, 3]);
@@ -255,4 +451,117 @@ mod tests {
         let system_schema = SystemSchema::new(&snapshot);
         assert_eq!(system_schema.state_hash(), HashTag::empty_map_hash());
     }
+
+    #[test]
+    fn aggregated_prefixes_are_not_created_implicitly() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        initial_changes(&fork);
+        let patch = fork.into_patch();
+        let pool = IndexesPool::new(&patch);
+        assert!(pool
+            .iter()
+            .all(|(addr, _)| addr.name() != AGGREGATED_PREFIXES));
+    }
+
+    #[test]
+    fn prefix_aggregation() {
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        initial_changes(&fork);
+        fork.get_proof_list("list.other").push(1_u32);
+        db.merge(fork.into_patch()).unwrap();
+
+        let mut fork = db.fork();
+        fork.enable_prefix_aggregation("list");
+        fork.get_proof_entry("list.new").set(1_u8);
+        let patch = fork.into_patch();
+        let schema = SystemSchema::new(&patch);
+        assert!(schema.is_prefix_aggregated("list"));
+        assert!(!schema.is_prefix_aggregated("map"));
+
+        let aggregator = schema.state_aggregator();
+        assert_eq!(
+            aggregator.keys().collect::<Vec<_>>(),
+            vec!["entry".to_owned(), "list".to_owned(), "map".to_owned()]
+        );
+        assert_eq!(
+            aggregator.get("list"),
+            Some(schema.prefix_state_hash("list"))
+        );
+        let prefix_aggregator = schema.prefix_state_aggregator("list");
+        assert_eq!(
+            prefix_aggregator.keys().collect::<Vec<_>>(),
+            vec![
+                "list".to_owned(),
+                "list.new".to_owned(),
+                "list.other".to_owned()
+            ]
+        );
+        assert_eq!(
+            prefix_aggregator.get("list"),
+            Some(patch.get_proof_list::<_, u32>("list").object_hash())
+        );
+        assert_eq!(
+            prefix_aggregator.get("list.new"),
+            Some(patch.get_proof_entry::<_, u8>("list.new").object_hash())
+        );
+        db.merge(patch).unwrap();
+
+        // Check that changes in indexes with the prefix are propagated to the state hash.
+        let state_hash = SystemSchema::new(&db.snapshot()).state_hash();
+        let fork = db.fork();
+        fork.get_proof_list("list.other").push(2_u32);
+        let patch = fork.into_patch();
+        let schema = SystemSchema::new(&patch);
+        assert_ne!(schema.state_hash(), state_hash);
+        let proof = schema.state_aggregator().get_proof("list".to_owned());
+        let checked_proof = proof.check_against_hash(schema.state_hash()).unwrap();
+        let prefix_hash = schema.prefix_state_hash("list");
+        assert_eq!(
+            checked_proof.entries().collect::<Vec<_>>(),
+            vec![(&"list".to_owned(), &prefix_hash)]
+        );
+        let prefix_aggregator = schema.prefix_state_aggregator("list");
+        assert_eq!(
+            prefix_aggregator.get("list.other"),
+            Some(patch.get_proof_list::<_, u32>("list.other").object_hash())
+        );
+    }
+
+    #[test]
+    fn prefix_aggregation_with_removals() {
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        fork.enable_prefix_aggregation("test");
+        fork.get_proof_entry("test.entry").set(1_u8);
+        fork.get_proof_entry("test.removed").set(2_u8);
+        fork.get_proof_list("test.moved").push(3_u8);
+        db.merge(fork.into_patch()).unwrap();
+
+        let mut fork = db.fork();
+        Migration::new("test", &fork).create_tombstone("removed");
+        Migration::new("test", &fork)
+            .get_proof_entry("new")
+            .set(4_u8);
+        fork.flush_migration("test");
+        fork.move_index("test.moved", "moved").unwrap();
+        let patch = fork.into_patch();
+
+        let schema = SystemSchema::new(&patch);
+        let aggregator = schema.state_aggregator();
+        assert_eq!(
+            aggregator.keys().collect::<Vec<_>>(),
+            vec!["moved".to_owned(), "test".to_owned()]
+        );
+        let prefix_aggregator = schema.prefix_state_aggregator("test");
+        assert_eq!(
+            prefix_aggregator.keys().collect::<Vec<_>>(),
+            vec!["test.entry".to_owned(), "test.new".to_owned()]
+        );
+        assert_eq!(
+            aggregator.get("test"),
+            Some(prefix_aggregator.object_hash())
+        );
+    }
 }
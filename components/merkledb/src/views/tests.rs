@@ -15,14 +15,15 @@
 use assert_matches::assert_matches;
 use url::form_urlencoded::byte_serialize;
 
-use std::{num::NonZeroU64, panic, rc::Rc};
+use std::{num::NonZeroU64, ops::Bound, panic, rc::Rc};
 
 use crate::{
     access::AccessExt,
     db,
     validation::is_valid_identifier,
     views::{IndexAddress, IndexType, RawAccess, View, ViewWithMetadata},
-    Database, DbOptions, Fork, ListIndex, MapIndex, ResolvedAddress, RocksDB, TemporaryDB,
+    ColumnFamilyOverride, Database, DbOptions, Fork, ListIndex, MapIndex, ResolvedAddress, RocksDB,
+    TemporaryDB,
 };
 
 const IDX_NAME: &str = "idx_name";
@@ -49,6 +50,26 @@ fn assert_iter<T: RawAccess>(view: &View<T>, from: u8, assumed: &[(u8, u8)]) {
     assert_eq!(values, assumed);
 }
 
+fn assert_iter_rev<T: RawAccess>(view: &View<T>, upper_bound: Bound<u8>, assumed: &[(u8, u8)]) {
+    let bytes = match upper_bound {
+        Bound::Included(key) | Bound::Excluded(key) => vec![key],
+        Bound::Unbounded => vec![],
+    };
+    let upper_bound = match upper_bound {
+        Bound::Included(_) => Bound::Included(&bytes[..]),
+        Bound::Excluded(_) => Bound::Excluded(&bytes[..]),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+
+    let mut iter = view.iter_bytes_rev(upper_bound);
+    let mut values = Vec::new();
+    while let Some((k, v)) = iter.next() {
+        values.push((k[0], v[0]));
+    }
+
+    assert_eq!(values, assumed);
+}
+
 fn assert_initial_state<T: RawAccess>(view: &View<T>) {
     assert_eq!(view.get_bytes(&[1]), Some(vec![1]));
     assert_eq!(view.get_bytes(&[2]), Some(vec![2]));
@@ -58,7 +79,7 @@ fn assert_initial_state<T: RawAccess>(view: &View<T>) {
 
 fn test_changelog<T, I>(db: &T, address: I)
 where
-    T: Database,
+    T: Database + ?Sized,
     I: Into<ResolvedAddress> + Copy,
 {
     let mut fork = db.fork();
@@ -125,7 +146,7 @@ where
     assert_eq!(view.get_bytes(&[4]), None);
 }
 
-fn _views_in_same_family<T: Database>(db: &T) {
+fn _views_in_same_family<T: Database + ?Sized>(db: &T) {
     const IDX_1: (&str, u64) = ("foo", 23);
     const IDX_2: (&str, u64) = ("foo", 42);
 
@@ -182,7 +203,7 @@ fn _views_in_same_family<T: Database>(db: &T) {
 
 fn test_two_mutable_borrows<T, I>(db: &T, address: I)
 where
-    T: Database,
+    T: Database + ?Sized,
     I: Into<ResolvedAddress> + Copy,
 {
     let fork = db.fork();
@@ -195,7 +216,7 @@ where
 
 fn test_mutable_and_immutable_borrows<T, I>(db: &T, address: I)
 where
-    T: Database,
+    T: Database + ?Sized,
     I: Into<ResolvedAddress> + Copy,
 {
     let fork = db.fork();
@@ -208,7 +229,7 @@ where
 
 fn test_clear_view<T, I>(db: &T, address: I)
 where
-    T: Database,
+    T: Database + ?Sized,
     I: Into<ResolvedAddress> + Copy,
 {
     let fork = db.fork();
@@ -269,9 +290,61 @@ where
     assert_iter(&view, 4, &[(4, 0)]);
 }
 
+fn test_remove_range<T, I>(db: &T, address: I)
+where
+    T: Database + ?Sized,
+    I: Into<ResolvedAddress> + Copy,
+{
+    let fork = db.fork();
+    {
+        let mut view = View::new(&fork, address);
+        for i in 1..=5 {
+            view.put(&vec![i * 10], vec![i]);
+        }
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let mut fork = db.fork();
+    {
+        let mut view = View::new(&fork, address);
+        view.remove_range::<[u8]>(Bound::Included(&[20]), Bound::Excluded(&[40]));
+
+        assert_eq!(view.get_bytes(&[20]), None);
+        assert_eq!(view.get_bytes(&[30]), None);
+        assert_eq!(view.get_bytes(&[40]), Some(vec![4]));
+        assert_iter(&view, 0, &[(10, 1), (40, 4), (50, 5)]);
+        assert_iter(&view, 15, &[(40, 4), (50, 5)]);
+        assert_iter_rev(&view, Bound::Unbounded, &[(50, 5), (40, 4), (10, 1)]);
+        assert_iter_rev(&view, Bound::Excluded(40), &[(10, 1)]);
+
+        // Keys put after the range removal are visible.
+        view.put(&vec![25], vec![2]);
+        assert_iter(&view, 0, &[(10, 1), (25, 2), (40, 4), (50, 5)]);
+
+        // Overlapping ranges are merged.
+        view.remove_range::<[u8]>(Bound::Excluded(&[30]), Bound::Included(&[40]));
+        assert_iter(&view, 0, &[(10, 1), (25, 2), (50, 5)]);
+        assert_iter_rev(&view, Bound::Included(45), &[(25, 2), (10, 1)]);
+    }
+    fork.flush();
+    {
+        let mut view = View::new(&fork, address);
+        assert_iter(&view, 0, &[(10, 1), (25, 2), (50, 5)]);
+        view.remove_range::<[u8]>(Bound::Excluded(&[45]), Bound::Unbounded);
+        assert_iter(&view, 0, &[(10, 1), (25, 2)]);
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let view = View::new(&snapshot, address);
+    assert_eq!(view.get_bytes(&[30]), None);
+    assert_iter(&view, 0, &[(10, 1), (25, 2)]);
+    assert_iter_rev(&view, Bound::Unbounded, &[(25, 2), (10, 1)]);
+}
+
 fn test_fork_iter<T, I>(db: &T, address: I)
 where
-    T: Database,
+    T: Database + ?Sized,
     I: Into<ResolvedAddress> + Copy,
 {
     let fork = db.fork();
@@ -394,28 +467,248 @@ fn test_database_check_incorrect_version() {
 }
 
 #[test]
-fn fork_iter() {
-    test_fork_iter(&TemporaryDB::new(), IDX_NAME);
+fn database_with_column_family_overrides() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions {
+        write_buffer_size: Some(1 << 20),
+        block_cache_size: Some(1 << 20),
+        column_family_overrides: vec![
+            ColumnFamilyOverride {
+                index_type: Some(IndexType::List),
+                write_buffer_size: Some(4 << 20),
+                ..ColumnFamilyOverride::default()
+            },
+            ColumnFamilyOverride {
+                name_prefix: Some("maps.".to_owned()),
+                bloom_filter_bits_per_key: Some(10),
+                ..ColumnFamilyOverride::default()
+            },
+        ],
+        ..DbOptions::default()
+    };
+
+    {
+        let db = RocksDB::open(&dir, &opts).unwrap();
+        let fork = db.fork();
+        fork.get_list("list").extend(vec![1_u32, 2, 3]);
+        fork.get_map("maps.map").put(&1_u32, 2_u32);
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    // Reopening the database requires reading index types.
+    let db = RocksDB::open(&dir, &opts).unwrap();
+    let snapshot = db.snapshot();
+    assert_eq!(
+        snapshot
+            .get_list::<_, u32>("list")
+            .iter()
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(snapshot.get_map::<_, u32, u32>("maps.map").get(&1), Some(2));
 }
 
 #[test]
-fn fork_iter_prefixed() {
-    test_fork_iter(&TemporaryDB::new(), PREFIXED_IDX);
+fn read_only_database() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions::default();
+    let db = RocksDB::open(&dir, &opts).unwrap();
+    let fork = db.fork();
+    fork.get_list("list").extend(vec![1_u32, 2, 3]);
+    db.merge(fork.into_patch()).unwrap();
+
+    let read_only = RocksDB::open_read_only(&dir, &opts).unwrap();
+    assert!(read_only.is_read_only());
+    let snapshot = read_only.snapshot();
+    assert_eq!(snapshot.get_list::<_, u32>("list").len(), 3);
+
+    // Changes made by the primary instance are not visible.
+    let fork = db.fork();
+    fork.get_list("list").push(4_u32);
+    db.merge(fork.into_patch()).unwrap();
+    assert_eq!(snapshot.get_list::<_, u32>("list").len(), 3);
+
+    let fork = read_only.fork();
+    fork.get_list("list").push(5_u32);
+    assert!(read_only.merge(fork.into_patch()).is_err());
+    assert!(read_only.try_catch_up_with_primary().is_err());
 }
 
 #[test]
-fn changelog() {
-    test_changelog(&TemporaryDB::new(), IDX_NAME);
+fn secondary_database() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let secondary_dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions::default();
+    let db = RocksDB::open(&dir, &opts).unwrap();
+    let fork = db.fork();
+    fork.get_list("list").extend(vec![1_u32, 2, 3]);
+    db.merge(fork.into_patch()).unwrap();
+
+    let secondary = RocksDB::open_as_secondary(&dir, &secondary_dir, &opts).unwrap();
+    assert!(secondary.is_read_only());
+    let fork = db.fork();
+    fork.get_list("list").push(4_u32);
+    db.merge(fork.into_patch()).unwrap();
+
+    {
+        let snapshot = secondary.snapshot();
+        assert_eq!(snapshot.get_list::<_, u32>("list").len(), 3);
+        // Catching up is impossible while there are live snapshots.
+        assert!(secondary.try_catch_up_with_primary().is_err());
+    }
+    secondary.try_catch_up_with_primary().unwrap();
+    let snapshot = secondary.snapshot();
+    let list = snapshot.get_list::<_, u32>("list");
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+
+    let fork = secondary.fork();
+    fork.get_list("list").push(5_u32);
+    assert!(secondary.merge(fork.into_patch()).is_err());
 }
 
 #[test]
-fn changelog_prefixed() {
-    test_changelog(&TemporaryDB::new(), PREFIXED_IDX);
+fn column_families_are_created_with_live_snapshots() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let db = RocksDB::open(&dir, &DbOptions::default()).unwrap();
+    let fork = db.fork();
+    fork.get_list("list").push(1_u32);
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let fork = db.fork();
+    fork.get_list("list").push(2_u32);
+    fork.get_list("other_list").push(3_u32);
+    db.merge(fork.into_patch()).unwrap();
+
+    // The snapshot does not observe changes in the new column family.
+    assert_eq!(snapshot.get_list::<_, u32>("list").len(), 1);
+    assert_eq!(snapshot.get_list::<_, u32>("other_list").len(), 0);
+    let snapshot = db.snapshot();
+    assert_eq!(snapshot.get_list::<_, u32>("list").len(), 2);
+    assert_eq!(snapshot.get_list::<_, u32>("other_list").len(), 1);
 }
 
-#[test]
-fn multiple_views() {
-    let db = TemporaryDB::new();
+fn test_fork_iter_rev<T, I>(db: &T, address: I)
+where
+    T: Database + ?Sized,
+    I: Into<ResolvedAddress> + Copy,
+{
+    let fork = db.fork();
+    {
+        let mut view = View::new(&fork, address);
+        view.put(&vec![10], vec![10]);
+        view.put(&vec![20], vec![20]);
+        view.put(&vec![30], vec![30]);
+
+        // Neighboring views in the same column family must not be visited.
+        View::new(&fork, ("idx", 41)).put(&vec![5], vec![5]);
+        View::new(&fork, ("idx", 43)).put(&vec![40], vec![40]);
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let mut fork = db.fork();
+    let mut view = View::new(&fork, address);
+
+    // Stored
+    assert_iter_rev(&view, Bound::Unbounded, &[(30, 30), (20, 20), (10, 10)]);
+    assert_iter_rev(&view, Bound::Included(30), &[(30, 30), (20, 20), (10, 10)]);
+    assert_iter_rev(&view, Bound::Excluded(30), &[(20, 20), (10, 10)]);
+    assert_iter_rev(&view, Bound::Included(25), &[(20, 20), (10, 10)]);
+    assert_iter_rev(&view, Bound::Excluded(10), &[]);
+
+    // Inserted, replaced and deleted
+    view.put(&vec![35], vec![35]);
+    view.put(&vec![25], vec![25]);
+    view.put(&vec![10], vec![11]);
+    view.remove(&vec![20]);
+    view.remove(&vec![15]);
+    assert_iter_rev(
+        &view,
+        Bound::Unbounded,
+        &[(35, 35), (30, 30), (25, 25), (10, 11)],
+    );
+    assert_iter_rev(&view, Bound::Excluded(30), &[(25, 25), (10, 11)]);
+    assert_iter_rev(&view, Bound::Included(20), &[(10, 11)]);
+    drop(view);
+
+    // Changes flushed to the fork patch
+    fork.flush();
+    let mut view = View::new(&fork, address);
+    view.put(&vec![20], vec![21]);
+    assert_iter_rev(
+        &view,
+        Bound::Unbounded,
+        &[(35, 35), (30, 30), (25, 25), (20, 21), (10, 11)],
+    );
+
+    // Cleared
+    view.clear();
+    assert_iter_rev(&view, Bound::Unbounded, &[]);
+    view.put(&vec![15], vec![15]);
+    assert_iter_rev(&view, Bound::Unbounded, &[(15, 15)]);
+}
+
+fn fork_iter(db: &dyn Database) {
+    test_fork_iter(db, IDX_NAME);
+}
+
+fn fork_iter_rev(db: &dyn Database) {
+    test_fork_iter_rev(db, IDX_NAME);
+}
+
+fn fork_iter_rev_prefixed(db: &dyn Database) {
+    test_fork_iter_rev(db, PREFIXED_IDX);
+}
+
+fn iter_with_bounds(db: &dyn Database) {
+    let fork = db.fork();
+    let mut view = View::new(&fork, PREFIXED_IDX);
+    for i in 0_u8..10 {
+        view.put(&vec![1, i], i);
+        view.put(&vec![2, i], i);
+    }
+
+    let keys: Vec<_> = view
+        .iter_rev::<_, Vec<u8>, u8>(&1_u8)
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(keys, (0..10).rev().map(|i| vec![1, i]).collect::<Vec<_>>());
+    let keys: Vec<_> = view
+        .iter_rev_from::<_, _, Vec<u8>, u8>(&2_u8, &vec![2, 3])
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(keys, vec![vec![2, 3], vec![2, 2], vec![2, 1], vec![2, 0]]);
+
+    let start = vec![1, 7];
+    let end = vec![2, 2];
+    let keys: Vec<_> = view
+        .iter_range::<_, _, Vec<u8>, u8>(&(), Bound::Excluded(&start), Bound::Included(&end))
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(
+        keys,
+        vec![vec![1, 8], vec![1, 9], vec![2, 0], vec![2, 1], vec![2, 2]]
+    );
+    let keys: Vec<_> = view
+        .iter_range::<_, _, Vec<u8>, u8>(&2_u8, Bound::Unbounded, Bound::Excluded(&end))
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(keys, vec![vec![2, 0], vec![2, 1]]);
+}
+
+fn fork_iter_prefixed(db: &dyn Database) {
+    test_fork_iter(db, PREFIXED_IDX);
+}
+
+fn changelog(db: &dyn Database) {
+    test_changelog(db, IDX_NAME);
+}
+
+fn changelog_prefixed(db: &dyn Database) {
+    test_changelog(db, PREFIXED_IDX);
+}
+
+fn multiple_views(db: &dyn Database) {
     let fork = db.fork();
     {
         // Writing to multiple views at the same time
@@ -462,9 +755,7 @@ fn multiple_views() {
     }
 }
 
-#[test]
-fn multiple_indexes() {
-    let db = TemporaryDB::new();
+fn multiple_indexes(db: &dyn Database) {
     let fork = db.fork();
     {
         let mut list: ListIndex<_, u32> = fork.get_list(IDX_NAME);
@@ -496,13 +787,10 @@ fn multiple_indexes() {
     assert!(map.iter_from(&3).all(|(k, v)| k < 10 && v == k.to_string()));
 }
 
-#[test]
-fn views_in_same_family() {
+fn views_in_same_family(db: &dyn Database) {
     const IDX_1: (&str, u64) = ("foo", 23);
     const IDX_2: (&str, u64) = ("foo", 42);
 
-    let db = TemporaryDB::new();
-
     let mut fork = db.fork();
     {
         let mut view1 = View::new(&fork, IDX_1);
@@ -554,8 +842,7 @@ fn views_in_same_family() {
     assert_iter(&view2, 0, &[(0, 0), (1, 2), (2, 4)]);
 }
 
-#[test]
-fn rollbacks_for_indexes_in_same_family() {
+fn rollbacks_for_indexes_in_same_family(db: &dyn Database) {
     use crate::ProofListIndex;
 
     fn indexes(fork: &Fork) -> (ProofListIndex<&Fork, i64>, ProofListIndex<&Fork, i64>) {
@@ -564,7 +851,6 @@ fn rollbacks_for_indexes_in_same_family() {
         (list1, list2)
     }
 
-    let db = TemporaryDB::new();
     let mut fork = db.fork();
     {
         let (mut list1, mut list2) = indexes(&fork);
@@ -603,18 +889,33 @@ fn rollbacks_for_indexes_in_same_family() {
     assert_eq!(list2.iter().collect::<Vec<_>>(), vec![2, 3, 5, 8]);
 }
 
-#[test]
-fn clear_view() {
-    test_clear_view(&TemporaryDB::new(), IDX_NAME);
+fn clear_view(db: &dyn Database) {
+    test_clear_view(db, IDX_NAME);
 }
 
-#[test]
-fn clear_prefixed_view() {
-    test_clear_view(&TemporaryDB::new(), PREFIXED_IDX);
+fn clear_prefixed_view(db: &dyn Database) {
+    test_clear_view(db, PREFIXED_IDX);
 }
 
-#[test]
-fn clear_sibling_views() {
+fn remove_range(db: &dyn Database) {
+    test_remove_range(db, IDX_NAME);
+}
+
+fn remove_range_in_prefixed_view(db: &dyn Database) {
+    // Neighboring views must not be affected by the range removal.
+    let fork = db.fork();
+    View::new(&fork, ("idx", 41)).put(&vec![255], vec![1]);
+    View::new(&fork, ("idx", 43)).put(&vec![0], vec![2]);
+    db.merge(fork.into_patch()).unwrap();
+
+    test_remove_range(db, PREFIXED_IDX);
+
+    let snapshot = db.snapshot();
+    assert_iter(&View::new(&snapshot, ("idx", 41)), 0, &[(255, 1)]);
+    assert_iter(&View::new(&snapshot, ("idx", 43)), 0, &[(0, 2)]);
+}
+
+fn clear_sibling_views(db: &dyn Database) {
     const IDX_1: (&str, u64) = ("foo", 23);
     const IDX_2: (&str, u64) = ("foo", 42);
 
@@ -629,7 +930,6 @@ fn clear_sibling_views() {
         assert_iter(&view2, 1, &[(2, 4)]);
     }
 
-    let db = TemporaryDB::new();
     let fork = db.fork();
     {
         let mut view1 = View::new(&fork, IDX_1);
@@ -667,33 +967,23 @@ fn clear_sibling_views() {
     assert_iter(&view1, 0, &[(0, 5), (1, 8), (2, 7)]);
 }
 
-#[test]
-#[should_panic]
-fn two_mutable_borrows() {
-    test_two_mutable_borrows(&TemporaryDB::new(), IDX_NAME);
+fn two_mutable_borrows(db: &dyn Database) {
+    test_two_mutable_borrows(db, IDX_NAME);
 }
 
-#[test]
-#[should_panic]
-fn two_mutable_prefixed_borrows() {
-    test_two_mutable_borrows(&TemporaryDB::new(), PREFIXED_IDX);
+fn two_mutable_prefixed_borrows(db: &dyn Database) {
+    test_two_mutable_borrows(db, PREFIXED_IDX);
 }
 
-#[test]
-#[should_panic]
-fn mutable_and_immutable_borrows() {
-    test_mutable_and_immutable_borrows(&TemporaryDB::new(), IDX_NAME);
+fn mutable_and_immutable_borrows(db: &dyn Database) {
+    test_mutable_and_immutable_borrows(db, IDX_NAME);
 }
 
-#[test]
-#[should_panic]
-fn mutable_and_immutable_prefixed_borrows() {
-    test_mutable_and_immutable_borrows(&TemporaryDB::new(), PREFIXED_IDX);
+fn mutable_and_immutable_prefixed_borrows(db: &dyn Database) {
+    test_mutable_and_immutable_borrows(db, PREFIXED_IDX);
 }
 
-#[test]
-fn multiple_immutable_borrows_from_fork() {
-    let db = TemporaryDB::new();
+fn multiple_immutable_borrows_from_fork(db: &dyn Database) {
     let fork = db.fork();
     let view1 = View::new(fork.readonly(), IDX_NAME);
     let view2 = View::new(fork.readonly(), IDX_NAME);
@@ -706,9 +996,7 @@ fn multiple_immutable_borrows_from_fork() {
     assert_eq!(view2.get_bytes(&[0]), None);
 }
 
-#[test]
-fn immutable_view_from_fork_reflects_changes_in_fork() {
-    let db = TemporaryDB::new();
+fn immutable_view_from_fork_reflects_changes_in_fork(db: &dyn Database) {
     let fork = db.fork();
     {
         let mut view = View::new(&fork, IDX_NAME);
@@ -726,9 +1014,7 @@ fn immutable_view_from_fork_reflects_changes_in_fork() {
     assert_eq!(view.get_bytes(&[1]), None);
 }
 
-#[test]
-fn immutable_view_from_fork_reads_from_snapshot() {
-    let db = TemporaryDB::new();
+fn immutable_view_from_fork_reads_from_snapshot(db: &dyn Database) {
     let fork = db.fork();
     {
         let mut view = View::new(&fork, IDX_NAME);
@@ -754,9 +1040,7 @@ fn immutable_view_from_fork_reads_from_snapshot() {
     assert_eq!(other_view.get_bytes(&[2]), Some(vec![5, 6, 7]));
 }
 
-#[test]
-fn mutable_and_immutable_borrows_for_different_views() {
-    let db = TemporaryDB::new();
+fn mutable_and_immutable_borrows_for_different_views(db: &dyn Database) {
     let fork = db.fork();
     let readonly = fork.readonly();
 
@@ -774,14 +1058,12 @@ fn mutable_and_immutable_borrows_for_different_views() {
     assert_eq!(immutable_view2.get_bytes(&[1]), None);
 }
 
-#[test]
-fn views_based_on_rc_fork() {
+fn views_based_on_rc_fork(db: &dyn Database) {
     fn test_lifetime<T: 'static>(_: T) {}
 
     const IDX_1: (&str, u64) = ("foo", 23);
     const IDX_2: (&str, u64) = ("foo", 42);
 
-    let db = TemporaryDB::new();
     let fork = Rc::new(db.fork());
 
     let view1 = View::new(fork.clone(), IDX_1);
@@ -825,9 +1107,8 @@ fn views_based_on_rc_fork() {
     assert_eq!(view2.get_bytes(&[2]), Some(vec![4]));
 }
 
-fn test_metadata(addr: impl Into<IndexAddress>) {
+fn test_metadata(db: &dyn Database, addr: impl Into<IndexAddress>) {
     let addr = addr.into();
-    let db = TemporaryDB::new();
     // Creates the index metadata.
     let fork = db.fork();
     ViewWithMetadata::get_or_create(&fork, &addr, IndexType::ProofMap)
@@ -851,14 +1132,12 @@ fn test_metadata(addr: impl Into<IndexAddress>) {
         .unwrap();
 }
 
-#[test]
-fn test_metadata_simple() {
-    test_metadata("simple");
+fn test_metadata_simple(db: &dyn Database) {
+    test_metadata(db, "simple");
 }
 
-#[test]
-fn test_metadata_index_family() {
-    test_metadata(("family", "family_id"));
+fn test_metadata_index_family(db: &dyn Database) {
+    test_metadata(db, ("family", "family_id"));
 }
 
 fn get_address<T: RawAccess>(view: &View<T>) -> &ResolvedAddress {
@@ -868,9 +1147,7 @@ fn get_address<T: RawAccess>(view: &View<T>) -> &ResolvedAddress {
     }
 }
 
-#[test]
-fn test_metadata_index_identifiers() {
-    let db = TemporaryDB::new();
+fn test_metadata_index_identifiers(db: &dyn Database) {
     let fork = db.fork();
     // Creates the first index metadata.
     let view: View<_> =
@@ -904,9 +1181,7 @@ fn test_metadata_index_identifiers() {
     assert_eq!(recreated_address.id.unwrap().get(), id);
 }
 
-#[test]
-fn test_metadata_in_migrated_indexes() {
-    let db = TemporaryDB::new();
+fn test_metadata_in_migrated_indexes(db: &dyn Database) {
     let fork = db.fork();
 
     let view: View<_> = ViewWithMetadata::get_or_create(&fork, &"simple".into(), IndexType::Map)
@@ -929,9 +1204,7 @@ fn test_metadata_in_migrated_indexes() {
     assert_ne!(old_id, new_id);
 }
 
-#[test]
-fn test_metadata_incorrect_index_type() {
-    let db = TemporaryDB::new();
+fn test_metadata_incorrect_index_type(db: &dyn Database) {
     let fork = db.fork();
 
     ViewWithMetadata::get_or_create(&fork, &"simple".into(), IndexType::Map)
@@ -942,14 +1215,12 @@ fn test_metadata_incorrect_index_type() {
         .unwrap_err();
 }
 
-#[test]
-fn test_metadata_index_wrong_type() {
+fn test_metadata_index_wrong_type(db: &dyn Database) {
     use crate::{
         access::{AccessError, AccessErrorKind, FromAccess},
         ListIndex,
     };
 
-    let db = TemporaryDB::new();
     let fork = db.fork();
     {
         let mut map = fork.get_map("simple");
@@ -968,15 +1239,13 @@ fn test_metadata_index_wrong_type() {
     );
 }
 
-#[test]
-fn test_valid_tombstone() {
+fn test_valid_tombstone(db: &dyn Database) {
     use crate::{
         access::{AccessErrorKind, FromAccess},
         migration::Migration,
         ListIndex,
     };
 
-    let db = TemporaryDB::new();
     let fork = db.fork();
     let migration = Migration::new("foo", &fork);
 
@@ -1000,11 +1269,9 @@ fn test_valid_tombstone() {
     );
 }
 
-#[test]
-fn test_invalid_tombstone() {
+fn test_invalid_tombstone(db: &dyn Database) {
     use crate::access::{Access, AccessErrorKind};
 
-    let db = TemporaryDB::new();
     let fork = db.fork();
     // A tombstone cannot be created outside the migration!
     let err = fork
@@ -1098,9 +1365,7 @@ fn check_valid_name(name: &str) -> bool {
     catch_result.is_ok()
 }
 
-#[test]
-fn fork_from_patch() {
-    let db = TemporaryDB::new();
+fn fork_from_patch(db: &dyn Database) {
     let fork = db.fork();
     {
         let mut index = fork.get_list("index");
@@ -1128,3 +1393,85 @@ fn fork_from_patch() {
     db.merge(fork.into_patch())
         .expect("Fork created from patch should be merged successfully");
 }
+
+backend_tests!(
+    fork_iter,
+    fork_iter_rev,
+    fork_iter_rev_prefixed,
+    iter_with_bounds,
+    fork_iter_prefixed,
+    changelog,
+    changelog_prefixed,
+    multiple_views,
+    multiple_indexes,
+    views_in_same_family,
+    rollbacks_for_indexes_in_same_family,
+    clear_view,
+    clear_prefixed_view,
+    remove_range,
+    remove_range_in_prefixed_view,
+    clear_sibling_views,
+    #[should_panic]
+    two_mutable_borrows,
+    #[should_panic]
+    two_mutable_prefixed_borrows,
+    #[should_panic]
+    mutable_and_immutable_borrows,
+    #[should_panic]
+    mutable_and_immutable_prefixed_borrows,
+    multiple_immutable_borrows_from_fork,
+    immutable_view_from_fork_reflects_changes_in_fork,
+    immutable_view_from_fork_reads_from_snapshot,
+    mutable_and_immutable_borrows_for_different_views,
+    views_based_on_rc_fork,
+    test_metadata_simple,
+    test_metadata_index_family,
+    test_metadata_index_identifiers,
+    test_metadata_in_migrated_indexes,
+    test_metadata_incorrect_index_type,
+    test_metadata_index_wrong_type,
+    test_valid_tombstone,
+    test_invalid_tombstone,
+    fork_from_patch,
+);
+
+#[cfg(feature = "sled")]
+mod sled_metadata {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::SledDB;
+
+    fn sled_db() -> (SledDB, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = SledDB::open(&dir, &DbOptions::default()).unwrap();
+        (db, dir)
+    }
+
+    #[test]
+    fn database_check_correct_version() {
+        let (db, _dir) = sled_db();
+        let snapshot = db.snapshot();
+
+        let view = View::new(&snapshot, ResolvedAddress::system(db::DB_METADATA));
+        let version: u8 = view.get(db::VERSION_NAME).unwrap();
+        assert_eq!(version, db::DB_VERSION);
+    }
+
+    #[test]
+    #[should_panic(expected = "actual 2, expected 0")]
+    fn database_check_incorrect_version() {
+        let dir = TempDir::new().unwrap();
+        let opts = DbOptions::default();
+        {
+            let db = SledDB::open(&dir, &opts).unwrap();
+            let fork = db.fork();
+            {
+                let mut view = View::new(&fork, ResolvedAddress::system(db::DB_METADATA));
+                view.put(db::VERSION_NAME, 2_u8);
+            }
+            db.merge(fork.into_patch()).unwrap();
+        }
+        SledDB::open(&dir, &opts).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! Tests related to `BinaryKey` derivation.
+
+use exonum_crypto::{Hash, PublicKey, PUBLIC_KEY_LENGTH};
+use exonum_derive::BinaryKey;
+
+use exonum_merkledb::{access::AccessExt, BinaryKey, Database, FixedSizeKey, TemporaryDB};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, BinaryKey)]
+enum Kind {
+    Deposit,
+    Transfer,
+    Withdrawal,
+}
+
+#[derive(Debug, Clone, PartialEq, BinaryKey)]
+struct HistoryKey {
+    owner: PublicKey,
+    height: u64,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq, BinaryKey)]
+struct Nested(HistoryKey, i16);
+
+#[derive(Debug, Clone, PartialEq, BinaryKey)]
+struct NamedKey {
+    hash: Hash,
+    #[binary_key(variable_size)]
+    name: String,
+}
+
+fn to_bytes<K: BinaryKey + ?Sized>(key: &K) -> Vec<u8> {
+    let mut buffer = vec![0; key.size()];
+    assert_eq!(key.write(&mut buffer), buffer.len());
+    buffer
+}
+
+fn assert_roundtrip<K>(key: &K)
+where
+    K: BinaryKey<Owned = K> + PartialEq + std::fmt::Debug,
+{
+    let bytes = to_bytes(key);
+    assert_eq!(K::read(&bytes), *key);
+}
+
+#[test]
+fn enum_keys() {
+    assert_eq!(Kind::SIZE, 1);
+    assert_eq!(to_bytes(&Kind::Deposit), vec![0]);
+    assert_eq!(to_bytes(&Kind::Withdrawal), vec![2]);
+    for &kind in &[Kind::Deposit, Kind::Transfer, Kind::Withdrawal] {
+        assert_roundtrip(&kind);
+    }
+}
+
+#[test]
+fn fixed_size_keys() {
+    assert_eq!(HistoryKey::SIZE, PUBLIC_KEY_LENGTH + 8 + 1);
+    assert_eq!(Nested::SIZE, HistoryKey::SIZE + 2);
+
+    let key = HistoryKey {
+        owner: PublicKey::new([1; PUBLIC_KEY_LENGTH]),
+        height: 0x0102,
+        kind: Kind::Transfer,
+    };
+    let bytes = to_bytes(&key);
+    assert_eq!(bytes.len(), HistoryKey::SIZE);
+    assert_eq!(bytes[..PUBLIC_KEY_LENGTH], [1; PUBLIC_KEY_LENGTH]);
+    assert_eq!(bytes[PUBLIC_KEY_LENGTH..], [0, 0, 0, 0, 0, 0, 1, 2, 1]);
+    assert_roundtrip(&key);
+
+    let nested = Nested(key, -1);
+    assert_eq!(to_bytes(&nested).len(), Nested::SIZE);
+    assert_roundtrip(&nested);
+}
+
+#[test]
+fn variable_size_keys() {
+    let key = NamedKey {
+        hash: Hash::zero(),
+        name: "foo".to_owned(),
+    };
+    assert_eq!(key.size(), 35);
+    assert_roundtrip(&key);
+
+    let empty_name = NamedKey {
+        name: String::new(),
+        ..key
+    };
+    assert_eq!(empty_name.size(), 32);
+    assert_roundtrip(&empty_name);
+}
+
+#[test]
+fn composite_keys_are_sorted() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut map = fork.get_map("history");
+
+    let owners = [
+        PublicKey::new([2; PUBLIC_KEY_LENGTH]),
+        PublicKey::new([1; PUBLIC_KEY_LENGTH]),
+    ];
+    let mut keys = vec![];
+    for &owner in &owners {
+        for &height in &[256_u64, 1, 3] {
+            for &kind in &[Kind::Withdrawal, Kind::Deposit] {
+                let key = HistoryKey {
+                    owner,
+                    height,
+                    kind,
+                };
+                map.put(&key, height);
+                keys.push(key);
+            }
+        }
+    }
+
+    keys.sort_by(|x, y| (x.owner, x.height, x.kind).cmp(&(y.owner, y.height, y.kind)));
+    let stored_keys: Vec<_> = map.keys().collect();
+    assert_eq!(stored_keys, keys);
+
+    // Iteration over a prefix of the composite key.
+    let owner_heights: Vec<_> = map
+        .iter_from(&HistoryKey {
+            owner: owners[0],
+            height: 0,
+            kind: Kind::Deposit,
+        })
+        .map(|(key, _)| (key.height, key.kind))
+        .collect();
+    assert_eq!(
+        owner_heights,
+        vec![
+            (1, Kind::Deposit),
+            (1, Kind::Withdrawal),
+            (3, Kind::Deposit),
+            (3, Kind::Withdrawal),
+            (256, Kind::Deposit),
+            (256, Kind::Withdrawal),
+        ]
+    );
+}
@@ -4,7 +4,8 @@ use exonum_derive::FromAccess;
 
 use exonum_merkledb::{
     access::{Access, AccessExt, FromAccess, RawAccessMut},
-    BinaryKey, Database, Entry, Group, Lazy, ListIndex, ObjectHash, ProofMapIndex, TemporaryDB,
+    BinaryKey, Database, Entry, Group, Lazy, ListIndex, ObjectHash, ProofKeySetIndex,
+    ProofMapIndex, TemporaryDB,
 };
 
 #[derive(FromAccess)]
@@ -218,3 +219,18 @@ fn opt_out_from_schema() {
     let schema = NotSchema::new(&fork, "huh?");
     assert_eq!(schema.map.get(&1_u64).unwrap(), 2);
 }
+
+#[test]
+fn component_with_proof_key_set() {
+    #[derive(FromAccess)]
+    struct Schema<T: Access> {
+        set: ProofKeySetIndex<T::Base, str>,
+    }
+
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    fork.get_proof_key_set::<_, str>("set").insert("foo");
+    let schema = Schema::new(&fork);
+    assert!(schema.set.contains("foo"));
+    assert!(!schema.set.contains("bar"));
+}
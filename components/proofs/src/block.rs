@@ -0,0 +1,552 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::{fmt, str::FromStr};
+
+use ed25519_dalek as ed25519;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    hash::{decode_hex_into, encode_hex, hash, FromHexError, Hash, ObjectHash},
+    map::{CheckedMapProof, MapProof, MapProofError},
+    proto::{DecodeError, Reader, Value, Writer},
+    ValidationError,
+};
+
+/// Size of an Ed25519 public key in bytes.
+pub const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// Ed25519 public key of a validator.
+///
+/// The key is (de)serialized as a hex string, which is compatible with
+/// `exonum_crypto::PublicKey`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PublicKey([u8; PUBLIC_KEY_LENGTH]);
+
+impl PublicKey {
+    /// Creates a public key from the given bytes.
+    pub const fn new(bytes: [u8; PUBLIC_KEY_LENGTH]) -> Self {
+        PublicKey(bytes)
+    }
+
+    /// Creates a public key from the given byte slice. Returns `None` if the slice has
+    /// an incorrect length.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() == PUBLIC_KEY_LENGTH {
+            let mut inner = [0; PUBLIC_KEY_LENGTH];
+            inner.copy_from_slice(bytes);
+            Some(PublicKey(inner))
+        } else {
+            None
+        }
+    }
+
+    /// Parses a public key from a hex string.
+    pub fn from_hex(hex: &str) -> Result<Self, FromHexError> {
+        let mut inner = [0; PUBLIC_KEY_LENGTH];
+        decode_hex_into(hex, &mut inner)?;
+        Ok(PublicKey(inner))
+    }
+
+    /// Returns the hex representation of the public key.
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.0)
+    }
+}
+
+impl AsRef<[u8]> for PublicKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "PublicKey({})", self)
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        Self::from_hex(&hex).map_err(de::Error::custom)
+    }
+}
+
+/// Header of a block, as returned by the explorer API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    /// Height of the block.
+    pub height: u64,
+    /// Number of transactions in the block.
+    pub tx_count: u32,
+    /// Hash link to the previous block in the blockchain.
+    pub prev_hash: Hash,
+    /// Root hash of the Merkle tree of transactions in the block.
+    pub tx_hash: Hash,
+    /// Hash of the blockchain state after applying transactions in the block.
+    pub state_hash: Hash,
+    /// Root hash of the Merkle Patricia tree of the erroneous calls performed within the block.
+    pub error_hash: Hash,
+    /// Additional headers of the block, such as the identifier of the validator
+    /// which has proposed the block.
+    #[serde(default)]
+    pub additional_headers: BTreeMap<String, Vec<u8>>,
+}
+
+/// Serializes a hash in the same way as the `exonum.crypto.Hash` Protobuf message.
+fn hash_message(hash: &Hash) -> Vec<u8> {
+    let mut writer = Writer::new();
+    writer.bytes(1, hash.as_ref());
+    writer.into_bytes()
+}
+
+/// The hash of the block is computed in the same way as in Exonum nodes, i.e., as the SHA-256
+/// digest of the `exonum.Block` Protobuf message corresponding to the header.
+impl ObjectHash for BlockHeader {
+    fn object_hash(&self) -> Hash {
+        let mut headers = Writer::new();
+        for (key, value) in &self.additional_headers {
+            let mut entry = Writer::new();
+            entry.bytes(1, key.as_bytes());
+            entry.bytes(2, value);
+            headers.message(1, &entry.into_bytes());
+        }
+
+        let mut block = Writer::new();
+        block.uint(2, self.height);
+        block.uint(3, u64::from(self.tx_count));
+        block.message(4, &hash_message(&self.prev_hash));
+        block.message(5, &hash_message(&self.tx_hash));
+        block.message(6, &hash_message(&self.state_hash));
+        block.message(7, &hash_message(&self.error_hash));
+        block.message(8, &headers.into_bytes());
+        hash(&block.into_bytes())
+    }
+}
+
+/// Contents of a `Precommit` message, i.e., a vote of a validator for accepting a block.
+/// The timestamp of the message is not decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Precommit {
+    /// Identifier of the validator, i.e., its index in the list of validators.
+    pub validator: u16,
+    /// Height of the block.
+    pub height: u64,
+    /// Consensus round in which the block was accepted.
+    pub round: u32,
+    /// Hash of the `Propose` message corresponding to the block.
+    pub propose_hash: Hash,
+    /// Hash of the block.
+    pub block_hash: Hash,
+}
+
+/// Reads a hash from the `exonum.crypto.Hash` Protobuf message.
+fn read_hash(message: &[u8]) -> Result<Hash, DecodeError> {
+    let mut reader = Reader::new(message);
+    let mut hash = None;
+    while let Some((field, value)) = reader.next_field()? {
+        if let (1, Value::Bytes(bytes)) = (field, value) {
+            hash = Hash::from_slice(bytes);
+        }
+    }
+    hash.ok_or(DecodeError)
+}
+
+impl Precommit {
+    /// Decodes a precommit from the `exonum.consensus.ExonumMessage` Protobuf message.
+    fn from_payload(payload: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(payload);
+        let mut message = None;
+        while let Some((field, value)) = reader.next_field()? {
+            // Other fields correspond to other kinds of messages.
+            if let (4, Value::Bytes(bytes)) = (field, value) {
+                message = Some(bytes);
+            }
+        }
+
+        let mut reader = Reader::new(message.ok_or(DecodeError)?);
+        let (mut validator, mut height, mut round) = (0, 0, 0);
+        let (mut propose_hash, mut block_hash) = (None, None);
+        while let Some((field, value)) = reader.next_field()? {
+            match (field, value) {
+                (1, Value::Uint(value)) => validator = value,
+                (2, Value::Uint(value)) => height = value,
+                (3, Value::Uint(value)) => round = value,
+                (4, Value::Bytes(bytes)) => propose_hash = Some(read_hash(bytes)?),
+                (5, Value::Bytes(bytes)) => block_hash = Some(read_hash(bytes)?),
+                _ => { /* Skip the timestamp and unknown fields. */ }
+            }
+        }
+
+        if validator > u64::from(u16::max_value()) || round > u64::from(u32::max_value()) {
+            return Err(DecodeError);
+        }
+        Ok(Self {
+            validator: validator as u16,
+            height,
+            round: round as u32,
+            propose_hash: propose_hash.ok_or(DecodeError)?,
+            block_hash: block_hash.ok_or(DecodeError)?,
+        })
+    }
+}
+
+/// `Precommit` message signed by a validator.
+///
+/// The message is (de)serialized as a hex string with the `exonum.consensus.SignedMessage`
+/// Protobuf message, which is compatible with `Verified<Precommit>` in the explorer API.
+/// The message is not verified on deserialization; use [`verify()`] or
+/// [`BlockProof::verify()`] to check it.
+///
+/// [`verify()`]: #method.verify
+/// [`BlockProof::verify()`]: struct.BlockProof.html#method.verify
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedPrecommit {
+    raw: Vec<u8>,
+}
+
+impl SignedPrecommit {
+    /// Creates a message from the Protobuf serialization of `exonum.consensus.SignedMessage`.
+    pub fn from_bytes(raw: Vec<u8>) -> Self {
+        Self { raw }
+    }
+
+    /// Returns the Protobuf serialization of the message.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// Verifies the signature of the message and decodes it.
+    ///
+    /// If the check succeeds, the author of the message is returned together with the decoded
+    /// `Precommit`. Note that the author must still be checked against the validator keys.
+    pub fn verify(&self) -> Result<(PublicKey, Precommit), BlockProofError> {
+        let (mut payload, mut author, mut signature) = (None, None, None);
+        let mut reader = Reader::new(&self.raw);
+        while let Some((field, value)) = reader.next_field()? {
+            match (field, value) {
+                (1, Value::Bytes(bytes)) => payload = Some(bytes),
+                (2, Value::Bytes(bytes)) => author = Some(bytes),
+                (3, Value::Bytes(bytes)) => signature = Some(bytes),
+                _ => {}
+            }
+        }
+        let payload = payload.ok_or(DecodeError)?;
+        // Public keys and signatures are wrapped into messages with a single `data` field
+        // in the same way as hashes.
+        let read_data = |message: Option<&[u8]>| -> Result<Vec<u8>, DecodeError> {
+            let mut reader = Reader::new(message.ok_or(DecodeError)?);
+            let mut data = None;
+            while let Some((field, value)) = reader.next_field()? {
+                if let (1, Value::Bytes(bytes)) = (field, value) {
+                    data = Some(bytes.to_vec());
+                }
+            }
+            data.ok_or(DecodeError)
+        };
+        let author = read_data(author)?;
+        let signature = read_data(signature)?;
+
+        let public_key = ed25519::PublicKey::from_bytes(&author)
+            .map_err(|_| BlockProofError::InvalidSignature)?;
+        let signature = ed25519::Signature::from_bytes(&signature)
+            .map_err(|_| BlockProofError::InvalidSignature)?;
+        public_key
+            .verify(payload, &signature)
+            .map_err(|_| BlockProofError::InvalidSignature)?;
+
+        let author = PublicKey::from_slice(&author).ok_or(DecodeError)?;
+        let precommit = Precommit::from_payload(payload)?;
+        Ok((author, precommit))
+    }
+}
+
+impl Serialize for SignedPrecommit {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&encode_hex(&self.raw))
+    }
+}
+
+impl<'de> Deserialize<'de> for SignedPrecommit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        let mut raw = vec![0; hex.len() / 2];
+        decode_hex_into(&hex, &mut raw).map_err(de::Error::custom)?;
+        Ok(Self { raw })
+    }
+}
+
+/// Proof of authenticity for a block header, which consists of the header and `Precommit`
+/// messages of the validators that have voted for the block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockProof {
+    /// Header of the block.
+    pub block: BlockHeader,
+    /// Signed `Precommit` messages for the block.
+    pub precommits: Vec<SignedPrecommit>,
+}
+
+impl BlockProof {
+    /// Verifies the block header against the consensus keys of the validators active
+    /// at the height of the block, ordered by the validator identifier.
+    ///
+    /// The check succeeds if the proof contains `Precommit`s for the block from a Byzantine
+    /// majority of validators (i.e., more than 2/3 of them), all of which are correctly signed,
+    /// relate to the same consensus round and are authored by distinct validators.
+    pub fn verify(&self, validator_keys: &[PublicKey]) -> Result<(), BlockProofError> {
+        let quorum = validator_keys.len() * 2 / 3 + 1;
+        if self.precommits.len() < quorum {
+            return Err(BlockProofError::NotEnoughPrecommits);
+        }
+
+        let block_hash = self.block.object_hash();
+        let mut round = None;
+        let mut has_voted = vec![false; validator_keys.len()];
+        for precommit in &self.precommits {
+            let (author, precommit) = precommit.verify()?;
+            if precommit.height != self.block.height || precommit.block_hash != block_hash {
+                return Err(BlockProofError::UnrelatedPrecommit);
+            }
+            if *round.get_or_insert(precommit.round) != precommit.round {
+                return Err(BlockProofError::RoundMismatch);
+            }
+
+            let validator = usize::from(precommit.validator);
+            if validator_keys.get(validator) != Some(&author) {
+                return Err(BlockProofError::UnknownValidator);
+            }
+            if has_voted[validator] {
+                return Err(BlockProofError::DuplicateValidator);
+            }
+            has_voted[validator] = true;
+        }
+        Ok(())
+    }
+}
+
+/// An error returned when a block proof is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockProofError {
+    /// The proof contains fewer precommits than necessary for a Byzantine majority
+    /// of validators.
+    NotEnoughPrecommits,
+    /// A precommit cannot be decoded.
+    MalformedPrecommit,
+    /// A precommit has an invalid signature.
+    InvalidSignature,
+    /// A precommit is authored by a key not matching the validator identifier.
+    UnknownValidator,
+    /// Several precommits are authored by the same validator.
+    DuplicateValidator,
+    /// A precommit refers to another block.
+    UnrelatedPrecommit,
+    /// Precommits relate to different consensus rounds.
+    RoundMismatch,
+}
+
+impl From<DecodeError> for BlockProofError {
+    fn from(_: DecodeError) -> Self {
+        BlockProofError::MalformedPrecommit
+    }
+}
+
+impl fmt::Display for BlockProofError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            BlockProofError::NotEnoughPrecommits => "not enough precommits for the block",
+            BlockProofError::MalformedPrecommit => "malformed precommit",
+            BlockProofError::InvalidSignature => "invalid signature of a precommit",
+            BlockProofError::UnknownValidator => "precommit authored by an unknown validator",
+            BlockProofError::DuplicateValidator => "several precommits from the same validator",
+            BlockProofError::UnrelatedPrecommit => "precommit for another block",
+            BlockProofError::RoundMismatch => "precommits from different rounds",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BlockProofError {}
+
+/// Proof of authenticity for a single index within the blockchain state, as returned
+/// by the explorer API.
+///
+/// The proof ties the index hash to the `state_hash` of the block header, which is in turn
+/// authenticated by the `Precommit`s of the validators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexProof {
+    /// Proof of authenticity for the block header.
+    #[serde(flatten)]
+    pub block_proof: BlockProof,
+    /// Proof of authenticity for the index. Must contain a single key - a full index name,
+    /// e.g., `cryptocurrency.wallets`, or the prefix of the name (e.g., `cryptocurrency`)
+    /// if `prefix_proof` is present.
+    pub index_proof: MapProof<String, Hash>,
+    /// Proof of authenticity for the index within the state aggregator of its prefix.
+    /// Present only if hierarchical state aggregation is enabled for the prefix
+    /// of the index name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix_proof: Option<MapProof<String, Hash>>,
+}
+
+impl IndexProof {
+    /// Verifies the block proof against the consensus keys of the validators (see
+    /// [`BlockProof::verify()`]) and checks that `index_proof` is valid and matches
+    /// the `state_hash` of the block header. If `prefix_proof` is present, it is checked
+    /// against the prefix hash proven by `index_proof`.
+    ///
+    /// If the check succeeds, the returned proof contains the proven index name and hash.
+    ///
+    /// [`BlockProof::verify()`]: struct.BlockProof.html#method.verify
+    pub fn verify(
+        &self,
+        validator_keys: &[PublicKey],
+    ) -> Result<CheckedMapProof<'_, String, Hash>, IndexProofError> {
+        self.block_proof
+            .verify(validator_keys)
+            .map_err(IndexProofError::Block)?;
+        let checked_proof = self
+            .index_proof
+            .check_against_hash(self.block_proof.block.state_hash)
+            .map_err(IndexProofError::Index)?;
+        let prefix_proof = match self.prefix_proof {
+            Some(ref proof) => proof,
+            None => return Ok(checked_proof),
+        };
+
+        let (prefix, prefix_hash) = checked_proof
+            .entries()
+            .next()
+            .ok_or(IndexProofError::Prefix(ValidationError::UnmatchedRootHash))?;
+        let checked_proof = prefix_proof
+            .check_against_hash(*prefix_hash)
+            .map_err(IndexProofError::Prefix)?;
+        // Each index in the prefix aggregator must have the proven prefix.
+        let has_prefix = checked_proof
+            .all_entries()
+            .all(|(name, _)| name.split('.').next() == Some(prefix.as_str()));
+        if has_prefix {
+            Ok(checked_proof)
+        } else {
+            Err(IndexProofError::Prefix(ValidationError::UnmatchedRootHash))
+        }
+    }
+
+    /// Verifies the proof and returns the hash of the index with the specified name.
+    /// `None` is returned if the proof is valid, but does not contain the index.
+    pub fn index_hash(
+        &self,
+        name: &str,
+        validator_keys: &[PublicKey],
+    ) -> Result<Option<Hash>, IndexProofError> {
+        let checked_proof = self.verify(validator_keys)?;
+        let hash = checked_proof
+            .entries()
+            .find(|(key, _)| key.as_str() == name)
+            .map(|(_, hash)| *hash);
+        Ok(hash)
+    }
+}
+
+/// An error returned when an index proof is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexProofError {
+    /// The block header is not authenticated.
+    Block(BlockProofError),
+    /// The proof for the index is invalid or does not match the block header.
+    Index(ValidationError<MapProofError>),
+    /// The proof for the index within the prefix aggregator is invalid or does not match
+    /// the prefix proven by the index proof.
+    Prefix(ValidationError<MapProofError>),
+}
+
+impl fmt::Display for IndexProofError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexProofError::Block(e) => write!(formatter, "Invalid block proof: {}", e),
+            IndexProofError::Index(e) => write!(formatter, "Invalid index proof: {}", e),
+            IndexProofError::Prefix(e) => write!(formatter, "Invalid prefix proof: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IndexProofError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IndexProofError::Block(e) => Some(e),
+            IndexProofError::Index(e) | IndexProofError::Prefix(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_key_hex_roundtrip() {
+        let key = PublicKey::new([0xab; PUBLIC_KEY_LENGTH]);
+        assert_eq!(key.to_hex().parse::<PublicKey>(), Ok(key));
+        assert_eq!(PublicKey::from_hex("00"), Err(FromHexError::InvalidLength));
+    }
+
+    #[test]
+    fn malformed_precommits() {
+        let precommit = SignedPrecommit::from_bytes(vec![0x0a, 0x05, 1, 2]);
+        assert_eq!(
+            precommit.verify().unwrap_err(),
+            BlockProofError::MalformedPrecommit
+        );
+        let precommit = SignedPrecommit::from_bytes(vec![0x0a, 0x02, 1, 2]);
+        assert_eq!(
+            precommit.verify().unwrap_err(),
+            BlockProofError::MalformedPrecommit
+        );
+    }
+}
@@ -0,0 +1,439 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt, str::FromStr};
+
+use serde::{
+    de::{self, Deserialize, Deserializer, Unexpected, Visitor},
+    Serialize, Serializer,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{path::ProofPath, values::BinaryValue};
+
+/// Size of the SHA-256 hash in bytes.
+pub const HASH_SIZE: usize = 32;
+
+const EMPTY_LIST_HASH: Hash = Hash([
+    0xc6, 0xc0, 0xaa, 0x07, 0xf2, 0x74, 0x93, 0xd2, 0xf2, 0xe5, 0xcf, 0xf5, 0x6c, 0x89, 0x0a, 0x35,
+    0x3a, 0x20, 0x08, 0x6d, 0x6c, 0x25, 0xec, 0x82, 0x51, 0x28, 0xe1, 0x2a, 0xe7, 0x52, 0xb2, 0xd9,
+]);
+const EMPTY_MAP_HASH: Hash = Hash([
+    0x73, 0x24, 0xb5, 0xc7, 0x2b, 0x51, 0xbb, 0x5d, 0x4c, 0x18, 0x0f, 0x11, 0x09, 0xcf, 0xd3, 0x47,
+    0xb6, 0x04, 0x73, 0x88, 0x21, 0x45, 0x84, 0x1c, 0x39, 0xf3, 0xe5, 0x84, 0x57, 0x62, 0x96, 0xf9,
+]);
+
+/// SHA-256 hash.
+///
+/// The hash is (de)serialized as a hex string, which is compatible with `exonum_crypto::Hash`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Hash([u8; HASH_SIZE]);
+
+impl Hash {
+    /// Creates a hash from the given bytes.
+    pub const fn new(bytes: [u8; HASH_SIZE]) -> Self {
+        Hash(bytes)
+    }
+
+    /// Creates a hash filled with zeros.
+    pub const fn zero() -> Self {
+        Hash([0; HASH_SIZE])
+    }
+
+    /// Creates a hash from the given byte slice. Returns `None` if the slice has
+    /// an incorrect length.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() == HASH_SIZE {
+            let mut inner = [0; HASH_SIZE];
+            inner.copy_from_slice(bytes);
+            Some(Hash(inner))
+        } else {
+            None
+        }
+    }
+
+    /// Parses a hash from a hex string.
+    pub fn from_hex(hex: &str) -> Result<Self, FromHexError> {
+        if hex.len() != 2 * HASH_SIZE {
+            return Err(FromHexError::InvalidLength);
+        }
+        let mut inner = [0; HASH_SIZE];
+        decode_hex_into(hex, &mut inner)?;
+        Ok(Hash(inner))
+    }
+
+    /// Returns the hex representation of the hash.
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.0)
+    }
+}
+
+/// Decodes a hex string into the provided buffer, the length of which must be equal
+/// to the half of the string length.
+pub(crate) fn decode_hex_into(hex: &str, buffer: &mut [u8]) -> Result<(), FromHexError> {
+    fn nibble(ch: u8) -> Result<u8, FromHexError> {
+        match ch {
+            b'0'..=b'9' => Ok(ch - b'0'),
+            b'a'..=b'f' => Ok(ch - b'a' + 10),
+            b'A'..=b'F' => Ok(ch - b'A' + 10),
+            _ => Err(FromHexError::InvalidCharacter),
+        }
+    }
+
+    let hex = hex.as_bytes();
+    if hex.len() != 2 * buffer.len() {
+        return Err(FromHexError::InvalidLength);
+    }
+    for (byte, chunk) in buffer.iter_mut().zip(hex.chunks(2)) {
+        *byte = (nibble(chunk[0])? << 4) | nibble(chunk[1])?;
+    }
+    Ok(())
+}
+
+/// Returns the hex representation of the provided bytes.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    use core::fmt::Write;
+
+    let mut hex = String::with_capacity(2 * bytes.len());
+    for byte in bytes {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
+
+impl AsRef<[u8]> for Hash {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(formatter, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "Hash({})", self)
+    }
+}
+
+impl FromStr for Hash {
+    type Err = FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HexVisitor;
+
+        impl<'v> Visitor<'v> for HexVisitor {
+            type Value = Hash;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("hex string with 64 characters")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Hash, E>
+            where
+                E: de::Error,
+            {
+                Hash::from_hex(value).map_err(|_| E::invalid_value(Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_str(HexVisitor)
+    }
+}
+
+/// Error parsing a `Hash` from a hex string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FromHexError {
+    /// The string has a length different from `2 * HASH_SIZE`.
+    InvalidLength,
+    /// The string contains a non-hex character.
+    InvalidCharacter,
+}
+
+impl fmt::Display for FromHexError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            FromHexError::InvalidLength => "invalid hex string length",
+            FromHexError::InvalidCharacter => "invalid character in hex string",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FromHexError {}
+
+/// Computes the SHA-256 hash of the given data.
+pub fn hash(data: &[u8]) -> Hash {
+    hash_parts(&[data])
+}
+
+fn hash_parts(parts: &[&[u8]]) -> Hash {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.input(part);
+    }
+    Hash::from_slice(&hasher.result()).unwrap()
+}
+
+/// Prefixes for different types of hashed objects, which provide domain separation
+/// among hashed objects of different types.
+///
+/// The prefixes and hashing rules are the same as in `exonum_merkledb::HashTag`.
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum HashTag {
+    /// Hash prefix of a blob (i.e., a value hashed according to its [`BinaryValue`]
+    /// representation).
+    ///
+    /// [`BinaryValue`]: trait.BinaryValue.html
+    Blob = 0,
+    /// Hash prefix of a branch node in a Merkle tree built for a Merkelized list.
+    ListBranchNode = 1,
+    /// Hash prefix of a Merkelized list.
+    ListNode = 2,
+    /// Hash prefix of a Merkelized map.
+    MapNode = 3,
+    /// Hash prefix of a branch node in a Merkle Patricia tree built for a Merkelized map.
+    MapBranchNode = 4,
+}
+
+impl HashTag {
+    /// Obtains a hashed value of a leaf in a Merkle tree.
+    pub fn hash_leaf(value: &[u8]) -> Hash {
+        hash_parts(&[&[HashTag::Blob as u8], value])
+    }
+
+    /// Obtains a hashed value of a branch in a Merkle tree.
+    pub fn hash_node(left_hash: &Hash, right_hash: &Hash) -> Hash {
+        hash_parts(&[
+            &[HashTag::ListBranchNode as u8],
+            left_hash.as_ref(),
+            right_hash.as_ref(),
+        ])
+    }
+
+    /// Obtains a hashed value of a Merkle tree branch with one child.
+    pub fn hash_single_node(hash: &Hash) -> Hash {
+        hash_parts(&[&[HashTag::ListBranchNode as u8], hash.as_ref()])
+    }
+
+    /// Obtains hash of a Merkelized list. `len` is the length of the list, and `root` is
+    /// the hash of the root node of the Merkle tree corresponding to the list.
+    ///
+    /// ```text
+    /// h = sha-256( HashTag::ListNode || len as u64 || merkle_root )
+    /// ```
+    pub fn hash_list_node(len: u64, root: Hash) -> Hash {
+        hash_parts(&[
+            &[HashTag::ListNode as u8],
+            &len.to_le_bytes(),
+            root.as_ref(),
+        ])
+    }
+
+    /// Hash of an empty Merkelized list.
+    ///
+    /// ```text
+    /// h = sha-256( HashTag::ListNode || 0 || Hash::zero() )
+    /// ```
+    pub fn empty_list_hash() -> Hash {
+        EMPTY_LIST_HASH
+    }
+
+    /// Computes the hash for a Merkelized list containing the given values.
+    pub fn hash_list<V: BinaryValue>(values: &[V]) -> Hash {
+        let mut hashes: Vec<_> = values
+            .iter()
+            .map(|value| Self::hash_leaf(&value.to_bytes()))
+            .collect();
+        while hashes.len() > 1 {
+            hashes = hashes
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => Self::hash_node(left, right),
+                    [single] => Self::hash_single_node(single),
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        let root = hashes.first().copied().unwrap_or_else(Hash::zero);
+        Self::hash_list_node(values.len() as u64, root)
+    }
+
+    /// Hash of a Merkelized map with at least 2 entries. `root` is the recursively defined
+    /// hash of the root node of the binary Patricia Merkle tree corresponding to the map.
+    ///
+    /// ```text
+    /// h = sha-256( HashTag::MapNode || merkle_root )
+    /// ```
+    pub fn hash_map_node(root: Hash) -> Hash {
+        hash_parts(&[&[HashTag::MapNode as u8], root.as_ref()])
+    }
+
+    /// Hash of a branch node in a Merkle Patricia tree. `branch_node` is the binary serialization
+    /// of the node.
+    ///
+    /// ```text
+    /// h = sha-256( HashTag::MapBranchNode || <branch_node> )
+    /// ```
+    pub fn hash_map_branch(branch_node: &[u8]) -> Hash {
+        hash_parts(&[&[HashTag::MapBranchNode as u8], branch_node])
+    }
+
+    /// Hash of a Merkelized map with a single entry.
+    ///
+    /// ```text
+    /// h = sha-256( HashTag::MapBranchNode || <path> || <child_hash> )
+    /// ```
+    pub fn hash_single_entry_map(path: &ProofPath, child_hash: &Hash) -> Hash {
+        hash_parts(&[
+            &[HashTag::MapBranchNode as u8],
+            path.as_bytes(),
+            child_hash.as_ref(),
+        ])
+    }
+
+    /// Hash of an empty Merkelized map.
+    ///
+    /// ```text
+    /// sha-256( HashTag::MapNode || Hash::default() )
+    /// ```
+    pub fn empty_map_hash() -> Hash {
+        EMPTY_MAP_HASH
+    }
+}
+
+/// A common trait for the ability to compute a unique hash. This hash is used
+/// to transform keys of `MapProof`s with the [`Hashed`] key mode.
+///
+/// Implementations for standard types are the same as in `exonum-merkledb`.
+///
+/// [`Hashed`]: struct.Hashed.html
+pub trait ObjectHash {
+    /// Returns a hash of the value.
+    fn object_hash(&self) -> Hash;
+}
+
+/// Just returns the original hash.
+impl ObjectHash for Hash {
+    fn object_hash(&self) -> Hash {
+        *self
+    }
+}
+
+impl ObjectHash for str {
+    fn object_hash(&self) -> Hash {
+        hash(self.as_bytes())
+    }
+}
+
+impl ObjectHash for [u8] {
+    fn object_hash(&self) -> Hash {
+        hash(self)
+    }
+}
+
+macro_rules! impl_object_hash_for_binary_value {
+    ($( $type:ty ),*) => {
+        $(
+            impl ObjectHash for $type {
+                fn object_hash(&self) -> Hash {
+                    hash(&self.to_bytes())
+                }
+            }
+        )*
+    };
+}
+
+impl_object_hash_for_binary_value! {
+    (), bool, Vec<u8>, String,
+    u8, u16, u32, u64, u128, i8, i16, i32, i64, i128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_hashes() {
+        let empty_list_hash = hash_parts(&[&[2], &[0; 8], &[0; HASH_SIZE]]);
+        assert_eq!(empty_list_hash, HashTag::empty_list_hash());
+        assert_eq!(
+            HashTag::hash_list_node(0, Hash::zero()),
+            HashTag::empty_list_hash()
+        );
+        assert_eq!(HashTag::hash_list::<u32>(&[]), HashTag::empty_list_hash());
+
+        let empty_map_hash = hash_parts(&[&[3], &[0; HASH_SIZE]]);
+        assert_eq!(empty_map_hash, HashTag::empty_map_hash());
+    }
+
+    #[test]
+    fn hex_roundtrip() {
+        let hash = hash(b"foo");
+        let hex = hash.to_hex();
+        assert_eq!(hex.len(), 2 * HASH_SIZE);
+        assert_eq!(Hash::from_hex(&hex), Ok(hash));
+        assert_eq!(hex.parse::<Hash>(), Ok(hash));
+
+        assert_eq!(Hash::from_hex("00"), Err(FromHexError::InvalidLength));
+        let invalid: String = "z".repeat(2 * HASH_SIZE);
+        assert_eq!(
+            Hash::from_hex(&invalid),
+            Err(FromHexError::InvalidCharacter)
+        );
+    }
+
+    #[test]
+    fn list_hash() {
+        let values = [1_u32, 2, 3];
+        let leaves: Vec<_> = values
+            .iter()
+            .map(|value| HashTag::hash_leaf(&value.to_bytes()))
+            .collect();
+        let root = HashTag::hash_node(
+            &HashTag::hash_node(&leaves[0], &leaves[1]),
+            &HashTag::hash_single_node(&leaves[2]),
+        );
+        assert_eq!(
+            HashTag::hash_list(&values),
+            HashTag::hash_list_node(3, root)
+        );
+    }
+}
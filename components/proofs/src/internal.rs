@@ -0,0 +1,28 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Building blocks shared with `exonum-merkledb`, which stores Merkle trees
+//! in the same format and verifies its proofs with the same algorithms.
+//!
+//! **This module is unstable.** It is available only with the `internal` crate feature,
+//! and its contents may change in any release without a major version bump.
+
+pub use crate::{
+    list::{prefix_subtrees, restore_consistent_roots, restore_list_root, ProofListKey, MAX_INDEX},
+    map::restore_map_root,
+    path::{
+        BitsRange, ChildKind, PathRange, ProofPathExt, BRANCH_KEY_PREFIX, LEAF_KEY_PREFIX,
+        PROOF_PATH_KEY_POS, PROOF_PATH_KIND_POS, PROOF_PATH_LEN_POS,
+    },
+};
@@ -0,0 +1,150 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of Merkle proofs produced by Exonum nodes.
+//!
+//! This crate contains the proof types of [`exonum-merkledb`] together with the logic
+//! necessary to check them, but without the storage backend and the rest of the framework.
+//! It depends only on `serde`, `sha2` and `ed25519-dalek` and does not require the standard
+//! library, so it can be used in light clients running in browsers (via WebAssembly)
+//! or on embedded devices.
+//!
+//! The following proofs are supported:
+//!
+//! - [`ListProof`] proves elements of a `ProofListIndex`
+//! - [`MapProof`] proves presence or absence of keys in a `ProofMapIndex`
+//! - [`BlockProof`] proves that a block header is authorized by the validators, i.e.,
+//!   contains `Precommit` messages signed by a Byzantine majority of validators
+//! - [`IndexProof`] proves that an index with a certain hash is a part of the blockchain state
+//!   fixed in an authorized block header
+//!
+//! Verification of block and index proofs requires the consensus keys of the validators,
+//! which the light client should obtain from a trusted source.
+//!
+//! Proofs are (de)serialized in the same format as in `exonum-merkledb`, so they can be
+//! deserialized directly from the responses of the explorer API. Hashes of the values in proofs
+//! are computed according to the [`BinaryValue`] trait, which mirrors the encoding of standard
+//! types in `exonum-merkledb`; implement it for your own value types.
+//!
+//! # Crate features
+//!
+//! - `std` (enabled by default) implements `std::error::Error` for the error types
+//!   of the crate. Disable default features to build the crate without the standard library;
+//!   an allocator is still required.
+//! - `internal` exposes the [`internal`] module with building blocks shared with
+//!   `exonum-merkledb`. The module is unstable and is not covered by semantic versioning.
+//!
+//! # Examples
+//!
+//! ```
+//! use exonum_proofs::MapProof;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! // Proof for a map with a single entry, as returned by a node.
+//! let proof = r#"{
+//!     "entries": [{ "key": "hello", "value": "world" }],
+//!     "proof": []
+//! }"#;
+//! let proof: MapProof<String, String> = serde_json::from_str(proof)?;
+//! let checked_proof = proof.check()?;
+//! let entries: Vec<_> = checked_proof.entries().collect();
+//! assert_eq!(entries, vec![(&"hello".to_owned(), &"world".to_owned())]);
+//! // `checked_proof.index_hash()` should be compared with a trusted hash of the map.
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`exonum-merkledb`]: https://docs.rs/exonum-merkledb
+//! [`ListProof`]: struct.ListProof.html
+//! [`MapProof`]: struct.MapProof.html
+//! [`BlockProof`]: struct.BlockProof.html
+//! [`IndexProof`]: struct.IndexProof.html
+//! [`BinaryValue`]: trait.BinaryValue.html
+//! [`internal`]: internal/index.html
+
+#![no_std]
+#![warn(
+    missing_debug_implementations,
+    unsafe_code,
+    bare_trait_objects,
+    missing_docs
+)]
+#![warn(clippy::pedantic)]
+#![allow(
+    // Next `cast_*` lints don't give alternatives.
+    clippy::cast_possible_wrap, clippy::cast_possible_truncation, clippy::cast_sign_loss,
+    // Next lints produce too much noise/false positives.
+    clippy::module_name_repetitions, clippy::similar_names,
+    // '... may panic' lints.
+    clippy::indexing_slicing,
+    clippy::use_self,
+)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub use self::{
+    block::{
+        BlockHeader, BlockProof, BlockProofError, IndexProof, IndexProofError, Precommit,
+        PublicKey, SignedPrecommit, PUBLIC_KEY_LENGTH,
+    },
+    hash::{hash, FromHexError, Hash, HashTag, ObjectHash, HASH_SIZE},
+    list::{CheckedListProof, ListProof, ListProofError},
+    map::{CheckedMapProof, Hashed, MapProof, MapProofError, Raw, ToProofPath},
+    path::{ProofPath, PROOF_MAP_KEY_SIZE, PROOF_PATH_SIZE},
+    values::BinaryValue,
+};
+
+use core::fmt;
+
+mod block;
+mod hash;
+#[cfg(feature = "internal")]
+pub mod internal;
+mod list;
+mod map;
+mod path;
+mod proto;
+mod values;
+
+/// Errors that can occur while validating a `ListProof` or `MapProof` against
+/// a trusted collection hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationError<E> {
+    /// The hash of the proof is not equal to the trusted root hash.
+    UnmatchedRootHash,
+    /// The proof is malformed.
+    Malformed(E),
+}
+
+impl<E: fmt::Display> fmt::Display for ValidationError<E> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::UnmatchedRootHash => formatter
+                .write_str("hash of the proof is not equal to the trusted hash of the collection"),
+            ValidationError::Malformed(e) => write!(formatter, "Malformed proof: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for ValidationError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ValidationError::UnmatchedRootHash => None,
+            ValidationError::Malformed(e) => Some(e),
+        }
+    }
+}
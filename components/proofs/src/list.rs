@@ -0,0 +1,672 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{vec, vec::Vec};
+use core::{cmp::Ordering, fmt};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    hash::{Hash, HashTag},
+    values::BinaryValue,
+    ValidationError,
+};
+
+const HEIGHT_SHIFT: u64 = 56;
+/// Maximum index of an element in a `ProofListIndex`.
+pub const MAX_INDEX: u64 = 0xFF_FFFF_FFFF_FFFF; // 2_u64.pow(56) - 1
+
+/// Position of a node in the Merkle tree of a list.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofListKey {
+    index: u64,
+    height: u8,
+}
+
+#[cfg_attr(not(feature = "internal"), allow(dead_code))]
+impl ProofListKey {
+    /// Creates a key with the specified height and index.
+    pub fn new(height: u8, index: u64) -> Self {
+        debug_assert!(height <= 58 && index <= MAX_INDEX);
+        Self { height, index }
+    }
+
+    /// Returns the height of the node. Leaves have height 0.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Returns the index of the node among nodes at the same height.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Returns the key of the leaf with the specified index.
+    pub fn leaf(index: u64) -> Self {
+        Self::new(0, index)
+    }
+
+    /// Packs the key into a `u64` used as the key in the storage.
+    pub fn as_db_key(&self) -> u64 {
+        (u64::from(self.height) << HEIGHT_SHIFT) + self.index
+    }
+
+    /// Unpacks the key from the representation produced by `as_db_key()`.
+    pub fn from_db_key(key: u64) -> Self {
+        Self::new((key >> HEIGHT_SHIFT) as u8, key & MAX_INDEX)
+    }
+
+    /// Returns the key of the parent node.
+    pub fn parent(&self) -> Self {
+        Self::new(self.height + 1, self.index >> 1)
+    }
+
+    /// Returns the index of the leftmost element at height 1 covered by the node.
+    pub fn first_left_leaf_index(&self) -> u64 {
+        if self.height < 2 {
+            self.index
+        } else {
+            self.index << (self.height - 1)
+        }
+    }
+
+    /// Checks if the node is the left child of its parent.
+    pub fn is_left(&self) -> bool {
+        self.index.trailing_zeros() >= 1
+    }
+
+    /// Returns the key of the left node among this node and its sibling.
+    pub fn as_left(&self) -> Self {
+        Self::new(self.height, self.index & !1)
+    }
+
+    /// Returns the key of the right node among this node and its sibling.
+    pub fn as_right(&self) -> Self {
+        Self::new(self.height, self.index | 1)
+    }
+}
+
+impl PartialOrd for ProofListKey {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl Ord for ProofListKey {
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        (self.height, self.index).cmp(&(rhs.height, rhs.index))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct HashedEntry {
+    #[serde(flatten)]
+    key: ProofListKey,
+    hash: Hash,
+}
+
+impl HashedEntry {
+    fn new(key: ProofListKey, hash: Hash) -> Self {
+        Self { key, hash }
+    }
+}
+
+impl From<(ProofListKey, Hash)> for HashedEntry {
+    fn from((key, hash): (ProofListKey, Hash)) -> Self {
+        Self::new(key, hash)
+    }
+}
+
+fn tree_height_by_length(len: u64) -> u8 {
+    if len == 0 {
+        0
+    } else {
+        len.next_power_of_two().trailing_zeros() as u8 + 1
+    }
+}
+
+/// View of a `ProofListIndex`, i.e., a subset of its elements coupled with a *proof*,
+/// which jointly allow restoring the `object_hash()` of the index. Apart from proving
+/// elements in the list, `ListProof` can assert that the list is shorter than the requested
+/// range of indexes.
+///
+/// Proofs of single elements, ranges and arbitrary sets of elements are all represented
+/// by this type.
+///
+/// # JSON serialization
+///
+/// `ListProof` is serialized to JSON as an object with the following fields:
+///
+/// - `proof` is an array of `{ height: number, index: number, hash: Hash }` objects.
+/// - `entries` is an array with list elements and their indexes, that is,
+///   tuples `[number, V]`.
+/// - `length` is the length of the underlying `ProofListIndex`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListProof<V> {
+    proof: Vec<HashedEntry>,
+    entries: Vec<(u64, V)>,
+    length: u64,
+}
+
+/// Merges two sorted vectors with `HashedEntry`s so that the elements in the resulting vector
+/// are ordered by increasing `HashedEntry.key`. Returns an error if there is an equal key
+/// present in both inputs.
+fn merge(first: Vec<HashedEntry>, second: Vec<HashedEntry>) -> Result<Vec<HashedEntry>, ()> {
+    let mut merged = Vec::with_capacity(first.len() + second.len());
+    let (mut first, mut second) = (first.into_iter().peekable(), second.into_iter().peekable());
+
+    loop {
+        let ordering = match (first.peek(), second.peek()) {
+            (Some(x), Some(y)) => x.key.cmp(&y.key),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        match ordering {
+            Ordering::Less => merged.push(first.next().unwrap()),
+            Ordering::Greater => merged.push(second.next().unwrap()),
+            Ordering::Equal => return Err(()),
+        }
+    }
+    Ok(merged)
+}
+
+/// Takes a subset of hashes at a particular height in the Merkle tree and
+/// computes all known hashes on the next height. `last_index` is the index of the last
+/// element in the Merkle tree on the given height.
+fn hash_layer(layer: &mut Vec<HashedEntry>, last_index: u64) -> Result<(), ListProofError> {
+    let new_len = (layer.len() + 1) / 2;
+    for i in 0..new_len {
+        let x = &layer[2 * i];
+        layer[i] = if let Some(y) = layer.get(2 * i + 1) {
+            // To be able to zip two hashes on the layer, they need to be adjacent to each other,
+            // and the first of them needs to have an even index.
+            if !x.key.is_left() || y.key.index != x.key.index + 1 {
+                return Err(ListProofError::MissingHash);
+            }
+            HashedEntry::new(x.key.parent(), HashTag::hash_node(&x.hash, &y.hash))
+        } else {
+            // If there is an odd number of hashes on the layer, the solitary hash must have
+            // the greatest possible index.
+            if last_index % 2 == 1 || x.key.index != last_index {
+                return Err(ListProofError::MissingHash);
+            }
+            HashedEntry::new(x.key.parent(), HashTag::hash_single_node(&x.hash))
+        };
+    }
+
+    layer.truncate(new_len);
+    Ok(())
+}
+
+/// Restores the root hash of a Merkle tree for a list with the specified `length`
+/// from the known hashes at height 1 (`layer`) and the known hashes at greater heights
+/// (`hashes`).
+///
+/// `layer` and `hashes` need to be ordered by increasing key; `hashes` must not contain
+/// the root of the tree.
+fn fold_layers(
+    mut layer: Vec<HashedEntry>,
+    mut hashes: Vec<HashedEntry>,
+    length: u64,
+) -> Result<Hash, ListProofError> {
+    let tree_height = tree_height_by_length(length);
+    let mut last_index = length - 1;
+
+    for height in 1..tree_height {
+        // Split `hashes` into those at `height` and those having greater height.
+        let split_key = ProofListKey::new(height + 1, 0);
+        let split_index = hashes
+            .binary_search_by(|entry| entry.key.cmp(&split_key))
+            .unwrap_or_else(|i| i);
+        let remaining_hashes = hashes.split_off(split_index);
+
+        // Merge `hashes` with those obtained by zipping the previous layer.
+        layer = merge(layer, hashes).map_err(|_| ListProofError::RedundantHash)?;
+
+        // Zip the current layer.
+        hash_layer(&mut layer, last_index)?;
+        last_index /= 2;
+        hashes = remaining_hashes;
+    }
+
+    match layer[..] {
+        [HashedEntry { key, hash }] if key == ProofListKey::new(tree_height, 0) => Ok(hash),
+        _ => Err(ListProofError::MissingHash),
+    }
+}
+
+/// Checks that `hashes` are ordered by increasing key and have positions feasible
+/// for a list with the specified `length`, excluding the root of the tree.
+fn check_hash_positions(hashes: &[HashedEntry], length: u64) -> Result<(), ListProofError> {
+    let tree_height = tree_height_by_length(length);
+
+    let hashes_ordered = hashes
+        .windows(2)
+        .all(|window| window[0].key < window[1].key);
+    if !hashes_ordered {
+        return Err(ListProofError::Unordered);
+    }
+
+    for &HashedEntry { key, .. } in hashes {
+        let height = key.height;
+        if height == 0 {
+            return Err(ListProofError::UnexpectedLeaf);
+        }
+
+        // `length - 1` is the index of the last element at `height = 1`. This index
+        // is divided by 2 with each new height.
+        if height >= tree_height || key.index > (length - 1) >> u64::from(height - 1) {
+            return Err(ListProofError::UnexpectedBranch);
+        }
+    }
+    Ok(())
+}
+
+impl<V> ListProof<V> {
+    /// Returns the length of the underlying `ProofListIndex`.
+    pub fn list_len(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns indexes and references to elements in the proof without verifying it.
+    pub fn entries_unchecked(&self) -> &[(u64, V)] {
+        &self.entries
+    }
+
+    /// Returns iterator over indexes of the elements in the proof without verifying
+    /// proof integrity.
+    pub fn indexes_unchecked<'s>(&'s self) -> impl Iterator<Item = u64> + 's {
+        self.entries.iter().map(|(index, _)| *index)
+    }
+}
+
+/// Returns keys of the perfect subtrees covering the first `length` elements of a list,
+/// ordered by increasing key.
+///
+/// The hashes of these subtrees do not change when elements are appended to the list;
+/// thus, they are the part shared by the Merkle trees of the list before and after
+/// appending elements.
+#[cfg_attr(not(feature = "internal"), allow(dead_code))]
+pub fn prefix_subtrees(length: u64) -> Vec<ProofListKey> {
+    let mut keys = Vec::new();
+    let mut offset = 0;
+    for bit in (0..64).rev() {
+        let subtree_len = 1 << bit;
+        if length & subtree_len != 0 {
+            keys.push(ProofListKey::new(bit + 1, offset >> bit));
+            offset += subtree_len;
+        }
+    }
+    keys.reverse();
+    keys
+}
+
+/// Restores the root hash of a Merkle tree for a list with the specified `length`
+/// from the hashes of its nodes, which are ordered by increasing key. Unlike `fold_layers`,
+/// `hashes` may consist of the tree root alone.
+fn restore_root(hashes: Vec<HashedEntry>, length: u64) -> Result<Hash, ListProofError> {
+    let tree_height = tree_height_by_length(length);
+    if tree_height == 0 {
+        return if hashes.is_empty() {
+            Ok(Hash::zero())
+        } else {
+            Err(ListProofError::NonEmptyProof)
+        };
+    }
+
+    if let [HashedEntry { key, hash }] = hashes[..] {
+        if key == ProofListKey::new(tree_height, 0) {
+            return Ok(hash);
+        }
+    }
+    check_hash_positions(&hashes, length)?;
+    fold_layers(vec![], hashes, length)
+}
+
+/// Restores the root hash of the Merkle tree of a list with the specified `length`
+/// from the proven elements and the hashes of other tree nodes (`proof`).
+///
+/// `entries` contain indexes of the elements together with their hashes computed
+/// with `HashTag::hash_leaf()`. This is the algorithm behind `ListProof::check()`
+/// exposed for proofs with other value encodings (e.g., in `exonum-merkledb`).
+///
+/// The root hash is computed by iterating over each height of the Merkle tree
+/// and computing hashes on this height based on the information in the proof.
+/// We don't need to restore *all* hashes on *all* heights; we just need sufficient information
+/// to restore the single hash at the last height (which is the Merkle tree root).
+///
+/// For proofs of a single element or a contiguous range of elements,
+/// the total number of restored hashes is `O(log_2(N))`, where `N` is the list length.
+/// For proofs of `k` arbitrary elements, the number of restored hashes is
+/// `O(k * log_2(N))`; hashes shared by paths from several elements to the root
+/// are restored only once.
+pub fn restore_list_root(
+    entries: impl IntoIterator<Item = (u64, Hash)>,
+    proof: impl IntoIterator<Item = (ProofListKey, Hash)>,
+    length: u64,
+) -> Result<Hash, ListProofError> {
+    let layer: Vec<_> = entries
+        .into_iter()
+        .map(|(index, hash)| HashedEntry::new(ProofListKey::new(1, index), hash))
+        .collect();
+    let proof: Vec<_> = proof.into_iter().map(HashedEntry::from).collect();
+    let tree_height = tree_height_by_length(length);
+
+    // First, check an edge case when the list contains no elements.
+    if tree_height == 0 {
+        return if proof.is_empty() && layer.is_empty() {
+            Ok(Hash::zero())
+        } else {
+            Err(ListProofError::NonEmptyProof)
+        };
+    }
+
+    // Fast path in case there are no values: in this case, the proof can contain
+    // only a single root hash.
+    if layer.is_empty() {
+        return match proof[..] {
+            [] => Err(ListProofError::MissingHash),
+            [HashedEntry { key, hash }] if key == ProofListKey::new(tree_height, 0) => Ok(hash),
+            _ => Err(ListProofError::UnexpectedBranch),
+        };
+    }
+
+    // Check ordering of entries and hashes, which is relied upon in the following steps.
+    let values_ordered = layer
+        .windows(2)
+        .all(|window| window[0].key.index < window[1].key.index);
+    if !values_ordered {
+        return Err(ListProofError::Unordered);
+    }
+    // Unlike hashes, entries are not covered by `check_hash_positions`.
+    if layer.iter().any(|entry| entry.key.index >= length) {
+        return Err(ListProofError::UnexpectedBranch);
+    }
+
+    check_hash_positions(&proof, length)?;
+    fold_layers(layer, proof, length)
+}
+
+/// Restores the root hashes of the Merkle trees of a list before and after appending
+/// elements, as asserted by a consistency proof.
+///
+/// `old_hashes` must correspond exactly to the perfect subtrees covering the older list
+/// (see `prefix_subtrees()`), and `new_hashes` must contain the remaining hashes
+/// necessary to restore the newer tree. Both sets of hashes are ordered by increasing key.
+/// Returns the older and the newer root hashes.
+#[cfg_attr(not(feature = "internal"), allow(dead_code))]
+pub fn restore_consistent_roots(
+    old_length: u64,
+    new_length: u64,
+    old_hashes: impl IntoIterator<Item = (ProofListKey, Hash)>,
+    new_hashes: impl IntoIterator<Item = (ProofListKey, Hash)>,
+) -> Result<(Hash, Hash), ListProofError> {
+    if old_length > new_length {
+        return Err(ListProofError::InvalidLength);
+    }
+
+    let old_hashes: Vec<_> = old_hashes.into_iter().map(HashedEntry::from).collect();
+    let expected_keys = prefix_subtrees(old_length);
+    if old_hashes.len() < expected_keys.len() {
+        return Err(ListProofError::MissingHash);
+    }
+    let keys_match = old_hashes.iter().map(|entry| entry.key).eq(expected_keys);
+    if !keys_match {
+        return Err(ListProofError::UnexpectedBranch);
+    }
+
+    let old_root = restore_root(old_hashes.clone(), old_length)?;
+    let new_hashes = new_hashes.into_iter().map(HashedEntry::from).collect();
+    let new_hashes = merge(old_hashes, new_hashes).map_err(|_| ListProofError::RedundantHash)?;
+    let new_root = restore_root(new_hashes, new_length)?;
+    Ok((old_root, new_root))
+}
+
+impl<V: BinaryValue> ListProof<V> {
+    /// Verifies the correctness of the proof.
+    ///
+    /// If the proof is valid, a checked list proof is returned, which allows to access
+    /// proven elements.
+    ///
+    /// ## Errors
+    ///
+    /// An error is returned if proof is malformed. The following checks are performed:
+    ///
+    /// - `proof` field is ordered by increasing `(height, index)` tuple.
+    /// - `entries` are ordered by increasing index.
+    /// - Positions of elements in `proof` and `entries` are feasible.
+    /// - There is sufficient information in `proof` and `entries` to restore the Merkle tree root.
+    /// - There are no redundant entries in `proof` (i.e., ones that can be inferred from other
+    ///   `proof` elements / `entries`).
+    pub fn check(&self) -> Result<CheckedListProof<'_, V>, ListProofError> {
+        let tree_root = restore_list_root(
+            self.entries
+                .iter()
+                .map(|(index, value)| (*index, HashTag::hash_leaf(&value.to_bytes()))),
+            self.proof.iter().map(|entry| (entry.key, entry.hash)),
+            self.length,
+        )?;
+        Ok(CheckedListProof {
+            entries: &self.entries,
+            length: self.length,
+            hash: HashTag::hash_list_node(self.length, tree_root),
+        })
+    }
+
+    /// Verifies the correctness of the proof according to the trusted list hash.
+    ///
+    /// The method is essentially a convenience wrapper around `check()`.
+    pub fn check_against_hash(
+        &self,
+        expected_list_hash: Hash,
+    ) -> Result<CheckedListProof<'_, V>, ValidationError<ListProofError>> {
+        self.check()
+            .map_err(ValidationError::Malformed)
+            .and_then(|checked_proof| {
+                if checked_proof.index_hash() == expected_list_hash {
+                    Ok(checked_proof)
+                } else {
+                    Err(ValidationError::UnmatchedRootHash)
+                }
+            })
+    }
+}
+
+/// Version of `ListProof` obtained after verification.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CheckedListProof<'a, V> {
+    entries: &'a [(u64, V)],
+    length: u64,
+    hash: Hash,
+}
+
+impl<'a, V> CheckedListProof<'a, V> {
+    /// Returns indexes and references to elements in the proof.
+    pub fn entries(&self) -> &'a [(u64, V)] {
+        self.entries
+    }
+
+    /// Returns iterator over indexes of the elements in the proof.
+    pub fn indexes<'s>(&'s self) -> impl Iterator<Item = u64> + 's {
+        self.entries.iter().map(|(index, _)| *index)
+    }
+
+    /// Returns the length of the underlying `ProofListIndex`.
+    pub fn list_len(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns the `object_hash()` of the underlying `ProofListIndex`.
+    pub fn index_hash(&self) -> Hash {
+        self.hash
+    }
+}
+
+/// An error that is returned when the list proof is invalid.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ListProofError {
+    /// Proof contains a hash in a place where a value was expected.
+    UnexpectedLeaf,
+    /// Proof contains a hash in the position which is impossible according to the list length.
+    UnexpectedBranch,
+    /// Values or hashes in the proof are not ordered by their keys.
+    Unordered,
+    /// There are redundant hashes in the proof: the hash of the underlying list can be calculated
+    /// without some of them.
+    RedundantHash,
+    /// Proof does not contain necessary information to compute the hash of the underlying list.
+    MissingHash,
+    /// Non-empty proof for an empty list.
+    ///
+    /// Empty lists should always have empty proofs, since there is no data to get values
+    /// or hashes from.
+    NonEmptyProof,
+    /// Length of the older list in a consistency proof is greater than the length
+    /// of the newer list.
+    InvalidLength,
+}
+
+impl fmt::Display for ListProofError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            ListProofError::UnexpectedLeaf => {
+                "proof contains a hash in a place where a value was expected"
+            }
+            ListProofError::UnexpectedBranch => {
+                "proof contains a hash in the position which is impossible according to the list length"
+            }
+            ListProofError::Unordered => {
+                "values or hashes in the proof are not ordered by their keys"
+            }
+            ListProofError::RedundantHash => "redundant hash in the proof",
+            ListProofError::MissingHash => "missing hash",
+            ListProofError::NonEmptyProof => "non-empty proof for an empty list",
+            ListProofError::InvalidLength => {
+                "length of the older list is greater than length of the newer list"
+            }
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ListProofError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(height: u8, index: u64) -> HashedEntry {
+        HashedEntry::new(
+            ProofListKey::new(height, index),
+            HashTag::hash_leaf(&index.to_bytes()),
+        )
+    }
+
+    #[test]
+    fn proof_list_key_ord() {
+        assert!(ProofListKey::new(0, 1000) < ProofListKey::new(0, 1001));
+        assert!(ProofListKey::new(0, 1000) < ProofListKey::new(1, 0));
+        assert_eq!(ProofListKey::new(1, 100), ProofListKey::new(1, 100));
+    }
+
+    #[test]
+    fn merge_example() {
+        let first = vec![entry(1, 0), entry(1, 5), entry(2, 5)];
+        let second = vec![
+            entry(1, 1),
+            entry(2, 2),
+            entry(2, 3),
+            entry(3, 0),
+            entry(4, 1),
+        ];
+        let merged = merge(first, second).unwrap();
+
+        assert_eq!(
+            merged,
+            vec![
+                entry(1, 0),
+                entry(1, 1),
+                entry(1, 5),
+                entry(2, 2),
+                entry(2, 3),
+                entry(2, 5),
+                entry(3, 0),
+                entry(4, 1),
+            ]
+        );
+        assert!(merge(vec![entry(1, 0)], vec![entry(1, 0)]).is_err());
+    }
+
+    #[test]
+    fn hash_layer_example() {
+        let mut layer = vec![
+            entry(1, 0),
+            entry(1, 1),
+            entry(1, 6),
+            entry(1, 7),
+            entry(1, 8),
+        ];
+        hash_layer(&mut layer, 8).unwrap();
+        assert!(layer.iter().map(|entry| entry.key).eq(vec![
+            ProofListKey::new(2, 0),
+            ProofListKey::new(2, 3),
+            ProofListKey::new(2, 4),
+        ]));
+
+        assert_eq!(
+            layer[0].hash,
+            HashTag::hash_node(
+                &HashTag::hash_leaf(&0_u64.to_bytes()),
+                &HashTag::hash_leaf(&1_u64.to_bytes()),
+            )
+        );
+        assert_eq!(
+            layer[2].hash,
+            HashTag::hash_single_node(&HashTag::hash_leaf(&8_u64.to_bytes()))
+        );
+
+        // layer[0] has odd index
+        let mut layer = vec![entry(1, 1), entry(1, 2)];
+        assert!(hash_layer(&mut layer, 2).is_err());
+
+        // layer[1] is not adjacent to layer[0]
+        let mut layer = vec![entry(1, 0), entry(1, 2)];
+        assert!(hash_layer(&mut layer, 3).is_err());
+        let mut layer = vec![entry(1, 0), entry(1, 3)];
+        assert!(hash_layer(&mut layer, 3).is_err());
+
+        // layer[-1] has odd index, while there is even number of elements in the layer
+        let mut layer = vec![entry(1, 0), entry(1, 1), entry(1, 7)];
+        assert!(hash_layer(&mut layer, 7).is_err());
+
+        // layer[-1] has index lesser that the layer length
+        let mut layer = vec![entry(1, 0), entry(1, 1), entry(1, 4)];
+        assert!(hash_layer(&mut layer, 6).is_err());
+    }
+
+    #[test]
+    fn prefix_subtrees_example() {
+        assert!(prefix_subtrees(0).is_empty());
+        assert_eq!(prefix_subtrees(4), vec![ProofListKey::new(3, 0)]);
+        assert_eq!(
+            prefix_subtrees(7),
+            vec![
+                ProofListKey::new(1, 6),
+                ProofListKey::new(2, 2),
+                ProofListKey::new(3, 0),
+            ]
+        );
+    }
+}
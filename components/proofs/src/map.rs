@@ -0,0 +1,558 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{vec, vec::Vec};
+use core::{cmp::Ordering, fmt, marker::PhantomData};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    hash::{Hash, HashTag, ObjectHash, HASH_SIZE},
+    path::{BitsRange, ProofPath, COMPRESSED_PATH_MAX_SIZE},
+    values::BinaryValue,
+    ValidationError,
+};
+
+/// Hashed variant of a key, representing the transform via SHA-256 hash function.
+/// This is the default key mode of `ProofMapIndex`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Hashed;
+
+/// Raw variant of a key, representing the identity transform. Corresponds
+/// to `RawProofMapIndex`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Raw;
+
+/// Trait defining key transformation into the format usable in Merkle Patricia trees.
+///
+/// See `exonum_merkledb::proof_map::ToProofPath` for details.
+pub trait ToProofPath<K: ?Sized> {
+    /// Transforms the provided key.
+    fn transform_key(key: &K) -> ProofPath;
+}
+
+impl<K: ObjectHash + ?Sized> ToProofPath<K> for Hashed {
+    fn transform_key(key: &K) -> ProofPath {
+        let mut bytes = [0; HASH_SIZE];
+        bytes.copy_from_slice(key.object_hash().as_ref());
+        ProofPath::new(&bytes)
+    }
+}
+
+impl ToProofPath<Hash> for Raw {
+    fn transform_key(key: &Hash) -> ProofPath {
+        let mut bytes = [0; HASH_SIZE];
+        bytes.copy_from_slice(key.as_ref());
+        ProofPath::new(&bytes)
+    }
+}
+
+impl ToProofPath<[u8; HASH_SIZE]> for Raw {
+    fn transform_key(key: &[u8; HASH_SIZE]) -> ProofPath {
+        ProofPath::new(key)
+    }
+}
+
+/// An error returned when a map proof is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapProofError {
+    /// Non-terminal node for a map consisting of a single node.
+    NonTerminalNode(ProofPath),
+
+    /// One path in the proof is a prefix of another path.
+    EmbeddedPaths {
+        /// Prefix key.
+        prefix: ProofPath,
+        /// Key containing the prefix.
+        path: ProofPath,
+    },
+
+    /// One path is mentioned several times in the proof.
+    DuplicatePath(ProofPath),
+
+    /// Entries in the proof are not ordered by increasing path.
+    InvalidOrdering(ProofPath, ProofPath),
+
+    /// An entry in a range proof lies outside of the proven range, or asserts absence
+    /// of a key.
+    InvalidRangeEntry(ProofPath),
+
+    /// A hashed subtree in a range proof may contain keys from the proven range.
+    IncompleteRange(ProofPath),
+}
+
+impl fmt::Display for MapProofError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            MapProofError::NonTerminalNode(_) => "non-terminal node as a single key in proof",
+            MapProofError::EmbeddedPaths { .. } => "embedded paths in proof",
+            MapProofError::DuplicatePath(_) => "duplicate path in proof",
+            MapProofError::InvalidOrdering(..) => "invalid path ordering",
+            MapProofError::InvalidRangeEntry(_) => "invalid entry in range proof",
+            MapProofError::IncompleteRange(_) => "range proof does not cover the entire range",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MapProofError {}
+
+// Used instead of `(ProofPath, Hash)` only for the purpose of clearer (de)serialization.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct MapProofEntry {
+    path: ProofPath,
+    hash: Hash,
+}
+
+// Used instead of `(K, Option<V>)` only for the purpose of clearer (de)serialization.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum OptionalEntry<K, V> {
+    Missing { missing: K },
+    KV { key: K, value: V },
+}
+
+impl<K, V> OptionalEntry<K, V> {
+    fn key(&self) -> &K {
+        match self {
+            OptionalEntry::Missing { missing } => missing,
+            OptionalEntry::KV { key, .. } => key,
+        }
+    }
+
+    fn as_missing(&self) -> Option<&K> {
+        match self {
+            OptionalEntry::Missing { missing } => Some(missing),
+            _ => None,
+        }
+    }
+
+    fn as_kv(&self) -> Option<(&K, &V)> {
+        match self {
+            OptionalEntry::KV { key, value } => Some((key, value)),
+            _ => None,
+        }
+    }
+
+    fn as_tuple(&self) -> (&K, Option<&V>) {
+        match self {
+            OptionalEntry::Missing { missing } => (missing, None),
+            OptionalEntry::KV { key, value } => (key, Some(value)),
+        }
+    }
+}
+
+/// View of a `ProofMapIndex`, i.e., a subset of its elements coupled with a *proof*,
+/// which jointly allow restoring the `object_hash()` of the index. Apart from the
+/// existing elements, `MapProof` can assert absence of certain keys from the underlying
+/// index.
+///
+/// The key mode (`Hashed` or `Raw`) must correspond to the kind of the proven index,
+/// that is, `ProofMapIndex` or `RawProofMapIndex` respectively.
+///
+/// # JSON serialization
+///
+/// `MapProof` is serialized to JSON as an object with 2 array fields:
+///
+/// - `proof` is an array of `{ path: ProofPath, hash: Hash }` objects.
+/// - `entries` is an array with 2 kinds of objects: `{ missing: K }` for keys missing from
+///   the underlying index, and `{ key: K, value: V }` for key-value pairs, existence of
+///   which is asserted by the proof.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MapProof<K, V, KeyMode = Hashed> {
+    entries: Vec<OptionalEntry<K, V>>,
+    proof: Vec<MapProofEntry>,
+    #[serde(skip)]
+    _key_mode: PhantomData<KeyMode>,
+}
+
+/// Version of `MapProof` obtained after verification.
+#[derive(Debug, Clone, Copy)]
+pub struct CheckedMapProof<'a, K, V> {
+    entries: &'a [OptionalEntry<K, V>],
+    hash: Hash,
+}
+
+/// Computes the hash of a branch node in a Merkle Patricia tree with the specified children.
+///
+/// The node is serialized as hashes of the left and right child followed by their paths
+/// in the compressed form; see `ProofPath::write_compressed()`.
+fn hash_branch(left_child: &MapProofEntry, right_child: &MapProofEntry) -> Hash {
+    let mut bytes = [0_u8; 2 * (HASH_SIZE + COMPRESSED_PATH_MAX_SIZE)];
+    bytes[..HASH_SIZE].copy_from_slice(left_child.hash.as_ref());
+    bytes[HASH_SIZE..2 * HASH_SIZE].copy_from_slice(right_child.hash.as_ref());
+    let mut pos = 2 * HASH_SIZE;
+    pos += left_child.path.write_compressed(&mut bytes[pos..]);
+    pos += right_child.path.write_compressed(&mut bytes[pos..]);
+    HashTag::hash_map_branch(&bytes[..pos])
+}
+
+/// Computes the root hash of the Merkle Patricia tree backing the specified entries
+/// in the map view.
+///
+/// The tree is not restored in full; instead, we add the paths to the tree in their
+/// lexicographic order and keep track of the rightmost nodes (the right contour) of the tree.
+/// Adding paths in the lexicographic order means that only the nodes in the right contour
+/// may be updated on each step.
+///
+/// `entries` are assumed to be sorted by the path in increasing order.
+fn collect(entries: &[MapProofEntry]) -> Result<Hash, MapProofError> {
+    fn common_prefix(x: &ProofPath, y: &ProofPath) -> ProofPath {
+        x.prefix(x.common_prefix_len(y))
+    }
+
+    /// Folds two last entries in a contour and replaces them with the folded entry.
+    ///
+    /// Returns an updated common prefix between two last entries in the contour.
+    fn fold(contour: &mut Vec<MapProofEntry>, last_prefix: ProofPath) -> Option<ProofPath> {
+        let last_entry = contour.pop().unwrap();
+        let penultimate_entry = contour.pop().unwrap();
+
+        contour.push(MapProofEntry {
+            path: last_prefix,
+            hash: hash_branch(&penultimate_entry, &last_entry),
+        });
+
+        if contour.len() > 1 {
+            let penultimate_entry = contour[contour.len() - 2];
+            Some(common_prefix(&penultimate_entry.path, &last_prefix))
+        } else {
+            None
+        }
+    }
+
+    match entries.len() {
+        0 => Ok(Hash::default()),
+
+        1 => {
+            if entries[0].path.is_leaf() {
+                Ok(HashTag::hash_single_entry_map(
+                    &entries[0].path,
+                    &entries[0].hash,
+                ))
+            } else {
+                Err(MapProofError::NonTerminalNode(entries[0].path))
+            }
+        }
+
+        _ => {
+            let (first_entry, second_entry) = (entries[0], entries[1]);
+            let mut contour = vec![first_entry, second_entry];
+            // invariant: equal to the common prefix of the 2 last nodes in the contour
+            let mut last_prefix = common_prefix(&first_entry.path, &second_entry.path);
+
+            for entry in entries.iter().skip(2) {
+                let new_prefix = common_prefix(&contour.last().unwrap().path, &entry.path);
+                let new_prefix_len = new_prefix.len();
+
+                while contour.len() > 1 && new_prefix_len < last_prefix.len() {
+                    if let Some(prefix) = fold(&mut contour, last_prefix) {
+                        last_prefix = prefix;
+                    }
+                }
+
+                contour.push(*entry);
+                last_prefix = new_prefix;
+            }
+
+            while contour.len() > 1 {
+                if let Some(prefix) = fold(&mut contour, last_prefix) {
+                    last_prefix = prefix;
+                }
+            }
+
+            Ok(contour[0].hash)
+        }
+    }
+}
+
+impl<K, V, KeyMode> MapProof<K, V, KeyMode> {
+    /// Provides access to the proof part of the view. Useful mainly for debug purposes.
+    pub fn proof_unchecked(&self) -> Vec<(ProofPath, Hash)> {
+        self.proof.iter().map(|e| (e.path, e.hash)).collect()
+    }
+
+    /// Retrieves references to keys that the proof shows as missing from the map.
+    /// This method does not perform any integrity checks of the proof.
+    pub fn missing_keys_unchecked(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().filter_map(OptionalEntry::as_missing)
+    }
+
+    /// Retrieves references to existing and non-existing entries in the proof.
+    ///
+    /// Existing entries have `Some` value, non-existing have `None`.
+    /// This method does not perform any integrity checks of the proof.
+    pub fn all_entries_unchecked(&self) -> impl Iterator<Item = (&K, Option<&V>)> {
+        self.entries.iter().map(OptionalEntry::as_tuple)
+    }
+}
+
+/// Checks that `proof` is ordered by increasing path, and that no path from `proof`
+/// is a prefix of another path from `proof` or `entry_paths`.
+fn precheck(
+    proof: &[(ProofPath, Hash)],
+    entry_paths: impl Iterator<Item = ProofPath>,
+) -> Result<(), MapProofError> {
+    use self::MapProofError::*;
+
+    // Check that entries in `proof` are in increasing order.
+    for w in proof.windows(2) {
+        let (prev_path, path) = (&w[0].0, &w[1].0);
+        match prev_path.partial_cmp(path) {
+            Some(Ordering::Less) => {
+                if path.starts_with(prev_path) {
+                    return Err(EmbeddedPaths {
+                        prefix: *prev_path,
+                        path: *path,
+                    });
+                }
+            }
+            Some(Ordering::Equal) => {
+                return Err(DuplicatePath(*path));
+            }
+            Some(Ordering::Greater) => {
+                return Err(InvalidOrdering(*prev_path, *path));
+            }
+            None => unreachable!("Incomparable keys in proof"),
+        }
+    }
+
+    // Check that no entry has a prefix among the paths in the proof entries.
+    // In order to do this, it suffices to locate the closest smaller path in the proof entries
+    // and check only it.
+    for path in entry_paths {
+        match proof.binary_search_by(|(proof_path, _)| {
+            proof_path
+                .partial_cmp(&path)
+                .expect("Incomparable paths in proof")
+        }) {
+            Ok(_) => {
+                return Err(DuplicatePath(path));
+            }
+
+            Err(index) if index > 0 => {
+                let prev_path = &proof[index - 1].0;
+                if path.starts_with(prev_path) {
+                    return Err(EmbeddedPaths {
+                        prefix: *prev_path,
+                        path,
+                    });
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Restores the root hash of the Merkle Patricia tree of a map from the hashes
+/// of subtrees (`proof`) and the proven entries.
+///
+/// `entries` contain paths of the proven keys together with the hashes of the values
+/// computed with `HashTag::hash_leaf()`, or `None` for keys missing from the map.
+/// This is the algorithm behind `MapProof::check()` exposed for proofs with other
+/// value encodings (e.g., in `exonum-merkledb`).
+pub fn restore_map_root(
+    proof: &[(ProofPath, Hash)],
+    entries: &[(ProofPath, Option<Hash>)],
+) -> Result<Hash, MapProofError> {
+    precheck(proof, entries.iter().map(|(path, _)| *path))?;
+
+    let mut nodes: Vec<_> = proof
+        .iter()
+        .map(|&(path, hash)| MapProofEntry { path, hash })
+        .collect();
+    nodes.extend(
+        entries
+            .iter()
+            .filter_map(|&(path, hash)| hash.map(|hash| MapProofEntry { path, hash })),
+    );
+
+    // Rust docs state that in the case `proof` and `entries` are sorted
+    // (which is the case for proofs created by `ProofMapIndex`),
+    // the sort is performed very quickly.
+    nodes.sort_unstable_by(|x, y| {
+        x.path.partial_cmp(&y.path).expect(
+            "Incorrectly formed paths supplied to MapProof; \
+             paths should have `start` field set to 0",
+        )
+    });
+
+    // This check is required as duplicate paths can be introduced by entries
+    // (further, it's generally possible that two different entry keys lead to the same
+    // `ProofPath`).
+    for window in nodes.windows(2) {
+        if window[0].path == window[1].path {
+            return Err(MapProofError::DuplicatePath(window[0].path));
+        }
+    }
+
+    collect(&nodes)
+}
+
+impl<K, V, KeyMode> MapProof<K, V, KeyMode>
+where
+    V: BinaryValue,
+    KeyMode: ToProofPath<K>,
+{
+    /// Checks this proof.
+    ///
+    /// ## Errors
+    ///
+    /// An error is returned if proof is malformed. The following checks are performed:
+    ///
+    /// - `proof` elements are ordered by increasing `path` field.
+    /// - No path in `proof` is a prefix of another path in `proof` or a path inferred from
+    ///   an entry.
+    /// - Paths in `proof` and ones computed from `entries` are all distinct.
+    pub fn check(&self) -> Result<CheckedMapProof<'_, K, V>, MapProofError> {
+        let proof = self.proof_unchecked();
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|e| {
+                let (key, value) = e.as_tuple();
+                let hash = value.map(|value| HashTag::hash_leaf(&value.to_bytes()));
+                (KeyMode::transform_key(key), hash)
+            })
+            .collect();
+
+        restore_map_root(&proof, &entries).map(|root_hash| CheckedMapProof {
+            entries: &self.entries,
+            hash: HashTag::hash_map_node(root_hash),
+        })
+    }
+
+    /// Checks this proof against a trusted map hash. Fails if the proof is malformed or the
+    /// hash does not match the one computed from the proof.
+    pub fn check_against_hash(
+        &self,
+        expected_map_hash: Hash,
+    ) -> Result<CheckedMapProof<'_, K, V>, ValidationError<MapProofError>> {
+        self.check()
+            .map_err(ValidationError::Malformed)
+            .and_then(|checked| {
+                if checked.index_hash() == expected_map_hash {
+                    Ok(checked)
+                } else {
+                    Err(ValidationError::UnmatchedRootHash)
+                }
+            })
+    }
+}
+
+impl<'a, K, V> CheckedMapProof<'a, K, V> {
+    /// Retrieves references to keys that the proof shows as missing from the map.
+    pub fn missing_keys(&self) -> impl Iterator<Item = &'a K> {
+        self.entries.iter().filter_map(OptionalEntry::as_missing)
+    }
+
+    /// Retrieves references to key-value pairs that the proof shows as present in the map.
+    pub fn entries(&self) -> impl Iterator<Item = (&'a K, &'a V)> {
+        self.entries.iter().filter_map(OptionalEntry::as_kv)
+    }
+
+    /// Retrieves references to existing and non-existing entries in the proof.
+    /// Existing entries have `Some` value, non-existing have `None`.
+    pub fn all_entries(&self) -> impl Iterator<Item = (&'a K, Option<&'a V>)> {
+        self.entries.iter().map(OptionalEntry::as_tuple)
+    }
+
+    /// Returns the `object_hash()` of the underlying `ProofMapIndex`.
+    pub fn index_hash(&self) -> Hash {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::ToOwned, string::String};
+
+    use super::*;
+    use crate::hash::hash;
+
+    #[test]
+    fn branch_hash() {
+        // Same as the branch node in `exonum-merkledb` tests (the left path there ends at bit 24).
+        let left = MapProofEntry {
+            path: ProofPath::new(&[253; HASH_SIZE]).prefix(24),
+            hash: hash(&[1, 2]),
+        };
+        let right = MapProofEntry {
+            path: ProofPath::new(&[244; HASH_SIZE]),
+            hash: hash(&[3, 4]),
+        };
+        assert_eq!(
+            hash_branch(&left, &right).to_hex(),
+            "2a179b2c4d93c88128a37ad9aae0e749bf9848f9c2a2463ebb6b53b31792a8bb"
+        );
+    }
+
+    #[test]
+    fn single_entry_proof() {
+        let proof: MapProof<String, u32> = MapProof {
+            entries: vec![OptionalEntry::KV {
+                key: "foo".to_owned(),
+                value: 1,
+            }],
+            proof: vec![],
+            _key_mode: PhantomData,
+        };
+        let checked_proof = proof.check().unwrap();
+        let path = Hashed::transform_key("foo");
+        assert_eq!(
+            checked_proof.index_hash(),
+            HashTag::hash_map_node(HashTag::hash_single_entry_map(
+                &path,
+                &HashTag::hash_leaf(&1_u32.to_bytes())
+            ))
+        );
+    }
+
+    #[test]
+    fn malformed_proofs() {
+        let path = ProofPath::new(&[1; HASH_SIZE]);
+        let hash = hash(&[1]);
+        let proof: MapProof<Hash, u32> = MapProof {
+            entries: vec![],
+            proof: vec![
+                MapProofEntry { path, hash },
+                MapProofEntry {
+                    path: path.prefix(10),
+                    hash,
+                },
+            ],
+            _key_mode: PhantomData,
+        };
+        assert_eq!(
+            proof.check().unwrap_err(),
+            MapProofError::InvalidOrdering(path, path.prefix(10))
+        );
+
+        let proof: MapProof<Hash, u32> = MapProof {
+            entries: vec![],
+            proof: vec![MapProofEntry {
+                path: path.prefix(10),
+                hash,
+            }],
+            _key_mode: PhantomData,
+        };
+        assert_eq!(
+            proof.check().unwrap_err(),
+            MapProofError::NonTerminalNode(path.prefix(10))
+        );
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal Protobuf encoding and decoding necessary to hash block headers and to read
+//! `Precommit` messages. Only the wire format features used by these messages are supported.
+
+use alloc::vec::Vec;
+
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LENGTH_DELIMITED: u8 = 2;
+const FIXED32: u8 = 5;
+
+/// Writer of Protobuf messages. As in the Protobuf implementation used by Exonum nodes,
+/// scalar fields with default values are omitted, while embedded messages are always written.
+#[derive(Debug, Default)]
+pub(crate) struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    fn write_tag(&mut self, field: u32, wire_type: u8) {
+        self.write_varint(u64::from((field << 3) | u32::from(wire_type)));
+    }
+
+    pub fn uint(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.write_tag(field, VARINT);
+            self.write_varint(value);
+        }
+    }
+
+    pub fn bytes(&mut self, field: u32, value: &[u8]) {
+        if !value.is_empty() {
+            self.message(field, value);
+        }
+    }
+
+    pub fn message(&mut self, field: u32, message: &[u8]) {
+        self.write_tag(field, LENGTH_DELIMITED);
+        self.write_varint(message.len() as u64);
+        self.buffer.extend_from_slice(message);
+    }
+}
+
+/// Value of a single field read from a Protobuf message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Value<'a> {
+    Uint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Reader of the fields of a Protobuf message.
+#[derive(Debug)]
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn read_varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0_u64;
+        for (i, &byte) in self.bytes.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7f) << (7 * i as u32);
+            if byte & 0x80 == 0 {
+                self.bytes = &self.bytes[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(DecodeError)
+    }
+
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError);
+        }
+        let (slice, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(slice)
+    }
+
+    /// Reads the next field from the message. Returns `Ok(None)` if the message has ended.
+    pub fn next_field(&mut self) -> Result<Option<(u32, Value<'a>)>, DecodeError> {
+        if self.bytes.is_empty() {
+            return Ok(None);
+        }
+        let tag = self.read_varint()?;
+        let field = (tag >> 3) as u32;
+        let value = match (tag & 7) as u8 {
+            VARINT => Value::Uint(self.read_varint()?),
+            LENGTH_DELIMITED => {
+                let len = self.read_varint()? as usize;
+                Value::Bytes(self.read_slice(len)?)
+            }
+            FIXED64 => {
+                self.read_slice(8)?;
+                Value::Fixed
+            }
+            FIXED32 => {
+                self.read_slice(4)?;
+                Value::Fixed
+            }
+            _ => return Err(DecodeError),
+        };
+        Ok(Some((field, value)))
+    }
+}
+
+/// Error decoding a Protobuf message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DecodeError;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writing_and_reading_fields() {
+        let mut writer = Writer::new();
+        writer.uint(1, 0);
+        writer.uint(2, 300);
+        writer.bytes(3, &[]);
+        writer.bytes(4, b"foo");
+        writer.message(5, &[]);
+        let bytes = writer.into_bytes();
+        assert_eq!(
+            bytes,
+            [0x10, 0xac, 0x02, 0x22, 3, b'f', b'o', b'o', 0x2a, 0]
+        );
+
+        let mut reader = Reader::new(&bytes);
+        assert_eq!(reader.next_field(), Ok(Some((2, Value::Uint(300)))));
+        assert_eq!(reader.next_field(), Ok(Some((4, Value::Bytes(b"foo")))));
+        assert_eq!(reader.next_field(), Ok(Some((5, Value::Bytes(&[])))));
+        assert_eq!(reader.next_field(), Ok(None));
+
+        let mut reader = Reader::new(&bytes[..5]);
+        reader.next_field().unwrap();
+        assert_eq!(reader.next_field(), Err(DecodeError));
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+
+use crate::hash::Hash;
+
+/// Binary representation of values in proofs.
+///
+/// This is the encoding half of `exonum_merkledb::BinaryValue`; values are hashed according
+/// to this representation when a proof is checked. Thus, an implementation for a type
+/// must produce exactly the same bytes as the implementation of `exonum_merkledb::BinaryValue`
+/// used by the node for the corresponding type. Implementations for standard types
+/// are provided by the crate.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_proofs::BinaryValue;
+///
+/// struct Wallet {
+///     balance: u64,
+///     name: String,
+/// }
+///
+/// impl BinaryValue for Wallet {
+///     fn to_bytes(&self) -> Vec<u8> {
+///         // Must match the encoding used by the service, e.g., Protobuf.
+///         let mut bytes = self.balance.to_bytes();
+///         bytes.extend_from_slice(self.name.as_bytes());
+///         bytes
+///     }
+/// }
+/// ```
+pub trait BinaryValue {
+    /// Serializes the value into a vector of bytes.
+    fn to_bytes(&self) -> Vec<u8>;
+}
+
+macro_rules! impl_binary_value_scalar {
+    ($( $type:ty ),*) => {
+        $(
+            impl BinaryValue for $type {
+                fn to_bytes(&self) -> Vec<u8> {
+                    self.to_le_bytes().to_vec()
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_value_scalar! { u8, u16, u32, u64, u128, i8, i16, i32, i64, i128 }
+
+/// No-op implementation.
+impl BinaryValue for () {
+    fn to_bytes(&self) -> Vec<u8> {
+        Vec::default()
+    }
+}
+
+impl BinaryValue for bool {
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+}
+
+impl BinaryValue for Vec<u8> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl BinaryValue for String {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_owned()
+    }
+}
+
+impl BinaryValue for Hash {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_ref().to_vec()
+    }
+}
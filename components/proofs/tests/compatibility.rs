@@ -0,0 +1,355 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests that proofs created by `exonum-merkledb` are verified by this crate
+//! with the same results.
+
+use chrono::Utc;
+use exonum::{
+    blockchain::{AdditionalHeaders, Block, ProposerId},
+    helpers::{Height, Round, ValidatorId},
+    messages::{Precommit, Verified},
+};
+use exonum_crypto::{gen_keypair, hash};
+use exonum_merkledb::{access::AccessExt, Database, ObjectHash, TemporaryDB};
+use serde::{de::DeserializeOwned, Serialize};
+
+use exonum_proofs::{
+    BlockProof, BlockProofError, Hash, HashTag, IndexProof, IndexProofError, ListProof, MapProof,
+    ObjectHash as _, PublicKey, Raw, SignedPrecommit, ValidationError,
+};
+
+fn convert<T: Serialize, U: DeserializeOwned>(value: &T) -> U {
+    let json = serde_json::to_value(value).unwrap();
+    serde_json::from_value(json).unwrap()
+}
+
+fn convert_hash(hash: exonum_crypto::Hash) -> Hash {
+    Hash::from_slice(hash.as_ref()).unwrap()
+}
+
+#[test]
+fn hashes_are_compatible() {
+    let data = [1_u8, 2, 3];
+    assert_eq!(exonum_proofs::hash(&data), convert_hash(hash(&data)));
+    assert_eq!(
+        HashTag::empty_list_hash(),
+        convert_hash(exonum_merkledb::HashTag::empty_list_hash())
+    );
+    assert_eq!(
+        HashTag::empty_map_hash(),
+        convert_hash(exonum_merkledb::HashTag::empty_map_hash())
+    );
+
+    let hash = hash(&data);
+    assert_eq!(convert::<_, Hash>(&hash), convert_hash(hash));
+    assert_eq!(hash.to_hex(), convert_hash(hash).to_hex());
+}
+
+#[test]
+fn list_proofs() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut list = fork.get_proof_list("list");
+
+    let empty_proof: ListProof<u64> = convert(&list.get_range_proof(..));
+    let checked_proof = empty_proof.check().unwrap();
+    assert_eq!(checked_proof.index_hash(), HashTag::empty_list_hash());
+
+    list.extend(0_u64..37);
+    let list_hash = convert_hash(list.object_hash());
+
+    for i in 0..40 {
+        let proof: ListProof<u64> = convert(&list.get_proof(i));
+        let checked_proof = proof.check_against_hash(list_hash).unwrap();
+        if i < 37 {
+            assert_eq!(*checked_proof.entries(), [(i, i)]);
+        } else {
+            assert!(checked_proof.entries().is_empty());
+        }
+    }
+
+    for (start, end) in vec![(0, 37), (3, 10), (16, 32), (30, 45)] {
+        let proof: ListProof<u64> = convert(&list.get_range_proof(start..end));
+        let checked_proof = proof.check_against_hash(list_hash).unwrap();
+        assert!(checked_proof.indexes().eq(start..end.min(37)));
+    }
+
+    let proof: ListProof<u64> = convert(&list.get_multiproof(vec![1, 5, 17, 36]));
+    let checked_proof = proof.check_against_hash(list_hash).unwrap();
+    assert!(checked_proof.indexes().eq(vec![1, 5, 17, 36]));
+
+    let other_hash = convert_hash(hash(&[1]));
+    assert_eq!(
+        proof.check_against_hash(other_hash).unwrap_err(),
+        ValidationError::UnmatchedRootHash
+    );
+}
+
+#[test]
+fn map_proofs() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut map = fork.get_proof_map("map");
+
+    let empty_proof: MapProof<String, u64> = convert(&map.get_proof("foo".to_owned()));
+    let checked_proof = empty_proof.check().unwrap();
+    assert_eq!(checked_proof.index_hash(), HashTag::empty_map_hash());
+    assert!(checked_proof.missing_keys().eq(vec!["foo"]));
+
+    map.put(&"foo".to_owned(), 1_u64);
+    let proof: MapProof<String, u64> = convert(&map.get_proof("foo".to_owned()));
+    let checked_proof = proof
+        .check_against_hash(convert_hash(map.object_hash()))
+        .unwrap();
+    assert_eq!(checked_proof.entries().count(), 1);
+
+    for i in 0..100_u64 {
+        map.put(&i.to_string(), i);
+    }
+    let map_hash = convert_hash(map.object_hash());
+
+    for key in &["0", "42", "99", "100", "foo", "bar"] {
+        let proof: MapProof<String, u64> = convert(&map.get_proof((*key).to_owned()));
+        let checked_proof = proof.check_against_hash(map_hash).unwrap();
+        assert_eq!(
+            checked_proof.all_entries().count(),
+            1,
+            "Invalid proof for key {}",
+            key
+        );
+    }
+
+    let keys: Vec<_> = (0..200).step_by(7).map(|i: u64| i.to_string()).collect();
+    let proof: MapProof<String, u64> = convert(&map.get_multiproof(keys.clone()));
+    let checked_proof = proof.check_against_hash(map_hash).unwrap();
+    assert_eq!(checked_proof.all_entries().count(), keys.len());
+    assert_eq!(
+        checked_proof.entries().count(),
+        keys.iter()
+            .filter(|key| key.parse::<u64>().unwrap() < 100)
+            .count()
+    );
+}
+
+#[test]
+fn raw_map_proofs() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut map = fork.get_raw_proof_map("map");
+    for i in 0_u8..50 {
+        map.put(&hash(&[i]), u32::from(i));
+    }
+    let map_hash = convert_hash(map.object_hash());
+
+    let keys: Vec<_> = (0_u8..60).step_by(3).map(|i| hash(&[i])).collect();
+    let proof: MapProof<Hash, u32, Raw> = convert(&map.get_multiproof(keys.clone()));
+    let checked_proof = proof.check_against_hash(map_hash).unwrap();
+    assert_eq!(checked_proof.entries().count(), 17);
+    assert_eq!(checked_proof.missing_keys().count(), 3);
+}
+
+fn create_block_proof(
+    keys: &[(exonum_crypto::PublicKey, exonum_crypto::SecretKey)],
+    state_hash: exonum_crypto::Hash,
+) -> exonum::blockchain::BlockProof {
+    let mut block = Block {
+        height: Height(5),
+        tx_count: 1,
+        prev_hash: hash(&[1]),
+        tx_hash: hash(&[2]),
+        state_hash,
+        error_hash: exonum_merkledb::HashTag::empty_map_hash(),
+        additional_headers: AdditionalHeaders::new(),
+    };
+    block.add_header::<ProposerId>(ValidatorId(1).into());
+    let block_hash = block.object_hash();
+
+    let precommits = keys
+        .iter()
+        .enumerate()
+        .map(|(i, (public_key, secret_key))| {
+            let precommit = Precommit::new(
+                ValidatorId(i as u16),
+                Height(5),
+                Round(2),
+                hash(b"propose"),
+                block_hash,
+                Utc::now(),
+            );
+            Verified::from_value(precommit, *public_key, secret_key)
+        })
+        .collect();
+    exonum::blockchain::BlockProof { block, precommits }
+}
+
+fn validator_keys(keys: &[(exonum_crypto::PublicKey, exonum_crypto::SecretKey)]) -> Vec<PublicKey> {
+    keys.iter()
+        .map(|(public_key, _)| PublicKey::from_slice(public_key.as_ref()).unwrap())
+        .collect()
+}
+
+#[test]
+fn block_proofs() {
+    let keys: Vec<_> = (0..4).map(|_| gen_keypair()).collect();
+    let validator_keys = validator_keys(&keys);
+    let block_proof = create_block_proof(&keys, hash(&[3]));
+    let proof: BlockProof = convert(&block_proof);
+    assert_eq!(
+        proof.block.object_hash(),
+        convert_hash(block_proof.block.object_hash())
+    );
+    proof.verify(&validator_keys).unwrap();
+
+    let (author, precommit) = proof.precommits[1].verify().unwrap();
+    assert_eq!(author, validator_keys[1]);
+    assert_eq!(precommit.validator, 1);
+    assert_eq!(precommit.height, 5);
+    assert_eq!(precommit.round, 2);
+    assert_eq!(precommit.block_hash, proof.block.object_hash());
+
+    // 3 precommits are a Byzantine majority for 4 validators, but 2 are not.
+    let mut partial_proof = proof.clone();
+    partial_proof.precommits.truncate(3);
+    partial_proof.verify(&validator_keys).unwrap();
+    partial_proof.precommits.truncate(2);
+    assert_eq!(
+        partial_proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::NotEnoughPrecommits
+    );
+
+    let mut duplicate_proof = proof.clone();
+    duplicate_proof.precommits[3] = duplicate_proof.precommits[0].clone();
+    assert_eq!(
+        duplicate_proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::DuplicateValidator
+    );
+
+    let mut other_keys = validator_keys.clone();
+    other_keys.swap(0, 1);
+    assert_eq!(
+        proof.verify(&other_keys).unwrap_err(),
+        BlockProofError::UnknownValidator
+    );
+
+    let mut tampered_proof = proof.clone();
+    tampered_proof.block.tx_count = 2;
+    assert_eq!(
+        tampered_proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::UnrelatedPrecommit
+    );
+
+    let mut tampered_proof = proof.clone();
+    let mut precommit_bytes = tampered_proof.precommits[0].as_bytes().to_vec();
+    // The last byte of the message belongs to the signature.
+    *precommit_bytes.last_mut().unwrap() ^= 1;
+    tampered_proof.precommits[0] = SignedPrecommit::from_bytes(precommit_bytes);
+    assert_eq!(
+        tampered_proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::InvalidSignature
+    );
+}
+
+#[test]
+fn index_proofs() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut state = fork.get_proof_map::<_, String, exonum_crypto::Hash>("state");
+    let wallets_hash = hash(b"wallets");
+    state.put(&"token.wallets".to_owned(), wallets_hash);
+    state.put(&"token.history".to_owned(), hash(b"history"));
+    let state_hash = state.object_hash();
+
+    let keys: Vec<_> = (0..4).map(|_| gen_keypair()).collect();
+    let validator_keys = validator_keys(&keys);
+    let index_proof = exonum::blockchain::IndexProof {
+        block_proof: create_block_proof(&keys, state_hash),
+        index_proof: state.get_proof("token.wallets".to_owned()),
+        prefix_proof: None,
+    };
+
+    let proof: IndexProof = convert(&index_proof);
+    assert_eq!(proof.block_proof.block.height, 5);
+    assert_eq!(
+        proof.index_hash("token.wallets", &validator_keys).unwrap(),
+        Some(convert_hash(wallets_hash))
+    );
+    assert_eq!(
+        proof.index_hash("token.history", &validator_keys).unwrap(),
+        None
+    );
+
+    let mut tampered_proof = proof.clone();
+    tampered_proof.block_proof.block.state_hash = convert_hash(hash(&[2]));
+    assert_eq!(
+        tampered_proof.verify(&validator_keys).unwrap_err(),
+        IndexProofError::Block(BlockProofError::UnrelatedPrecommit)
+    );
+
+    let mut tampered_proof = proof.clone();
+    tampered_proof.index_proof = convert(&state.get_proof("token.history".to_owned()));
+    tampered_proof.block_proof = convert(&create_block_proof(&keys, hash(&[2])));
+    assert_eq!(
+        tampered_proof.verify(&validator_keys).unwrap_err(),
+        IndexProofError::Index(ValidationError::UnmatchedRootHash)
+    );
+}
+
+#[test]
+fn index_proofs_with_prefix_aggregation() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut prefix_state = fork.get_proof_map::<_, String, exonum_crypto::Hash>("prefix_state");
+    let wallets_hash = hash(b"wallets");
+    prefix_state.put(&"token.wallets".to_owned(), wallets_hash);
+    prefix_state.put(&"token.history".to_owned(), hash(b"history"));
+    let mut state = fork.get_proof_map::<_, String, exonum_crypto::Hash>("state");
+    state.put(&"token".to_owned(), prefix_state.object_hash());
+    state.put(&"other".to_owned(), prefix_state.object_hash());
+    let state_hash = state.object_hash();
+
+    let keys: Vec<_> = (0..4).map(|_| gen_keypair()).collect();
+    let validator_keys = validator_keys(&keys);
+    let index_proof = exonum::blockchain::IndexProof {
+        block_proof: create_block_proof(&keys, state_hash),
+        index_proof: state.get_proof("token".to_owned()),
+        prefix_proof: Some(prefix_state.get_proof("token.wallets".to_owned())),
+    };
+    assert!(index_proof.check_against_block().is_ok());
+
+    let proof: IndexProof = convert(&index_proof);
+    assert_eq!(
+        proof.index_hash("token.wallets", &validator_keys).unwrap(),
+        Some(convert_hash(wallets_hash))
+    );
+    assert_eq!(
+        proof.index_hash("token.history", &validator_keys).unwrap(),
+        None
+    );
+
+    let mut tampered_proof = proof.clone();
+    tampered_proof.prefix_proof = Some(convert(&state.get_proof("token".to_owned())));
+    assert_eq!(
+        tampered_proof.verify(&validator_keys).unwrap_err(),
+        IndexProofError::Prefix(ValidationError::UnmatchedRootHash)
+    );
+
+    // The prefix proof must only contain indexes with the proven prefix.
+    let mut tampered_proof = proof.clone();
+    tampered_proof.index_proof = convert(&state.get_proof("other".to_owned()));
+    assert_eq!(
+        tampered_proof.verify(&validator_keys).unwrap_err(),
+        IndexProofError::Prefix(ValidationError::UnmatchedRootHash)
+    );
+}
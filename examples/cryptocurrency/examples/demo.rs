@@ -51,7 +51,9 @@ fn node_config() -> NodeConfig {
         mempool: Default::default(),
         services_configs: Default::default(),
         database: Default::default(),
+        history: None,
         thread_pool_size: Default::default(),
+        tx_fork_size_limit: None,
         master_key_path: Default::default(),
         keys: Keys::from_keys(
             consensus_public_key,
@@ -13,7 +13,10 @@
 // limitations under the License.
 
 use exonum_derive::{BinaryValue, ObjectHash};
-use exonum_merkledb::{BinaryValue, MapProof};
+use exonum_merkledb::{
+    proof_map::{CheckedMapProof, MapProofError},
+    BinaryValue, MapProof, ValidationError,
+};
 use exonum_proto::ProtobufConvert;
 use failure::Error;
 
@@ -211,8 +214,50 @@ pub struct IndexProof {
 
     /// Proof of authenticity for the index. Must contain a single key - a full index name
     /// in the form `$service_name.$name_within_service`, e.g., `cryptocurrency.wallets`.
+    /// If `prefix_proof` is present, the key is the prefix of the index name instead,
+    /// e.g., `cryptocurrency`.
     /// The root hash of the proof must be equal to the `state_hash` mentioned in `block_proof`.
     pub index_proof: MapProof<String, Hash>,
+
+    /// Proof of authenticity for the index within the state aggregator of its prefix.
+    /// Present only if hierarchical state aggregation is enabled for the prefix
+    /// of the index name. Must contain a single key - a full index name. The root hash
+    /// of the proof must be equal to the prefix hash proven by `index_proof`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix_proof: Option<MapProof<String, Hash>>,
+}
+
+impl IndexProof {
+    /// Checks that `index_proof` (and `prefix_proof`, if present) is valid and matches
+    /// the `state_hash` of the block header. The block header itself is not checked.
+    ///
+    /// If the check succeeds, the returned proof contains the proven index name and hash.
+    pub fn check_against_block(
+        &self,
+    ) -> Result<CheckedMapProof<'_, String, Hash>, ValidationError<MapProofError>> {
+        let checked_proof = self
+            .index_proof
+            .check_against_hash(self.block_proof.block.state_hash)?;
+        let prefix_proof = match self.prefix_proof {
+            Some(ref proof) => proof,
+            None => return Ok(checked_proof),
+        };
+
+        let (prefix, &prefix_hash) = checked_proof
+            .entries()
+            .next()
+            .ok_or(ValidationError::UnmatchedRootHash)?;
+        let checked_proof = prefix_proof.check_against_hash(prefix_hash)?;
+        // Each index in the prefix aggregator must have the proven prefix.
+        let has_prefix = checked_proof
+            .all_entries()
+            .all(|(name, _)| name.split('.').next() == Some(prefix.as_str()));
+        if has_prefix {
+            Ok(checked_proof)
+        } else {
+            Err(ValidationError::UnmatchedRootHash)
+        }
+    }
 }
 
 #[cfg(test)]
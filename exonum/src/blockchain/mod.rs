@@ -27,8 +27,8 @@ pub(crate) use crate::runtime::{ExecutionError, ExecutionStatus};
 
 use exonum_crypto::gen_keypair;
 use exonum_merkledb::{
-    access::RawAccess, Database, Fork, MapIndex, ObjectHash, Patch, Result as StorageResult,
-    Snapshot, SystemSchema, TemporaryDB,
+    access::RawAccess, Database, DatabaseExt, Fork, HistoryOptions, MapIndex, ObjectHash, Patch,
+    Result as StorageResult, Snapshot, SystemSchema, TemporaryDB,
 };
 use failure::{ensure, Error};
 use futures::Future;
@@ -66,6 +66,8 @@ pub struct Blockchain {
     pub(crate) api_sender: ApiSender,
     db: Arc<dyn Database>,
     service_keypair: (PublicKey, SecretKey),
+    history: Option<HistoryOptions>,
+    tx_fork_size_limit: Option<usize>,
 }
 
 impl Blockchain {
@@ -79,9 +81,34 @@ impl Blockchain {
             db: database.into(),
             service_keypair,
             api_sender,
+            history: None,
+            tx_fork_size_limit: None,
         }
     }
 
+    /// Enables recording of the historical blockchain states, which can be later accessed
+    /// via [`snapshot_at`]. The states are recorded for each committed block, starting from
+    /// the first block committed after this call.
+    ///
+    /// [`snapshot_at`]: #method.snapshot_at
+    pub fn with_history(mut self, options: HistoryOptions) -> Self {
+        self.history = Some(options);
+        self
+    }
+
+    /// Limits the size of changes made by a single transaction to `limit` bytes
+    /// (as per [`Fork::size`]). A transaction exceeding the limit fails with an unexpected
+    /// execution error; its changes are discarded, and the block execution continues.
+    ///
+    /// Since the limit influences the outcome of transactions, it must be equal
+    /// for all validators in the network.
+    ///
+    /// [`Fork::size`]: ../../exonum_merkledb/struct.Fork.html#method.size
+    pub fn with_tx_fork_size_limit(mut self, limit: usize) -> Self {
+        self.tx_fork_size_limit = Some(limit);
+        self
+    }
+
     /// Creates a non-persisting blockchain, all data in which is irrevocably lost on drop.
     ///
     /// The created blockchain cannot send transactions; an attempt to do so will result
@@ -95,6 +122,29 @@ impl Blockchain {
         self.db.snapshot()
     }
 
+    /// Creates a read-only snapshot of the storage state immediately after the block
+    /// at the specified `height` has been committed.
+    ///
+    /// Returns `None` if the block at `height` is not committed yet, or if the historical
+    /// state is not available (e.g., history recording is not enabled via [`with_history`],
+    /// or the state was pruned according to the history options).
+    ///
+    /// Changes to the transaction pool made after the block commit are recorded together
+    /// with the next block, so the pool contents in the returned snapshot correspond
+    /// to the time of the block commit.
+    ///
+    /// [`with_history`]: #method.with_history
+    pub fn snapshot_at(&self, height: Height) -> Option<Box<dyn Snapshot>> {
+        if self.history.is_none() {
+            return None;
+        }
+        let snapshot = self.snapshot();
+        if height.0 >= Schema::new(&snapshot).block_hashes_by_height().len() {
+            return None;
+        }
+        self.db.snapshot_at(height.0)
+    }
+
     /// Returns the hash of the latest committed block.
     /// If genesis block was not committed returns `Hash::zero()`.
     pub fn last_hash(&self) -> Hash {
@@ -114,6 +164,18 @@ impl Blockchain {
         Schema::new(&self.snapshot()).transactions_pool_len()
     }
 
+    /// Commits changes from the patch to the storage. If history recording is enabled,
+    /// the changes are labeled with the height of the next block to be committed.
+    fn merge(&self, patch: Patch) -> StorageResult<()> {
+        if let Some(ref options) = self.history {
+            let snapshot = self.db.snapshot();
+            let version = Schema::new(&snapshot).block_hashes_by_height().len();
+            self.db.merge_with_history(patch, version, options)
+        } else {
+            self.db.merge(patch)
+        }
+    }
+
     /// Returns `Connect` messages from peers saved in the cache, if any.
     pub(crate) fn get_saved_peers(&self) -> HashMap<PublicKey, Verified<Connect>> {
         let snapshot = self.snapshot();
@@ -208,8 +270,11 @@ impl BlockchainMut {
 
     /// Commits changes from the patch to the blockchain storage.
     /// See [`Fork`](../../exonum_merkledb/struct.Fork.html) for details.
+    ///
+    /// If history recording is enabled, the changes are labeled with the height
+    /// of the next block to be committed.
     pub fn merge(&mut self, patch: Patch) -> StorageResult<()> {
-        self.inner.db.merge(patch)
+        self.inner.merge(patch)
     }
 
     /// Creates and commits the genesis block with the given genesis configuration.
@@ -367,9 +432,15 @@ impl BlockchainMut {
             .unwrap_or_else(|| panic!("BUG: Cannot find transaction {:?} in database", tx_hash));
         fork.flush();
 
+        let size_limit = self
+            .inner
+            .tx_fork_size_limit
+            .map(|limit| fork.size() + limit);
+        fork.set_size_limit(size_limit);
         let tx_result = self
             .dispatcher
             .execute(fork, tx_hash, index as u64, &transaction);
+        fork.set_size_limit(None);
         let mut schema = Schema::new(&*fork);
 
         if let Err(e) = tx_result {
@@ -432,25 +503,26 @@ impl BlockchainMut {
         // ^-- mutable reference taken for future compatibility.
         transactions: impl IntoIterator<Item = Verified<AnyTx>>,
     ) {
-        Self::add_transactions_into_db_pool(self.inner.db.as_ref(), transactions);
+        Self::add_transactions_into_db_pool(&self.inner, transactions);
     }
 
-    /// Same as `add_transactions_into_pool()`, but accepting a database handle instead
-    /// of the `BlockchainMut` instance. Beware that accesses to database need to be synchronized
-    /// across threads.
+    /// Same as `add_transactions_into_pool()`, but accepting an immutable blockchain handle
+    /// instead of the `BlockchainMut` instance. Beware that accesses to database need
+    /// to be synchronized across threads.
     #[doc(hidden)] // used by testkit, should not be used anywhere else
-    pub fn add_transactions_into_db_pool<Db: Database + ?Sized>(
-        db: &Db,
+    pub fn add_transactions_into_db_pool(
+        blockchain: &Blockchain,
         transactions: impl IntoIterator<Item = Verified<AnyTx>>,
     ) {
-        let fork = db.fork();
+        let fork = blockchain.db.fork();
         let mut schema = Schema::new(&fork);
         for transaction in transactions {
             if !schema.transactions().contains(&transaction.object_hash()) {
                 schema.add_transaction_into_pool(transaction);
             }
         }
-        db.merge(fork.into_patch())
+        blockchain
+            .merge(fork.into_patch())
             .expect("Cannot update transaction pool");
     }
 
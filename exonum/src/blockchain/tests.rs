@@ -15,7 +15,8 @@
 use exonum_crypto::{PublicKey, SecretKey};
 use exonum_derive::FromAccess;
 use exonum_merkledb::{
-    access::Access, BinaryValue, Error as MerkledbError, ObjectHash, ProofListIndex, Snapshot,
+    access::{Access, AccessExt, Prefixed},
+    BinaryValue, Error as MerkledbError, HistoryOptions, ObjectHash, ProofListIndex, Snapshot,
     SystemSchema,
 };
 use futures::{Future, IntoFuture};
@@ -35,9 +36,10 @@ use crate::{
     helpers::{generate_testnet_config, Height, ValidatorId},
     messages::Verified,
     runtime::{
-        catch_panic, AnyTx, ArtifactId, CallInfo, Dispatcher, DispatcherError, DispatcherSchema,
-        ErrorMatch, ExecutionContext, ExecutionError, ExecutionFail, InstanceId, InstanceSpec,
-        InstanceStatus, Mailbox, Runtime, SnapshotExt, WellKnownRuntime, SUPERVISOR_INSTANCE_ID,
+        catch_panic, AnyTx, ArtifactId, BlockchainData, CallInfo, Dispatcher, DispatcherError,
+        DispatcherSchema, ErrorKind, ErrorMatch, ExecutionContext, ExecutionError, ExecutionFail,
+        InstanceId, InstanceSpec, InstanceStatus, Mailbox, Runtime, SnapshotExt, WellKnownRuntime,
+        SUPERVISOR_INSTANCE_ID,
     },
 };
 
@@ -148,6 +150,10 @@ impl Execute for AfterTransactionsAction {
 enum Transaction {
     /// Add some value to the inspector schema index.
     AddValue(u64),
+    /// Add some value to the inspector schema index within the service prefix.
+    AddServiceValue(u64),
+    /// Add several values to the inspector schema index.
+    AddValues(Vec<u64>),
     /// Emit panic.
     Panic,
     /// Emit MerkleDb error.
@@ -186,6 +192,19 @@ impl Execute for Transaction {
                 Ok(())
             }
 
+            Transaction::AddServiceValue(value) => {
+                let access = Prefixed::new(TEST_SERVICE_NAME, &*context.fork);
+                let mut schema = InspectorSchema::new(access);
+                schema.values.push(value);
+                Ok(())
+            }
+
+            Transaction::AddValues(values) => {
+                let mut schema = InspectorSchema::new(&*context.fork);
+                schema.values.extend(values);
+                Ok(())
+            }
+
             Transaction::Panic => {
                 let mut schema = InspectorSchema::new(&*context.fork);
                 schema.values.push(42);
@@ -353,12 +372,22 @@ fn execute_transaction(
     blockchain: &mut BlockchainMut,
     tx: Verified<AnyTx>,
 ) -> Result<(), ExecutionError> {
-    let tx_hash = tx.object_hash();
+    execute_block(blockchain, vec![tx]).pop().unwrap()
+}
+
+/// Executes transactions in a single block and returns their results.
+fn execute_block(
+    blockchain: &mut BlockchainMut,
+    txs: Vec<Verified<AnyTx>>,
+) -> Vec<Result<(), ExecutionError>> {
+    let tx_hashes: Vec<_> = txs.iter().map(ObjectHash::object_hash).collect();
     blockchain
         .merge({
             let fork = blockchain.fork();
             let mut schema = Schema::new(&fork);
-            schema.add_transaction_into_pool(tx);
+            for tx in txs {
+                schema.add_transaction_into_pool(tx);
+            }
             fork.into_patch()
         })
         .unwrap();
@@ -371,7 +400,7 @@ fn execute_transaction(
     let (block_hash, patch) = blockchain.create_patch(
         ValidatorId::zero().into(),
         height,
-        &[tx_hash],
+        &tx_hashes,
         &mut BTreeMap::new(),
     );
 
@@ -380,8 +409,13 @@ fn execute_transaction(
         .unwrap();
     let snapshot = blockchain.snapshot();
     let schema = Schema::new(&snapshot);
-    let location = schema.transactions_locations().get(&tx_hash).unwrap();
-    schema.transaction_result(location).unwrap()
+    tx_hashes
+        .iter()
+        .map(|tx_hash| {
+            let location = schema.transactions_locations().get(tx_hash).unwrap();
+            schema.transaction_result(location).unwrap()
+        })
+        .collect()
 }
 
 /// Attempts to create a blockchain, returning an error if the genesis block
@@ -504,6 +538,53 @@ fn handling_tx_merkledb_error() {
     .unwrap();
 }
 
+#[test]
+fn handling_oversized_transactions() {
+    let (pk, sk) = exonum_crypto::gen_keypair();
+
+    let instance = InitAction::Noop.into_default_instance();
+    let genesis_config = GenesisConfigBuilder::with_consensus_config(create_consensus_config())
+        .with_artifact(instance.instance_spec.artifact.clone())
+        .with_instance(instance)
+        .build();
+    let blockchain = Blockchain::build_for_tests().with_tx_fork_size_limit(1_024);
+    let mut blockchain = BlockchainBuilder::new(blockchain, genesis_config)
+        .with_runtime(RuntimeInspector::default())
+        .build()
+        .unwrap();
+
+    let txs = vec![
+        Transaction::AddValue(10),
+        Transaction::AddValues(vec![0; 1_000]),
+        Transaction::AddValue(20),
+    ];
+    let txs = txs
+        .into_iter()
+        .map(|tx| tx.sign(TEST_SERVICE_ID, pk, &sk))
+        .collect();
+    let mut results = execute_block(&mut blockchain, txs);
+
+    assert!(results[0].is_ok());
+    let err = results
+        .remove(1)
+        .expect_err("Oversized transaction must fail");
+    assert_eq!(err.kind(), ErrorKind::Unexpected);
+    assert!(err.description().contains("exceeds the limit"));
+    assert!(results[1].is_ok());
+
+    // Changes of the oversized transaction are discarded; the block continues executing.
+    let snapshot = blockchain.snapshot();
+    let values: Vec<_> = InspectorSchema::new(&snapshot).values.iter().collect();
+    assert_eq!(values, vec![10, 20]);
+
+    // The limit applies to each transaction rather than to the entire block.
+    let txs = (0..100)
+        .map(|i| Transaction::AddValue(i).sign(TEST_SERVICE_ID, pk, &sk))
+        .collect();
+    let results = execute_block(&mut blockchain, txs);
+    assert!(results.iter().all(Result::is_ok));
+}
+
 #[test]
 fn initialize_service_ok() {
     create_blockchain(
@@ -821,3 +902,125 @@ fn state_aggregation() {
         .collect();
     assert_eq!(actual_indexes, expected_indexes);
 }
+
+#[test]
+fn proofs_for_prefix_aggregated_indexes() {
+    let (pk, sk) = exonum_crypto::gen_keypair();
+
+    let mut blockchain = create_blockchain(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    )
+    .unwrap();
+    let mut fork = blockchain.fork();
+    fork.enable_prefix_aggregation(TEST_SERVICE_NAME);
+    blockchain.merge(fork.into_patch()).unwrap();
+
+    for &value in &[10, 20] {
+        execute_transaction(
+            &mut blockchain,
+            Transaction::AddServiceValue(value).sign(TEST_SERVICE_ID, pk, &sk),
+        )
+        .expect("Transaction must success");
+    }
+
+    let snapshot = blockchain.snapshot();
+    let data = BlockchainData::new(
+        snapshot.as_ref(),
+        (TEST_SERVICE_ID, TEST_SERVICE_NAME).into(),
+    );
+    let proof = data.proof_for_service_index("values").unwrap();
+    assert!(proof.prefix_proof.is_some());
+    let checked_proof = proof.check_against_block().unwrap();
+    let values_hash = Prefixed::new(TEST_SERVICE_NAME, snapshot.as_ref())
+        .get_proof_list::<_, u64>("values")
+        .object_hash();
+    let entries: Vec<_> = checked_proof.entries().collect();
+    assert_eq!(
+        entries,
+        vec![(&"test_service.values".to_owned(), &values_hash)]
+    );
+
+    // The default state aggregator contains the prefix rather than the index.
+    let system_schema = SystemSchema::new(&snapshot);
+    let aggregator = system_schema.state_aggregator();
+    assert!(aggregator.get("test_service.values").is_none());
+    assert_eq!(
+        aggregator.get(TEST_SERVICE_NAME),
+        Some(system_schema.prefix_state_hash(TEST_SERVICE_NAME))
+    );
+}
+
+#[test]
+fn historical_snapshots() {
+    let (pk, sk) = exonum_crypto::gen_keypair();
+
+    let instance = InitAction::Noop.into_default_instance();
+    let genesis_config = GenesisConfigBuilder::with_consensus_config(create_consensus_config())
+        .with_artifact(instance.instance_spec.artifact.clone())
+        .with_instance(instance)
+        .build();
+    let blockchain = Blockchain::build_for_tests().with_history(HistoryOptions::default());
+    let mut blockchain = BlockchainBuilder::new(blockchain, genesis_config)
+        .with_runtime(RuntimeInspector::default())
+        .build()
+        .unwrap();
+
+    for &value in &[10, 20] {
+        execute_transaction(
+            &mut blockchain,
+            Transaction::AddValue(value).sign(TEST_SERVICE_ID, pk, &sk),
+        )
+        .expect("Transaction must success");
+    }
+
+    let blockchain = blockchain.as_ref();
+    let expected_values = vec![vec![], vec![10], vec![10, 20]];
+    for (height, expected_values) in expected_values.into_iter().enumerate() {
+        let snapshot = blockchain.snapshot_at(Height(height as u64)).unwrap();
+        assert_eq!(Schema::new(&snapshot).height(), Height(height as u64));
+        let values: Vec<_> = InspectorSchema::new(&snapshot).values.iter().collect();
+        assert_eq!(values, expected_values);
+    }
+    assert!(blockchain.snapshot_at(Height(3)).is_none());
+}
+
+#[test]
+fn historical_snapshots_with_pool_transactions() {
+    let (pk, sk) = exonum_crypto::gen_keypair();
+
+    let instance = InitAction::Noop.into_default_instance();
+    let genesis_config = GenesisConfigBuilder::with_consensus_config(create_consensus_config())
+        .with_artifact(instance.instance_spec.artifact.clone())
+        .with_instance(instance)
+        .build();
+    let blockchain = Blockchain::build_for_tests().with_history(HistoryOptions::default());
+    let mut blockchain = BlockchainBuilder::new(blockchain, genesis_config)
+        .with_runtime(RuntimeInspector::default())
+        .build()
+        .unwrap();
+
+    let tx = Transaction::AddValue(10).sign(TEST_SERVICE_ID, pk, &sk);
+    let tx_hash = tx.object_hash();
+    blockchain.add_transactions_into_pool(vec![tx]);
+    let (block_hash, patch) = blockchain.create_patch(
+        ValidatorId::zero().into(),
+        Height(1),
+        &[tx_hash],
+        &mut BTreeMap::new(),
+    );
+    blockchain
+        .commit(patch, block_hash, vec![], &mut BTreeMap::new())
+        .unwrap();
+
+    let blockchain = blockchain.as_ref();
+    let snapshot = blockchain.snapshot_at(Height(0)).unwrap();
+    let schema = Schema::new(&snapshot);
+    assert!(!schema.transactions().contains(&tx_hash));
+    assert_eq!(schema.transactions_pool_len(), 0);
+
+    let snapshot = blockchain.snapshot_at(Height(1)).unwrap();
+    let schema = Schema::new(&snapshot);
+    assert!(schema.transactions().contains(&tx_hash));
+    assert!(schema.transactions_locations().contains(&tx_hash));
+}
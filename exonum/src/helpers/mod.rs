@@ -81,7 +81,9 @@ pub fn generate_testnet_config(count: u16, start_port: u16) -> Vec<crate::node::
             mempool: Default::default(),
             services_configs: Default::default(),
             database: Default::default(),
+            history: None,
             thread_pool_size: Default::default(),
+            tx_fork_size_limit: None,
             master_key_path: "master.key.toml".into(),
             keys,
         })
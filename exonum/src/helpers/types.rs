@@ -14,6 +14,7 @@
 
 //! Common widely used type definitions.
 
+use exonum_derive::BinaryKey;
 use exonum_merkledb::{impl_object_hash_for_binary_value, BinaryValue, ObjectHash};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{borrow::Cow, fmt, num::ParseIntError, str::FromStr};
@@ -24,7 +25,11 @@ use crate::crypto::Hash;
 pub type Milliseconds = u64;
 
 /// Blockchain height (number of blocks).
+///
+/// Height can be used as a key in indexes, including as a field of composite keys;
+/// it is serialized in the same way as `u64`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(BinaryKey)]
 pub struct Height(pub u64);
 
 impl Height {
@@ -34,7 +34,7 @@ pub mod constants {
 pub(crate) use self::state::SharedConnectList;
 
 use exonum_keys::Keys;
-use exonum_merkledb::{Database, DbOptions, ObjectHash};
+use exonum_merkledb::{Database, DbOptions, HistoryOptions, ObjectHash};
 use failure::Error;
 use futures::{sync::mpsc, Future, Sink};
 use tokio_core::reactor::Core;
@@ -269,10 +269,20 @@ pub struct NodeConfig {
     /// Optional database configuration.
     #[serde(default)]
     pub database: DbOptions,
+    /// Options for recording historical blockchain states, which can be accessed
+    /// via `Blockchain::snapshot_at`. `None` means that the history is not recorded.
+    #[serde(default)]
+    pub history: Option<HistoryOptions>,
     /// Node's ConnectList.
     pub connect_list: ConnectListConfig,
     /// Transaction Verification Thread Pool size.
     pub thread_pool_size: Option<u8>,
+    /// Soft limit on the size of changes (in bytes) made by a single transaction.
+    /// Transactions exceeding the limit fail, and their changes are discarded.
+    /// Since the limit influences the outcome of transactions, it must be equal
+    /// for all validators in the network. `None` means that the size is not limited.
+    #[serde(default)]
+    pub tx_fork_size_limit: Option<usize>,
     /// Path to the master key file.
     pub master_key_path: PathBuf,
     /// Validator keys.
@@ -921,11 +931,17 @@ impl Node {
             .validate()
             .expect("Node configuration is inconsistent");
         let channel = NodeChannel::new(&node_cfg.mempool.events_pool_capacity);
-        let blockchain = Blockchain::new(
+        let mut blockchain = Blockchain::new(
             database,
             node_cfg.service_keypair(),
             ApiSender::new(channel.api_requests.0.clone()),
         );
+        if let Some(options) = node_cfg.history {
+            blockchain = blockchain.with_history(options);
+        }
+        if let Some(limit) = node_cfg.tx_fork_size_limit {
+            blockchain = blockchain.with_tx_fork_size_limit(limit);
+        }
         let rust_runtime = services.into_iter().fold(
             RustRuntime::new(channel.endpoints.0.clone()),
             |runtime, factory| runtime.with_factory(factory),
@@ -110,7 +110,9 @@ pub trait SnapshotExt {
         id: impl Into<InstanceQuery<'q>>,
     ) -> Option<Prefixed<'static, &dyn Snapshot>>;
 
-    /// Returns a proof for a Merkelized index with the specified name.
+    /// Returns a proof for a Merkelized index with the specified name. If hierarchical
+    /// state aggregation is enabled for the prefix of the name, the proof includes
+    /// a `prefix_proof` for the index within the prefix aggregator.
     ///
     /// # Return value
     ///
@@ -144,12 +146,27 @@ impl SnapshotExt for dyn Snapshot {
         let height = core_schema.height();
         let block_proof = core_schema.block_and_precommits(height).unwrap();
 
-        let aggregator = SystemSchema::new(self).state_aggregator();
-        aggregator.get(index_name)?;
-        let index_proof = aggregator.get_proof(index_name.to_string());
-        Some(IndexProof {
-            block_proof,
-            index_proof,
-        })
+        let system_schema = SystemSchema::new(self);
+        let aggregator = system_schema.state_aggregator();
+        // Indexes with a hierarchically aggregated prefix are proven in two steps:
+        // the prefix aggregator is proven by the default aggregator, and the index
+        // is proven by the prefix aggregator.
+        let prefix = index_name.split('.').next().unwrap_or_default();
+        if system_schema.is_prefix_aggregated(prefix) {
+            let prefix_aggregator = system_schema.prefix_state_aggregator(prefix);
+            prefix_aggregator.get(index_name)?;
+            Some(IndexProof {
+                block_proof,
+                index_proof: aggregator.get_proof(prefix.to_owned()),
+                prefix_proof: Some(prefix_aggregator.get_proof(index_name.to_owned())),
+            })
+        } else {
+            aggregator.get(index_name)?;
+            Some(IndexProof {
+                block_proof,
+                index_proof: aggregator.get_proof(index_name.to_owned()),
+                prefix_proof: None,
+            })
+        }
     }
 }
@@ -21,7 +21,7 @@ mod tests;
 
 use exonum_derive::*;
 use exonum_merkledb::Error as MerkledbError;
-use exonum_merkledb::{BinaryValue, ObjectHash};
+use exonum_merkledb::{BinaryValue, ObjectHash, SizeLimitExceeded};
 use exonum_proto::ProtobufConvert;
 
 use std::{
@@ -469,6 +469,9 @@ impl ExecutionError {
             error.description().to_string()
         } else if let Some(error) = any.downcast_ref::<failure::Error>() {
             error.to_string()
+        } else if let Some(error) = any.downcast_ref::<SizeLimitExceeded>() {
+            // Writes to a fork with exceeded size limit.
+            error.to_string()
         } else {
             // Unknown error kind; keep its description empty.
             String::new()
@@ -435,6 +435,19 @@ fn failure_panic() {
     assert_eq!(ExecutionError::from_panic(panic).description, description);
 }
 
+#[test]
+fn size_limit_panic() {
+    let error = SizeLimitExceeded {
+        limit: 1_024,
+        size: 2_048,
+    };
+    let panic = make_panic(error);
+    assert_eq!(
+        ExecutionError::from_panic(panic).description,
+        error.to_string()
+    );
+}
+
 #[test]
 fn unknown_panic() {
     let panic = make_panic(1);
@@ -234,9 +234,8 @@ impl TestKit {
         let api_sender = ApiSender::new(api_channel.0.clone());
         let db = database.into();
         let db_handler = db.handler();
-        let db = Arc::new(db);
         let blockchain = Blockchain::new(
-            Arc::clone(&db) as Arc<dyn Database>,
+            Arc::new(db) as Arc<dyn Database>,
             network.us().service_keypair(),
             api_sender.clone(),
         );
@@ -256,13 +255,14 @@ impl TestKit {
 
         let processing_lock = Arc::new(Mutex::new(()));
         let processing_lock_ = Arc::clone(&processing_lock);
+        let blockchain_ = blockchain.immutable_view();
 
         let events_stream: Box<dyn Stream<Item = (), Error = ()> + Send + Sync> =
             Box::new(api_channel.1.and_then(move |event| {
                 let _guard = processing_lock_.lock().unwrap();
                 match event {
                     ExternalMessage::Transaction(tx) => {
-                        BlockchainMut::add_transactions_into_db_pool(db.as_ref(), iter::once(tx));
+                        BlockchainMut::add_transactions_into_db_pool(&blockchain_, iter::once(tx));
                     }
                     ExternalMessage::PeerAdd(_)
                     | ExternalMessage::Enable(_)